@@ -14,7 +14,7 @@ use test::Bencher;
 #[bench]
 fn bench_process_transaction(bencher: &mut Bencher) {
     let (genesis_block, mint_keypair) = GenesisBlock::new(100_000_000);
-    let bank = Bank::new(&genesis_block);
+    let bank = Bank::new(&genesis_block).unwrap();
 
     // Create transactions between unrelated parties.
     let transactions: Vec<_> = (0..4096)
@@ -48,7 +48,7 @@ fn bench_banking_stage_multi_accounts(bencher: &mut Bencher) {
     let (genesis_block, mint_keypair) = GenesisBlock::new(mint_total);
 
     let (verified_sender, verified_receiver) = channel();
-    let bank = Arc::new(Bank::new(&genesis_block));
+    let bank = Arc::new(Bank::new(&genesis_block).unwrap());
     let dummy_leader_id = Keypair::new().pubkey();
     let dummy = Transaction::system_move(
         &mint_keypair,
@@ -110,6 +110,7 @@ fn bench_banking_stage_multi_accounts(bencher: &mut Bencher) {
         None,
         dummy_leader_id,
         &to_leader_sender,
+        false,
     );
 
     let mut id = genesis_block.last_id();
@@ -143,7 +144,7 @@ fn bench_banking_stage_multi_programs(bencher: &mut Bencher) {
     let (genesis_block, mint_keypair) = GenesisBlock::new(mint_total);
 
     let (verified_sender, verified_receiver) = channel();
-    let bank = Arc::new(Bank::new(&genesis_block));
+    let bank = Arc::new(Bank::new(&genesis_block).unwrap());
     let dummy_leader_id = Keypair::new().pubkey();
     let dummy = Transaction::system_move(
         &mint_keypair,
@@ -220,6 +221,7 @@ fn bench_banking_stage_multi_programs(bencher: &mut Bencher) {
         None,
         dummy_leader_id,
         &to_leader_sender,
+        false,
     );
 
     let mut id = genesis_block.last_id();
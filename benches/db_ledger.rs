@@ -48,7 +48,7 @@ fn setup_read_bench(
     entries.extend(make_tiny_test_entries(num_small_blobs as usize));
 
     // Convert the entries to blobs, write the blobs to the ledger
-    let mut blobs = entries.to_blobs();
+    let mut blobs = entries.to_blobs().unwrap();
     for (index, b) in blobs.iter_mut().enumerate() {
         b.set_index(index as u64).unwrap();
         b.set_slot(slot).unwrap();
@@ -65,7 +65,7 @@ fn bench_write_small(bench: &mut Bencher) {
     let ledger_path = get_tmp_ledger_path("bench_write_small");
     let num_entries = 32 * 1024;
     let entries = make_tiny_test_entries(num_entries);
-    let mut blobs = entries.to_blobs();
+    let mut blobs = entries.to_blobs().unwrap();
     for (index, b) in blobs.iter_mut().enumerate() {
         b.set_index(index as u64).unwrap();
     }
@@ -79,7 +79,7 @@ fn bench_write_big(bench: &mut Bencher) {
     let ledger_path = get_tmp_ledger_path("bench_write_big");
     let num_entries = 32 * 1024;
     let entries = make_large_test_entries(num_entries);
-    let mut blobs = entries.to_blobs();
+    let mut blobs = entries.to_blobs().unwrap();
     for (index, b) in blobs.iter_mut().enumerate() {
         b.set_index(index as u64).unwrap();
     }
@@ -153,7 +153,7 @@ fn bench_insert_data_blob_small(bench: &mut Bencher) {
         DbLedger::open(&ledger_path).expect("Expected to be able to open database ledger");
     let num_entries = 32 * 1024;
     let entries = make_tiny_test_entries(num_entries);
-    let mut blobs = entries.to_blobs();
+    let mut blobs = entries.to_blobs().unwrap();
 
     blobs.shuffle(&mut thread_rng());
 
@@ -176,7 +176,7 @@ fn bench_insert_data_blob_big(bench: &mut Bencher) {
         DbLedger::open(&ledger_path).expect("Expected to be able to open database ledger");
     let num_entries = 32 * 1024;
     let entries = make_large_test_entries(num_entries);
-    let mut shared_blobs = entries.to_shared_blobs();
+    let mut shared_blobs = entries.to_shared_blobs().unwrap();
     shared_blobs.shuffle(&mut thread_rng());
 
     bench.iter(move || {
@@ -19,7 +19,7 @@ fn bench_block_to_blobs_to_block(bencher: &mut Bencher) {
     let entries = next_entries(&zero, 1, transactions);
 
     bencher.iter(|| {
-        let blobs = entries.to_blobs();
+        let blobs = entries.to_blobs().unwrap();
         assert_eq!(reconstruct_entries_from_blobs(blobs).unwrap().0, entries);
     });
 }
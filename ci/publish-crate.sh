@@ -17,7 +17,7 @@ CRATES=(
   keygen
   metrics
   drone
-  programs/native/{budget,bpf_loader,lua_loader,native_loader,noop,system,vote}
+  programs/native/{budget,bpf_loader,lua_loader,native_loader,noop,stake,system,vote}
   .
   fullnode-config
   fullnode
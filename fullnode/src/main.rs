@@ -130,12 +130,20 @@ fn main() {
 
     let matches = App::new("fullnode")
         .version(crate_version!())
+        .arg(
+            Arg::with_name("blob_compression")
+                .long("blob-compression")
+                .help("Compress entries in the blobs this node broadcasts as leader"),
+        )
         .arg(
             Arg::with_name("entry_stream")
                 .long("entry-stream")
                 .takes_value(true)
-                .value_name("UNIX DOMAIN SOCKET")
-                .help("Open entry stream at this unix domain socket location")
+                .value_name("LOCATION")
+                .help(
+                    "Open entry stream at this location: a unix domain socket path, \
+                     or one of file:<PATH>, tcp:<HOST:PORT> or kafka:<HOST:PORT>/<TOPIC>",
+                )
         )
         .arg(
             Arg::with_name("identity")
@@ -185,7 +193,10 @@ fn main() {
             Arg::with_name("no_sigverify")
                 .short("v")
                 .long("no-sigverify")
-                .help("Run without signature verification"),
+                .help(
+                    "Run without signature verification, including of the transactions in \
+                     the ledger replayed at startup",
+                ),
         )
         .arg(
             Arg::with_name("rpc_port")
@@ -206,6 +217,7 @@ fn main() {
 
     let mut fullnode_config = FullnodeConfig::default();
     fullnode_config.sigverify_disabled = matches.is_present("no_sigverify");
+    fullnode_config.blob_compression = matches.is_present("blob_compression");
     let no_signer = matches.is_present("no_signer");
     let use_only_bootstrap_leader = matches.is_present("no_leader_rotation");
     let (keypair, gossip) = parse_identity(&matches);
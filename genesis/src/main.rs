@@ -67,12 +67,14 @@ fn main() -> Result<(), Box<dyn error::Error>> {
     let bootstrap_leader_keypair = read_keypair(bootstrap_leader_keypair_file)?;
     let mint_keypair = read_keypair(mint_keypair_file)?;
 
-    let genesis_block = GenesisBlock {
+    let mut genesis_block = GenesisBlock {
         mint_id: mint_keypair.pubkey(),
         tokens: num_tokens,
         bootstrap_leader_id: bootstrap_leader_keypair.pubkey(),
         bootstrap_leader_tokens: BOOTSTRAP_LEADER_TOKENS,
+        native_programs: vec![],
     };
+    genesis_block.pin_native_programs()?;
 
     create_empty_ledger(ledger_path, &genesis_block)?;
     Ok(())
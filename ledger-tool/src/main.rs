@@ -2,7 +2,9 @@ use clap::{crate_version, App, Arg, SubCommand};
 use solana::bank::Bank;
 use solana::db_ledger::DbLedger;
 use solana::genesis_block::GenesisBlock;
-use std::io::{stdout, Write};
+use solana::ledger_archive::{export_ledger, import_ledger};
+use std::fs::File;
+use std::io::{stdin, stdout, BufReader, BufWriter, Write};
 use std::process::exit;
 
 fn main() {
@@ -43,10 +45,57 @@ fn main() {
         .subcommand(SubCommand::with_name("print").about("Print the ledger"))
         .subcommand(SubCommand::with_name("json").about("Print the ledger in JSON format"))
         .subcommand(SubCommand::with_name("verify").about("Verify the ledger's PoH"))
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the ledger to a portable archive")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("Write the archive to FILE instead of stdout"),
+                )
+                .arg(
+                    Arg::with_name("coding")
+                        .long("coding")
+                        .help("Include erasure coding blobs in the archive"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Create the ledger from a portable archive, if it doesn't exist yet")
+                .arg(
+                    Arg::with_name("input")
+                        .short("i")
+                        .long("input")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("Read the archive from FILE instead of stdin"),
+                ),
+        )
         .get_matches();
 
     let ledger_path = matches.value_of("ledger").unwrap();
 
+    // Importing creates the ledger, so it can't be opened beforehand
+    if let ("import", Some(import_matches)) = matches.subcommand() {
+        let result = match import_matches.value_of("input") {
+            Some(input) => File::open(input)
+                .map_err(|err| err.into())
+                .and_then(|file| import_ledger(BufReader::new(file), ledger_path)),
+            None => import_ledger(BufReader::new(stdin()), ledger_path),
+        };
+        match result {
+            Ok((_, num_entries)) => eprintln!("imported {} entries", num_entries),
+            Err(err) => {
+                eprintln!("Failed to import ledger to {}: {:?}", ledger_path, err);
+                exit(1);
+            }
+        }
+        return;
+    }
+
     let genesis_block = GenesisBlock::load(ledger_path).unwrap_or_else(|err| {
         eprintln!(
             "Failed to open ledger genesis_block at {}: {}",
@@ -112,7 +161,13 @@ fn main() {
             stdout().write_all(b"\n]}\n").expect("close array");
         }
         ("verify", _) => {
-            let bank = Bank::new(&genesis_block);
+            let bank = match Bank::new(&genesis_block) {
+                Ok(bank) => bank,
+                Err(e) => {
+                    eprintln!("failed to create bank from the genesis block: {:?}", e);
+                    exit(1);
+                }
+            };
             let mut last_id = bank.last_id();
             let mut num_entries = 0;
             for (i, entry) in entries.enumerate() {
@@ -129,7 +184,7 @@ fn main() {
                 last_id = entry.id;
                 num_entries += 1;
 
-                if let Err(e) = bank.process_entry(&entry) {
+                if let Err(e) = bank.process_entries(&[entry]) {
                     eprintln!("verify failed at entry[{}], err: {:?}", i + 2, e);
                     if !matches.is_present("continue") {
                         exit(1);
@@ -138,6 +193,24 @@ fn main() {
             }
             println!("{} entries.  last_id={:?}", num_entries, last_id);
         }
+        ("export", Some(export_matches)) => {
+            let include_coding = export_matches.is_present("coding");
+            let result = match export_matches.value_of("output") {
+                Some(output) => File::create(output)
+                    .map_err(|err| err.into())
+                    .and_then(|file| {
+                        export_ledger(ledger_path, BufWriter::new(file), include_coding)
+                    }),
+                None => export_ledger(ledger_path, BufWriter::new(stdout()), include_coding),
+            };
+            match result {
+                Ok(num_entries) => eprintln!("exported {} entries", num_entries),
+                Err(err) => {
+                    eprintln!("Failed to export ledger at {}: {:?}", ledger_path, err);
+                    exit(1);
+                }
+            }
+        }
         ("", _) => {
             eprintln!("{}", matches.usage());
             exit(1);
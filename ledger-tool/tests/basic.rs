@@ -1,7 +1,8 @@
-use solana::db_ledger::create_tmp_sample_ledger;
+use solana::db_ledger::{create_tmp_sample_ledger, get_tmp_ledger_path, DbLedger};
 use solana_sdk::signature::{Keypair, KeypairUtil};
 
 use assert_cmd::prelude::*;
+use std::fs::{remove_dir_all, remove_file};
 use std::process::Command;
 use std::process::Output;
 use std::sync::Arc;
@@ -59,3 +60,36 @@ fn nominal() {
     assert!(output.status.success());
     assert_eq!(count_newlines(&output.stdout), 0);
 }
+
+#[test]
+fn export_import() {
+    let keypair = Arc::new(Keypair::new());
+    let (_genesis_block, _mint, ledger_path, _genesis_entries) =
+        create_tmp_sample_ledger("test_ledger_tool_export", 100, 10, keypair.pubkey(), 50);
+    let archive_path = format!("{}.archive", ledger_path);
+    let imported_path = get_tmp_ledger_path("test_ledger_tool_import");
+
+    let output = run_ledger_tool(&["-l", &ledger_path, "export", "-o", &archive_path]);
+    assert!(output.status.success());
+
+    let output = run_ledger_tool(&["-l", &imported_path, "import", "-i", &archive_path]);
+    assert!(output.status.success());
+
+    // The imported ledger verifies and prints the same entries
+    let output = run_ledger_tool(&["-l", &imported_path, "verify"]);
+    assert!(output.status.success());
+    let original = run_ledger_tool(&["-l", &ledger_path, "print"]);
+    let imported = run_ledger_tool(&["-l", &imported_path, "print"]);
+    assert_eq!(count_newlines(&imported.stdout), 10);
+    assert_eq!(imported.stdout, original.stdout);
+
+    // Importing over an existing ledger fails
+    let output = run_ledger_tool(&["-l", &ledger_path, "import", "-i", &archive_path]);
+    assert!(!output.status.success());
+
+    DbLedger::destroy(&ledger_path).unwrap();
+    DbLedger::destroy(&imported_path).unwrap();
+    let _ignored = remove_dir_all(&ledger_path);
+    let _ignored = remove_dir_all(&imported_path);
+    let _ignored = remove_file(&archive_path);
+}
@@ -21,14 +21,15 @@ usage() {
     echo
   fi
   cat <<EOF
-usage: $0 [-x] [--entry-stream PATH] [--init-complete-file FILE] [--no-leader-rotation] [--no-signer] [--rpc-port port] [rsync network path to bootstrap leader configuration] [network entry point]
+usage: $0 [-x] [--entry-stream LOCATION] [--init-complete-file FILE] [--no-leader-rotation] [--no-signer] [--rpc-port port] [rsync network path to bootstrap leader configuration] [network entry point]
 
 Start a full node on the specified network
 
   -x                    - start a new, dynamically-configured full node
   -X [label]            - start or restart a dynamically-configured full node with
                           the specified label
-  --entry-stream PATH   - open entry stream at this unix domain socket location
+  --entry-stream LOCATION - open entry stream at this unix domain socket path, or at
+                          file:<PATH>, tcp:<HOST:PORT> or kafka:<HOST:PORT>/<TOPIC>
   --init-complete-file FILE - create this file, if it doesn't already exist, once node initialization is complete
   --no-leader-rotation  - disable leader rotation
   --no-signer           - start node without vote signer
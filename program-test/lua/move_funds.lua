@@ -0,0 +1,7 @@
+-- Move the number of tokens in `data` from the first account to the second
+local tokens, _ = string.unpack("I", data)
+if accounts[1].tokens < tokens then
+    fail("ResultWithNegativeTokens")
+end
+accounts[1].tokens = accounts[1].tokens - tokens
+accounts[2].tokens = accounts[2].tokens + tokens
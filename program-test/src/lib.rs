@@ -0,0 +1,278 @@
+//! In-process harness for testing programs
+//! Wraps a `Bank` created from a `GenesisBlock`, so that programs can be deployed and their
+//! transactions processed synchronously, without running a fullnode
+
+use solana::bank::{Bank, BankError, Result};
+use solana::genesis_block::GenesisBlock;
+use solana_native_loader;
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader;
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::loader_transaction::LoaderTransaction;
+use solana_sdk::native_loader::NativeProgram;
+use solana_sdk::native_program::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, KeypairUtil};
+use solana_sdk::system_transaction::SystemTransaction;
+use solana_sdk::transaction::Transaction;
+use std::fs;
+use std::path::Path;
+
+/// Number of tokens the mint of `ProgramTest::default()` holds
+pub const DEFAULT_MINT_TOKENS: u64 = 1_000_000_000;
+
+/// Number of bytes of a program written by each loader transaction
+const WRITE_CHUNK_SIZE: usize = 256;
+
+pub struct ProgramTest {
+    bank: Bank,
+    mint_keypair: Keypair,
+    lua_loader: Option<Pubkey>,
+}
+
+impl Default for ProgramTest {
+    fn default() -> Self {
+        Self::new(DEFAULT_MINT_TOKENS)
+    }
+}
+
+impl ProgramTest {
+    /// Create a harness whose mint holds `tokens`
+    pub fn new(tokens: u64) -> Self {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(tokens);
+        Self::new_with_genesis_block(&genesis_block, mint_keypair)
+    }
+
+    pub fn new_with_genesis_block(genesis_block: &GenesisBlock, mint_keypair: Keypair) -> Self {
+        ProgramTest {
+            bank: Bank::new(genesis_block).expect("genesis block"),
+            mint_keypair,
+            lua_loader: None,
+        }
+    }
+
+    pub fn bank(&self) -> &Bank {
+        &self.bank
+    }
+
+    pub fn mint_keypair(&self) -> &Keypair {
+        &self.mint_keypair
+    }
+
+    pub fn last_id(&self) -> Hash {
+        self.bank.last_id()
+    }
+
+    pub fn tick_height(&self) -> u64 {
+        self.bank.tick_height()
+    }
+
+    /// Register `ticks` new ticks, as a leader would between entries
+    pub fn advance_ticks(&self, ticks: u64) {
+        for _ in 0..ticks {
+            let last_id = hash(self.last_id().as_ref());
+            self.bank.register_tick(&last_id);
+        }
+    }
+
+    /// Process `tx`. A program that fails makes it return `BankError::ProgramError` with the
+    /// index of the failed instruction
+    pub fn process_transaction(&self, tx: &Transaction) -> Result<()> {
+        self.bank.process_transaction(tx)
+    }
+
+    /// Move `tokens` from the mint to `to`
+    pub fn fund(&self, to: Pubkey, tokens: u64) -> Result<()> {
+        let tx = Transaction::system_move(&self.mint_keypair, to, tokens, self.last_id(), 0);
+        self.process_transaction(&tx)
+    }
+
+    /// Create the account `pubkey` with `tokens` from the mint and `space` bytes of userdata,
+    /// assigned to `program_id`
+    pub fn create_account(
+        &self,
+        pubkey: Pubkey,
+        tokens: u64,
+        space: u64,
+        program_id: Pubkey,
+    ) -> Result<()> {
+        let tx = Transaction::system_create(
+            &self.mint_keypair,
+            pubkey,
+            self.last_id(),
+            tokens,
+            space,
+            program_id,
+            0,
+        );
+        self.process_transaction(&tx)
+    }
+
+    pub fn get_account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.bank.get_account(pubkey)
+    }
+
+    pub fn get_balance(&self, pubkey: &Pubkey) -> u64 {
+        self.bank.get_balance(pubkey)
+    }
+
+    /// Panic unless the account `pubkey` holds `tokens` and `userdata` and is owned by `owner`
+    pub fn assert_account(&self, pubkey: &Pubkey, tokens: u64, owner: &Pubkey, userdata: &[u8]) {
+        let account = self
+            .get_account(pubkey)
+            .unwrap_or_else(|| panic!("Account {} doesn't exist", pubkey));
+        assert_eq!(account.tokens, tokens, "tokens of {}", pubkey);
+        assert_eq!(account.owner, *owner, "owner of {}", pubkey);
+        assert_eq!(&account.userdata[..], userdata, "userdata of {}", pubkey);
+    }
+
+    /// Process `tx`, panicking unless its instruction `index` fails with `err`
+    pub fn assert_program_error(&self, tx: &Transaction, index: u8, err: ProgramError) {
+        assert_eq!(
+            self.process_transaction(tx),
+            Err(BankError::ProgramError(index, err))
+        );
+    }
+
+    /// Deploy the native program implemented by the shared library `name`, pinned to the
+    /// library's current contents. Libraries are found next to the current executable, as the
+    /// native loader finds them, so the crate that builds `name` must be a dependency of the
+    /// test, as `solana-noop` and `solana-lualoader` are of this one
+    pub fn deploy_native(&self, name: &str) -> Pubkey {
+        let code_hash = solana_native_loader::hash_library(name).unwrap_or_else(|err| {
+            panic!(
+                "Unable to hash native program {}, is its crate a dependency? {:?}",
+                name, err
+            )
+        });
+        let program = NativeProgram::new(name, env!("CARGO_PKG_VERSION"), Some(code_hash));
+        self.deploy(solana_native_loader::id(), &program.serialize())
+    }
+
+    /// Deploy the BPF program in the ELF file at `path`
+    pub fn deploy_bpf<P: AsRef<Path>>(&self, path: P) -> Pubkey {
+        self.deploy(bpf_loader::id(), &read_program(path.as_ref()))
+    }
+
+    /// Deploy the Lua program in the file at `path`. The Lua loader is deployed the first time
+    /// this is called
+    pub fn deploy_lua<P: AsRef<Path>>(&mut self, path: P) -> Pubkey {
+        let lua_loader = match self.lua_loader {
+            Some(lua_loader) => lua_loader,
+            None => {
+                let lua_loader = self.deploy_native("solana_lua_loader");
+                self.lua_loader = Some(lua_loader);
+                lua_loader
+            }
+        };
+        self.deploy(lua_loader, &read_program(path.as_ref()))
+    }
+
+    /// Allocate, write, finalize and spawn a program account holding `userdata`, loaded by
+    /// `loader`. Panics if any of these steps fails
+    pub fn deploy(&self, loader: Pubkey, userdata: &[u8]) -> Pubkey {
+        let program = Keypair::new();
+        self.create_account(program.pubkey(), 1, userdata.len() as u64, loader)
+            .expect("Unable to allocate program");
+
+        for (i, chunk) in userdata.chunks(WRITE_CHUNK_SIZE).enumerate() {
+            let tx = Transaction::loader_write(
+                &program,
+                loader,
+                (i * WRITE_CHUNK_SIZE) as u32,
+                chunk.to_vec(),
+                self.last_id(),
+                0,
+            );
+            self.process_transaction(&tx)
+                .expect("Unable to write program");
+        }
+
+        let tx = Transaction::loader_finalize(&program, loader, self.last_id(), 0);
+        self.process_transaction(&tx)
+            .expect("Unable to finalize program");
+
+        let tx = Transaction::system_spawn(&program, self.last_id(), 0);
+        self.process_transaction(&tx)
+            .expect("Unable to spawn program");
+
+        program.pubkey()
+    }
+}
+
+fn read_program(path: &Path) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| panic!("Unable to read program {:?}: {:?}", path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::system_program;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_fund_and_advance_ticks() {
+        let program_test = ProgramTest::new(100);
+        let to = Keypair::new().pubkey();
+        program_test.fund(to, 10).unwrap();
+        program_test.assert_account(&to, 10, &system_program::id(), &[]);
+
+        let tx = Transaction::system_move(
+            program_test.mint_keypair(),
+            to,
+            91,
+            program_test.last_id(),
+            0,
+        );
+        program_test.assert_program_error(&tx, 0, ProgramError::ResultWithNegativeTokens);
+
+        let tick_height = program_test.tick_height();
+        let last_id = program_test.last_id();
+        program_test.advance_ticks(3);
+        assert_eq!(program_test.tick_height(), tick_height + 3);
+        assert_ne!(program_test.last_id(), last_id);
+    }
+
+    #[test]
+    fn test_deploy_native_noop() {
+        solana_logger::setup();
+
+        let program_test = ProgramTest::default();
+        let program_id = program_test.deploy_native("noop");
+        assert!(program_test.get_account(&program_id).unwrap().executable);
+
+        let tx = Transaction::new(
+            program_test.mint_keypair(),
+            &[],
+            program_id,
+            &1u8,
+            program_test.last_id(),
+            0,
+        );
+        program_test.process_transaction(&tx).unwrap();
+    }
+
+    #[test]
+    fn test_deploy_lua_move_funds() {
+        solana_logger::setup();
+
+        let mut program_test = ProgramTest::default();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("lua/move_funds.lua");
+        let program_id = program_test.deploy_lua(&path);
+
+        let from = Keypair::new();
+        let to = Keypair::new().pubkey();
+        program_test
+            .create_account(from.pubkey(), 10, 0, program_id)
+            .unwrap();
+        program_test.create_account(to, 1, 0, program_id).unwrap();
+
+        let tx = Transaction::new(&from, &[to], program_id, &11, program_test.last_id(), 0);
+        program_test.assert_program_error(&tx, 0, ProgramError::ResultWithNegativeTokens);
+
+        let tx = Transaction::new(&from, &[to], program_id, &10, program_test.last_id(), 0);
+        program_test.process_transaction(&tx).unwrap();
+        assert_eq!(program_test.get_balance(&from.pubkey()), 0);
+        program_test.assert_account(&to, 11, &program_id, &[]);
+    }
+}
@@ -25,13 +25,8 @@ fn check_prog_len(prog: &[u8]) -> Result<(), Error> {
         reject("No program set, call prog_set() to load one".to_string())?;
     }
 
-    // TODO BPF program may deterministically exit even if the last
-    // instruction in the block is not an exit (might be earlier and jumped to)
-    // TODO need to validate more intelligently
-    // let last_insn = ebpf::get_insn(prog, (prog.len() / ebpf::INSN_SIZE) - 1);
-    // if last_insn.opc != ebpf::EXIT {
-    //     reject("program does not end with “EXIT” instruction".to_string())?;
-    // }
+    // Whether the program exits is checked by `check_cfg`, since the last instruction need
+    // not be an exit if an earlier one is jumped to
 
     Ok(())
 }
@@ -55,8 +50,12 @@ fn check_imm_endian(insn: &ebpf::Insn, insn_ptr: usize) -> Result<(), Error> {
 }
 
 fn check_load_dw(prog: &[u8], insn_ptr: usize) -> Result<(), Error> {
-    // We know we can reach next insn since we enforce an EXIT insn at the end of program, while
-    // this function should be called only for LD_DW insn, that cannot be last in program.
+    if (insn_ptr + 1) * ebpf::INSN_SIZE >= prog.len() {
+        reject(format!(
+            "incomplete LD_DW instruction (insn #{:?})",
+            insn_ptr
+        ))?;
+    }
     let next_insn = ebpf::get_insn(prog, insn_ptr + 1);
     if next_insn.opc != 0 {
         reject(format!(
@@ -131,7 +130,6 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
             ebpf::LD_IND_DW => {}
 
             ebpf::LD_DW_IMM => {
-                store = true;
                 check_load_dw(prog, insn_ptr)?;
                 insn_ptr += 1;
             }
@@ -153,11 +151,11 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
             ebpf::ST_H_REG => store = true,
             ebpf::ST_W_REG => store = true,
             ebpf::ST_DW_REG => store = true,
-            ebpf::ST_W_XADD => {
-                unimplemented!();
-            }
-            ebpf::ST_DW_XADD => {
-                unimplemented!();
+            ebpf::ST_W_XADD | ebpf::ST_DW_XADD => {
+                reject(format!(
+                    "atomic add is not supported (insn #{:?})",
+                    insn_ptr
+                ))?;
             }
 
             // BPF_ALU class
@@ -202,9 +200,7 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
             ebpf::ADD64_REG => {}
             ebpf::SUB64_IMM => {}
             ebpf::SUB64_REG => {}
-            ebpf::MUL64_IMM => {
-                check_imm_nonzero(&insn, insn_ptr)?;
-            }
+            ebpf::MUL64_IMM => {}
             ebpf::MUL64_REG => {}
             ebpf::DIV64_IMM => {
                 check_imm_nonzero(&insn, insn_ptr)?;
@@ -219,7 +215,9 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
             ebpf::RSH64_IMM => {}
             ebpf::RSH64_REG => {}
             ebpf::NEG64 => {}
-            ebpf::MOD64_IMM => {}
+            ebpf::MOD64_IMM => {
+                check_imm_nonzero(&insn, insn_ptr)?;
+            }
             ebpf::MOD64_REG => {}
             ebpf::XOR64_IMM => {}
             ebpf::XOR64_REG => {}
@@ -299,7 +297,12 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
                 check_jmp_offset(prog, insn_ptr)?;
             }
             ebpf::CALL => {}
-            ebpf::TAIL_CALL => unimplemented!(),
+            ebpf::TAIL_CALL => {
+                reject(format!(
+                    "tail calls are not supported (insn #{:?})",
+                    insn_ptr
+                ))?;
+            }
             ebpf::EXIT => {}
 
             _ => {
@@ -322,3 +325,186 @@ pub fn check(prog: &[u8]) -> Result<(), Error> {
 
     Ok(())
 }
+
+fn is_jump(insn: &ebpf::Insn) -> bool {
+    insn.opc & 0x07 == ebpf::BPF_JMP
+        && insn.opc != ebpf::CALL
+        && insn.opc != ebpf::TAIL_CALL
+        && insn.opc != ebpf::EXIT
+}
+
+/// The number of stack bytes below the frame pointer `insn` accesses
+fn stack_depth(insn: &ebpf::Insn, insn_ptr: usize) -> Result<usize, Error> {
+    let base = match insn.opc & 0x07 {
+        ebpf::BPF_LDX => insn.src,
+        ebpf::BPF_ST | ebpf::BPF_STX => insn.dst,
+        _ => return Ok(0),
+    };
+    if base != 10 {
+        return Ok(0);
+    }
+    let size = match insn.opc & 0x18 {
+        0x00 => 4,
+        0x08 => 2,
+        0x10 => 1,
+        _ => 8,
+    };
+    let depth = -(insn.off as isize);
+    if depth < size || depth as usize > ebpf::STACK_SIZE {
+        reject(format!(
+            "stack access out of bounds at r10{:+} (insn #{:?})",
+            insn.off, insn_ptr
+        ))?;
+    }
+    Ok(depth as usize)
+}
+
+/// Walk the control flow of `prog` from `entrypoints`, the instructions its functions begin
+/// at, rejecting code that can't be reached, paths that run off the end of the program and
+/// out of bounds stack accesses. Returns the maximum stack depth in bytes
+pub fn check_cfg(prog: &[u8], entrypoints: &[usize]) -> Result<usize, Error> {
+    check(prog)?;
+
+    let insn_count = prog.len() / ebpf::INSN_SIZE;
+    let mut reachable = vec![false; insn_count];
+    let mut max_stack_depth = 0;
+    let mut pending = entrypoints.to_vec();
+    while let Some(insn_ptr) = pending.pop() {
+        if insn_ptr >= insn_count {
+            reject(format!("entrypoint out of code at #{:?}", insn_ptr))?;
+        }
+        if reachable[insn_ptr] {
+            continue;
+        }
+        reachable[insn_ptr] = true;
+
+        let insn = ebpf::get_insn(prog, insn_ptr);
+        max_stack_depth = max_stack_depth.max(stack_depth(&insn, insn_ptr)?);
+
+        let mut next_insn_ptr = insn_ptr + 1;
+        match insn.opc {
+            ebpf::EXIT => continue,
+            ebpf::LD_DW_IMM => {
+                reachable[insn_ptr + 1] = true;
+                next_insn_ptr += 1;
+            }
+            ebpf::CALL => {
+                // Calls to functions in the program are relative, calls to helpers are not
+                let dst_insn_ptr = next_insn_ptr as isize + insn.imm as isize;
+                if insn.src == 1 && dst_insn_ptr >= 0 && (dst_insn_ptr as usize) < insn_count {
+                    pending.push(dst_insn_ptr as usize);
+                }
+            }
+            _ if is_jump(&insn) => {
+                // `check` already rejected targets out of code
+                pending.push((next_insn_ptr as isize + insn.off as isize) as usize);
+                if insn.opc == ebpf::JA {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        if next_insn_ptr >= insn_count {
+            reject(format!(
+                "execution runs off the end of the program (insn #{:?})",
+                insn_ptr
+            ))?;
+        }
+        pending.push(next_insn_ptr);
+    }
+
+    if let Some(insn_ptr) = reachable.iter().position(|reachable| !reachable) {
+        reject(format!("unreachable code (insn #{:?})", insn_ptr))?;
+    }
+    Ok(max_stack_depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_cfg() {
+        #[rustfmt::skip]
+        let prog = &[
+            0x7b, 0x1a, 0xf0, 0xff, 0x00, 0x00, 0x00, 0x00, // *(u64 *)(r10 - 16) = r1
+            0x15, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // if r1 == 0 goto +1
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r0 = 1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert_eq!(check_cfg(prog, &[0]).unwrap(), 16);
+
+        // Execution may not run off the end
+        assert!(check_cfg(&prog[..8], &[0]).is_err());
+
+        // Every instruction must be reachable from an entrypoint
+        #[rustfmt::skip]
+        let prog = &[
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r0 = 1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert!(check_cfg(prog, &[0]).is_err());
+        assert_eq!(check_cfg(prog, &[0, 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_check_cfg_invalid() {
+        // Jump into the middle of a LD_DW
+        #[rustfmt::skip]
+        let prog = &[
+            0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, // goto +1
+            0x18, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r0 = 1 ll
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert!(check_cfg(prog, &[0]).is_err());
+
+        // Write to the frame pointer
+        #[rustfmt::skip]
+        let prog = &[
+            0x18, 0x0a, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // r10 = 1 ll
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert!(check_cfg(prog, &[0]).is_err());
+
+        // Stack access beyond the stack
+        #[rustfmt::skip]
+        let prog = &[
+            0x7b, 0x1a, 0xf8, 0xfd, 0x00, 0x00, 0x00, 0x00, // *(u64 *)(r10 - 520) = r1
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert!(check_cfg(prog, &[0]).is_err());
+    }
+
+    #[test]
+    fn test_check_unsupported() {
+        // Unsupported instructions are rejected rather than crashing the verifier
+        #[rustfmt::skip]
+        let progs: &[&[u8]] = &[
+            &[0xc3, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // lock *(u32 *)(r1 + 0) += r2
+            &[0xdb, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // lock *(u64 *)(r1 + 0) += r2
+            &[0x8d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // tail call
+        ];
+        for insn in progs {
+            let mut prog = insn.to_vec();
+            prog.extend_from_slice(&[0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]); // exit
+            assert!(check(&prog).is_err());
+        }
+    }
+
+    #[test]
+    fn test_check_imm_nonzero() {
+        #[rustfmt::skip]
+        let prog = &mut [
+            0x27, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r0 *= 0
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+        ];
+        assert!(check(prog).is_ok());
+
+        // Modulo by zero
+        prog[0] = 0x97;
+        assert!(check(prog).is_err());
+    }
+}
@@ -3,16 +3,23 @@ pub mod bpf_verifier;
 use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use libc::c_char;
 use log::*;
-use solana_rbpf::{EbpfVmRaw, MemoryRegion};
+use solana_rbpf::{ebpf, EbpfVmRaw, MemoryRegion};
 use solana_sdk::account::KeyedAccount;
+use solana_sdk::bpf_loader::{
+    deserialize_programdata, get_programdata_address, is_upgradeable_state, serialize_programdata,
+    UpgradeableLoaderState,
+};
+use solana_sdk::hash::{hash, Hash};
 use solana_sdk::loader_instruction::LoaderInstruction;
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use solana_sdk::solana_entrypoint;
+use std::cell::Cell;
 use std::ffi::CStr;
 use std::io::prelude::*;
-use std::io::{Error, ErrorKind};
-use std::mem;
+use std::io::{Cursor, Error, ErrorKind};
+use std::{mem, slice};
 
 // TODO use rbpf's disassemble
 #[allow(dead_code)]
@@ -77,18 +84,324 @@ pub fn helper_sol_log_u64(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64)
     0
 }
 
+/// Compute units a program may spend on metered helpers in one invocation
+const HELPER_BUDGET: u64 = 20_000;
+const SHA256_BASE_COST: u64 = 50;
+/// Cost of each 64-byte block hashed by `sol_sha256`
+const SHA256_BLOCK_COST: u64 = 10;
+const VERIFY_ED25519_COST: u64 = 2_000;
+const GET_TICK_HEIGHT_COST: u64 = 10;
+
+thread_local! {
+    // Helpers are plain functions, so the state of the invocation they run in is kept here
+    static HELPER_UNITS: Cell<u64> = Cell::new(0);
+    static TICK_HEIGHT: Cell<u64> = Cell::new(0);
+}
+
+/// Reset the helper state before a program is invoked at `tick_height`
+fn reset_helpers(tick_height: u64) {
+    HELPER_UNITS.with(|units| units.set(HELPER_BUDGET));
+    TICK_HEIGHT.with(|height| height.set(tick_height));
+}
+
+fn consume_helper_units(cost: u64) -> Result<(), Error> {
+    HELPER_UNITS.with(|units| {
+        if units.get() < cost {
+            units.set(0);
+            return Err(Error::new(
+                ErrorKind::Other,
+                "Error: Helper budget exceeded",
+            ));
+        }
+        units.set(units.get() - cost);
+        Ok(())
+    })
+}
+
+/// Check that `len` bytes at `addr` lie within one of `regions`
+fn check_region(addr: u64, len: u64, regions: &[MemoryRegion]) -> Result<(), Error> {
+    for region in regions.iter() {
+        if region.addr <= addr && addr.saturating_add(len) <= region.addr + region.len {
+            return Ok(());
+        }
+    }
+    Err(Error::new(
+        ErrorKind::Other,
+        format!("Error: Access violation at {:#x}, length {}", addr, len),
+    ))
+}
+
+fn check_readable(
+    addr: u64,
+    len: u64,
+    ro_regions: &[MemoryRegion],
+    rw_regions: &[MemoryRegion],
+) -> Result<(), Error> {
+    check_region(addr, len, ro_regions).or_else(|_| check_region(addr, len, rw_regions))
+}
+
+#[allow(unused_variables)]
+pub fn helper_sol_sha256_verify(
+    data_addr: u64,
+    data_len: u64,
+    result_addr: u64,
+    unused4: u64,
+    unused5: u64,
+    ro_regions: &[MemoryRegion],
+    rw_regions: &[MemoryRegion],
+) -> Result<(), Error> {
+    check_readable(data_addr, data_len, ro_regions, rw_regions)?;
+    check_region(result_addr, mem::size_of::<Hash>() as u64, rw_regions)?;
+    consume_helper_units(SHA256_BASE_COST + SHA256_BLOCK_COST * ((data_len + 63) / 64))
+}
+
+/// Write the SHA-256 hash of `data_len` bytes at `data_addr` to the 32 bytes at `result_addr`
+pub fn helper_sol_sha256(
+    data_addr: u64,
+    data_len: u64,
+    result_addr: u64,
+    _arg4: u64,
+    _arg5: u64,
+) -> u64 {
+    let data = unsafe { slice::from_raw_parts(data_addr as *const u8, data_len as usize) };
+    let result =
+        unsafe { slice::from_raw_parts_mut(result_addr as *mut u8, mem::size_of::<Hash>()) };
+    result.copy_from_slice(hash(data).as_ref());
+    0
+}
+
+#[allow(unused_variables)]
+pub fn helper_sol_verify_ed25519_verify(
+    message_addr: u64,
+    message_len: u64,
+    signature_addr: u64,
+    pubkey_addr: u64,
+    unused5: u64,
+    ro_regions: &[MemoryRegion],
+    rw_regions: &[MemoryRegion],
+) -> Result<(), Error> {
+    check_readable(message_addr, message_len, ro_regions, rw_regions)?;
+    check_readable(
+        signature_addr,
+        mem::size_of::<Signature>() as u64,
+        ro_regions,
+        rw_regions,
+    )?;
+    check_readable(
+        pubkey_addr,
+        mem::size_of::<Pubkey>() as u64,
+        ro_regions,
+        rw_regions,
+    )?;
+    consume_helper_units(VERIFY_ED25519_COST)
+}
+
+/// Returns 1 if the 64 bytes at `signature_addr` are the ed25519 signature of the message at
+/// `message_addr` by the public key at `pubkey_addr`, 0 otherwise
+pub fn helper_sol_verify_ed25519(
+    message_addr: u64,
+    message_len: u64,
+    signature_addr: u64,
+    pubkey_addr: u64,
+    _arg5: u64,
+) -> u64 {
+    let message = unsafe { slice::from_raw_parts(message_addr as *const u8, message_len as usize) };
+    let signature =
+        unsafe { slice::from_raw_parts(signature_addr as *const u8, mem::size_of::<Signature>()) };
+    let pubkey =
+        unsafe { slice::from_raw_parts(pubkey_addr as *const u8, mem::size_of::<Pubkey>()) };
+    Signature::new(signature).verify(pubkey, message) as u64
+}
+
+#[allow(unused_variables)]
+pub fn helper_sol_get_tick_height_verify(
+    unused1: u64,
+    unused2: u64,
+    unused3: u64,
+    unused4: u64,
+    unused5: u64,
+    ro_regions: &[MemoryRegion],
+    rw_regions: &[MemoryRegion],
+) -> Result<(), Error> {
+    consume_helper_units(GET_TICK_HEIGHT_COST)
+}
+
+/// Returns the tick height the program is running at
+pub fn helper_sol_get_tick_height(
+    _arg1: u64,
+    _arg2: u64,
+    _arg3: u64,
+    _arg4: u64,
+    _arg5: u64,
+) -> u64 {
+    TICK_HEIGHT.with(Cell::get)
+}
+
+/// The helpers `create_vm` registers, by the symbol programs import them as
+const HELPERS: [(&str, Option<ebpf::HelperVerifier>, ebpf::Helper); 7] = [
+    ("sol_log", Some(helper_sol_log_verify), helper_sol_log),
+    ("sol_log_", Some(helper_sol_log_verify), helper_sol_log),
+    ("sol_log_64", None, helper_sol_log_u64),
+    ("sol_log_64_", None, helper_sol_log_u64),
+    (
+        "sol_sha256",
+        Some(helper_sol_sha256_verify),
+        helper_sol_sha256,
+    ),
+    (
+        "sol_verify_ed25519",
+        Some(helper_sol_verify_ed25519_verify),
+        helper_sol_verify_ed25519,
+    ),
+    (
+        "sol_get_tick_height",
+        Some(helper_sol_get_tick_height_verify),
+        helper_sol_get_tick_height,
+    ),
+];
+
 pub fn create_vm(prog: &[u8]) -> Result<EbpfVmRaw, Error> {
     let mut vm = EbpfVmRaw::new(None)?;
     vm.set_verifier(bpf_verifier::check)?;
     vm.set_max_instruction_count(36000)?; // 36000 is a wag, need to tune
     vm.set_elf(&prog)?;
-    vm.register_helper_ex("sol_log", Some(helper_sol_log_verify), helper_sol_log)?;
-    vm.register_helper_ex("sol_log_", Some(helper_sol_log_verify), helper_sol_log)?;
-    vm.register_helper_ex("sol_log_64", None, helper_sol_log_u64)?;
-    vm.register_helper_ex("sol_log_64_", None, helper_sol_log_u64)?;
+    for (name, verifier, helper) in HELPERS.iter() {
+        vm.register_helper_ex(name, *verifier, *helper)?;
+    }
     Ok(vm)
 }
 
+/// Check that every symbol the program imports resolves to a registered helper, since an
+/// unresolved call would otherwise only be caught when it executes
+pub fn check_imports(prog: &[u8]) -> Result<(), Error> {
+    let elf = elf::File::open_stream(&mut Cursor::new(prog))
+        .map_err(|e| Error::new(ErrorKind::Other, format!("Error: invalid ELF: {:?}", e)))?;
+    for section in elf
+        .sections
+        .iter()
+        .filter(|section| section.shdr.shtype == elf::types::SHT_DYNSYM)
+    {
+        let symbols = elf.get_symbols(section).map_err(|e| {
+            Error::new(ErrorKind::Other, format!("Error: invalid symbols: {:?}", e))
+        })?;
+        for symbol in symbols {
+            // Undefined symbols have a section index of 0
+            if symbol.shndx == 0
+                && !symbol.name.is_empty()
+                && !HELPERS.iter().any(|(name, _, _)| *name == symbol.name)
+            {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("Error: unresolved symbol {}", symbol.name),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the control flow of the program's .text, starting from each function it defines.
+/// Returns the maximum stack depth of the program
+pub fn check_control_flow(prog: &[u8]) -> Result<usize, Error> {
+    let elf = elf::File::open_stream(&mut Cursor::new(prog))
+        .map_err(|e| Error::new(ErrorKind::Other, format!("Error: invalid ELF: {:?}", e)))?;
+    let text_index = elf
+        .sections
+        .iter()
+        .position(|section| section.shdr.name == ".text")
+        .ok_or_else(|| Error::new(ErrorKind::Other, "Error: no .text section"))?;
+    let text = &elf.sections[text_index];
+
+    let mut entrypoints = vec![];
+    for section in elf.sections.iter().filter(|section| {
+        section.shdr.shtype == elf::types::SHT_SYMTAB
+            || section.shdr.shtype == elf::types::SHT_DYNSYM
+    }) {
+        let symbols = elf.get_symbols(section).map_err(|e| {
+            Error::new(ErrorKind::Other, format!("Error: invalid symbols: {:?}", e))
+        })?;
+        for symbol in symbols {
+            if symbol.symtype == elf::types::STT_FUNC
+                && symbol.shndx as usize == text_index
+                && symbol.value >= text.shdr.addr
+            {
+                let offset = (symbol.value - text.shdr.addr) as usize;
+                entrypoints.push(offset / ebpf::INSN_SIZE);
+            }
+        }
+    }
+    if entrypoints.is_empty() {
+        entrypoints.push(0);
+    }
+    bpf_verifier::check_cfg(&text.data, &entrypoints)
+}
+
+/// Load the ELF, applying its relocations and verifying it, so that a program that can't
+/// run is never made executable
+fn check_program(prog: &[u8]) -> Result<(), Error> {
+    check_imports(prog)?;
+    let stack_depth = check_control_flow(prog)?;
+    debug!("program uses {} bytes of stack", stack_depth);
+    create_vm(prog)?;
+    Ok(())
+}
+
+/// Check that `programdata` is the program-data account of the upgradeable program `program`.
+/// Returns the upgrade authority and the program's ELF
+fn check_programdata<'a>(
+    loader_id: &Pubkey,
+    program: &KeyedAccount,
+    programdata: &'a KeyedAccount,
+) -> Result<(Option<Pubkey>, &'a [u8]), ProgramError> {
+    if get_programdata_address(&program.account) != Some(*programdata.unsigned_key())
+        || programdata.account.owner != *loader_id
+    {
+        warn!(
+            "account {:?} is not the program data of {:?}",
+            programdata.unsigned_key(),
+            program.unsigned_key()
+        );
+        Err(ProgramError::InvalidArgument)?;
+    }
+    let (program_address, upgrade_authority, elf) =
+        deserialize_programdata(&programdata.account.userdata)?;
+    if program_address != *program.unsigned_key() {
+        warn!(
+            "account {:?} is the program data of {:?}, not {:?}",
+            programdata.unsigned_key(),
+            program_address,
+            program.unsigned_key()
+        );
+        Err(ProgramError::InvalidArgument)?;
+    }
+    Ok((upgrade_authority, elf))
+}
+
+/// Check that `program` is an upgradeable program of this loader with the program data
+/// `programdata`, whose upgrade authority is `authority`, and that the authority signed.
+/// Returns the upgrade authority
+fn check_upgrade_authority(
+    loader_id: &Pubkey,
+    program: &KeyedAccount,
+    programdata: &KeyedAccount,
+    authority: &KeyedAccount,
+) -> Result<Option<Pubkey>, ProgramError> {
+    if !program.account.executable || program.account.loader != *loader_id {
+        warn!("account {:?} is not a program", program.unsigned_key());
+        Err(ProgramError::InvalidArgument)?;
+    }
+    let (upgrade_authority, _) = check_programdata(loader_id, program, programdata)?;
+    if upgrade_authority.is_none() || authority.signer_key() != upgrade_authority.as_ref() {
+        warn!(
+            "program {:?} can't be upgraded by {:?}",
+            program.unsigned_key(),
+            authority.unsigned_key()
+        );
+        Err(ProgramError::InvalidArgument)?;
+    }
+    Ok(upgrade_authority)
+}
+
 fn serialize_parameters(
     program_id: &Pubkey,
     keyed_accounts: &mut [KeyedAccount],
@@ -146,8 +459,22 @@ fn entrypoint(
     solana_logger::setup();
 
     if keyed_accounts[0].account.executable {
-        let (progs, params) = keyed_accounts.split_at_mut(1);
-        let prog = &progs[0].account.userdata;
+        // The runtime passes an upgradeable program's program-data account right after it
+        let num_programs = if get_programdata_address(&keyed_accounts[0].account).is_some() {
+            2
+        } else {
+            1
+        };
+        if keyed_accounts.len() < num_programs {
+            warn!("program data account is missing");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let (progs, params) = keyed_accounts.split_at_mut(num_programs);
+        let prog = if num_programs == 2 {
+            check_programdata(program_id, &progs[0], &progs[1])?.1
+        } else {
+            &progs[0].account.userdata[..]
+        };
         info!("Call BPF program");
         //dump_program(keyed_accounts[0].key, prog);
         let mut vm = match create_vm(prog) {
@@ -158,6 +485,7 @@ fn entrypoint(
             }
         };
         let mut v = serialize_parameters(program_id, params, &tx_data, tick_height);
+        reset_helpers(tick_height);
         match vm.execute_program(v.as_mut_slice()) {
             Ok(status) => {
                 if 0 == status {
@@ -181,6 +509,11 @@ fn entrypoint(
         }
         match instruction {
             LoaderInstruction::Write { offset, bytes } => {
+                // Only the loader may change an upgradeable program's state, or its code
+                if is_upgradeable_state(&keyed_accounts[0].account.userdata) {
+                    warn!("Write: account holds an upgradeable program");
+                    return Err(ProgramError::InvalidArgument);
+                }
                 let offset = offset as usize;
                 let len = bytes.len();
                 debug!("Write: offset={} length={}", offset, len);
@@ -195,12 +528,92 @@ fn entrypoint(
                 keyed_accounts[0].account.userdata[offset..offset + len].copy_from_slice(&bytes);
             }
             LoaderInstruction::Finalize => {
+                if let Err(e) = check_program(&keyed_accounts[0].account.userdata) {
+                    warn!("Finalize: invalid program: {}", e);
+                    return Err(ProgramError::GenericError);
+                }
+                if keyed_accounts.len() > 1 {
+                    // Make the program upgradeable, moving its code into the program data
+                    if keyed_accounts.len() < 3
+                        || keyed_accounts[1].signer_key().is_none()
+                        || keyed_accounts[1].account.owner != *program_id
+                        || !keyed_accounts[1].account.userdata.is_empty()
+                    {
+                        warn!("Finalize: expected empty program data and an authority");
+                        return Err(ProgramError::InvalidArgument);
+                    }
+                    let program_address = *keyed_accounts[0].unsigned_key();
+                    let programdata_address = *keyed_accounts[1].unsigned_key();
+                    let upgrade_authority = Some(*keyed_accounts[2].unsigned_key());
+                    let state = UpgradeableLoaderState::Program {
+                        programdata_address,
+                    };
+                    let elf = mem::replace(
+                        &mut keyed_accounts[0].account.userdata,
+                        bincode::serialize(&state).unwrap(),
+                    );
+                    keyed_accounts[1].account.userdata =
+                        serialize_programdata(&program_address, &upgrade_authority, &elf);
+                }
                 keyed_accounts[0].account.executable = true;
                 info!(
                     "Finalize: account {:?}",
                     keyed_accounts[0].signer_key().unwrap()
                 );
             }
+            LoaderInstruction::Upgrade => {
+                if keyed_accounts.len() < 4 {
+                    warn!(
+                        "Upgrade: expected a buffer, a program, its program data and an authority"
+                    );
+                    return Err(ProgramError::InvalidArgument);
+                }
+                let upgrade_authority = check_upgrade_authority(
+                    program_id,
+                    &keyed_accounts[1],
+                    &keyed_accounts[2],
+                    &keyed_accounts[3],
+                )?;
+                if let Err(e) = check_program(&keyed_accounts[0].account.userdata) {
+                    warn!("Upgrade: invalid program: {}", e);
+                    return Err(ProgramError::GenericError);
+                }
+                let userdata = serialize_programdata(
+                    keyed_accounts[1].unsigned_key(),
+                    &upgrade_authority,
+                    &keyed_accounts[0].account.userdata,
+                );
+                keyed_accounts[2].account.userdata = userdata;
+
+                // Close the buffer
+                keyed_accounts[3].account.tokens += keyed_accounts[0].account.tokens;
+                keyed_accounts[0].account.tokens = 0;
+                keyed_accounts[0].account.userdata.clear();
+                info!("Upgrade: program {:?}", keyed_accounts[1].unsigned_key());
+            }
+            LoaderInstruction::SetUpgradeAuthority(new_authority) => {
+                if keyed_accounts.len() < 3 {
+                    warn!("SetUpgradeAuthority: expected an authority, a program and program data");
+                    return Err(ProgramError::InvalidArgument);
+                }
+                check_upgrade_authority(
+                    program_id,
+                    &keyed_accounts[1],
+                    &keyed_accounts[2],
+                    &keyed_accounts[0],
+                )?;
+                let userdata = {
+                    let (_, elf) =
+                        check_programdata(program_id, &keyed_accounts[1], &keyed_accounts[2])?;
+                    serialize_programdata(keyed_accounts[1].unsigned_key(), &new_authority, elf)
+                };
+                keyed_accounts[2].account.userdata = userdata;
+                info!(
+                    "SetUpgradeAuthority: program {:?} authority {:?}",
+                    keyed_accounts[1].unsigned_key(),
+                    new_authority
+                );
+            }
         }
     } else {
         warn!("Invalid program transaction: {:?}", tx_data);
@@ -213,6 +626,9 @@ fn entrypoint(
 mod tests {
     use super::*;
     use solana_rbpf::helpers;
+    use solana_sdk::account::Account;
+    use solana_sdk::bpf_loader;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
 
     #[test]
     #[should_panic(expected = "Error: Execution exceeded maximum number of instructions")]
@@ -240,4 +656,266 @@ mod tests {
             .unwrap();
         vm.execute_program(input).unwrap();
     }
+
+    #[test]
+    fn test_finalize_invalid_elf() {
+        let program_id = Pubkey::new(&[1; 32]);
+        let mut program_account = Account::new(1, 0, bpf_loader::id());
+        program_account.userdata = vec![0xde; 64];
+
+        let mut keyed_accounts = [KeyedAccount::new(&program_id, true, &mut program_account)];
+        let data = bincode::serialize(&LoaderInstruction::Finalize).unwrap();
+        assert_eq!(
+            entrypoint(&bpf_loader::id(), &mut keyed_accounts, &data, 0),
+            Err(ProgramError::GenericError)
+        );
+        assert!(!program_account.executable);
+    }
+
+    #[cfg(feature = "bpf_c")]
+    fn read_bpf_program(name: &str) -> Vec<u8> {
+        use std::env;
+        use std::fs::File;
+        use std::path::PathBuf;
+
+        let mut pathbuf = {
+            let current_exe = env::current_exe().unwrap();
+            PathBuf::from(current_exe.parent().unwrap().parent().unwrap())
+        };
+        pathbuf.push("bpf/");
+        pathbuf.push(name);
+        pathbuf.set_extension("so");
+        let mut file = File::open(pathbuf).expect("file open failed");
+        let mut elf = Vec::new();
+        file.read_to_end(&mut elf).unwrap();
+        elf
+    }
+
+    #[cfg(feature = "bpf_c")]
+    #[test]
+    fn test_finalize_elf() {
+        // Both programs log strings from .rodata, and relative_call makes a relocated call
+        for program in ["multiple_static", "relative_call"].iter() {
+            let program_id = Pubkey::new(&[1; 32]);
+            let mut program_account = Account::new(1, 0, bpf_loader::id());
+            program_account.userdata = read_bpf_program(program);
+
+            let mut keyed_accounts = [KeyedAccount::new(&program_id, true, &mut program_account)];
+            let data = bincode::serialize(&LoaderInstruction::Finalize).unwrap();
+            entrypoint(&bpf_loader::id(), &mut keyed_accounts, &data, 0).unwrap();
+            assert!(program_account.executable);
+        }
+    }
+
+    #[test]
+    fn test_helper_sol_sha256() {
+        reset_helpers(0);
+        let data = b"order book";
+        let mut result = [0; 32];
+        let data_addr = data.as_ptr() as u64;
+        let result_addr = result.as_mut_ptr() as u64;
+        let ro_regions = [MemoryRegion::new_from_slice(data)];
+        let rw_regions = [MemoryRegion::new_from_slice(&result)];
+
+        // The result must be writable
+        assert!(
+            helper_sol_sha256_verify(data_addr, 10, data_addr, 0, 0, &ro_regions, &rw_regions)
+                .is_err()
+        );
+        assert!(helper_sol_sha256_verify(
+            data_addr,
+            11,
+            result_addr,
+            0,
+            0,
+            &ro_regions,
+            &rw_regions
+        )
+        .is_err());
+        helper_sol_sha256_verify(data_addr, 10, result_addr, 0, 0, &ro_regions, &rw_regions)
+            .unwrap();
+        assert_eq!(helper_sol_sha256(data_addr, 10, result_addr, 0, 0), 0);
+        assert_eq!(&result, hash(data).as_ref());
+    }
+
+    #[test]
+    fn test_helper_sol_verify_ed25519() {
+        let keypair = Keypair::new();
+        let message = b"order book";
+        let signature = keypair.sign_message(message);
+        let pubkey = keypair.pubkey();
+        let verify = |message: &[u8]| {
+            helper_sol_verify_ed25519(
+                message.as_ptr() as u64,
+                message.len() as u64,
+                signature.as_ref().as_ptr() as u64,
+                pubkey.as_ref().as_ptr() as u64,
+                0,
+            )
+        };
+        assert_eq!(verify(message), 1);
+        assert_eq!(verify(b"order boot"), 0);
+
+        // Verification is metered
+        reset_helpers(0);
+        let ro_regions = [
+            MemoryRegion::new_from_slice(message),
+            MemoryRegion::new_from_slice(signature.as_ref()),
+            MemoryRegion::new_from_slice(pubkey.as_ref()),
+        ];
+        let verify_verify = || {
+            helper_sol_verify_ed25519_verify(
+                message.as_ptr() as u64,
+                message.len() as u64,
+                signature.as_ref().as_ptr() as u64,
+                pubkey.as_ref().as_ptr() as u64,
+                0,
+                &ro_regions,
+                &[],
+            )
+        };
+        for _ in 0..HELPER_BUDGET / VERIFY_ED25519_COST {
+            verify_verify().unwrap();
+        }
+        assert!(verify_verify().is_err());
+    }
+
+    #[test]
+    fn test_helper_sol_get_tick_height() {
+        reset_helpers(42);
+        helper_sol_get_tick_height_verify(0, 0, 0, 0, 0, &[], &[]).unwrap();
+        assert_eq!(helper_sol_get_tick_height(0, 0, 0, 0, 0), 42);
+    }
+
+    fn set_upgrade_authority(
+        authority_id: &Pubkey,
+        is_signer: bool,
+        authority_account: &mut Account,
+        program_account: &mut Account,
+        programdata_id: &Pubkey,
+        programdata_account: &mut Account,
+        new_authority: Option<Pubkey>,
+    ) -> Result<(), ProgramError> {
+        let program_id = Pubkey::new(&[1; 32]);
+        let mut keyed_accounts = [
+            KeyedAccount::new(authority_id, is_signer, authority_account),
+            KeyedAccount::new(&program_id, false, program_account),
+            KeyedAccount::new(programdata_id, false, programdata_account),
+        ];
+        let data =
+            bincode::serialize(&LoaderInstruction::SetUpgradeAuthority(new_authority)).unwrap();
+        entrypoint(&bpf_loader::id(), &mut keyed_accounts, &data, 0)
+    }
+
+    fn create_upgradeable_program(
+        programdata_id: &Pubkey,
+        authority_id: &Pubkey,
+        elf: &[u8],
+    ) -> (Account, Account) {
+        let mut program_account = Account::new(1, 0, Pubkey::new(&[1; 32]));
+        program_account.userdata = bincode::serialize(&UpgradeableLoaderState::Program {
+            programdata_address: *programdata_id,
+        })
+        .unwrap();
+        program_account.executable = true;
+        program_account.loader = bpf_loader::id();
+
+        let mut programdata_account = Account::new(1, 0, bpf_loader::id());
+        programdata_account.userdata =
+            serialize_programdata(&Pubkey::new(&[1; 32]), &Some(*authority_id), elf);
+        (program_account, programdata_account)
+    }
+
+    #[test]
+    fn test_set_upgrade_authority() {
+        let elf = [0x7f, b'E', b'L', b'F'];
+        let authority_id = Pubkey::new(&[2; 32]);
+        let programdata_id = Pubkey::new(&[3; 32]);
+        let mut authority_account = Account::new(1, 0, Pubkey::default());
+        let (mut program_account, mut programdata_account) =
+            create_upgradeable_program(&programdata_id, &authority_id, &elf);
+
+        // The upgrade authority must sign
+        assert_eq!(
+            set_upgrade_authority(
+                &authority_id,
+                false,
+                &mut authority_account,
+                &mut program_account,
+                &programdata_id,
+                &mut programdata_account,
+                None
+            ),
+            Err(ProgramError::GenericError)
+        );
+
+        // The program data must be the program's own
+        let other_programdata_id = Pubkey::new(&[4; 32]);
+        let (_, mut other_programdata_account) =
+            create_upgradeable_program(&other_programdata_id, &authority_id, &elf);
+        assert_eq!(
+            set_upgrade_authority(
+                &authority_id,
+                true,
+                &mut authority_account,
+                &mut program_account,
+                &other_programdata_id,
+                &mut other_programdata_account,
+                None
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // Once the program is immutable, it has no upgrade authority to change that
+        set_upgrade_authority(
+            &authority_id,
+            true,
+            &mut authority_account,
+            &mut program_account,
+            &programdata_id,
+            &mut programdata_account,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            deserialize_programdata(&programdata_account.userdata),
+            Ok((Pubkey::new(&[1; 32]), None, &elf[..]))
+        );
+        assert_eq!(
+            set_upgrade_authority(
+                &authority_id,
+                true,
+                &mut authority_account,
+                &mut program_account,
+                &programdata_id,
+                &mut programdata_account,
+                Some(authority_id)
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_write_programdata() {
+        let elf = [0x7f, b'E', b'L', b'F'];
+        let programdata_id = Pubkey::new(&[3; 32]);
+        let (_, mut programdata_account) =
+            create_upgradeable_program(&programdata_id, &Pubkey::new(&[2; 32]), &elf);
+
+        // Only an upgrade can replace the code of an upgradeable program
+        let mut keyed_accounts = [KeyedAccount::new(
+            &programdata_id,
+            true,
+            &mut programdata_account,
+        )];
+        let data = bincode::serialize(&LoaderInstruction::Write {
+            offset: 0,
+            bytes: vec![0; 4],
+        })
+        .unwrap();
+        assert_eq!(
+            entrypoint(&bpf_loader::id(), &mut keyed_accounts, &data, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
 }
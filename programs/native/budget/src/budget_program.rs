@@ -1,33 +1,47 @@
 //! budget program
-use bincode::{self, deserialize, serialize_into, serialized_size};
-use chrono::prelude::{DateTime, Utc};
+use bincode::{self, deserialize, deserialize_from, serialize, serialize_into};
 use log::*;
 use serde_derive::{Deserialize, Serialize};
 use solana_sdk::account::KeyedAccount;
 use solana_sdk::budget_expr::BudgetExpr;
 use solana_sdk::budget_instruction::Instruction;
-use solana_sdk::payment_plan::Witness;
+use solana_sdk::budget_program::BudgetError;
+use solana_sdk::payment_plan::{Payment, Witness};
+use solana_sdk::pubkey::Pubkey;
 use std::io;
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub enum BudgetError {
-    InsufficientFunds,
-    ContractAlreadyExists,
-    ContractNotPending,
-    SourceIsPendingContract,
-    UninitializedContract,
-    NegativeTokens,
-    DestinationMissing,
-    FailedWitness,
-    UserdataTooSmall,
-    UserdataDeserializeFailure,
-    UnsignedKey,
-}
-
+/// The state of a contract account. It is stored as `initialized` and `pending_budget`,
+/// the layout contracts have always had, followed by `signers` only if there are any,
+/// so that contracts created before `signers` existed still deserialize and still fit
+/// in their accounts
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct BudgetProgram {
     pub initialized: bool,
     pub pending_budget: Option<BudgetExpr>,
+    /// Signers counted towards a threshold condition of `pending_budget` so far
+    pub signers: Vec<Pubkey>,
+}
+
+fn apply_witness(
+    keyed_accounts: &mut [KeyedAccount],
+    witness: &Witness,
+    witness_name: &str,
+) -> Result<(), BudgetError> {
+    if let Ok(mut program) = BudgetProgram::deserialize(&keyed_accounts[1].account.userdata) {
+        if !program.is_pending() {
+            Err(BudgetError::ContractNotPending)
+        } else if !program.initialized {
+            trace!("contract is uninitialized");
+            Err(BudgetError::UninitializedContract)
+        } else {
+            trace!("apply {}", witness_name);
+            program.apply_witness(keyed_accounts, witness)?;
+            trace!("apply {} committed", witness_name);
+            program.serialize(&mut keyed_accounts[1].account.userdata)
+        }
+    } else {
+        Err(BudgetError::UninitializedContract)
+    }
 }
 
 fn apply_debits(
@@ -41,6 +55,10 @@ fn apply_debits(
     match instruction {
         Instruction::NewBudget(expr) => {
             let expr = expr.clone();
+            if !expr.is_satisfiable() {
+                trace!("budget can never be satisfied");
+                return Err(BudgetError::UnsatisfiableBudget);
+            }
             if let Some(payment) = expr.final_payment() {
                 keyed_accounts[1].account.tokens += payment.tokens;
                 Ok(())
@@ -60,41 +78,16 @@ fn apply_debits(
             }
         }
         Instruction::ApplyTimestamp(dt) => {
-            if let Ok(mut program) = BudgetProgram::deserialize(&keyed_accounts[1].account.userdata)
-            {
-                if !program.is_pending() {
-                    Err(BudgetError::ContractNotPending)
-                } else if !program.initialized {
-                    trace!("contract is uninitialized");
-                    Err(BudgetError::UninitializedContract)
-                } else {
-                    trace!("apply timestamp");
-                    program.apply_timestamp(keyed_accounts, *dt)?;
-                    trace!("apply timestamp committed");
-                    program.serialize(&mut keyed_accounts[1].account.userdata)
-                }
-            } else {
-                Err(BudgetError::UninitializedContract)
-            }
+            apply_witness(keyed_accounts, &Witness::Timestamp(*dt), "timestamp")
         }
         Instruction::ApplySignature => {
-            if let Ok(mut program) = BudgetProgram::deserialize(&keyed_accounts[1].account.userdata)
-            {
-                if !program.is_pending() {
-                    Err(BudgetError::ContractNotPending)
-                } else if !program.initialized {
-                    trace!("contract is uninitialized");
-                    Err(BudgetError::UninitializedContract)
-                } else {
-                    trace!("apply signature");
-                    program.apply_signature(keyed_accounts)?;
-                    trace!("apply signature committed");
-                    program.serialize(&mut keyed_accounts[1].account.userdata)
-                }
-            } else {
-                Err(BudgetError::UninitializedContract)
-            }
+            apply_witness(keyed_accounts, &Witness::Signature, "signature")
         }
+        Instruction::ApplyPreimage(preimage) => apply_witness(
+            keyed_accounts,
+            &Witness::Preimage(preimage.clone()),
+            "preimage",
+        ),
     }
 }
 
@@ -135,65 +128,57 @@ impl BudgetProgram {
     fn is_pending(&self) -> bool {
         self.pending_budget != None
     }
-    /// Process a Witness Signature. Any payment plans waiting on this signature
-    /// will progress one step.
-    fn apply_signature(&mut self, keyed_accounts: &mut [KeyedAccount]) -> Result<(), BudgetError> {
-        let mut final_payment = None;
-        if let Some(ref mut expr) = self.pending_budget {
-            let key = match keyed_accounts[0].signer_key() {
-                None => return Err(BudgetError::UnsignedKey),
-                Some(key) => key,
-            };
-            expr.apply_witness(&Witness::Signature, key);
-            final_payment = expr.final_payment();
-        }
-
-        if let Some(payment) = final_payment {
-            if &payment.to != keyed_accounts[2].unsigned_key() {
-                trace!("destination missing");
-                return Err(BudgetError::DestinationMissing);
-            }
-            self.pending_budget = None;
-            keyed_accounts[1].account.tokens -= payment.tokens;
-            keyed_accounts[2].account.tokens += payment.tokens;
+    /// Move `payment` out of the contract and into its destination account.
+    fn apply_payment(
+        keyed_accounts: &mut [KeyedAccount],
+        payment: &Payment,
+    ) -> Result<(), BudgetError> {
+        if &payment.to != keyed_accounts[2].unsigned_key() {
+            trace!("destination missing");
+            return Err(BudgetError::DestinationMissing);
         }
+        keyed_accounts[1].account.tokens -= payment.tokens;
+        keyed_accounts[2].account.tokens += payment.tokens;
         Ok(())
     }
 
-    /// Process a Witness Timestamp. Any payment plans waiting on this timestamp
-    /// will progress one step.
-    fn apply_timestamp(
+    /// Process a Witness. Any payment plans waiting on this witness will progress
+    /// one step, and any scheduled tranches it releases are paid out.
+    fn apply_witness(
         &mut self,
         keyed_accounts: &mut [KeyedAccount],
-        dt: DateTime<Utc>,
+        witness: &Witness,
     ) -> Result<(), BudgetError> {
-        // Check to see if any timelocked transactions can be completed.
         let mut final_payment = None;
+        let mut released_payment = None;
 
         if let Some(ref mut expr) = self.pending_budget {
             let key = match keyed_accounts[0].signer_key() {
                 None => return Err(BudgetError::UnsignedKey),
                 Some(key) => key,
             };
-            expr.apply_witness(&Witness::Timestamp(dt), key);
+            expr.apply_witness_with_signers(witness, key, &mut self.signers);
             final_payment = expr.final_payment();
+            released_payment = expr.take_released_payment();
         }
 
+        if let Some(payment) = released_payment {
+            Self::apply_payment(keyed_accounts, &payment)?;
+        }
         if let Some(payment) = final_payment {
-            if &payment.to != keyed_accounts[2].unsigned_key() {
-                trace!("destination missing");
-                return Err(BudgetError::DestinationMissing);
-            }
+            Self::apply_payment(keyed_accounts, &payment)?;
             self.pending_budget = None;
-            keyed_accounts[1].account.tokens -= payment.tokens;
-            keyed_accounts[2].account.tokens += payment.tokens;
         }
         Ok(())
     }
 
     fn serialize(&self, output: &mut [u8]) -> Result<(), BudgetError> {
-        let len = serialized_size(self).unwrap() as u64;
-        if output.len() < len as usize {
+        let mut state = serialize(&(self.initialized, &self.pending_budget)).unwrap();
+        if !self.signers.is_empty() {
+            serialize_into(&mut state, &self.signers).unwrap();
+        }
+        let len = state.len() as u64;
+        if output.len() < 8 + len as usize {
             warn!(
                 "{} bytes required to serialize, only have {} bytes",
                 len,
@@ -206,10 +191,7 @@ impl BudgetProgram {
             serialize_into(writer, &len).unwrap();
         }
 
-        {
-            let writer = io::BufWriter::new(&mut output[8..8 + len as usize]);
-            serialize_into(writer, self).unwrap();
-        }
+        output[8..8 + len as usize].copy_from_slice(&state);
         Ok(())
     }
 
@@ -224,7 +206,18 @@ impl BudgetProgram {
         if input.len() < 8 + len as usize {
             return Err(Box::new(bincode::ErrorKind::SizeLimit));
         }
-        deserialize(&input[8..8 + len as usize])
+        let mut state = &input[8..8 + len as usize];
+        let (initialized, pending_budget) = deserialize_from(&mut state)?;
+        let signers = if state.is_empty() {
+            vec![]
+        } else {
+            deserialize_from(&mut state)?
+        };
+        Ok(BudgetProgram {
+            initialized,
+            pending_budget,
+            signers,
+        })
     }
 }
 
@@ -232,10 +225,11 @@ impl BudgetProgram {
 mod test {
     use super::*;
     use bincode::serialize;
+    use chrono::prelude::Utc;
     use solana_sdk::account::Account;
     use solana_sdk::budget_program::*;
     use solana_sdk::budget_transaction::BudgetTransaction;
-    use solana_sdk::hash::Hash;
+    use solana_sdk::hash::{hash, Hash};
     use solana_sdk::signature::{Keypair, KeypairUtil};
     use solana_sdk::transaction::{Instruction, Transaction};
 
@@ -268,12 +262,45 @@ mod test {
         let mut a = Account::new(0, 512, id());
         let b = BudgetProgram::default();
         b.serialize(&mut a.userdata).unwrap();
-        let buf = serialize(&b).unwrap();
+        let buf = serialize(&(false, None::<BudgetExpr>)).unwrap();
+        assert_eq!(a.userdata[..8], serialize(&(buf.len() as u64)).unwrap()[..]);
         assert_eq!(a.userdata[8..8 + buf.len()], buf[0..]);
         let c = BudgetProgram::deserialize(&a.userdata).unwrap();
         assert_eq!(b, c);
     }
 
+    #[test]
+    fn test_serializer_signers() {
+        let mut a = Account::new(0, 512, id());
+        let b = BudgetProgram {
+            initialized: true,
+            pending_budget: None,
+            signers: vec![Keypair::new().pubkey()],
+        };
+        b.serialize(&mut a.userdata).unwrap();
+        let c = BudgetProgram::deserialize(&a.userdata).unwrap();
+        assert_eq!(b, c);
+    }
+
+    #[test]
+    fn test_deserialize_legacy_contract() {
+        // Contracts written before `signers` existed hold only these two fields
+        let expr = BudgetExpr::new_payment(1, Keypair::new().pubkey());
+        let state = serialize(&(true, Some(expr.clone()))).unwrap();
+        let mut userdata = serialize(&(state.len() as u64)).unwrap();
+        userdata.extend_from_slice(&state);
+        userdata.resize(512, 0);
+        let program = BudgetProgram::deserialize(&userdata).unwrap();
+        assert_eq!(
+            program,
+            BudgetProgram {
+                initialized: true,
+                pending_budget: Some(expr),
+                signers: vec![],
+            }
+        );
+    }
+
     #[test]
     fn test_serializer_userdata_too_small() {
         let mut a = Account::new(0, 1, id());
@@ -525,6 +552,272 @@ mod test {
         assert_eq!(accounts[pay_account].tokens, 1);
     }
 
+    #[test]
+    fn test_threshold_multisig_transfer() {
+        let mut accounts = vec![
+            Account::new(1, 0, id()),
+            Account::new(0, 512, id()),
+            Account::new(0, 0, id()),
+        ];
+        let contract_account = 1;
+        let to_account = 2;
+        let from = Keypair::new();
+        let contract = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let witnesses: Vec<_> = (0..5).map(|_| Keypair::new()).collect();
+        let tx = Transaction::budget_new_when_multisigned(
+            &from,
+            to,
+            contract,
+            3,
+            witnesses.iter().map(|witness| witness.pubkey()).collect(),
+            None,
+            1,
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+
+        // Signing twice, or signing with a key that isn't a witness, doesn't count
+        let rando = Keypair::new();
+        for keypair in &[&witnesses[1], &witnesses[1], &rando, &witnesses[3]] {
+            let tx = Transaction::budget_new_signature(keypair, contract, to, Hash::default());
+            process_transaction(&tx, &mut accounts).unwrap();
+        }
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(program.is_pending());
+        assert_eq!(
+            program.signers,
+            vec![witnesses[1].pubkey(), witnesses[3].pubkey()]
+        );
+        assert_eq!(accounts[contract_account].tokens, 1);
+        assert_eq!(accounts[to_account].tokens, 0);
+
+        let tx = Transaction::budget_new_signature(&witnesses[0], contract, to, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(!program.is_pending());
+        assert!(program.signers.is_empty());
+        assert_eq!(accounts[contract_account].tokens, 0);
+        assert_eq!(accounts[to_account].tokens, 1);
+    }
+
+    #[test]
+    fn test_unsatisfiable_threshold() {
+        let mut accounts = vec![Account::new(1, 0, id()), Account::new(0, 512, id())];
+        let from = Keypair::new();
+        let contract = Keypair::new().pubkey();
+        let witness = Keypair::new().pubkey();
+        let tx = Transaction::budget_new_when_multisigned(
+            &from,
+            Keypair::new().pubkey(),
+            contract,
+            2,
+            vec![witness],
+            None,
+            1,
+            Hash::default(),
+        );
+        assert_eq!(
+            process_transaction(&tx, &mut accounts),
+            Err(BudgetError::UnsatisfiableBudget)
+        );
+        assert_eq!(accounts[0].tokens, 1);
+    }
+
+    #[test]
+    fn test_state_size() {
+        let witnesses = vec![Keypair::new().pubkey(); 3];
+        let expr =
+            BudgetExpr::new_threshold_multisig_payment(2, witnesses.clone(), 1, witnesses[0]);
+        let program = BudgetProgram {
+            initialized: true,
+            pending_budget: Some(expr.clone()),
+            signers: witnesses,
+        };
+        let state_size = get_state_size(&expr, 3);
+        let mut userdata = vec![0; state_size as usize];
+        program.serialize(&mut userdata).unwrap();
+        assert_eq!(BudgetProgram::deserialize(&userdata).unwrap(), program);
+        assert_eq!(
+            program.serialize(&mut vec![0; state_size as usize - 1]),
+            Err(BudgetError::UserdataTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_recurring_transfer() {
+        let mut accounts = vec![
+            Account::new(10, 0, id()),
+            Account::new(0, 512, id()),
+            Account::new(0, 0, id()),
+        ];
+        let from_account = 0;
+        let contract_account = 1;
+        let to_account = 2;
+        let from = Keypair::new();
+        let contract = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let start = Utc::now();
+        let tx = Transaction::budget_new_recurring(
+            &from,
+            to,
+            contract,
+            start,
+            60,
+            3,
+            from.pubkey(),
+            10,
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 10);
+
+        // Two periods in, two tranches are paid out
+        let dt = start + chrono::Duration::seconds(60);
+        let tx = Transaction::budget_new_timestamp(&from, contract, to, dt, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 4);
+        assert_eq!(accounts[to_account].tokens, 6);
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(program.is_pending());
+
+        // Replaying the timestamp doesn't pay the same tranches twice
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 4);
+        assert_eq!(accounts[to_account].tokens, 6);
+
+        // The payer cancels, and the rest goes back to them. Unit test hack, the
+        // destination account is keyed with `from` to avoid creating more accounts
+        let tx = Transaction::budget_new_cancel(&from, contract, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[from_account].tokens, 0);
+        assert_eq!(accounts[contract_account].tokens, 0);
+        assert_eq!(accounts[to_account].tokens, 10);
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(!program.is_pending());
+    }
+
+    #[test]
+    fn test_vesting_transfer() {
+        let mut accounts = vec![
+            Account::new(10, 0, id()),
+            Account::new(0, 512, id()),
+            Account::new(0, 0, id()),
+        ];
+        let contract_account = 1;
+        let to_account = 2;
+        let from = Keypair::new();
+        let contract = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let start = Utc::now();
+        let tx = Transaction::budget_new_vesting(
+            &from,
+            to,
+            contract,
+            start,
+            60,
+            6,
+            None,
+            from.pubkey(),
+            None,
+            10,
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+
+        // Five tranches in, a token per tranche is paid out
+        let dt = start + chrono::Duration::seconds(4 * 60);
+        let tx = Transaction::budget_new_timestamp(&from, contract, to, dt, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 5);
+        assert_eq!(accounts[to_account].tokens, 5);
+
+        // The last tranche pays out the remainder
+        let dt = start + chrono::Duration::seconds(5 * 60);
+        let tx = Transaction::budget_new_timestamp(&from, contract, to, dt, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 0);
+        assert_eq!(accounts[to_account].tokens, 10);
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(!program.is_pending());
+    }
+
+    #[test]
+    fn test_hash_time_locked_transfer() {
+        let mut accounts = vec![
+            Account::new(1, 0, id()),
+            Account::new(0, 512, id()),
+            Account::new(0, 0, id()),
+        ];
+        let from_account = 0;
+        let contract_account = 1;
+        let to_account = 2;
+        let from = Keypair::new();
+        let contract = Keypair::new().pubkey();
+        let to = Keypair::new();
+        let oracle = Keypair::new();
+        let preimage = b"swap secret".to_vec();
+        let dt = Utc::now();
+        let tx = Transaction::budget_new_when_hash_locked(
+            &from,
+            to.pubkey(),
+            contract,
+            hash(&preimage),
+            Some((dt, oracle.pubkey(), from.pubkey())),
+            1,
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+        let original_accounts = accounts.clone();
+
+        // The wrong preimage doesn't unlock anything
+        let tx = Transaction::budget_new_preimage(
+            &to,
+            contract,
+            to.pubkey(),
+            b"guess".to_vec(),
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 1);
+        assert_eq!(accounts[to_account].tokens, 0);
+
+        let tx = Transaction::budget_new_preimage(
+            &to,
+            contract,
+            to.pubkey(),
+            preimage.clone(),
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 0);
+        assert_eq!(accounts[to_account].tokens, 1);
+        let program = BudgetProgram::deserialize(&accounts[contract_account].userdata).unwrap();
+        assert!(!program.is_pending());
+
+        // The payer can't witness the refund time itself
+        let mut accounts = original_accounts;
+        let tx =
+            Transaction::budget_new_timestamp(&from, contract, from.pubkey(), dt, Hash::default());
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[contract_account].tokens, 1);
+        assert_eq!(accounts[to_account].tokens, 0);
+
+        // Nobody revealed the preimage, so the payer takes the refund. Unit test
+        // hack, the destination account is keyed with `from`
+        let tx = Transaction::budget_new_timestamp(
+            &oracle,
+            contract,
+            from.pubkey(),
+            dt,
+            Hash::default(),
+        );
+        process_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[from_account].tokens, 0);
+        assert_eq!(accounts[contract_account].tokens, 0);
+        assert_eq!(accounts[to_account].tokens, 1);
+    }
+
     #[test]
     fn test_userdata_too_small() {
         let mut accounts = vec![
@@ -5,8 +5,10 @@ use solana_sdk::account::KeyedAccount;
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::solana_entrypoint;
+use solana_sdk::token_program::TokenProgram;
 
 mod token_program;
+use crate::token_program::TokenProcessor;
 
 solana_entrypoint!(entrypoint);
 fn entrypoint(
@@ -17,8 +19,8 @@ fn entrypoint(
 ) -> Result<(), ProgramError> {
     solana_logger::setup();
 
-    token_program::TokenProgram::process(program_id, info, input).map_err(|err| {
+    TokenProgram::process(program_id, info, input).map_err(|err| {
         error!("error: {:?}", err);
-        ProgramError::GenericError
+        ProgramError::custom(&err)
     })
 }
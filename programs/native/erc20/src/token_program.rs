@@ -2,176 +2,90 @@
 
 use bincode;
 use log::*;
-use serde_derive::{Deserialize, Serialize};
 use solana_sdk::account::KeyedAccount;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::token_program::TokenError as Error;
+use solana_sdk::token_program::{
+    TokenAccountDelegateInfo, TokenAccountInfo, TokenInfo, TokenInstruction, TokenProgram,
+};
 use std;
 
-#[derive(Debug, PartialEq)]
-pub enum Error {
-    InvalidArgument,
-    InsufficentFunds,
-    NotOwner,
-}
+pub type Result<T> = std::result::Result<T, Error>;
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "error")
-    }
-}
-impl std::error::Error for Error {}
+/// Instruction processing for the token program, whose account state the SDK defines
+pub trait TokenProcessor {
+    fn map_to_invalid_args(err: std::boxed::Box<bincode::ErrorKind>) -> Error;
 
-pub type Result<T> = std::result::Result<T, Error>;
+    fn process_command_newtoken(
+        info: &mut [KeyedAccount],
+        token_info: TokenInfo,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
-pub struct TokenInfo {
-    /**
-     * Total supply of tokens
-     */
-    supply: u64,
-
-    /**
-     * Number of base 10 digits to the right of the decimal place in the total supply
-     */
-    decimals: u8,
-
-    /**
-     * Descriptive name of this token
-     */
-    name: String,
-
-    /**
-     * Symbol for this token
-     */
-    symbol: String,
-}
+    fn process_command_newaccount(
+        info: &mut [KeyedAccount],
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
-pub struct TokenAccountDelegateInfo {
-    /**
-     * The source account for the tokens
-     */
-    source: Pubkey,
-
-    /**
-     * The original amount that this delegate account was authorized to spend up to
-     */
-    original_amount: u64,
-}
+    fn process_command_transfer(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
-pub struct TokenAccountInfo {
-    /**
-     * The kind of token this account holds
-     */
-    token: Pubkey,
-
-    /**
-     * Owner of this account
-     */
-    owner: Pubkey,
-
-    /**
-     * Amount of tokens this account holds
-     */
-    amount: u64,
-
-    /**
-     * If `delegate` None, `amount` belongs to this account.
-     * If `delegate` is Option<_>, `amount` represents the remaining allowance
-     * of tokens that may be transferred from the `source` account.
-     */
-    delegate: Option<TokenAccountDelegateInfo>,
-}
+    fn process_command_approve(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-enum Command {
-    NewToken(TokenInfo),
-    NewTokenAccount,
-    Transfer(u64),
-    Approve(u64),
-    SetOwner,
-}
+    fn process_command_setowner(
+        info: &mut [KeyedAccount],
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub enum TokenProgram {
-    Unallocated,
-    Token(TokenInfo),
-    Account(TokenAccountInfo),
-    Invalid,
-}
-impl Default for TokenProgram {
-    fn default() -> TokenProgram {
-        TokenProgram::Unallocated
-    }
+    fn process_command_mintto(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
+
+    fn process_command_burn(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
+
+    fn process_command_setfrozen(
+        info: &mut [KeyedAccount],
+        is_frozen: bool,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()>;
+
+    fn process_command_closeaccount(
+        info: &mut [KeyedAccount],
+        input_program_accounts: &[TokenProgram],
+    ) -> Result<()>;
+
+    fn process(program_id: &Pubkey, info: &mut [KeyedAccount], input: &[u8]) -> Result<()>;
 }
 
-impl TokenProgram {
+impl TokenProcessor for TokenProgram {
     #[allow(clippy::needless_pass_by_value)]
     fn map_to_invalid_args(err: std::boxed::Box<bincode::ErrorKind>) -> Error {
         warn!("invalid argument: {:?}", err);
         Error::InvalidArgument
     }
 
-    pub fn deserialize(input: &[u8]) -> Result<TokenProgram> {
-        if input.is_empty() {
-            Err(Error::InvalidArgument)?;
-        }
-        match input[0] {
-            0 => Ok(TokenProgram::Unallocated),
-            1 => Ok(TokenProgram::Token(
-                bincode::deserialize(&input[1..]).map_err(Self::map_to_invalid_args)?,
-            )),
-            2 => Ok(TokenProgram::Account(
-                bincode::deserialize(&input[1..]).map_err(Self::map_to_invalid_args)?,
-            )),
-            _ => Err(Error::InvalidArgument),
-        }
-    }
-
-    fn serialize(self: &TokenProgram, output: &mut [u8]) -> Result<()> {
-        if output.is_empty() {
-            warn!("serialize fail: ouput.len is 0");
-            Err(Error::InvalidArgument)?;
-        }
-        match self {
-            TokenProgram::Unallocated | TokenProgram::Invalid => Err(Error::InvalidArgument),
-            TokenProgram::Token(token_info) => {
-                output[0] = 1;
-                let writer = std::io::BufWriter::new(&mut output[1..]);
-                bincode::serialize_into(writer, &token_info).map_err(Self::map_to_invalid_args)
-            }
-            TokenProgram::Account(account_info) => {
-                output[0] = 2;
-                let writer = std::io::BufWriter::new(&mut output[1..]);
-                bincode::serialize_into(writer, &account_info).map_err(Self::map_to_invalid_args)
-            }
-        }
-    }
-
-    #[allow(dead_code)]
-    pub fn amount(&self) -> Result<u64> {
-        if let TokenProgram::Account(account_info) = self {
-            Ok(account_info.amount)
-        } else {
-            Err(Error::InvalidArgument)
-        }
-    }
-
-    #[allow(dead_code)]
-    pub fn only_owner(&self, key: &Pubkey) -> Result<()> {
-        if *key != Pubkey::default() {
-            if let TokenProgram::Account(account_info) = self {
-                if account_info.owner == *key {
-                    return Ok(());
-                }
-            }
-        }
-        warn!("TokenProgram: non-owner rejected");
-        Err(Error::NotOwner)
-    }
-
-    pub fn process_command_newtoken(
+    fn process_command_newtoken(
         info: &mut [KeyedAccount],
         token_info: TokenInfo,
         input_program_accounts: &[TokenProgram],
@@ -209,7 +123,7 @@ impl TokenProgram {
         Ok(())
     }
 
-    pub fn process_command_newaccount(
+    fn process_command_newaccount(
         info: &mut [KeyedAccount],
         input_program_accounts: &[TokenProgram],
         output_program_accounts: &mut Vec<(usize, TokenProgram)>,
@@ -231,6 +145,7 @@ impl TokenProgram {
             owner: *info[1].unsigned_key(),
             amount: 0,
             delegate: None,
+            is_frozen: false,
         };
         if input_program_accounts.len() >= 4 {
             token_account_info.delegate = Some(TokenAccountDelegateInfo {
@@ -242,7 +157,7 @@ impl TokenProgram {
         Ok(())
     }
 
-    pub fn process_command_transfer(
+    fn process_command_transfer(
         info: &mut [KeyedAccount],
         amount: u64,
         input_program_accounts: &[TokenProgram],
@@ -271,6 +186,11 @@ impl TokenProgram {
                 Err(Error::InvalidArgument)?;
             }
 
+            if source_account.is_frozen || dest_account.is_frozen {
+                error!("account 1 and/or 2 are frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
             if source_account.amount < amount {
                 Err(Error::InsufficentFunds)?;
             }
@@ -295,6 +215,10 @@ impl TokenProgram {
                         error!("Account 1 is not a delegate of account 3");
                         Err(Error::InvalidArgument)?;
                     }
+                    if source_account.is_frozen {
+                        error!("account 3 is frozen");
+                        Err(Error::AccountFrozen)?;
+                    }
 
                     if source_account.amount < amount {
                         Err(Error::InsufficentFunds)?;
@@ -319,7 +243,7 @@ impl TokenProgram {
         Ok(())
     }
 
-    pub fn process_command_approve(
+    fn process_command_approve(
         info: &mut [KeyedAccount],
         amount: u64,
         input_program_accounts: &[TokenProgram],
@@ -348,6 +272,11 @@ impl TokenProgram {
                 Err(Error::InvalidArgument)?;
             }
 
+            if source_account.is_frozen {
+                error!("account 1 is frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
             match &delegate_account.delegate {
                 None => {
                     error!("account 2 is not a delegate");
@@ -376,7 +305,7 @@ impl TokenProgram {
         Ok(())
     }
 
-    pub fn process_command_setowner(
+    fn process_command_setowner(
         info: &mut [KeyedAccount],
         input_program_accounts: &[TokenProgram],
         output_program_accounts: &mut Vec<(usize, TokenProgram)>,
@@ -392,6 +321,11 @@ impl TokenProgram {
                 Err(Error::InvalidArgument)?;
             }
 
+            if source_account.is_frozen {
+                info!("account 1 is frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
             let mut output_source_account = source_account.clone();
             output_source_account.owner = *info[2].unsigned_key();
             output_program_accounts.push((1, TokenProgram::Account(output_source_account)));
@@ -402,8 +336,199 @@ impl TokenProgram {
         Ok(())
     }
 
-    pub fn process(program_id: &Pubkey, info: &mut [KeyedAccount], input: &[u8]) -> Result<()> {
-        let command = bincode::deserialize::<Command>(input).map_err(Self::map_to_invalid_args)?;
+    fn process_command_mintto(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()> {
+        // key 0 - Mint authority of the token
+        // key 1 - Token to mint
+        // key 2 - Destination token account
+        if input_program_accounts.len() != 3 {
+            error!("Expected 3 accounts");
+            Err(Error::InvalidArgument)?;
+        }
+
+        if let (TokenProgram::Token(token_info), TokenProgram::Account(dest_account)) =
+            (&input_program_accounts[1], &input_program_accounts[2])
+        {
+            if token_info.mint_authority.as_ref() != info[0].signer_key() {
+                error!("mint authority of account 1 not present");
+                Err(Error::NotOwner)?;
+            }
+
+            if info[1].unsigned_key() != &dest_account.token {
+                error!("account 1/2 token mismatch");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if dest_account.delegate.is_some() {
+                error!("account 2 is a delegate and cannot accept tokens");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if dest_account.is_frozen {
+                error!("account 2 is frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
+            let mut output_token_info = token_info.clone();
+            output_token_info.supply = token_info
+                .supply
+                .checked_add(amount)
+                .ok_or(Error::InvalidArgument)?;
+            output_program_accounts.push((1, TokenProgram::Token(output_token_info)));
+
+            let mut output_dest_account = dest_account.clone();
+            output_dest_account.amount += amount;
+            output_program_accounts.push((2, TokenProgram::Account(output_dest_account)));
+        } else {
+            error!("account 1 and/or 2 are invalid accounts");
+            Err(Error::InvalidArgument)?;
+        }
+        Ok(())
+    }
+
+    fn process_command_burn(
+        info: &mut [KeyedAccount],
+        amount: u64,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()> {
+        // key 0 - Owner of the source account
+        // key 1 - Source token account
+        // key 2 - Token to burn
+        if input_program_accounts.len() != 3 {
+            error!("Expected 3 accounts");
+            Err(Error::InvalidArgument)?;
+        }
+
+        if let (TokenProgram::Account(source_account), TokenProgram::Token(token_info)) =
+            (&input_program_accounts[1], &input_program_accounts[2])
+        {
+            if info[2].unsigned_key() != &source_account.token {
+                error!("account 1/2 token mismatch");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if info[0].signer_key().unwrap() != &source_account.owner {
+                error!("owner of account 1 not present");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if source_account.delegate.is_some() {
+                error!("account 1 is a delegate and cannot burn tokens");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if source_account.is_frozen {
+                error!("account 1 is frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
+            if source_account.amount < amount {
+                Err(Error::InsufficentFunds)?;
+            }
+
+            let mut output_source_account = source_account.clone();
+            output_source_account.amount -= amount;
+            output_program_accounts.push((1, TokenProgram::Account(output_source_account)));
+
+            let mut output_token_info = token_info.clone();
+            output_token_info.supply = token_info
+                .supply
+                .checked_sub(amount)
+                .ok_or(Error::InvalidArgument)?;
+            output_program_accounts.push((2, TokenProgram::Token(output_token_info)));
+        } else {
+            error!("account 1 and/or 2 are invalid accounts");
+            Err(Error::InvalidArgument)?;
+        }
+        Ok(())
+    }
+
+    fn process_command_setfrozen(
+        info: &mut [KeyedAccount],
+        is_frozen: bool,
+        input_program_accounts: &[TokenProgram],
+        output_program_accounts: &mut Vec<(usize, TokenProgram)>,
+    ) -> Result<()> {
+        // key 0 - Freeze authority of the token
+        // key 1 - Token account to freeze or thaw
+        // key 2 - Token the account is associated with
+        if input_program_accounts.len() != 3 {
+            error!("Expected 3 accounts");
+            Err(Error::InvalidArgument)?;
+        }
+
+        if let (TokenProgram::Account(source_account), TokenProgram::Token(token_info)) =
+            (&input_program_accounts[1], &input_program_accounts[2])
+        {
+            if info[2].unsigned_key() != &source_account.token {
+                error!("account 1/2 token mismatch");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if token_info.freeze_authority.as_ref() != info[0].signer_key() {
+                error!("freeze authority of account 2 not present");
+                Err(Error::NotOwner)?;
+            }
+
+            let mut output_source_account = source_account.clone();
+            output_source_account.is_frozen = is_frozen;
+            output_program_accounts.push((1, TokenProgram::Account(output_source_account)));
+        } else {
+            error!("account 1 and/or 2 are invalid accounts");
+            Err(Error::InvalidArgument)?;
+        }
+        Ok(())
+    }
+
+    fn process_command_closeaccount(
+        info: &mut [KeyedAccount],
+        input_program_accounts: &[TokenProgram],
+    ) -> Result<()> {
+        // key 0 - Owner of the account
+        // key 1 - Token account to close
+        // key 2 - Destination for the account's tokens
+        if input_program_accounts.len() != 3 {
+            error!("Expected 3 accounts");
+            Err(Error::InvalidArgument)?;
+        }
+
+        if let TokenProgram::Account(source_account) = &input_program_accounts[1] {
+            if info[0].signer_key().unwrap() != &source_account.owner {
+                error!("owner of account 1 not present");
+                Err(Error::InvalidArgument)?;
+            }
+
+            if source_account.is_frozen {
+                error!("account 1 is frozen");
+                Err(Error::AccountFrozen)?;
+            }
+
+            // A delegate's amount is only an allowance, so it may be closed at any time
+            if source_account.delegate.is_none() && source_account.amount != 0 {
+                error!("account 1 has a non-zero balance");
+                Err(Error::InvalidArgument)?;
+            }
+        } else {
+            error!("account 1 is invalid");
+            Err(Error::InvalidArgument)?;
+        }
+
+        info[2].account.tokens += info[1].account.tokens;
+        info[1].account.tokens = 0;
+        for byte in info[1].account.userdata.iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+
+    fn process(program_id: &Pubkey, info: &mut [KeyedAccount], input: &[u8]) -> Result<()> {
+        let command =
+            bincode::deserialize::<TokenInstruction>(input).map_err(Self::map_to_invalid_args)?;
         info!("process_transaction: command={:?}", command);
 
         if info[0].signer_key().is_none() {
@@ -415,7 +540,7 @@ impl TokenProgram {
             .map(|keyed_account| {
                 let account = &keyed_account.account;
                 if account.owner == *program_id {
-                    match Self::deserialize(&account.userdata) {
+                    match TokenProgram::deserialize(&account.userdata) {
                         Ok(token_program) => token_program,
                         Err(err) => {
                             error!("deserialize failed: {:?}", err);
@@ -435,37 +560,69 @@ impl TokenProgram {
         let mut output_program_accounts: Vec<(_, _)> = vec![];
 
         match command {
-            Command::NewToken(token_info) => Self::process_command_newtoken(
+            TokenInstruction::NewToken(token_info) => Self::process_command_newtoken(
                 info,
                 token_info,
                 &input_program_accounts,
                 &mut output_program_accounts,
             )?,
-            Command::NewTokenAccount => Self::process_command_newaccount(
+            TokenInstruction::NewTokenAccount => Self::process_command_newaccount(
+                info,
+                &input_program_accounts,
+                &mut output_program_accounts,
+            )?,
+
+            TokenInstruction::Transfer(amount) => Self::process_command_transfer(
+                info,
+                amount,
+                &input_program_accounts,
+                &mut output_program_accounts,
+            )?,
+
+            TokenInstruction::Approve(amount) => Self::process_command_approve(
                 info,
+                amount,
                 &input_program_accounts,
                 &mut output_program_accounts,
             )?,
 
-            Command::Transfer(amount) => Self::process_command_transfer(
+            TokenInstruction::SetOwner => Self::process_command_setowner(
+                info,
+                &input_program_accounts,
+                &mut output_program_accounts,
+            )?,
+
+            TokenInstruction::MintTo(amount) => Self::process_command_mintto(
                 info,
                 amount,
                 &input_program_accounts,
                 &mut output_program_accounts,
             )?,
 
-            Command::Approve(amount) => Self::process_command_approve(
+            TokenInstruction::Burn(amount) => Self::process_command_burn(
                 info,
                 amount,
                 &input_program_accounts,
                 &mut output_program_accounts,
             )?,
 
-            Command::SetOwner => Self::process_command_setowner(
+            TokenInstruction::FreezeAccount => Self::process_command_setfrozen(
                 info,
+                true,
                 &input_program_accounts,
                 &mut output_program_accounts,
             )?,
+
+            TokenInstruction::ThawAccount => Self::process_command_setfrozen(
+                info,
+                false,
+                &input_program_accounts,
+                &mut output_program_accounts,
+            )?,
+
+            TokenInstruction::CloseAccount => {
+                Self::process_command_closeaccount(info, &input_program_accounts)?
+            }
         }
 
         for (index, program_account) in &output_program_accounts {
@@ -473,7 +630,12 @@ impl TokenProgram {
                 "output_program_account: index={} userdata={:?}",
                 index, program_account
             );
-            Self::serialize(program_account, &mut info[*index].account.userdata)?;
+            program_account
+                .serialize(&mut info[*index].account.userdata)
+                .map_err(|err| {
+                    error!("serialize failed: {:?}", err);
+                    Error::InvalidArgument
+                })?;
         }
         Ok(())
     }
@@ -482,49 +644,135 @@ impl TokenProgram {
 #[cfg(test)]
 mod test {
     use super::*;
-    #[test]
-    pub fn serde() {
-        assert_eq!(TokenProgram::deserialize(&[0]), Ok(TokenProgram::default()));
-
-        let mut userdata = vec![0; 256];
+    use solana_sdk::account::Account;
+
+    fn process_command(
+        program_id: &Pubkey,
+        signer: &Pubkey,
+        keys: &[Pubkey],
+        accounts: &mut [Account],
+        command: &TokenInstruction,
+    ) -> Result<()> {
+        let mut signer_account = Account::new(0, 0, Pubkey::default());
+        let mut info = vec![KeyedAccount::new(signer, true, &mut signer_account)];
+        info.extend(
+            keys.iter()
+                .zip(accounts.iter_mut())
+                .map(|(key, account)| KeyedAccount::new(key, false, account)),
+        );
+        TokenProgram::process(program_id, &mut info, &bincode::serialize(command).unwrap())
+    }
 
-        let account = TokenProgram::Account(TokenAccountInfo {
-            token: Pubkey::new(&[1; 32]),
-            owner: Pubkey::new(&[2; 32]),
-            amount: 123,
-            delegate: None,
-        });
-        account.serialize(&mut userdata).unwrap();
-        assert_eq!(TokenProgram::deserialize(&userdata), Ok(account));
+    fn token_account(accounts: &[Account], index: usize) -> TokenAccountInfo {
+        match TokenProgram::deserialize(&accounts[index].userdata).unwrap() {
+            TokenProgram::Account(account_info) => account_info,
+            _ => panic!("not a token account"),
+        }
+    }
 
-        let account = TokenProgram::Token(TokenInfo {
-            supply: 12345,
-            decimals: 2,
+    #[test]
+    pub fn mint_burn_freeze_close() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let authority = Pubkey::new(&[1; 32]);
+        let owner = Pubkey::new(&[2; 32]);
+        let token = Pubkey::new(&[3; 32]);
+        let holder = Pubkey::new(&[4; 32]);
+        let refund = Pubkey::new(&[5; 32]);
+
+        let mut accounts = vec![
+            Account::new(1, 256, program_id),
+            Account::new(1, 256, program_id),
+            Account::new(0, 0, Pubkey::default()),
+        ];
+        TokenProgram::Token(TokenInfo {
+            supply: 100,
+            decimals: 0,
             name: "A test token".to_string(),
             symbol: "TEST".to_string(),
-        });
-        account.serialize(&mut userdata).unwrap();
-        assert_eq!(TokenProgram::deserialize(&userdata), Ok(account));
-    }
+            mint_authority: Some(authority),
+            freeze_authority: Some(authority),
+        })
+        .serialize(&mut accounts[0].userdata)
+        .unwrap();
+        TokenProgram::Account(TokenAccountInfo {
+            token,
+            owner,
+            amount: 0,
+            delegate: None,
+            is_frozen: false,
+        })
+        .serialize(&mut accounts[1].userdata)
+        .unwrap();
+
+        // Only the mint authority may mint
+        let mint_keys = [token, holder];
+        let mint = TokenInstruction::MintTo(50);
+        assert_eq!(
+            process_command(&program_id, &owner, &mint_keys, &mut accounts[..2], &mint),
+            Err(Error::NotOwner)
+        );
+        process_command(
+            &program_id,
+            &authority,
+            &mint_keys,
+            &mut accounts[..2],
+            &mint,
+        )
+        .unwrap();
+        assert_eq!(token_account(&accounts, 1).amount, 50);
+
+        // A frozen account can't burn until it is thawed
+        accounts.swap(0, 1);
+        let keys = [holder, token];
+        let burn = TokenInstruction::Burn(20);
+        process_command(
+            &program_id,
+            &authority,
+            &keys,
+            &mut accounts[..2],
+            &TokenInstruction::FreezeAccount,
+        )
+        .unwrap();
+        assert!(token_account(&accounts, 0).is_frozen);
+        assert_eq!(
+            process_command(&program_id, &owner, &keys, &mut accounts[..2], &burn),
+            Err(Error::AccountFrozen)
+        );
+        process_command(
+            &program_id,
+            &authority,
+            &keys,
+            &mut accounts[..2],
+            &TokenInstruction::ThawAccount,
+        )
+        .unwrap();
+        process_command(&program_id, &owner, &keys, &mut accounts[..2], &burn).unwrap();
+        assert_eq!(token_account(&accounts, 0).amount, 30);
+        match TokenProgram::deserialize(&accounts[1].userdata).unwrap() {
+            TokenProgram::Token(token_info) => assert_eq!(token_info.supply, 130),
+            _ => panic!("not a token"),
+        }
 
-    #[test]
-    pub fn serde_expect_fail() {
-        let mut userdata = vec![0; 256];
-
-        // Certain TokenProgram's may not be serialized
-        let account = TokenProgram::default();
-        assert_eq!(account, TokenProgram::Unallocated);
-        assert!(account.serialize(&mut userdata).is_err());
-        assert!(account.serialize(&mut userdata).is_err());
-        let account = TokenProgram::Invalid;
-        assert!(account.serialize(&mut userdata).is_err());
-
-        // Bad deserialize userdata
-        assert!(TokenProgram::deserialize(&[]).is_err());
-        assert!(TokenProgram::deserialize(&[1]).is_err());
-        assert!(TokenProgram::deserialize(&[1, 2]).is_err());
-        assert!(TokenProgram::deserialize(&[2, 2]).is_err());
-        assert!(TokenProgram::deserialize(&[3]).is_err());
+        // Only an empty account may be closed
+        accounts.swap(1, 2);
+        let keys = [holder, refund];
+        let close = TokenInstruction::CloseAccount;
+        assert_eq!(
+            process_command(&program_id, &owner, &keys, &mut accounts[..2], &close),
+            Err(Error::InvalidArgument)
+        );
+        let burn = TokenInstruction::Burn(30);
+        let burn_keys = [holder, token];
+        accounts.swap(1, 2);
+        process_command(&program_id, &owner, &burn_keys, &mut accounts[..2], &burn).unwrap();
+        accounts.swap(1, 2);
+        process_command(&program_id, &owner, &keys, &mut accounts[..2], &close).unwrap();
+        assert_eq!(
+            TokenProgram::deserialize(&accounts[0].userdata),
+            Ok(TokenProgram::Unallocated)
+        );
+        assert_eq!(accounts[0].tokens, 0);
+        assert_eq!(accounts[1].tokens, 1);
     }
 
     // Note: business logic tests are located in the @solana/web3.js test suite
@@ -76,6 +76,20 @@ local numtostring = {
 
 setmetatable(numtostring, { __index = function (t, k) return k end })
 
+
+-- isName: true if `s` can be written as a bare key, as in "^[%a_][%w_]*$".
+-- Pattern matching isn't available to programs.
+local function isName(s)
+   for i = 1, #s do
+      local c = s:byte(i)
+      local alpha = c == 95 or (c >= 65 and c <= 90) or (c >= 97 and c <= 122)
+      if not (alpha or (i > 1 and c >= 48 and c <= 57)) then
+         return false
+      end
+   end
+   return #s > 0
+end
+
 -- serialize:  Serialize a Lua data structure
 --
 --   x    = value to serialize
@@ -130,7 +144,7 @@ local function serialize(x, out, iter)
             if k == nextIndex then
                nextIndex = nextIndex + 1
             else
-               if type(k) == "string" and k:match("^[%a_][%w_]*$") then
+               if type(k) == "string" and isName(k) then
                   out(k.."=")
                else
                   out "["
@@ -1,13 +1,356 @@
 use log::*;
-use rlua::{Lua, Table};
+use rlua::{HookTriggers, Lua, Table, Value};
 use solana_sdk::account::KeyedAccount;
 use solana_sdk::loader_instruction::LoaderInstruction;
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::solana_entrypoint;
 use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-/// Make KeyAccount values available to Lua.
+/// Maximum number of bytes a program's Lua state may allocate
+const LUA_MEMORY_LIMIT: usize = 8 * 1024 * 1024;
+
+/// Maximum number of Lua VM instructions a program may execute
+const LUA_INSTRUCTION_LIMIT: usize = 1_000_000;
+
+/// Number of instructions between checks of the instruction limit
+const LUA_HOOK_INTERVAL: u32 = 1_000;
+
+/// Globals programs may use. The rest of the standard library, `os`, `io`, `debug` and module
+/// loading among it, is removed since it isn't deterministic or reaches outside the sandbox.
+/// So is `next`, whose order depends on table addresses
+const LUA_GLOBALS: [&str; 23] = [
+    "_G",
+    "_VERSION",
+    "assert",
+    "coroutine",
+    "error",
+    "getmetatable",
+    "ipairs",
+    "load",
+    "math",
+    "pairs",
+    "pcall",
+    "rawequal",
+    "rawget",
+    "rawlen",
+    "rawset",
+    "select",
+    "setmetatable",
+    "string",
+    "table",
+    "tonumber",
+    "tostring",
+    "type",
+    "utf8",
+];
+
+/// Pattern matching functions, removed since a pattern can backtrack for longer than the
+/// instruction limit allows without the hook ever running. So are the `string.pack` functions,
+/// which copy arbitrary amounts of data in one call
+const LUA_STRING_REMOVED: [&str; 7] = [
+    "find", "gmatch", "gsub", "match", "pack", "packsize", "unpack",
+];
+
+/// Replacements for the standard library functions whose results depend on addresses or on
+/// when the garbage collector runs
+const LUA_DETERMINISTIC: &str = r#"
+    local next, rawget, rawset, sort, type, error = next, rawget, rawset, table.sort, type, error
+    local gsub, gmatch = string.gsub, string.gmatch
+
+    -- Iterate in key order: numbers, then strings, then booleans. Other keys have no order
+    -- that doesn't depend on their address
+    local rank = { number = 1, string = 2, boolean = 3 }
+    local function before(a, b)
+        local ra, rb = rank[type(a)], rank[type(b)]
+        if ra ~= rb then
+            return ra < rb
+        end
+        if ra == 3 then
+            return not a and b
+        end
+        return a < b
+    end
+    _G.pairs = function(t)
+        local keys = {}
+        for k in next, t do
+            if not rank[type(k)] then
+                error("pairs: keys must be numbers, strings or booleans", 2)
+            end
+            keys[#keys + 1] = k
+        end
+        sort(keys, before)
+        local i = 0
+        return function()
+            i = i + 1
+            local k = keys[i]
+            if k ~= nil then
+                return k, rawget(t, k)
+            end
+        end, t, nil
+    end
+
+    -- Name values by their type rather than their address
+    local tostring = tostring
+    local by_address = { table = true, ["function"] = true, thread = true, userdata = true }
+    _G.tostring = function(v)
+        local s = tostring(v)
+        if by_address[type(v)] then
+            s = gsub(gsub(s, ": 0x%x+$", ""), ": %x+$", "")
+        end
+        return s
+    end
+
+    -- `%s` formats tables and functions by address too
+    local format, pack, unpack = string.format, table.pack, table.unpack
+    string.format = function(fmt, ...)
+        local args = pack(...)
+        local i = 0
+        for spec in gmatch(fmt, "%%[-+ #0]*%d*%.?%d*([%a%%])") do
+            if spec ~= "%" then
+                i = i + 1
+                if spec == "s" then
+                    args[i] = _G.tostring(args[i])
+                end
+            end
+        end
+        return format(fmt, unpack(args, 1, args.n))
+    end
+
+    -- Only load source, since malformed bytecode can crash the interpreter
+    local load = load
+    _G.load = function(chunk, chunkname, mode, ...)
+        return load(chunk, chunkname, "t", ...)
+    end
+
+    -- Finalizers and weak tables would make results depend on when the collector runs
+    local setmetatable = setmetatable
+    _G.setmetatable = function(t, mt)
+        if type(mt) == "table" then
+            rawset(mt, "__gc", nil)
+            rawset(mt, "__mode", nil)
+        end
+        return setmetatable(t, mt)
+    end
+"#;
+
+/// Restrict the Lua standard library to deterministic functions that can't escape the sandbox
+fn sandbox(lua: &Lua) -> rlua::Result<()> {
+    lua.exec::<_, ()>(LUA_DETERMINISTIC, None)?;
+
+    let globals = lua.globals();
+    let names = globals
+        .clone()
+        .pairs::<String, Value>()
+        .map(|pair| pair.map(|(name, _)| name))
+        .collect::<rlua::Result<Vec<_>>>()?;
+    for name in names {
+        if !LUA_GLOBALS.contains(&name.as_str()) {
+            globals.set(name, Value::Nil)?;
+        }
+    }
+
+    let math: Table = globals.get("math")?;
+    math.set("random", Value::Nil)?;
+    math.set("randomseed", Value::Nil)?;
+
+    let string: Table = globals.get("string")?;
+    for name in LUA_STRING_REMOVED.iter() {
+        string.set(*name, Value::Nil)?;
+    }
+    Ok(())
+}
+
+/// Charges for standard library functions written in C, which run without the hook. Each
+/// costs one instruction per byte or value it produces, shifts or scans
+const LUA_METERED: &str = r##"
+    local charge, select, type, tointeger = charge_instructions, select, type, math.tointeger
+
+    local function counted(...)
+        charge(select("#", ...))
+        return ...
+    end
+    local function by_result(f)
+        return function(...)
+            local s = f(...)
+            charge(#s)
+            return s
+        end
+    end
+    local function by_values(f)
+        return function(...)
+            return counted(f(...))
+        end
+    end
+    local function by_length(f)
+        return function(s, ...)
+            if type(s) == "string" then
+                charge(#s)
+            end
+            return f(s, ...)
+        end
+    end
+
+    for _, name in ipairs({ "char", "format", "lower", "rep", "reverse", "sub", "upper" }) do
+        string[name] = by_result(string[name])
+    end
+    string.byte = by_values(string.byte)
+    table.concat = by_result(table.concat)
+    local pack = table.pack
+    table.pack = function(...)
+        charge(select("#", ...))
+        return pack(...)
+    end
+    table.unpack = by_values(table.unpack)
+    utf8.char = by_result(utf8.char)
+    utf8.codepoint = by_values(utf8.codepoint)
+    utf8.len = by_length(utf8.len)
+    utf8.offset = by_length(utf8.offset)
+    _G.load = by_length(load)
+    _G.tonumber = by_length(tonumber)
+
+    local sort, insert, remove, move = table.sort, table.insert, table.remove, table.move
+    table.sort = function(t, ...)
+        local n, cost = #t, 0
+        local m = n
+        while m > 1 do
+            cost = cost + n
+            m = m // 2
+        end
+        charge(cost)
+        return sort(t, ...)
+    end
+    table.insert = function(t, ...)
+        if select("#", ...) > 1 then
+            charge(#t)
+        end
+        return insert(t, ...)
+    end
+    table.remove = function(t, ...)
+        if select("#", ...) > 0 then
+            charge(#t)
+        end
+        return remove(t, ...)
+    end
+    table.move = function(a1, f, e, ...)
+        if type(f) == "number" and type(e) == "number" then
+            charge(tointeger(e - f + 1) or 0)
+        end
+        return move(a1, f, e, ...)
+    end
+"##;
+
+/// Fail the program once it has executed `LUA_INSTRUCTION_LIMIT` instructions. Errors raised
+/// by the hook could be caught by `pcall`, `xpcall` or `coroutine.resume`, so those raise
+/// them again
+fn set_instruction_limit(lua: &mut Lua) -> rlua::Result<()> {
+    let instructions = Arc::new(AtomicUsize::new(0));
+    let exceeded = {
+        let instructions = instructions.clone();
+        move || instructions.load(Ordering::Relaxed) > LUA_INSTRUCTION_LIMIT
+    };
+
+    let charge = {
+        let instructions = instructions.clone();
+        let exceeded = exceeded.clone();
+        move |n: i64| {
+            if n > 0 {
+                let n = (n as usize).min(LUA_INSTRUCTION_LIMIT + 1);
+                instructions.fetch_add(n, Ordering::Relaxed);
+            }
+            exceeded()
+        }
+    };
+    let charge_instructions = lua.create_function(move |_, n: i64| {
+        if charge(n) {
+            Err(rlua::Error::RuntimeError(
+                "instruction limit exceeded".to_string(),
+            ))?;
+        }
+        Ok(())
+    })?;
+    lua.globals()
+        .set("charge_instructions", charge_instructions)?;
+    lua.exec::<_, ()>(LUA_METERED, None)?;
+    lua.exec::<_, ()>(
+        r#"
+            local charge, resume = charge_instructions, coroutine.resume
+            local pcall, xpcall = pcall, xpcall
+            charge_instructions = nil
+            local function checked(...)
+                charge(0)
+                return ...
+            end
+            _G.pcall = function(...)
+                return checked(pcall(...))
+            end
+            _G.xpcall = function(...)
+                return checked(xpcall(...))
+            end
+            coroutine.resume = function(...)
+                return checked(resume(...))
+            end
+        "#,
+        None,
+    )?;
+
+    let triggers = HookTriggers {
+        every_nth_instruction: Some(LUA_HOOK_INTERVAL),
+        ..HookTriggers::default()
+    };
+    lua.set_hook(triggers, move |_| {
+        instructions.fetch_add(LUA_HOOK_INTERVAL as usize, Ordering::Relaxed);
+        if exceeded() {
+            Err(rlua::Error::RuntimeError(
+                "instruction limit exceeded".to_string(),
+            ))?;
+        }
+        Ok(())
+    });
+    Ok(())
+}
+
+/// Lets programs fail with a `ProgramError`, as in `fail("InvalidArgument")`, or with a
+/// program-specific `CustomError` code, as in `fail(42)`
+fn set_fail(lua: &Lua) -> rlua::Result<()> {
+    let fail = lua.create_function(|_, err: Value| -> rlua::Result<()> {
+        let err = match err {
+            Value::Integer(code) if code >= 0 && code <= i64::from(u32::max_value()) => {
+                ProgramError::CustomError(code as u32)
+            }
+            Value::String(name) => match name.to_str()? {
+                "GenericError" => ProgramError::GenericError,
+                "InvalidArgument" => ProgramError::InvalidArgument,
+                "ResultWithNegativeTokens" => ProgramError::ResultWithNegativeTokens,
+                "InvalidUserdata" => ProgramError::InvalidUserdata,
+                "UserdataTooSmall" => ProgramError::UserdataTooSmall,
+                name => Err(rlua::Error::RuntimeError(format!(
+                    "unknown program error {}",
+                    name
+                )))?,
+            },
+            err => Err(rlua::Error::RuntimeError(format!(
+                "invalid program error {:?}",
+                err
+            )))?,
+        };
+        Err(rlua::Error::external(err))
+    })?;
+    lua.globals().set("fail", fail)
+}
+
+/// The `ProgramError` a Lua error was raised with, if any
+fn program_error(err: &rlua::Error) -> Option<ProgramError> {
+    match err {
+        rlua::Error::CallbackError { cause, .. } => program_error(cause),
+        rlua::Error::ExternalError(err) => err.downcast_ref::<ProgramError>().cloned(),
+        _ => None,
+    }
+}
+
+/// Make KeyAccount values available to Lua. Only `tokens` and `userdata` may be modified.
 fn set_accounts(lua: &Lua, name: &str, keyed_accounts: &[KeyedAccount]) -> rlua::Result<()> {
     let accounts = lua.create_table()?;
     for (i, keyed_account) in keyed_accounts.iter().enumerate() {
@@ -20,6 +363,9 @@ fn set_accounts(lua: &Lua, name: &str, keyed_accounts: &[KeyedAccount]) -> rlua:
             },
             keyed_account.unsigned_key().to_string(),
         )?;
+        account.set("signer", keyed_account.signer_key().is_some())?;
+        account.set("owner", keyed_account.account.owner.to_string())?;
+        account.set("executable", keyed_account.account.executable)?;
         account.set("tokens", keyed_account.account.tokens)?;
         let data_str = lua.create_string(&keyed_account.account.userdata)?;
         account.set("userdata", data_str)?;
@@ -29,21 +375,44 @@ fn set_accounts(lua: &Lua, name: &str, keyed_accounts: &[KeyedAccount]) -> rlua:
     globals.set(name, accounts)
 }
 
-/// Commit the new KeyedAccount values.
+/// Commit the new KeyedAccount values, failing if the program modified a read-only field.
 fn update_accounts(lua: &Lua, name: &str, keyed_accounts: &mut [KeyedAccount]) -> rlua::Result<()> {
     let globals = lua.globals();
     let accounts: Table = globals.get(name)?;
-    for (i, keyed_account) in keyed_accounts.into_iter().enumerate() {
+    for (i, keyed_account) in keyed_accounts.iter_mut().enumerate() {
         let account: Table = accounts.get(i + 1)?;
-        keyed_account.account.tokens = account.get("tokens")?;
+        let owner: String = account.get("owner")?;
+        if account.get::<_, bool>("signer")? != keyed_account.signer_key().is_some()
+            || account.get::<_, bool>("executable")? != keyed_account.account.executable
+            || owner != keyed_account.account.owner.to_string()
+        {
+            warn!("account {} modified a read-only field", i + 1);
+            Err(rlua::Error::external(ProgramError::InvalidArgument))?;
+        }
+
+        let tokens: i64 = account.get("tokens")?;
+        if tokens < 0 {
+            Err(rlua::Error::external(
+                ProgramError::ResultWithNegativeTokens,
+            ))?;
+        }
+        keyed_account.account.tokens = tokens as u64;
+
         let data_str: rlua::String = account.get("userdata")?;
-        keyed_account.account.userdata = data_str.as_bytes().to_vec();
+        if data_str.as_bytes() != keyed_account.account.userdata.as_slice() {
+            keyed_account.account.userdata = data_str.as_bytes().to_vec();
+        }
     }
     Ok(())
 }
 
 fn run_lua(keyed_accounts: &mut [KeyedAccount], code: &str, data: &[u8]) -> rlua::Result<()> {
-    let lua = Lua::new();
+    let mut lua = Lua::new();
+    lua.set_memory_limit(Some(LUA_MEMORY_LIMIT));
+    sandbox(&lua)?;
+    set_fail(&lua)?;
+    set_instruction_limit(&mut lua)?;
+
     let globals = lua.globals();
     let data_str = lua.create_string(data)?;
     globals.set("data", data_str)?;
@@ -65,14 +434,14 @@ fn entrypoint(
     if keyed_accounts[0].account.executable {
         let (codes, params) = keyed_accounts.split_at_mut(1);
         let code = &codes[0].account.userdata;
-        let code = str::from_utf8(code).unwrap();
+        let code = str::from_utf8(code).map_err(|_| ProgramError::InvalidUserdata)?;
         match run_lua(params, &code, tx_data) {
             Ok(()) => {
                 trace!("Lua success");
             }
             Err(e) => {
                 warn!("Lua Error: {:#?}", e);
-                return Err(ProgramError::GenericError);
+                return Err(program_error(&e).unwrap_or(ProgramError::GenericError));
             }
         }
     } else if let Ok(instruction) = bincode::deserialize(tx_data) {
@@ -103,6 +472,13 @@ fn entrypoint(
                     keyed_accounts[0].signer_key().unwrap()
                 );
             }
+
+            // Upgradeable programs are only supported by the BPF loader. A Lua program is
+            // immutable once finalized; to change it, deploy a new program
+            LoaderInstruction::Upgrade | LoaderInstruction::SetUpgradeAuthority(_) => {
+                warn!("LuaLoader: programs can't be upgraded");
+                return Err(ProgramError::GenericError);
+            }
         }
     } else {
         warn!("Invalid program transaction: {:?}", tx_data);
@@ -150,6 +526,108 @@ mod tests {
         assert!(run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]).is_err());
     }
 
+    #[test]
+    fn test_lua_sandbox() {
+        let code = r#"
+            assert(os == nil and io == nil and debug == nil and require == nil)
+            assert(math.random == nil and math.floor ~= nil)
+            assert(load("return 1")() == 1)
+            assert(load(string.dump(function() end)) == nil)
+            assert(string.gsub == nil and string.match == nil and ("x").find == nil)
+
+            local mt = { __gc = function() end, __mode = "k", __index = { a = 1 } }
+            local t = setmetatable({}, mt)
+            assert(getmetatable(t) == mt and t.a == 1)
+            assert(mt.__gc == nil and mt.__mode == nil)
+        "#;
+        let mut accounts = [(Pubkey::default(), Account::default())];
+        run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]).unwrap();
+
+        let code = r#"
+            local t = {}
+            for i = 1, 1e9 do
+                t[i] = i
+            end
+        "#;
+        assert!(run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]).is_err());
+    }
+
+    #[test]
+    fn test_lua_deterministic() {
+        let code = r#"
+            assert(next == nil)
+            local keys = {}
+            for k, v in pairs({c = 1, [2] = 2, a = 3, [true] = 4, [1] = 5, b = 6}) do
+                keys[#keys + 1] = tostring(k)
+            end
+            assert(table.concat(keys, ",") == "1,2,a,b,c,true")
+            assert(not pcall(pairs, {[{}] = 1}))
+
+            assert(tostring({}) == "table" and tostring(assert) == "function")
+            assert(tostring(1) == "1" and tostring("x") == "x")
+            assert(string.format("%s %d%% %s", {}, 1, "x") == "table 1% x")
+            assert(("%s"):format(pairs) == "function")
+        "#;
+        let mut accounts = [(Pubkey::default(), Account::default())];
+        run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_lua_instruction_limit() {
+        let mut accounts = [(Pubkey::default(), Account::default())];
+        let mut run = |code| run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]);
+
+        run("for i = 1, 1000 do accounts[1].tokens = accounts[1].tokens + 1 end").unwrap();
+        assert!(run("while true do end").is_err());
+
+        // The limit can't be caught
+        assert!(run("while true do pcall(function() while true do end end) end").is_err());
+        assert!(run(r#"
+                local co = coroutine.create(function() while true do end end)
+                while true do coroutine.resume(co) end
+            "#)
+        .is_err());
+
+        // C functions are charged for the data they produce or move
+        run("local t = {} for i = 1, 1000 do t[i] = -i end table.sort(t)").unwrap();
+        assert!(run(r#"local s = ("x"):rep(1000) for i = 1, 1e4 do s:upper() end"#).is_err());
+        assert!(run("for i = 1, 100 do local s = ('x'):rep(1e5) end").is_err());
+        assert!(run("table.move({}, 1, 1e9, 2)").is_err());
+
+        assert_eq!(accounts[0].1.tokens, 1000);
+    }
+
+    #[test]
+    fn test_lua_program_error() {
+        let mut accounts = [(Pubkey::default(), Account::new(1, 0, Pubkey::default()))];
+        let mut run = |code| {
+            let err = run_lua(&mut create_keyed_accounts(&mut accounts), code, &[]).unwrap_err();
+            program_error(&err)
+        };
+        assert_eq!(
+            run(r#"fail("InvalidArgument")"#),
+            Some(ProgramError::InvalidArgument)
+        );
+        assert_eq!(run(r#"fail(42)"#), Some(ProgramError::CustomError(42)));
+        assert_eq!(run(r#"fail("NotAnError")"#), None);
+        assert_eq!(run(r#"fail(-1)"#), None);
+        assert_eq!(run(r#"error("oops")"#), None);
+
+        // Only tokens and userdata may be modified
+        assert_eq!(
+            run(r#"accounts[1].owner = "x""#),
+            Some(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            run(r#"accounts[1].signer = true"#),
+            Some(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            run(r#"accounts[1].tokens = -1"#),
+            Some(ProgramError::ResultWithNegativeTokens)
+        );
+    }
+
     #[test]
     fn test_move_funds_with_lua_via_process() {
         let userdata = r#"
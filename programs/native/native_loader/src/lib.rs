@@ -6,14 +6,18 @@ use libloading::os::unix::*;
 use libloading::os::windows::*;
 use log::*;
 use solana_sdk::account::KeyedAccount;
+use solana_sdk::hash::{hash, Hash};
 use solana_sdk::loader_instruction::LoaderInstruction;
 pub use solana_sdk::native_loader::*;
 use solana_sdk::native_program;
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::io;
 use std::path::PathBuf;
-use std::str;
 
 /// Dynamic link library prefixes
 #[cfg(unix)]
@@ -52,6 +56,67 @@ pub fn check_id(program_id: &Pubkey) -> bool {
     program_id.as_ref() == NATIVE_LOADER_PROGRAM_ID
 }
 
+thread_local! {
+    // Libraries are never unloaded, so each one only needs to be hashed once
+    static LIBRARY_HASHES: RefCell<HashMap<PathBuf, Hash>> = RefCell::new(HashMap::new());
+}
+
+/// Hash the contents of the shared library `name`
+pub fn hash_library(name: &str) -> io::Result<Hash> {
+    let path = create_path(name);
+    LIBRARY_HASHES.with(|hashes| {
+        if let Some(code_hash) = hashes.borrow().get(&path) {
+            return Ok(*code_hash);
+        }
+        let code_hash = hash(&fs::read(&path)?);
+        hashes.borrow_mut().insert(path, code_hash);
+        Ok(code_hash)
+    })
+}
+
+/// The part of a version that must match for releases to be compatible: the major version, or
+/// for 0.x releases the minor version too, as in Cargo's default requirements
+fn compatible_version(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    Some(if major == 0 { (0, minor) } else { (major, 0) })
+}
+
+/// Check that `program` is compatible with this release, and that its shared library matches
+/// the code hash it pins, if any. Programs of an earlier patch release keep running after an
+/// upgrade
+pub fn check_program(program: &NativeProgram) -> Result<(), ProgramError> {
+    let expected = compatible_version(env!("CARGO_PKG_VERSION"));
+    if expected.is_none() || compatible_version(&program.version) != expected {
+        warn!(
+            "{} {}: expected a version compatible with {}",
+            program.name,
+            program.version,
+            env!("CARGO_PKG_VERSION")
+        );
+        return Err(ProgramError::GenericError);
+    }
+    if let Some(code_hash) = program.code_hash {
+        match hash_library(&program.name) {
+            Ok(library_hash) => {
+                if library_hash != code_hash {
+                    warn!(
+                        "{} {}: library hash {:?} doesn't match the pinned {:?}",
+                        program.name, program.version, library_hash, code_hash
+                    );
+                    return Err(ProgramError::GenericError);
+                }
+            }
+            Err(e) => {
+                warn!("Unable to hash {}: {:?}", program.name, e);
+                return Err(ProgramError::GenericError);
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn entrypoint(
     program_id: &Pubkey,
     keyed_accounts: &mut [KeyedAccount],
@@ -60,17 +125,11 @@ pub fn entrypoint(
 ) -> Result<(), ProgramError> {
     if keyed_accounts[0].account.executable {
         // dispatch it
-        let (names, params) = keyed_accounts.split_at_mut(1);
-        let name = &names[0].account.userdata;
-        let name = match str::from_utf8(name) {
-            Ok(v) => v,
-            Err(e) => {
-                warn!("Invalid UTF-8 sequence: {}", e);
-                return Err(ProgramError::GenericError);
-            }
-        };
-        trace!("Call native {:?}", name);
-        let path = create_path(&name);
+        let (programs, params) = keyed_accounts.split_at_mut(1);
+        let program = NativeProgram::deserialize(&programs[0].account.userdata)?;
+        check_program(&program)?;
+        trace!("Call native {:?} {}", program.name, program.version);
+        let path = create_path(&program.name);
         // TODO linux tls bug can cause crash on dlclose(), workaround by never unloading
         match Library::open(Some(&path), libc::RTLD_NODELETE | libc::RTLD_NOW) {
             Ok(library) => unsafe {
@@ -110,17 +169,30 @@ pub fn entrypoint(
                     );
                     return Err(ProgramError::GenericError);
                 }
-                // native loader takes a name and we assume it all comes in at once
+                // native loader takes a serialized `NativeProgram` and we assume it all comes
+                // in at once
                 keyed_accounts[0].account.userdata = bytes;
             }
 
             LoaderInstruction::Finalize => {
+                let program = NativeProgram::deserialize(&keyed_accounts[0].account.userdata)?;
+                // Only the builtin programs may run whatever library is installed
+                if program.code_hash.is_none() {
+                    warn!("NativeLoader::Finalize: {} has no code hash", program.name);
+                    return Err(ProgramError::InvalidArgument);
+                }
+                check_program(&program)?;
                 keyed_accounts[0].account.executable = true;
                 trace!(
                     "NativeLoader::Finalize prog: {:?}",
                     keyed_accounts[0].signer_key().unwrap()
                 );
             }
+
+            LoaderInstruction::Upgrade | LoaderInstruction::SetUpgradeAuthority(_) => {
+                warn!("NativeLoader: programs can't be upgraded");
+                return Err(ProgramError::GenericError);
+            }
         }
     } else {
         warn!("Invalid userdata in instruction: {:?}", ix_userdata);
@@ -128,3 +200,54 @@ pub fn entrypoint(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn test_check_program() {
+        let program = NativeProgram::new("solana_no_such_program", "0.12.0", None);
+        assert_eq!(check_program(&program), Ok(()));
+
+        let program = NativeProgram::new("solana_no_such_program", "0.12.0", Some(hash(&[])));
+        assert_eq!(check_program(&program), Err(ProgramError::GenericError));
+
+        let program = NativeProgram::new("solana_no_such_program", "0.12.9", None);
+        assert_eq!(check_program(&program), Ok(()));
+
+        let program = NativeProgram::new("solana_no_such_program", "0.11.0", None);
+        assert_eq!(check_program(&program), Err(ProgramError::GenericError));
+
+        let program = NativeProgram::new("solana_no_such_program", "0.12", None);
+        assert_eq!(check_program(&program), Ok(()));
+
+        let program = NativeProgram::new("solana_no_such_program", "twelve", None);
+        assert_eq!(check_program(&program), Err(ProgramError::GenericError));
+    }
+
+    #[test]
+    fn test_compatible_version() {
+        assert_eq!(compatible_version("0.12.0"), compatible_version("0.12.1"));
+        assert_ne!(compatible_version("0.12.0"), compatible_version("0.13.0"));
+        assert_eq!(compatible_version("1.2.0"), compatible_version("1.3.4"));
+        assert_ne!(compatible_version("1.2.0"), compatible_version("2.2.0"));
+        assert_eq!(compatible_version("1"), None);
+    }
+
+    #[test]
+    fn test_finalize_without_code_hash() {
+        let program_id = Pubkey::new(&[1; 32]);
+        let mut program_account = Account::new(1, 0, id());
+        program_account.userdata = NativeProgram::new("solana_noop", "0.12.0", None).serialize();
+
+        let mut keyed_accounts = [KeyedAccount::new(&program_id, true, &mut program_account)];
+        let data = bincode::serialize(&LoaderInstruction::Finalize).unwrap();
+        assert_eq!(
+            entrypoint(&id(), &mut keyed_accounts, &data, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert!(!program_account.executable);
+    }
+}
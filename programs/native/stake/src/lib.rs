@@ -0,0 +1,265 @@
+//! Stake program
+//! Delegates the tokens of stake accounts to validators, warming the stake up and cooling it
+//! down over time
+
+use bincode::deserialize;
+use log::*;
+use solana_sdk::account::KeyedAccount;
+use solana_sdk::native_program::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::solana_entrypoint;
+use solana_sdk::stake_program::{self, StakeInstruction, StakeState};
+use solana_sdk::vote_program::{self, VoteProgram};
+
+fn delegate_stake(
+    keyed_accounts: &mut [KeyedAccount],
+    tick_height: u64,
+) -> Result<(), ProgramError> {
+    if keyed_accounts.len() < 2 {
+        error!("no vote account to delegate to");
+        Err(ProgramError::InvalidArgument)?;
+    }
+    if !vote_program::check_id(&keyed_accounts[1].account.owner) {
+        error!("account[1] is not assigned to the VOTE_PROGRAM");
+        Err(ProgramError::InvalidArgument)?;
+    }
+    match VoteProgram::deserialize(&keyed_accounts[1].account.userdata) {
+        Ok(ref vote_state) if *vote_state != VoteProgram::default() => (),
+        _ => {
+            error!("account[1] is not a registered vote account");
+            Err(ProgramError::InvalidArgument)?;
+        }
+    }
+
+    let stake_state = StakeState::deserialize(&keyed_accounts[0].account.userdata)?;
+    if stake_state.locked_stake(tick_height) > 0 {
+        error!("stake account is already delegated");
+        Err(ProgramError::InvalidArgument)?;
+    }
+
+    let stake_state = StakeState::Delegated {
+        voter_id: *keyed_accounts[1].unsigned_key(),
+        stake: keyed_accounts[0].account.tokens,
+        activation_height: tick_height,
+        deactivation_height: None,
+    };
+    stake_state.serialize(&mut keyed_accounts[0].account.userdata)
+}
+
+fn deactivate(keyed_accounts: &mut [KeyedAccount], tick_height: u64) -> Result<(), ProgramError> {
+    let mut stake_state = StakeState::deserialize(&keyed_accounts[0].account.userdata)?;
+    match stake_state {
+        StakeState::Delegated {
+            ref mut deactivation_height,
+            ..
+        } => {
+            if deactivation_height.is_some() {
+                error!("stake account is already deactivated");
+                Err(ProgramError::InvalidArgument)?;
+            }
+            *deactivation_height = Some(tick_height);
+        }
+        StakeState::Uninitialized => {
+            error!("stake account is not delegated");
+            Err(ProgramError::InvalidArgument)?;
+        }
+    }
+    stake_state.serialize(&mut keyed_accounts[0].account.userdata)
+}
+
+fn withdraw(
+    keyed_accounts: &mut [KeyedAccount],
+    tokens: u64,
+    tick_height: u64,
+) -> Result<(), ProgramError> {
+    if keyed_accounts.len() < 2 {
+        error!("no account to withdraw to");
+        Err(ProgramError::InvalidArgument)?;
+    }
+
+    let stake_state = StakeState::deserialize(&keyed_accounts[0].account.userdata)?;
+    if tokens > keyed_accounts[0].account.tokens {
+        info!(
+            "Withdraw: insufficient tokens ({}, need {})",
+            keyed_accounts[0].account.tokens, tokens
+        );
+        Err(ProgramError::ResultWithNegativeTokens)?;
+    }
+    let locked_stake = stake_state.locked_stake(tick_height);
+    if keyed_accounts[0].account.tokens - tokens < locked_stake {
+        info!(
+            "Withdraw: {} tokens are locked by a delegation",
+            locked_stake
+        );
+        Err(ProgramError::InvalidArgument)?;
+    }
+    keyed_accounts[0].account.tokens -= tokens;
+    keyed_accounts[1].account.tokens += tokens;
+
+    Ok(())
+}
+
+solana_entrypoint!(entrypoint);
+fn entrypoint(
+    _program_id: &Pubkey,
+    keyed_accounts: &mut [KeyedAccount],
+    data: &[u8],
+    tick_height: u64,
+) -> Result<(), ProgramError> {
+    solana_logger::setup();
+
+    trace!("process_instruction: {:?}", data);
+    trace!("keyed_accounts: {:?}", keyed_accounts);
+
+    // all stake instructions require that accounts_keys[0] be a signer
+    if keyed_accounts[0].signer_key().is_none() {
+        error!("account[0] is unsigned");
+        Err(ProgramError::InvalidArgument)?;
+    }
+    if !stake_program::check_id(&keyed_accounts[0].account.owner) {
+        error!("account[0] is not assigned to the STAKE_PROGRAM");
+        Err(ProgramError::InvalidArgument)?;
+    }
+
+    match deserialize(data).map_err(|_| ProgramError::InvalidUserdata)? {
+        StakeInstruction::DelegateStake => delegate_stake(keyed_accounts, tick_height),
+        StakeInstruction::Deactivate => deactivate(keyed_accounts, tick_height),
+        StakeInstruction::Withdraw(tokens) => withdraw(keyed_accounts, tokens, tick_height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::Account;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
+    use solana_sdk::stake_program::{STAKE_COOLDOWN_TICKS, STAKE_WARMUP_TICKS};
+
+    fn create_stake_account(tokens: u64) -> Account {
+        Account::new(tokens, stake_program::get_max_size(), stake_program::id())
+    }
+
+    fn create_vote_account() -> Account {
+        let mut vote_account = Account::new(1, vote_program::get_max_size(), vote_program::id());
+        let vote_id = Keypair::new().pubkey();
+        VoteProgram::new(Keypair::new().pubkey(), vote_id)
+            .serialize(&mut vote_account.userdata)
+            .unwrap();
+        vote_account
+    }
+
+    #[test]
+    fn test_delegate_stake() {
+        let stake_id = Keypair::new().pubkey();
+        let mut stake_account = create_stake_account(100);
+
+        let vote_id = Keypair::new().pubkey();
+        let mut vote_account = create_vote_account();
+
+        // Stake can only be delegated to a "vote account"
+        let not_vote_id = Keypair::new().pubkey();
+        let mut not_vote_account = Account::new(1, 0, Pubkey::default());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&not_vote_id, false, &mut not_vote_account),
+        ];
+        assert_eq!(
+            delegate_stake(&mut keyed_accounts, 10),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // ...and only once the "vote account" is registered
+        let mut unregistered_vote_account =
+            Account::new(1, vote_program::get_max_size(), vote_program::id());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&vote_id, false, &mut unregistered_vote_account),
+        ];
+        assert_eq!(
+            delegate_stake(&mut keyed_accounts, 10),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&vote_id, false, &mut vote_account),
+        ];
+        delegate_stake(&mut keyed_accounts, 10).unwrap();
+        assert_eq!(
+            delegate_stake(&mut keyed_accounts, 11),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let stake_state = StakeState::deserialize(&stake_account.userdata).unwrap();
+        assert_eq!(
+            stake_state,
+            StakeState::Delegated {
+                voter_id: vote_id,
+                stake: 100,
+                activation_height: 10,
+                deactivation_height: None,
+            }
+        );
+        assert_eq!(stake_state.active_stake(10 + STAKE_WARMUP_TICKS), 100);
+    }
+
+    #[test]
+    fn test_deactivate_and_withdraw() {
+        let stake_id = Keypair::new().pubkey();
+        let mut stake_account = create_stake_account(100);
+
+        let vote_id = Keypair::new().pubkey();
+        let mut vote_account = create_vote_account();
+
+        let to_id = Keypair::new().pubkey();
+        let mut to_account = Account::new(0, 0, Pubkey::default());
+
+        // Undelegated tokens may be withdrawn freely, but can't be deactivated
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        assert_eq!(
+            deactivate(&mut keyed_accounts, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 101, 0),
+            Err(ProgramError::ResultWithNegativeTokens)
+        );
+        withdraw(&mut keyed_accounts, 10, 0).unwrap();
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&vote_id, false, &mut vote_account),
+        ];
+        delegate_stake(&mut keyed_accounts, 0).unwrap();
+
+        // Delegated tokens are locked until they cool down
+        let mut keyed_accounts = [
+            KeyedAccount::new(&stake_id, true, &mut stake_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 1, STAKE_WARMUP_TICKS),
+            Err(ProgramError::InvalidArgument)
+        );
+        deactivate(&mut keyed_accounts, STAKE_WARMUP_TICKS).unwrap();
+        assert_eq!(
+            deactivate(&mut keyed_accounts, STAKE_WARMUP_TICKS),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 90, STAKE_WARMUP_TICKS),
+            Err(ProgramError::InvalidArgument)
+        );
+        withdraw(
+            &mut keyed_accounts,
+            90,
+            STAKE_WARMUP_TICKS + STAKE_COOLDOWN_TICKS,
+        )
+        .unwrap();
+        assert_eq!(stake_account.tokens, 0);
+        assert_eq!(to_account.tokens, 100);
+    }
+}
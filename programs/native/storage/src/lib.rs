@@ -10,18 +10,88 @@ use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::solana_entrypoint;
 use solana_sdk::storage_program::*;
+use solana_sdk::vote_program::{self, VoteProgram};
 
 pub const TOTAL_VALIDATOR_REWARDS: u64 = 1000;
 pub const TOTAL_REPLICATOR_REWARDS: u64 = 1000;
 
-fn count_valid_proofs(proofs: &[ProofStatus]) -> u64 {
-    let mut num = 0;
-    for proof in proofs {
-        if let ProofStatus::Valid = proof {
-            num += 1;
+// A proof takes the verdict of the majority of the validators that checked it, so no
+// single validator can veto an honest proof. Ties are treated as unchecked
+fn get_proof_statuses(proofs: &[ProofInfo], validations: &[ValidationInfo]) -> Vec<ProofStatus> {
+    (0..proofs.len())
+        .map(|i| {
+            let mut num_valid = 0;
+            let mut num_not_valid = 0;
+            for validation in validations {
+                match validation.proof_mask.get(i) {
+                    Some(ProofStatus::Valid) => num_valid += 1,
+                    Some(ProofStatus::NotValid) => num_not_valid += 1,
+                    _ => (),
+                }
+            }
+            if num_valid > num_not_valid {
+                ProofStatus::Valid
+            } else if num_not_valid > num_valid {
+                ProofStatus::NotValid
+            } else {
+                ProofStatus::Skipped
+            }
+        })
+        .collect()
+}
+
+// Count the verdicts in `proof_mask` that agree with the majority verdict
+fn count_agreeing_verdicts(proof_mask: &[ProofStatus], statuses: &[ProofStatus]) -> u64 {
+    proof_mask
+        .iter()
+        .zip(statuses)
+        .filter(|(verdict, status)| verdict == status && **status != ProofStatus::Skipped)
+        .count() as u64
+}
+
+fn get_validator_reward(id: &Pubkey, proofs: &[ProofInfo], validations: &[ValidationInfo]) -> u64 {
+    let statuses = get_proof_statuses(proofs, validations);
+    let mut num_validations = 0;
+    let mut total_validations = 0;
+    for validation in validations {
+        let num_agreeing = count_agreeing_verdicts(&validation.proof_mask, &statuses);
+        if *id == validation.id {
+            num_validations += num_agreeing;
+        }
+        total_validations += num_agreeing;
+    }
+    if total_validations > 0 {
+        (TOTAL_VALIDATOR_REWARDS * num_validations) / total_validations
+    } else {
+        0
+    }
+}
+
+fn get_replicator_reward(id: &Pubkey, proofs: &[ProofInfo], validations: &[ValidationInfo]) -> u64 {
+    let mut num_proofs = 0;
+    let mut total_proofs = 0;
+    for (proof, status) in proofs.iter().zip(get_proof_statuses(proofs, validations)) {
+        match status {
+            ProofStatus::Valid => {
+                total_proofs += 1;
+                if *id == proof.id {
+                    num_proofs += 1;
+                }
+            }
+            ProofStatus::NotValid => {
+                if *id == proof.id {
+                    info!("replicator {:?} submitted an invalid proof", id);
+                    return 0;
+                }
+            }
+            ProofStatus::Skipped => (),
         }
     }
-    num
+    if total_proofs > 0 {
+        (TOTAL_REPLICATOR_REWARDS * num_proofs) / total_proofs
+    } else {
+        0
+    }
 }
 
 solana_entrypoint!(entrypoint);
@@ -33,7 +103,7 @@ fn entrypoint(
 ) -> Result<(), ProgramError> {
     solana_logger::setup();
 
-    if keyed_accounts.len() != 2 {
+    if keyed_accounts.len() < 2 {
         // keyed_accounts[1] should be the main storage key
         // to access its userdata
         Err(ProgramError::InvalidArgument)?;
@@ -67,6 +137,15 @@ fn entrypoint(
             "deserialized state height: {}",
             storage_account_state.entry_height
         );
+        // Only a proof validation names another account, the validator's vote account
+        let num_accounts = match syscall {
+            StorageProgram::ProofValidation { .. } => 3,
+            _ => 2,
+        };
+        if keyed_accounts.len() != num_accounts {
+            Err(ProgramError::InvalidArgument)?;
+        }
+
         match syscall {
             StorageProgram::SubmitMiningProof {
                 sha_state,
@@ -85,12 +164,23 @@ fn entrypoint(
                     sha_state, entry_height
                 );
 
+                // Validators refer to proofs by their signatures, so they must be unique
+                let proofs = &mut storage_account_state.proofs[segment_index];
+                if proofs.iter().any(|proof| proof.signature == signature) {
+                    info!("proof {:?} was already submitted", signature);
+                    return Err(ProgramError::InvalidArgument);
+                }
+                if proofs.len() >= MAX_PROOFS_PER_SEGMENT {
+                    info!("segment {} has no room for more proofs", segment_index);
+                    return Err(ProgramError::InvalidArgument);
+                }
+
                 let proof_info = ProofInfo {
                     id: *keyed_accounts[0].signer_key().unwrap(),
                     sha_state,
                     signature,
                 };
-                storage_account_state.proofs[segment_index].push(proof_info);
+                proofs.push(proof_info);
             }
             StorageProgram::AdvertiseStorageLastId { id, entry_height } => {
                 let original_segments = storage_account_state.entry_height / ENTRIES_PER_SEGMENT;
@@ -106,7 +196,8 @@ fn entrypoint(
                 storage_account_state.entry_height = entry_height;
                 storage_account_state.id = id;
 
-                // move the proofs to previous_proofs
+                // move previous_proofs to reward_proofs, and the proofs to previous_proofs
+                storage_account_state.reward_proofs = storage_account_state.previous_proofs.clone();
                 storage_account_state.previous_proofs = storage_account_state.proofs.clone();
                 storage_account_state.proofs.clear();
                 storage_account_state
@@ -120,50 +211,116 @@ fn entrypoint(
                 storage_account_state
                     .lockout_validations
                     .resize(segments as usize, Vec::new());
+
+                // nobody has claimed the new reward_validations yet
+                storage_account_state.reward_claims.clear();
+                storage_account_state
+                    .reward_claims
+                    .resize(storage_account_state.reward_validations.len(), Vec::new());
             }
             StorageProgram::ProofValidation {
                 entry_height,
-                proof_mask,
+                proof_statuses,
             } => {
                 if entry_height >= storage_account_state.entry_height {
                     return Err(ProgramError::InvalidArgument);
                 }
 
+                // Only validators with a registered vote account may validate proofs
+                let id = *keyed_accounts[0].signer_key().unwrap();
+                if !vote_program::check_id(&keyed_accounts[2].account.owner) {
+                    info!("account[2] is not assigned to the VOTE_PROGRAM");
+                    return Err(ProgramError::InvalidArgument);
+                }
+                let vote_state = VoteProgram::deserialize(&keyed_accounts[2].account.userdata)?;
+                if vote_state.node_id != id {
+                    info!("{:?} is not the node of vote account {:?}", id, vote_state);
+                    return Err(ProgramError::InvalidArgument);
+                }
+
                 let segment_index = get_segment_from_entry(entry_height);
-                if storage_account_state.previous_proofs[segment_index].len() != proof_mask.len() {
+                let proofs = match storage_account_state.previous_proofs.get(segment_index) {
+                    Some(proofs) => proofs,
+                    None => return Err(ProgramError::InvalidArgument),
+                };
+                if proof_statuses
+                    .iter()
+                    .any(|(signature, _)| !proofs.iter().any(|proof| proof.signature == *signature))
+                {
+                    info!(
+                        "validation of an unknown proof in segment {}",
+                        segment_index
+                    );
                     return Err(ProgramError::InvalidArgument);
                 }
 
-                // TODO: Check that each proof mask matches the signature
-                /*for (i, entry) in proof_mask.iter().enumerate() {
-                    if storage_account_state.previous_proofs[segment_index][i] != signature.as_ref[0] {
-                        return Err(ProgramError::InvalidArgument);
-                    }
-                }*/
+                // Line the verdicts up with the proofs they're for
+                let proof_mask = proofs
+                    .iter()
+                    .map(|proof| {
+                        proof_statuses
+                            .iter()
+                            .find(|(signature, _)| *signature == proof.signature)
+                            .map_or(ProofStatus::Skipped, |(_, status)| status.clone())
+                    })
+                    .collect();
+
+                let validations = &mut storage_account_state.lockout_validations[segment_index];
+                if validations.iter().any(|validation| validation.id == id) {
+                    info!(
+                        "validator {:?} already validated segment {}",
+                        id, segment_index
+                    );
+                    return Err(ProgramError::InvalidArgument);
+                }
+                if validations.len() >= MAX_VALIDATIONS_PER_SEGMENT {
+                    info!("segment {} has no room for more validations", segment_index);
+                    return Err(ProgramError::InvalidArgument);
+                }
 
-                let info = ValidationInfo {
-                    id: *keyed_accounts[0].signer_key().unwrap(),
-                    proof_mask,
-                };
-                storage_account_state.lockout_validations[segment_index].push(info);
+                let info = ValidationInfo { id, proof_mask };
+                validations.push(info);
             }
             StorageProgram::ClaimStorageReward { entry_height } => {
                 let claims_index = get_segment_from_entry(entry_height);
-                let account_key = keyed_accounts[0].signer_key().unwrap();
-                let mut num_validations = 0;
-                let mut total_validations = 0;
-                for validation in &storage_account_state.reward_validations[claims_index] {
-                    if *account_key == validation.id {
-                        num_validations += count_valid_proofs(&validation.proof_mask);
-                    } else {
-                        total_validations += count_valid_proofs(&validation.proof_mask);
-                    }
+                if claims_index >= storage_account_state.reward_validations.len() {
+                    return Err(ProgramError::InvalidArgument);
                 }
-                total_validations += num_validations;
-                if total_validations > 0 {
-                    keyed_accounts[0].account.tokens +=
-                        (TOTAL_VALIDATOR_REWARDS * num_validations) / total_validations;
+
+                let account_key = *keyed_accounts[0].signer_key().unwrap();
+                if storage_account_state.reward_claims[claims_index].contains(&account_key) {
+                    info!(
+                        "{:?} already claimed the reward for segment {}",
+                        account_key, claims_index
+                    );
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                let validations = &storage_account_state.reward_validations[claims_index];
+                let proofs = storage_account_state
+                    .reward_proofs
+                    .get(claims_index)
+                    .map_or(&[][..], |proofs| &proofs[..]);
+                let reward = get_validator_reward(&account_key, proofs, validations)
+                    + get_replicator_reward(&account_key, proofs, validations);
+                if reward == 0 {
+                    info!(
+                        "{:?} has no reward to claim for segment {}",
+                        account_key, claims_index
+                    );
+                    return Err(ProgramError::InvalidArgument);
                 }
+
+                if reward > keyed_accounts[1].account.tokens {
+                    info!(
+                        "storage reward pool is exhausted ({}, need {})",
+                        keyed_accounts[1].account.tokens, reward
+                    );
+                    return Err(ProgramError::ResultWithNegativeTokens);
+                }
+                keyed_accounts[1].account.tokens -= reward;
+                keyed_accounts[0].account.tokens += reward;
+                storage_account_state.reward_claims[claims_index].push(account_key);
             }
         }
 
@@ -223,6 +380,14 @@ mod test {
         ret
     }
 
+    fn create_vote_account(node_id: Pubkey) -> Account {
+        let mut vote_account = Account::new(1, vote_program::get_max_size(), vote_program::id());
+        VoteProgram::new(node_id, Keypair::new().pubkey())
+            .serialize(&mut vote_account.userdata)
+            .unwrap();
+        vote_account
+    }
+
     #[test]
     fn test_storage_tx() {
         let keypair = Keypair::new();
@@ -324,7 +489,12 @@ mod test {
     fn test_validate_mining() {
         solana_logger::setup();
         let keypair = Keypair::new();
-        let mut accounts = [Account::default(), Account::default()];
+        let vote_id = Keypair::new().pubkey();
+        let mut accounts = [
+            Account::default(),
+            Account::default(),
+            create_vote_account(keypair.pubkey()),
+        ];
         accounts[1].userdata.resize(16 * 1024, 0);
 
         let entry_height = 0;
@@ -357,9 +527,10 @@ mod test {
 
         let tx = Transaction::storage_new_proof_validation(
             &keypair,
+            vote_id,
             Hash::default(),
             entry_height,
-            vec![ProofStatus::Valid],
+            vec![(Signature::default(), ProofStatus::Valid)],
         );
         test_transaction(&tx, &mut accounts).unwrap();
 
@@ -371,9 +542,282 @@ mod test {
         );
         test_transaction(&tx, &mut accounts).unwrap();
 
+        // The reward pool can't cover the claim
         let tx = Transaction::storage_new_reward_claim(&keypair, Hash::default(), entry_height);
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::ResultWithNegativeTokens)
+        );
+
+        accounts[1].tokens = TOTAL_VALIDATOR_REWARDS + TOTAL_REPLICATOR_REWARDS;
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        // keypair is both the replicator and the validator of the proof
+        assert_eq!(
+            accounts[0].tokens,
+            TOTAL_VALIDATOR_REWARDS + TOTAL_REPLICATOR_REWARDS
+        );
+        assert_eq!(accounts[1].tokens, 0);
+
+        // Rewards can only be claimed once per segment
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_proof_statuses_majority() {
+        let proofs = vec![ProofInfo::default(); 3];
+        let validation = |proof_mask| ValidationInfo {
+            id: Keypair::new().pubkey(),
+            proof_mask,
+        };
+        let validations = vec![
+            validation(vec![
+                ProofStatus::NotValid,
+                ProofStatus::NotValid,
+                ProofStatus::NotValid,
+            ]),
+            validation(vec![ProofStatus::Valid, ProofStatus::Valid]),
+            validation(vec![ProofStatus::Valid]),
+        ];
+
+        // A lone dissenting validator doesn't override the others, and ties count as unchecked
+        assert_eq!(
+            get_proof_statuses(&proofs, &validations),
+            vec![
+                ProofStatus::Valid,
+                ProofStatus::Skipped,
+                ProofStatus::NotValid
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_proof_forfeits_reward() {
+        solana_logger::setup();
+        let replicator = Keypair::new();
+        let validator = Keypair::new();
+        let vote_id = Keypair::new().pubkey();
+        let mut accounts = [
+            Account::default(),
+            Account::default(),
+            create_vote_account(validator.pubkey()),
+        ];
+        accounts[1].userdata.resize(16 * 1024, 0);
+        accounts[1].tokens = TOTAL_VALIDATOR_REWARDS + TOTAL_REPLICATOR_REWARDS;
+
+        let entry_height = 0;
+
+        let tx = Transaction::storage_new_advertise_last_id(
+            &validator,
+            Hash::default(),
+            Hash::default(),
+            ENTRIES_PER_SEGMENT,
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let tx = Transaction::storage_new_mining_proof(
+            &replicator,
+            Hash::default(),
+            Hash::default(),
+            entry_height,
+            Signature::default(),
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let tx = Transaction::storage_new_advertise_last_id(
+            &validator,
+            Hash::default(),
+            Hash::default(),
+            ENTRIES_PER_SEGMENT * 2,
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let tx = Transaction::storage_new_proof_validation(
+            &validator,
+            vote_id,
+            Hash::default(),
+            entry_height,
+            vec![(Signature::default(), ProofStatus::NotValid)],
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        // A validator may only validate a segment once
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let tx = Transaction::storage_new_advertise_last_id(
+            &validator,
+            Hash::default(),
+            Hash::default(),
+            ENTRIES_PER_SEGMENT * 3,
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let tx = Transaction::storage_new_reward_claim(&replicator, Hash::default(), entry_height);
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(accounts[0].tokens, 0);
+
+        // The validator that caught the invalid proof is paid for it
+        let tx = Transaction::storage_new_reward_claim(&validator, Hash::default(), entry_height);
+        test_transaction(&tx, &mut accounts).unwrap();
+        assert_eq!(accounts[0].tokens, TOTAL_VALIDATOR_REWARDS);
+        assert_eq!(accounts[1].tokens, TOTAL_REPLICATOR_REWARDS);
+    }
+
+    #[test]
+    fn test_proof_validation_by_unregistered_validator() {
+        solana_logger::setup();
+        let keypair = Keypair::new();
+        let vote_id = Keypair::new().pubkey();
+        let mut accounts = [
+            Account::default(),
+            Account::default(),
+            create_vote_account(Keypair::new().pubkey()),
+        ];
+        accounts[1].userdata.resize(16 * 1024, 0);
+
+        for entry_height in &[ENTRIES_PER_SEGMENT, ENTRIES_PER_SEGMENT * 2] {
+            let tx = Transaction::storage_new_advertise_last_id(
+                &keypair,
+                Hash::default(),
+                Hash::default(),
+                *entry_height,
+            );
+            test_transaction(&tx, &mut accounts).unwrap();
+        }
+
+        // The vote account belongs to another node
+        let tx = Transaction::storage_new_proof_validation(
+            &keypair,
+            vote_id,
+            Hash::default(),
+            0,
+            vec![],
+        );
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // ...and an account the vote program doesn't own is no vote account at all
+        accounts[2] = Account::new(1, vote_program::get_max_size(), Pubkey::default());
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        accounts[2] = create_vote_account(keypair.pubkey());
         test_transaction(&tx, &mut accounts).unwrap();
+    }
 
-        assert!(accounts[0].tokens == TOTAL_VALIDATOR_REWARDS);
+    #[test]
+    fn test_proof_validation_by_signature() {
+        solana_logger::setup();
+        let keypair = Keypair::new();
+        let vote_id = Keypair::new().pubkey();
+        let mut accounts = [
+            Account::default(),
+            Account::default(),
+            create_vote_account(keypair.pubkey()),
+        ];
+        accounts[1].userdata.resize(16 * 1024, 0);
+
+        let tx = Transaction::storage_new_advertise_last_id(
+            &keypair,
+            Hash::default(),
+            Hash::default(),
+            ENTRIES_PER_SEGMENT,
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let signatures: Vec<_> = (0..2).map(|i| Signature::new(&[i; 64])).collect();
+        for signature in &signatures {
+            let tx = Transaction::storage_new_mining_proof(
+                &keypair,
+                Hash::default(),
+                Hash::default(),
+                0,
+                *signature,
+            );
+            test_transaction(&tx, &mut accounts).unwrap();
+
+            // A proof can only be submitted once
+            assert_eq!(
+                test_transaction(&tx, &mut accounts),
+                Err(ProgramError::InvalidArgument)
+            );
+        }
+
+        let tx = Transaction::storage_new_advertise_last_id(
+            &keypair,
+            Hash::default(),
+            Hash::default(),
+            ENTRIES_PER_SEGMENT * 2,
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        // Verdicts on proofs that weren't submitted are rejected
+        let tx = Transaction::storage_new_proof_validation(
+            &keypair,
+            vote_id,
+            Hash::default(),
+            0,
+            vec![(Signature::new(&[2; 64]), ProofStatus::Valid)],
+        );
+        assert_eq!(
+            test_transaction(&tx, &mut accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // Verdicts are matched to proofs by signature, whatever order they're sent in
+        let tx = Transaction::storage_new_proof_validation(
+            &keypair,
+            vote_id,
+            Hash::default(),
+            0,
+            vec![(signatures[1], ProofStatus::NotValid)],
+        );
+        test_transaction(&tx, &mut accounts).unwrap();
+
+        let state: StorageProgramState = bincode::deserialize(&accounts[1].userdata).unwrap();
+        assert_eq!(
+            state.lockout_validations[0][0].proof_mask,
+            vec![ProofStatus::Skipped, ProofStatus::NotValid]
+        );
+    }
+
+    #[test]
+    fn test_validator_reward_agrees_with_majority() {
+        let proofs = vec![ProofInfo::default()];
+        let validators: Vec<_> = (0..3).map(|_| Keypair::new().pubkey()).collect();
+        let validations: Vec<_> = validators
+            .iter()
+            .zip(&[
+                ProofStatus::Valid,
+                ProofStatus::Valid,
+                ProofStatus::NotValid,
+            ])
+            .map(|(id, status)| ValidationInfo {
+                id: *id,
+                proof_mask: vec![status.clone()],
+            })
+            .collect();
+
+        assert_eq!(
+            get_validator_reward(&validators[0], &proofs, &validations),
+            TOTAL_VALIDATOR_REWARDS / 2
+        );
+        assert_eq!(
+            get_validator_reward(&validators[2], &proofs, &validations),
+            0
+        );
     }
 }
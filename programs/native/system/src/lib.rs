@@ -1,17 +1,19 @@
+use bincode::serialize;
 use log::*;
 use solana_sdk::account::KeyedAccount;
+use solana_sdk::hash::{extend_and_hash, hashv};
 use solana_sdk::native_program::ProgramError;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN};
 use solana_sdk::solana_entrypoint;
 use solana_sdk::system_instruction::SystemInstruction;
-use solana_sdk::system_program;
+use solana_sdk::system_program::{self, NonceState, MAX_PROGRAM_ACCOUNT_SPACE};
 
 solana_entrypoint!(entrypoint);
 pub fn entrypoint(
     _program_id: &Pubkey,
     keyed_accounts: &mut [KeyedAccount],
     data: &[u8],
-    _tick_height: u64,
+    tick_height: u64,
 ) -> Result<(), ProgramError> {
     if let Ok(syscall) = bincode::deserialize(data) {
         trace!("process_instruction: {:?}", syscall);
@@ -90,6 +92,112 @@ pub fn entrypoint(
                 keyed_accounts[from].account.loader = keyed_accounts[from].account.owner;
                 keyed_accounts[from].account.owner = *keyed_accounts[from].signer_key().unwrap();
             }
+            SystemInstruction::InitializeNonce => {
+                if !system_program::check_id(&keyed_accounts[from].account.owner) {
+                    info!("InitializeNonce: invalid account[from] owner");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                if NonceState::deserialize(&keyed_accounts[from].account.userdata)?
+                    != NonceState::Uninitialized
+                {
+                    info!("InitializeNonce: account[from] is already initialized");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                // Seed the nonce with the tick height so that a nonce account recreated
+                // at the same key doesn't reuse old nonces
+                let nonce = hashv(&[
+                    keyed_accounts[from].unsigned_key().as_ref(),
+                    &serialize(&tick_height).unwrap(),
+                ]);
+                NonceState::Initialized { nonce }
+                    .serialize(&mut keyed_accounts[from].account.userdata)?;
+            }
+            SystemInstruction::AdvanceNonce => {
+                if !system_program::check_id(&keyed_accounts[from].account.owner) {
+                    info!("AdvanceNonce: invalid account[from] owner");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                match NonceState::deserialize(&keyed_accounts[from].account.userdata)? {
+                    NonceState::Initialized { nonce } => {
+                        let nonce = extend_and_hash(&nonce, &serialize(&tick_height).unwrap());
+                        NonceState::Initialized { nonce }
+                            .serialize(&mut keyed_accounts[from].account.userdata)?;
+                    }
+                    NonceState::Uninitialized => {
+                        info!("AdvanceNonce: account[from] is not initialized");
+                        Err(ProgramError::InvalidArgument)?;
+                    }
+                }
+            }
+            SystemInstruction::CreateProgramAccount {
+                seeds,
+                tokens,
+                space,
+                program_id,
+            } => {
+                let to = 1;
+                if !system_program::check_id(&keyed_accounts[from].account.owner) {
+                    info!("CreateProgramAccount: invalid account[from] owner");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                if seeds.first().map(Vec::as_slice)
+                    != Some(keyed_accounts[from].unsigned_key().as_ref())
+                {
+                    info!("CreateProgramAccount: the first seed isn't account[from]");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                if space > MAX_PROGRAM_ACCOUNT_SPACE {
+                    info!(
+                        "CreateProgramAccount: space {} exceeds {}",
+                        space, MAX_PROGRAM_ACCOUNT_SPACE
+                    );
+                    Err(ProgramError::InvalidArgument)?;
+                }
+
+                let seeds: Vec<_> = seeds.iter().map(Vec::as_slice).collect();
+                let address =
+                    Pubkey::create_program_address(&seeds, &program_id).map_err(|err| {
+                        info!("CreateProgramAccount: invalid seeds: {:?}", err);
+                        ProgramError::InvalidArgument
+                    })?;
+                if *keyed_accounts[to].unsigned_key() != address {
+                    info!(
+                        "CreateProgramAccount: account[to] is not the program address {}",
+                        address
+                    );
+                    Err(ProgramError::InvalidArgument)?;
+                }
+
+                // Tokens may have been moved to the address before it was claimed, but an
+                // account already in use can't be claimed again
+                if !keyed_accounts[to].account.userdata.is_empty()
+                    || !system_program::check_id(&keyed_accounts[to].account.owner)
+                {
+                    info!("CreateProgramAccount: account[to] is already in use");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+                if tokens > keyed_accounts[from].account.tokens {
+                    info!(
+                        "CreateProgramAccount: insufficient tokens ({}, need {})",
+                        keyed_accounts[from].account.tokens, tokens
+                    );
+                    Err(ProgramError::ResultWithNegativeTokens)?;
+                }
+                keyed_accounts[from].account.tokens -= tokens;
+                keyed_accounts[to].account.tokens += tokens;
+                keyed_accounts[to].account.owner = program_id;
+                keyed_accounts[to].account.userdata = vec![0; space as usize];
+                keyed_accounts[to].account.executable = false;
+                keyed_accounts[to].account.loader = Pubkey::default();
+            }
+            SystemInstruction::SignForProgramAddress { seeds } => {
+                // The runtime derives the address from the seeds and each instruction's
+                // program when the transaction's other instructions run
+                if seeds.len() > MAX_SEEDS || seeds.iter().any(|seed| seed.len() > MAX_SEED_LEN) {
+                    info!("SignForProgramAddress: seeds exceed the limits");
+                    Err(ProgramError::InvalidArgument)?;
+                }
+            }
         }
         Ok(())
     } else {
@@ -97,3 +205,187 @@ pub fn entrypoint(
         Err(ProgramError::InvalidArgument)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::Account;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
+
+    fn process(
+        keyed_accounts: &mut [KeyedAccount],
+        instruction: &SystemInstruction,
+        tick_height: u64,
+    ) -> Result<(), ProgramError> {
+        let data = serialize(instruction).unwrap();
+        entrypoint(&system_program::id(), keyed_accounts, &data, tick_height)
+    }
+
+    fn get_nonce(account: &Account) -> NonceState {
+        NonceState::deserialize(&account.userdata).unwrap()
+    }
+
+    #[test]
+    fn test_nonce_account() {
+        let nonce_id = Keypair::new().pubkey();
+        let mut nonce_account = Account::new(1, NonceState::get_max_size(), system_program::id());
+
+        let mut keyed_accounts = [KeyedAccount::new(&nonce_id, true, &mut nonce_account)];
+        assert_eq!(
+            process(&mut keyed_accounts, &SystemInstruction::AdvanceNonce, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        process(&mut keyed_accounts, &SystemInstruction::InitializeNonce, 0).unwrap();
+        assert_eq!(
+            process(&mut keyed_accounts, &SystemInstruction::InitializeNonce, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        let nonce_state = get_nonce(&nonce_account);
+        assert_ne!(nonce_state, NonceState::Uninitialized);
+
+        let mut keyed_accounts = [KeyedAccount::new(&nonce_id, true, &mut nonce_account)];
+        process(&mut keyed_accounts, &SystemInstruction::AdvanceNonce, 1).unwrap();
+        assert_ne!(get_nonce(&nonce_account), nonce_state);
+
+        // Only the nonce account may advance its nonce
+        let mut keyed_accounts = [KeyedAccount::new(&nonce_id, false, &mut nonce_account)];
+        assert_eq!(
+            process(&mut keyed_accounts, &SystemInstruction::AdvanceNonce, 2),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_nonce_account_unowned() {
+        let nonce_id = Keypair::new().pubkey();
+        let mut nonce_account = Account::new(1, NonceState::get_max_size(), Pubkey::new(&[1; 32]));
+        let mut keyed_accounts = [KeyedAccount::new(&nonce_id, true, &mut nonce_account)];
+        assert_eq!(
+            process(&mut keyed_accounts, &SystemInstruction::InitializeNonce, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_create_program_account() {
+        let from_id = Keypair::new().pubkey();
+        let mut from_account = Account::new(100, 0, system_program::id());
+        let program_id = Keypair::new().pubkey();
+        let (address, bump_seed) =
+            Pubkey::find_program_address(&[from_id.as_ref(), b"vault"], &program_id);
+        let mut to_account = Account::new(0, 0, system_program::id());
+
+        let create = |seeds: &[&[u8]], space, program_id| SystemInstruction::CreateProgramAccount {
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+            tokens: 10,
+            space,
+            program_id,
+        };
+
+        // The new account must be at the address derived from the seeds and program
+        let mut keyed_accounts = [
+            KeyedAccount::new(&from_id, true, &mut from_account),
+            KeyedAccount::new(&address, false, &mut to_account),
+        ];
+        assert_eq!(
+            process(
+                &mut keyed_accounts,
+                &create(&[from_id.as_ref(), b"vault"], 8, program_id),
+                0
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            process(
+                &mut keyed_accounts,
+                &create(&[from_id.as_ref(), b"vault", &[bump_seed]], 8, from_id),
+                0
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // Nor can it take more than the maximum space
+        let seeds: &[&[u8]] = &[from_id.as_ref(), b"vault", &[bump_seed]];
+        assert_eq!(
+            process(
+                &mut keyed_accounts,
+                &create(seeds, MAX_PROGRAM_ACCOUNT_SPACE + 1, program_id),
+                0
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let instruction = create(seeds, 8, program_id);
+        process(&mut keyed_accounts, &instruction, 0).unwrap();
+        assert_eq!(
+            process(&mut keyed_accounts, &instruction, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(from_account.tokens, 90);
+        assert_eq!(to_account.tokens, 10);
+        assert_eq!(to_account.owner, program_id);
+        assert_eq!(to_account.userdata, vec![0; 8]);
+    }
+
+    #[test]
+    fn test_create_program_account_for_someone_else() {
+        let from_id = Keypair::new().pubkey();
+        let mut from_account = Account::new(100, 0, system_program::id());
+        let program_id = Keypair::new().pubkey();
+        let other_id = Keypair::new().pubkey();
+        let (address, bump_seed) =
+            Pubkey::find_program_address(&[other_id.as_ref(), b"vault"], &program_id);
+        let mut to_account = Account::new(0, 0, system_program::id());
+
+        // Claiming an address derived for another account would let anyone occupy it first
+        let instruction = SystemInstruction::CreateProgramAccount {
+            seeds: vec![
+                other_id.as_ref().to_vec(),
+                b"vault".to_vec(),
+                vec![bump_seed],
+            ],
+            tokens: 10,
+            space: 8,
+            program_id,
+        };
+        let mut keyed_accounts = [
+            KeyedAccount::new(&from_id, true, &mut from_account),
+            KeyedAccount::new(&address, false, &mut to_account),
+        ];
+        assert_eq!(
+            process(&mut keyed_accounts, &instruction, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(from_account.tokens, 100);
+        assert_eq!(to_account.owner, system_program::id());
+    }
+
+    #[test]
+    fn test_sign_for_program_address() {
+        let from_id = Keypair::new().pubkey();
+        let mut from_account = Account::new(100, 0, system_program::id());
+        let address = Keypair::new().pubkey();
+        let mut to_account = Account::new(10, 0, Keypair::new().pubkey());
+
+        let sign = |seeds: Vec<Vec<u8>>| SystemInstruction::SignForProgramAddress { seeds };
+        let mut keyed_accounts = [
+            KeyedAccount::new(&from_id, true, &mut from_account),
+            KeyedAccount::new(&address, false, &mut to_account),
+        ];
+        process(&mut keyed_accounts, &sign(vec![b"vault".to_vec()]), 0).unwrap();
+        assert_eq!(
+            process(
+                &mut keyed_accounts,
+                &sign(vec![vec![0; MAX_SEED_LEN + 1]]),
+                0
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            process(&mut keyed_accounts, &sign(vec![vec![]; MAX_SEEDS + 1]), 0),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(from_account.tokens, 100);
+        assert_eq!(to_account.tokens, 10);
+    }
+}
@@ -10,42 +10,125 @@ use solana_sdk::solana_entrypoint;
 use solana_sdk::vote_program::{self, Vote, VoteInstruction, VoteProgram};
 
 fn register(keyed_accounts: &mut [KeyedAccount]) -> Result<(), ProgramError> {
+    if keyed_accounts[0].signer_key().is_none() {
+        error!("account[0] is unsigned");
+        Err(ProgramError::InvalidArgument)?;
+    }
+
     if !vote_program::check_id(&keyed_accounts[1].account.owner) {
         error!("account[1] is not assigned to the VOTE_PROGRAM");
         Err(ProgramError::InvalidArgument)?;
     }
 
+    // A registered "vote account" belongs to its validator, so it may not be registered again
+    if let Ok(vote_state) = VoteProgram::deserialize(&keyed_accounts[1].account.userdata) {
+        if vote_state != VoteProgram::default() {
+            error!("account[1] is already registered to {}", vote_state.node_id);
+            Err(ProgramError::InvalidArgument)?;
+        }
+    }
+
     // TODO: a single validator could register multiple "vote accounts"
     // which would clutter the "accounts" structure. See github issue 1654.
-    let vote_state = VoteProgram::new(*keyed_accounts[0].signer_key().unwrap());
+    //
+    // The "vote account" signs its own votes until the validator authorizes another voter
+    let vote_state = VoteProgram::new(
+        *keyed_accounts[0].signer_key().unwrap(),
+        *keyed_accounts[1].unsigned_key(),
+    );
     vote_state.serialize(&mut keyed_accounts[1].account.userdata)?;
 
     Ok(())
 }
 
-fn process_vote(keyed_accounts: &mut [KeyedAccount], vote: Vote) -> Result<(), ProgramError> {
+fn deserialize_vote_state(keyed_accounts: &[KeyedAccount]) -> Result<VoteProgram, ProgramError> {
     if !vote_program::check_id(&keyed_accounts[0].account.owner) {
         error!("account[0] is not assigned to the VOTE_PROGRAM");
         Err(ProgramError::InvalidArgument)?;
     }
+    VoteProgram::deserialize(&keyed_accounts[0].account.userdata)
+}
 
-    let mut vote_state = VoteProgram::deserialize(&keyed_accounts[0].account.userdata)?;
+// Fails unless keyed_accounts[1] is `authority_id`, and signed the transaction
+fn check_authority(
+    keyed_accounts: &[KeyedAccount],
+    authority_id: &Pubkey,
+) -> Result<(), ProgramError> {
+    if keyed_accounts.len() < 2 || keyed_accounts[1].signer_key() != Some(authority_id) {
+        error!("{} did not sign", authority_id);
+        Err(ProgramError::InvalidArgument)?;
+    }
+    Ok(())
+}
 
-    // TODO: Integrity checks
-    // a) Verify the vote's bank hash matches what is expected
-    // b) Verify vote is older than previous votes
+fn process_vote(keyed_accounts: &mut [KeyedAccount], vote: Vote) -> Result<(), ProgramError> {
+    let mut vote_state = deserialize_vote_state(keyed_accounts)?;
 
-    // Only keep around the most recent MAX_VOTE_HISTORY votes
-    if vote_state.votes.len() == vote_program::MAX_VOTE_HISTORY {
-        vote_state.votes.pop_front();
+    let authorized_voter_id = vote_state.authorized_voter_id;
+    if !keyed_accounts
+        .iter()
+        .any(|keyed_account| keyed_account.signer_key() == Some(&authorized_voter_id))
+    {
+        error!("authorized voter {} did not sign", authorized_voter_id);
+        Err(ProgramError::InvalidArgument)?;
     }
 
-    vote_state.votes.push_back(vote);
+    // The vote's bank hash is recorded but not verified. Checking it is deferred until the
+    // bank keeps its recent slot hashes in an account the program can read; until then,
+    // consumers of vote accounts must not take `vote.hash` on trust
+    vote_state.process_vote(&vote)?;
     vote_state.serialize(&mut keyed_accounts[0].account.userdata)?;
 
     Ok(())
 }
 
+fn authorize_voter(
+    keyed_accounts: &mut [KeyedAccount],
+    authorized_voter_id: Pubkey,
+) -> Result<(), ProgramError> {
+    let mut vote_state = deserialize_vote_state(keyed_accounts)?;
+    check_authority(keyed_accounts, &vote_state.node_id)?;
+
+    vote_state.authorized_voter_id = authorized_voter_id;
+    vote_state.serialize(&mut keyed_accounts[0].account.userdata)?;
+
+    Ok(())
+}
+
+fn authorize_withdrawer(
+    keyed_accounts: &mut [KeyedAccount],
+    authorized_withdrawer_id: Pubkey,
+) -> Result<(), ProgramError> {
+    let mut vote_state = deserialize_vote_state(keyed_accounts)?;
+    check_authority(keyed_accounts, &vote_state.authorized_withdrawer_id)?;
+
+    vote_state.authorized_withdrawer_id = authorized_withdrawer_id;
+    vote_state.serialize(&mut keyed_accounts[0].account.userdata)?;
+
+    Ok(())
+}
+
+fn withdraw(keyed_accounts: &mut [KeyedAccount], tokens: u64) -> Result<(), ProgramError> {
+    let vote_state = deserialize_vote_state(keyed_accounts)?;
+    check_authority(keyed_accounts, &vote_state.authorized_withdrawer_id)?;
+
+    if keyed_accounts.len() < 3 {
+        error!("no account to withdraw to");
+        Err(ProgramError::InvalidArgument)?;
+    }
+    if tokens > keyed_accounts[0].account.tokens {
+        info!(
+            "Withdraw: insufficient tokens ({}, need {})",
+            keyed_accounts[0].account.tokens, tokens
+        );
+        Err(ProgramError::ResultWithNegativeTokens)?;
+    }
+    keyed_accounts[0].account.tokens -= tokens;
+    keyed_accounts[2].account.tokens += tokens;
+
+    Ok(())
+}
+
 solana_entrypoint!(entrypoint);
 fn entrypoint(
     _program_id: &Pubkey,
@@ -58,16 +141,10 @@ fn entrypoint(
     trace!("process_instruction: {:?}", data);
     trace!("keyed_accounts: {:?}", keyed_accounts);
 
-    // all vote instructions require that accounts_keys[0] be a signer
-    if keyed_accounts[0].signer_key().is_none() {
-        error!("account[0] is unsigned");
-        Err(ProgramError::InvalidArgument)?;
-    }
-
     match deserialize(data).map_err(|_| ProgramError::InvalidUserdata)? {
         VoteInstruction::RegisterAccount => register(keyed_accounts),
         VoteInstruction::NewVote(vote) => {
-            debug!("{:?} by {}", vote, keyed_accounts[0].signer_key().unwrap());
+            debug!("{:?} by {}", vote, keyed_accounts[0].unsigned_key());
             solana_metrics::submit(
                 solana_metrics::influxdb::Point::new("vote-native")
                     .add_field("count", solana_metrics::influxdb::Value::Integer(1))
@@ -75,6 +152,13 @@ fn entrypoint(
             );
             process_vote(keyed_accounts, vote)
         }
+        VoteInstruction::AuthorizeVoter(authorized_voter_id) => {
+            authorize_voter(keyed_accounts, authorized_voter_id)
+        }
+        VoteInstruction::Withdraw(tokens) => withdraw(keyed_accounts, tokens),
+        VoteInstruction::AuthorizeWithdrawer(authorized_withdrawer_id) => {
+            authorize_withdrawer(keyed_accounts, authorized_withdrawer_id)
+        }
     }
 }
 
@@ -82,8 +166,9 @@ fn entrypoint(
 mod tests {
     use super::*;
     use solana_sdk::account::Account;
+    use solana_sdk::hash::Hash;
     use solana_sdk::signature::{Keypair, KeypairUtil};
-    use solana_sdk::vote_program;
+    use solana_sdk::vote_program::{self, Lockout};
 
     fn create_vote_program(tokens: u64) -> Account {
         let space = vote_program::get_max_size();
@@ -122,9 +207,52 @@ mod tests {
 
         let vote_state = register_and_deserialize(&mut keyed_accounts).unwrap();
         assert_eq!(vote_state.node_id, voter_id);
+        assert_eq!(vote_state.authorized_voter_id, vote_state_id);
+        assert_eq!(vote_state.authorized_withdrawer_id, voter_id);
         assert!(vote_state.votes.is_empty());
     }
 
+    #[test]
+    fn test_reregistration() {
+        let node_id = Keypair::new().pubkey();
+        let mut node_account = Account::new(100, 0, Pubkey::default());
+
+        let vote_state_id = Keypair::new().pubkey();
+        let mut vote_state_account = create_vote_program(100);
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        register(&mut keyed_accounts).unwrap();
+
+        // Another validator may not take over a registered "vote account"
+        let attacker_id = Keypair::new().pubkey();
+        let mut attacker_account = Account::new(100, 0, Pubkey::default());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&attacker_id, true, &mut attacker_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        assert_eq!(
+            register(&mut keyed_accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // Nor may its own validator reset it
+        let mut keyed_accounts = [
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        assert_eq!(
+            register(&mut keyed_accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let vote_state = VoteProgram::deserialize(&vote_state_account.userdata).unwrap();
+        assert_eq!(vote_state.node_id, node_id);
+        assert_eq!(vote_state.authorized_withdrawer_id, node_id);
+    }
+
     #[test]
     fn test_vote() {
         let voter_id = Keypair::new().pubkey();
@@ -144,24 +272,184 @@ mod tests {
             true,
             &mut vote_state_account,
         )];
-        let vote = Vote::new(1);
+        let vote = Vote::new(1, Hash::default());
         let vote_state = vote_and_deserialize(&mut keyed_accounts, vote.clone()).unwrap();
-        assert_eq!(vote_state.votes, vec![vote]);
+        assert_eq!(vote_state.votes, vec![Lockout::new(&vote)]);
+
+        // Votes must be for increasing slots
+        assert_eq!(
+            vote_and_deserialize(&mut keyed_accounts, vote),
+            Err(ProgramError::InvalidArgument)
+        );
+        let vote = Vote::new(2, Hash::default());
+        let vote_state = vote_and_deserialize(&mut keyed_accounts, vote).unwrap();
+        assert_eq!(vote_state.votes.len(), 2);
     }
 
     #[test]
-    // TODO: Should this work?
     fn test_vote_without_registration() {
         let vote_state_id = Keypair::new().pubkey();
         let mut vote_state_account = create_vote_program(100);
 
+        // An unregistered "vote account" has no authorized voter
         let mut keyed_accounts = [KeyedAccount::new(
             &vote_state_id,
             true,
             &mut vote_state_account,
         )];
-        let vote = Vote::new(1);
+        let vote = Vote::new(1, Hash::default());
+        assert_eq!(
+            vote_and_deserialize(&mut keyed_accounts, vote),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_authorize_voter() {
+        let node_id = Keypair::new().pubkey();
+        let mut node_account = Account::new(100, 0, Pubkey::default());
+
+        let vote_state_id = Keypair::new().pubkey();
+        let mut vote_state_account = create_vote_program(100);
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        register(&mut keyed_accounts).unwrap();
+
+        // Only the validator may authorize a new voter
+        let voter_id = Keypair::new().pubkey();
+        let mut voter_account = Account::new(0, 0, Pubkey::default());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, true, &mut vote_state_account),
+            KeyedAccount::new(&voter_id, true, &mut voter_account),
+        ];
+        assert_eq!(
+            authorize_voter(&mut keyed_accounts, voter_id),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&node_id, true, &mut node_account),
+        ];
+        authorize_voter(&mut keyed_accounts, voter_id).unwrap();
+
+        // The "vote account" may no longer sign its own votes
+        let mut keyed_accounts = [KeyedAccount::new(
+            &vote_state_id,
+            true,
+            &mut vote_state_account,
+        )];
+        let vote = Vote::new(1, Hash::default());
+        assert_eq!(
+            vote_and_deserialize(&mut keyed_accounts, vote.clone()),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&voter_id, true, &mut voter_account),
+        ];
         let vote_state = vote_and_deserialize(&mut keyed_accounts, vote.clone()).unwrap();
-        assert_eq!(vote_state.votes, vec![vote]);
+        assert_eq!(vote_state.votes, vec![Lockout::new(&vote)]);
+    }
+
+    #[test]
+    fn test_withdraw() {
+        let node_id = Keypair::new().pubkey();
+        let mut node_account = Account::new(100, 0, Pubkey::default());
+
+        let vote_state_id = Keypair::new().pubkey();
+        let mut vote_state_account = create_vote_program(100);
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        register(&mut keyed_accounts).unwrap();
+
+        let to_id = Keypair::new().pubkey();
+        let mut to_account = Account::new(0, 0, Pubkey::default());
+
+        // The validator must sign
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, true, &mut vote_state_account),
+            KeyedAccount::new(&node_id, false, &mut node_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 10),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 101),
+            Err(ProgramError::ResultWithNegativeTokens)
+        );
+        withdraw(&mut keyed_accounts, 10).unwrap();
+        assert_eq!(vote_state_account.tokens, 90);
+        assert_eq!(to_account.tokens, 10);
+    }
+
+    #[test]
+    fn test_authorize_withdrawer() {
+        let node_id = Keypair::new().pubkey();
+        let mut node_account = Account::new(100, 0, Pubkey::default());
+
+        let vote_state_id = Keypair::new().pubkey();
+        let mut vote_state_account = create_vote_program(100);
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+        ];
+        register(&mut keyed_accounts).unwrap();
+
+        // Only the current withdrawer may authorize a new one
+        let withdrawer_id = Keypair::new().pubkey();
+        let mut withdrawer_account = Account::new(0, 0, Pubkey::default());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, true, &mut vote_state_account),
+            KeyedAccount::new(&withdrawer_id, true, &mut withdrawer_account),
+        ];
+        assert_eq!(
+            authorize_withdrawer(&mut keyed_accounts, withdrawer_id),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&node_id, true, &mut node_account),
+        ];
+        authorize_withdrawer(&mut keyed_accounts, withdrawer_id).unwrap();
+
+        // The validator may no longer withdraw, but the new withdrawer may
+        let to_id = Keypair::new().pubkey();
+        let mut to_account = Account::new(0, 0, Pubkey::default());
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&node_id, true, &mut node_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        assert_eq!(
+            withdraw(&mut keyed_accounts, 10),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let mut keyed_accounts = [
+            KeyedAccount::new(&vote_state_id, false, &mut vote_state_account),
+            KeyedAccount::new(&withdrawer_id, true, &mut withdrawer_account),
+            KeyedAccount::new(&to_id, false, &mut to_account),
+        ];
+        withdraw(&mut keyed_accounts, 10).unwrap();
+        assert_eq!(vote_state_account.tokens, 90);
+        assert_eq!(to_account.tokens, 10);
     }
 }
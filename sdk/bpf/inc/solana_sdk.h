@@ -106,6 +106,33 @@ SOL_FN_PREFIX bool SolPubkey_same(const SolPubkey *one, const SolPubkey *two) {
   return true;
 }
 
+/**
+ * Helper function that writes the SHA-256 hash of `len` bytes at `data` to the
+ * 32 bytes at `result`
+ */
+void sol_sha256(const uint8_t *data, uint64_t len, uint8_t *result);
+
+/**
+ * Helper function that verifies an ed25519 signature
+ *
+ * @param message Message that was signed
+ * @param len Length of the message in bytes
+ * @param signature 64 byte signature
+ * @param pubkey Public key of the signer
+ * @return true if the signature is valid
+ */
+bool sol_verify_ed25519(
+  const uint8_t *message,
+  uint64_t len,
+  const uint8_t *signature,
+  const SolPubkey *pubkey
+);
+
+/**
+ * Helper function that returns the current ledger tick
+ */
+uint64_t sol_get_tick_height(void);
+
 /**
  * Keyed Account
  */
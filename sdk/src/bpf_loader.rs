@@ -1,4 +1,7 @@
+use crate::account::Account;
+use crate::native_program::ProgramError;
 use crate::pubkey::Pubkey;
+use bincode::{deserialize, serialize, serialized_size};
 
 pub const BPF_LOADER_PROGRAM_ID: [u8; 32] = [
     128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -8,3 +11,141 @@ pub const BPF_LOADER_PROGRAM_ID: [u8; 32] = [
 pub fn id() -> Pubkey {
     Pubkey::new(&BPF_LOADER_PROGRAM_ID)
 }
+
+/// The state of an upgradeable program's accounts. The program account holds just the address
+/// of its program-data account, and the program-data account holds the upgrade authority
+/// followed by the program's ELF, so upgrading a program never touches the program account.
+/// A program deployed as a bare ELF has no state, and is immutable
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum UpgradeableLoaderState {
+    /// An account that doesn't hold an upgradeable program's state
+    Uninitialized,
+
+    /// An upgradeable program, whose code is in the account at `programdata_address`
+    Program { programdata_address: Pubkey },
+
+    /// The code of the program at `program_address`, followed by its ELF. A program without
+    /// an upgrade authority is immutable
+    ProgramData {
+        program_address: Pubkey,
+        upgrade_authority: Option<Pubkey>,
+    },
+}
+
+/// The userdata of a program-data account: its state, followed by the program's ELF
+pub fn serialize_programdata(
+    program_address: &Pubkey,
+    upgrade_authority: &Option<Pubkey>,
+    elf: &[u8],
+) -> Vec<u8> {
+    let mut userdata = serialize(&UpgradeableLoaderState::ProgramData {
+        program_address: *program_address,
+        upgrade_authority: *upgrade_authority,
+    })
+    .unwrap();
+    userdata.extend_from_slice(elf);
+    userdata
+}
+
+/// Split the userdata of a program-data account into the address of its program, the upgrade
+/// authority and the program's ELF
+pub fn deserialize_programdata(
+    userdata: &[u8],
+) -> Result<(Pubkey, Option<Pubkey>, &[u8]), ProgramError> {
+    let state = deserialize(userdata).map_err(|_| ProgramError::InvalidUserdata)?;
+    let header_len = serialized_size(&state).unwrap() as usize;
+    match state {
+        UpgradeableLoaderState::ProgramData {
+            program_address,
+            upgrade_authority,
+        } => Ok((program_address, upgrade_authority, &userdata[header_len..])),
+        _ => Err(ProgramError::InvalidUserdata),
+    }
+}
+
+/// The address of the program-data account of `program`, if it's an upgradeable program
+pub fn get_programdata_address(program: &Account) -> Option<Pubkey> {
+    if !program.executable || program.loader != id() {
+        return None;
+    }
+    match deserialize(&program.userdata) {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Some(programdata_address),
+        _ => None,
+    }
+}
+
+/// Return true if `userdata` holds an upgradeable program's state, which only the loader may
+/// change
+pub fn is_upgradeable_state(userdata: &[u8]) -> bool {
+    match deserialize(userdata) {
+        Ok(UpgradeableLoaderState::Uninitialized) | Err(_) => false,
+        Ok(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_programdata_serde() {
+        let elf = [0x7f, b'E', b'L', b'F', 1, 2, 3];
+        let program_address = Pubkey::new(&[1; 32]);
+        let authority = Some(Pubkey::new(&[2; 32]));
+        let userdata = serialize_programdata(&program_address, &authority, &elf);
+        assert_eq!(
+            deserialize_programdata(&userdata),
+            Ok((program_address, authority, &elf[..]))
+        );
+
+        let userdata = serialize_programdata(&program_address, &None, &elf);
+        assert_eq!(
+            deserialize_programdata(&userdata),
+            Ok((program_address, None, &elf[..]))
+        );
+
+        assert_eq!(
+            deserialize_programdata(&elf),
+            Err(ProgramError::InvalidUserdata)
+        );
+        let userdata = serialize(&UpgradeableLoaderState::Program {
+            programdata_address: Pubkey::default(),
+        })
+        .unwrap();
+        assert_eq!(
+            deserialize_programdata(&userdata),
+            Err(ProgramError::InvalidUserdata)
+        );
+    }
+
+    #[test]
+    fn test_get_programdata_address() {
+        let programdata_address = Pubkey::new(&[1; 32]);
+        let mut program = Account::new(1, 0, Pubkey::default());
+        program.userdata = serialize(&UpgradeableLoaderState::Program {
+            programdata_address,
+        })
+        .unwrap();
+        assert_eq!(get_programdata_address(&program), None);
+
+        program.executable = true;
+        program.loader = id();
+        assert_eq!(get_programdata_address(&program), Some(programdata_address));
+
+        program.userdata = vec![0x7f, b'E', b'L', b'F', 1, 2, 3];
+        assert_eq!(get_programdata_address(&program), None);
+    }
+
+    #[test]
+    fn test_is_upgradeable_state() {
+        assert!(!is_upgradeable_state(&[0x7f, b'E', b'L', b'F']));
+        assert!(!is_upgradeable_state(&[0; 64]));
+        assert!(is_upgradeable_state(&serialize_programdata(
+            &Pubkey::default(),
+            &None,
+            &[]
+        )));
+    }
+}
@@ -3,6 +3,7 @@
 //! which it uses to reduce the payment plan. When the budget is reduced to a
 //! `Payment`, the payment is executed.
 
+use crate::hash::{hash, Hash};
 use crate::payment_plan::{Payment, Witness};
 use crate::pubkey::Pubkey;
 use chrono::prelude::*;
@@ -16,19 +17,155 @@ pub enum Condition {
 
     /// Wait for a `Signature` `Witness` from `Pubkey`.
     Signature(Pubkey),
+
+    /// Wait for `Signature` `Witness`es from any `u64` of the listed `Pubkey`s, which
+    /// must be distinct.
+    Threshold(u64, Vec<Pubkey>),
+
+    /// Wait for a `Preimage` `Witness` that hashes to `Hash`.
+    Preimage(Hash),
 }
 
 impl Condition {
     /// Return true if the given Witness satisfies this Condition.
     pub fn is_satisfied(&self, witness: &Witness, from: &Pubkey) -> bool {
+        self.is_satisfied_with_signers(witness, from, &[])
+    }
+
+    /// Return true if the given Witness satisfies this Condition, counting the
+    /// `Signature` witnesses already seen from `signers`.
+    pub fn is_satisfied_with_signers(
+        &self,
+        witness: &Witness,
+        from: &Pubkey,
+        signers: &[Pubkey],
+    ) -> bool {
         match (self, witness) {
             (Condition::Signature(pubkey), Witness::Signature) => pubkey == from,
+            (Condition::Threshold(threshold, pubkeys), Witness::Signature) => {
+                let mut signed: Vec<_> = pubkeys
+                    .iter()
+                    .filter(|pubkey| *pubkey == from || signers.contains(pubkey))
+                    .collect();
+                // A key listed more than once still only signs once
+                signed.sort();
+                signed.dedup();
+                pubkeys.contains(from) && signed.len() as u64 >= *threshold
+            }
             (Condition::Timestamp(dt, pubkey), Witness::Timestamp(last_time)) => {
                 pubkey == from && dt <= last_time
             }
+            (Condition::Preimage(expected_hash), Witness::Preimage(preimage)) => {
+                hash(preimage) == *expected_hash
+            }
             _ => false,
         }
     }
+
+    /// Return true if a `Signature` witness from `from` counts towards this Condition.
+    fn awaits_signature(&self, from: &Pubkey) -> bool {
+        match self {
+            Condition::Threshold(_, pubkeys) => pubkeys.contains(from),
+            _ => false,
+        }
+    }
+
+    /// Return true if enough witnesses exist to satisfy this Condition. A threshold
+    /// that lists a key more than once is rejected, rather than letting that key
+    /// count for as many signatures as it is listed.
+    pub fn is_satisfiable(&self) -> bool {
+        match self {
+            Condition::Threshold(threshold, pubkeys) => {
+                let mut distinct = pubkeys.clone();
+                distinct.sort();
+                distinct.dedup();
+                distinct.len() == pubkeys.len() && *threshold <= pubkeys.len() as u64
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A schedule that releases a budget's tokens in tranches, one every `period_secs`
+/// starting at `start`, as `Timestamp` witnesses from `timestamp_pubkey` advance.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PaymentSchedule {
+    /// The `Pubkey` whose `Timestamp` witnesses advance the schedule.
+    pub timestamp_pubkey: Pubkey,
+
+    /// When the first tranche comes due.
+    pub start: DateTime<Utc>,
+
+    /// Seconds between tranches.
+    pub period_secs: u64,
+
+    /// Tranches that come due before the cliff are held until it passes.
+    pub cliff: Option<DateTime<Utc>>,
+
+    /// Tokens released by each tranche. The last tranche releases whatever remains.
+    pub tranche_tokens: u64,
+
+    /// Number of tranches in the schedule, or `None` to release tranches until the
+    /// tokens run out.
+    pub num_tranches: Option<u64>,
+
+    /// Number of tranches released so far.
+    pub tranches_paid: u64,
+
+    /// Tokens not yet released.
+    pub tokens_remaining: u64,
+
+    /// Tokens released by the last witness and not yet paid out.
+    pub tokens_released: u64,
+
+    /// The `Pubkey` the tranches are paid to.
+    pub to: Pubkey,
+
+    /// The `Pubkey` that can cancel the schedule with a `Signature` witness and
+    /// receive the tokens not yet released.
+    pub cancelable: Option<Pubkey>,
+}
+
+impl PaymentSchedule {
+    /// Return the number of tranches that have come due at `dt`.
+    fn num_tranches_due(&self, dt: &DateTime<Utc>) -> u64 {
+        if *dt < self.start || self.cliff.map_or(false, |cliff| *dt < cliff) {
+            return 0;
+        }
+        let elapsed_secs = dt.signed_duration_since(self.start).num_seconds() as u64;
+        elapsed_secs / self.period_secs.max(1) + 1
+    }
+
+    /// Apply a witness to the schedule, releasing any tranches that have come due.
+    /// Return the budget to replace the schedule with once it has run its course.
+    fn apply_witness(&mut self, witness: &Witness, from: &Pubkey) -> Option<Box<BudgetExpr>> {
+        match witness {
+            Witness::Signature if self.cancelable == Some(*from) => Some(Box::new(
+                BudgetExpr::new_payment(self.tokens_remaining, *from),
+            )),
+            Witness::Timestamp(dt) if *from == self.timestamp_pubkey => {
+                let num_due = self.num_tranches_due(dt);
+                if num_due <= self.tranches_paid {
+                    return None;
+                }
+                let tokens = if self.num_tranches.map_or(false, |n| num_due >= n) {
+                    self.tokens_remaining
+                } else {
+                    (num_due - self.tranches_paid)
+                        .saturating_mul(self.tranche_tokens)
+                        .min(self.tokens_remaining)
+                };
+                self.tranches_paid = num_due;
+                if tokens == self.tokens_remaining {
+                    return Some(Box::new(BudgetExpr::new_payment(tokens, self.to)));
+                }
+                self.tokens_remaining -= tokens;
+                self.tokens_released += tokens;
+                None
+            }
+            _ => None,
+        }
+    }
 }
 
 /// A data type representing a payment plan.
@@ -47,6 +184,9 @@ pub enum BudgetExpr {
 
     /// Make a payment after both of two conditions are satisfied
     And(Condition, Condition, Box<BudgetExpr>),
+
+    /// Make payments in tranches according to a schedule
+    Schedule(PaymentSchedule),
 }
 
 impl BudgetExpr {
@@ -72,6 +212,122 @@ impl BudgetExpr {
         )
     }
 
+    /// Create a budget that pays `tokens` to `to` after being witnessed by any
+    /// `threshold` of `witnesses`
+    pub fn new_threshold_multisig_payment(
+        threshold: u64,
+        witnesses: Vec<Pubkey>,
+        tokens: u64,
+        to: Pubkey,
+    ) -> Self {
+        BudgetExpr::After(
+            Condition::Threshold(threshold, witnesses),
+            Box::new(Self::new_payment(tokens, to)),
+        )
+    }
+
+    /// Create a budget that vests `tokens` to `to` in `num_tranches` tranches of
+    /// `tokens / num_tranches`, one every `period_secs` starting at `start`, as witnessed
+    /// by timestamps from `from`. The last tranche also releases the remainder, so 10
+    /// tokens over 4 tranches vest as 2, 2, 2 and 4. Tranches that come due before `cliff`
+    /// are held until it passes.
+    pub fn new_vesting_payment(
+        start: DateTime<Utc>,
+        period_secs: u64,
+        num_tranches: u64,
+        cliff: Option<DateTime<Utc>>,
+        from: Pubkey,
+        tokens: u64,
+        to: Pubkey,
+        cancelable: Option<Pubkey>,
+    ) -> Self {
+        let num_tranches = num_tranches.max(1);
+        BudgetExpr::Schedule(PaymentSchedule {
+            timestamp_pubkey: from,
+            start,
+            period_secs,
+            cliff,
+            tranche_tokens: tokens / num_tranches,
+            num_tranches: Some(num_tranches),
+            tranches_paid: 0,
+            tokens_remaining: tokens,
+            tokens_released: 0,
+            to,
+            cancelable,
+        })
+    }
+
+    /// Create a budget that vests all `tokens` to `to` once the `cliff` DateTime
+    /// has passed, unless cancelled by `cancelable` first.
+    pub fn new_cliff_vesting_payment(
+        cliff: DateTime<Utc>,
+        from: Pubkey,
+        tokens: u64,
+        to: Pubkey,
+        cancelable: Option<Pubkey>,
+    ) -> Self {
+        Self::new_vesting_payment(cliff, 0, 1, None, from, tokens, to, cancelable)
+    }
+
+    /// Create a budget that pays `tranche_tokens` to `to` every `period_secs`
+    /// starting at `start`, until `tokens` are exhausted or `from` cancels it.
+    pub fn new_recurring_payment(
+        start: DateTime<Utc>,
+        period_secs: u64,
+        tranche_tokens: u64,
+        from: Pubkey,
+        tokens: u64,
+        to: Pubkey,
+    ) -> Self {
+        BudgetExpr::Schedule(PaymentSchedule {
+            timestamp_pubkey: from,
+            start,
+            period_secs,
+            cliff: None,
+            tranche_tokens,
+            num_tranches: None,
+            tranches_paid: 0,
+            tokens_remaining: tokens,
+            tokens_released: 0,
+            to,
+            cancelable: Some(from),
+        })
+    }
+
+    /// Create a budget that pays `tokens` to `to` once the preimage of `hash`
+    /// is revealed.
+    pub fn new_hash_locked_payment(hash: Hash, tokens: u64, to: Pubkey) -> Self {
+        BudgetExpr::After(
+            Condition::Preimage(hash),
+            Box::new(Self::new_payment(tokens, to)),
+        )
+    }
+
+    /// Create a budget that pays `tokens` to `to` once the preimage of `hash` is
+    /// revealed, or refunds them to `from` if it hasn't been by the given DateTime,
+    /// as witnessed by a timestamp from `timestamp_pubkey`. The refund is only
+    /// time-locked if `timestamp_pubkey` is a third party trusted by both sides,
+    /// not `from`.
+    pub fn new_hash_time_locked_payment(
+        hash: Hash,
+        dt: DateTime<Utc>,
+        timestamp_pubkey: Pubkey,
+        from: Pubkey,
+        tokens: u64,
+        to: Pubkey,
+    ) -> Self {
+        BudgetExpr::Or(
+            (
+                Condition::Preimage(hash),
+                Box::new(Self::new_payment(tokens, to)),
+            ),
+            (
+                Condition::Timestamp(dt, timestamp_pubkey),
+                Box::new(Self::new_payment(tokens, from)),
+            ),
+        )
+    }
+
     /// Create a budget that pays `tokens` to `to` after the given DateTime.
     pub fn new_future_payment(dt: DateTime<Utc>, from: Pubkey, tokens: u64, to: Pubkey) -> Self {
         BudgetExpr::After(
@@ -116,35 +372,119 @@ impl BudgetExpr {
                 sub_expr.verify(spendable_tokens)
             }
             BudgetExpr::Or(a, b) => a.1.verify(spendable_tokens) && b.1.verify(spendable_tokens),
+            BudgetExpr::Schedule(schedule) => schedule
+                .tokens_remaining
+                .checked_add(schedule.tokens_released)
+                .map_or(false, |tokens| tokens == spendable_tokens),
+        }
+    }
+
+    /// Return the Payment of any tranches a schedule released since this was
+    /// last called.
+    pub fn take_released_payment(&mut self) -> Option<Payment> {
+        match self {
+            BudgetExpr::Schedule(schedule) if schedule.tokens_released > 0 => {
+                let tokens = mem::replace(&mut schedule.tokens_released, 0);
+                Some(Payment {
+                    tokens,
+                    to: schedule.to,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Return true if every condition in the budget can be satisfied, so that its
+    /// tokens can't be locked up forever.
+    pub fn is_satisfiable(&self) -> bool {
+        match self {
+            BudgetExpr::Pay(_) | BudgetExpr::Schedule(_) => true,
+            BudgetExpr::After(cond, sub_expr) => cond.is_satisfiable() && sub_expr.is_satisfiable(),
+            BudgetExpr::Or((cond0, sub_expr0), (cond1, sub_expr1)) => {
+                cond0.is_satisfiable()
+                    && sub_expr0.is_satisfiable()
+                    && cond1.is_satisfiable()
+                    && sub_expr1.is_satisfiable()
+            }
+            BudgetExpr::And(cond0, cond1, sub_expr) => {
+                cond0.is_satisfiable() && cond1.is_satisfiable() && sub_expr.is_satisfiable()
+            }
+        }
+    }
+
+    /// Return the conditions the budget is currently waiting on.
+    fn conditions(&self) -> Vec<&Condition> {
+        match self {
+            BudgetExpr::Pay(_) | BudgetExpr::Schedule(_) => vec![],
+            BudgetExpr::After(cond, _) => vec![cond],
+            BudgetExpr::Or((cond0, _), (cond1, _)) | BudgetExpr::And(cond0, cond1, _) => {
+                vec![cond0, cond1]
+            }
         }
     }
 
     /// Apply a witness to the budget to see if the budget can be reduced.
     /// If so, modify the budget in-place.
     pub fn apply_witness(&mut self, witness: &Witness, from: &Pubkey) {
-        let new_expr = match self {
-            BudgetExpr::After(cond, sub_expr) if cond.is_satisfied(witness, from) => {
-                Some(sub_expr.clone())
+        self.apply_witness_with_signers(witness, from, &mut vec![]);
+    }
+
+    /// Apply a witness to the budget, where `signers` are the pubkeys whose
+    /// `Signature` witnesses count towards a `Threshold` condition so far. A
+    /// signature that doesn't yet reduce the budget is recorded in `signers`. Once
+    /// the budget is reduced, the signers the satisfied condition consumed are
+    /// dropped, and the rest are kept if the reduced budget still awaits them.
+    pub fn apply_witness_with_signers(
+        &mut self,
+        witness: &Witness,
+        from: &Pubkey,
+        signers: &mut Vec<Pubkey>,
+    ) {
+        let is_satisfied =
+            |cond: &Condition| cond.is_satisfied_with_signers(witness, from, &signers[..]);
+        let reduced = match self {
+            BudgetExpr::After(cond, sub_expr) if is_satisfied(cond) => {
+                Some((Some(cond.clone()), sub_expr.clone()))
             }
-            BudgetExpr::Or((cond, sub_expr), _) if cond.is_satisfied(witness, from) => {
-                Some(sub_expr.clone())
+            BudgetExpr::Or((cond, sub_expr), _) if is_satisfied(cond) => {
+                Some((Some(cond.clone()), sub_expr.clone()))
             }
-            BudgetExpr::Or(_, (cond, sub_expr)) if cond.is_satisfied(witness, from) => {
-                Some(sub_expr.clone())
+            BudgetExpr::Or(_, (cond, sub_expr)) if is_satisfied(cond) => {
+                Some((Some(cond.clone()), sub_expr.clone()))
             }
             BudgetExpr::And(cond0, cond1, sub_expr) => {
-                if cond0.is_satisfied(witness, from) {
-                    Some(Box::new(BudgetExpr::After(cond1.clone(), sub_expr.clone())))
-                } else if cond1.is_satisfied(witness, from) {
-                    Some(Box::new(BudgetExpr::After(cond0.clone(), sub_expr.clone())))
+                if is_satisfied(cond0) {
+                    let expr = BudgetExpr::After(cond1.clone(), sub_expr.clone());
+                    Some((Some(cond0.clone()), Box::new(expr)))
+                } else if is_satisfied(cond1) {
+                    let expr = BudgetExpr::After(cond0.clone(), sub_expr.clone());
+                    Some((Some(cond1.clone()), Box::new(expr)))
                 } else {
                     None
                 }
             }
+            BudgetExpr::Schedule(schedule) => schedule
+                .apply_witness(witness, from)
+                .map(|expr| (None, expr)),
             _ => None,
         };
-        if let Some(expr) = new_expr {
+        if let Some((satisfied, expr)) = reduced {
             mem::replace(self, *expr);
+            let conditions = self.conditions();
+            signers.retain(|signer| {
+                !satisfied
+                    .as_ref()
+                    .map_or(false, |cond| cond.awaits_signature(signer))
+                    && conditions.iter().any(|cond| cond.awaits_signature(signer))
+            });
+        } else if *witness == Witness::Signature
+            && !signers.contains(from)
+            && self
+                .conditions()
+                .iter()
+                .any(|cond| cond.awaits_signature(from))
+        {
+            signers.push(*from);
         }
     }
 }
@@ -170,6 +510,16 @@ mod tests {
         assert!(!Condition::Timestamp(dt2, from).is_satisfied(&Witness::Timestamp(dt1), &from));
     }
 
+    #[test]
+    fn test_preimage_satisfied() {
+        let from = Pubkey::default();
+        let preimage = b"secret".to_vec();
+        let cond = Condition::Preimage(hash(&preimage));
+        assert!(cond.is_satisfied(&Witness::Preimage(preimage), &from));
+        assert!(!cond.is_satisfied(&Witness::Preimage(b"guess".to_vec()), &from));
+        assert!(!cond.is_satisfied(&Witness::Signature, &from));
+    }
+
     #[test]
     fn test_verify() {
         let dt = Utc.ymd(2014, 11, 14).and_hms(8, 9, 10);
@@ -179,6 +529,14 @@ mod tests {
         assert!(BudgetExpr::new_authorized_payment(from, 42, to).verify(42));
         assert!(BudgetExpr::new_future_payment(dt, from, 42, to).verify(42));
         assert!(BudgetExpr::new_cancelable_future_payment(dt, from, 42, to).verify(42));
+
+        // A schedule whose token counts overflow never verifies
+        let mut expr = BudgetExpr::new_vesting_payment(dt, 1, 2, None, from, 42, to, None);
+        if let BudgetExpr::Schedule(schedule) = &mut expr {
+            schedule.tokens_remaining = u64::max_value();
+            schedule.tokens_released = 43;
+        }
+        assert!(!expr.verify(42));
     }
 
     #[test]
@@ -256,6 +614,227 @@ mod tests {
         assert_eq!(expr, BudgetExpr::new_authorized_payment(from1, 42, to));
     }
 
+    #[test]
+    fn test_hash_time_locked_payment() {
+        let dt = Utc.ymd(2014, 11, 14).and_hms(8, 9, 10);
+        let oracle = Keypair::new().pubkey();
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let preimage = b"secret".to_vec();
+
+        let mut expr =
+            BudgetExpr::new_hash_time_locked_payment(hash(&preimage), dt, oracle, from, 42, to);
+        assert!(expr.verify(42));
+        let orig_expr = expr.clone();
+        expr.apply_witness(&Witness::Preimage(b"guess".to_vec()), &to);
+        assert_eq!(expr, orig_expr);
+        expr.apply_witness(&Witness::Preimage(preimage), &to);
+        assert_eq!(expr, BudgetExpr::new_payment(42, to));
+
+        // Nobody revealed the preimage in time. Only the oracle can say so
+        let mut expr = orig_expr.clone();
+        expr.apply_witness(&Witness::Timestamp(dt), &from);
+        assert_eq!(expr, orig_expr);
+        expr.apply_witness(&Witness::Timestamp(dt), &oracle);
+        assert_eq!(expr, BudgetExpr::new_payment(42, from));
+    }
+
+    #[test]
+    fn test_threshold_satisfied() {
+        let from0 = Keypair::new().pubkey();
+        let from1 = Keypair::new().pubkey();
+        let from2 = Keypair::new().pubkey();
+        let cond = Condition::Threshold(2, vec![from0, from1]);
+        assert!(!cond.is_satisfied(&Witness::Signature, &from0));
+        assert!(!cond.is_satisfied_with_signers(&Witness::Signature, &from0, &[from0]));
+        assert!(cond.is_satisfied_with_signers(&Witness::Signature, &from1, &[from0]));
+        assert!(!cond.is_satisfied_with_signers(&Witness::Signature, &from2, &[from0]));
+        assert!(!cond.is_satisfied_with_signers(&Witness::Signature, &from0, &[from2]));
+    }
+
+    #[test]
+    fn test_threshold_multisig_payment() {
+        let witnesses: Vec<_> = (0..5).map(|_| Keypair::new().pubkey()).collect();
+        let rando = Keypair::new().pubkey();
+        let to = Pubkey::default();
+
+        let mut expr = BudgetExpr::new_threshold_multisig_payment(3, witnesses.clone(), 42, to);
+        assert!(expr.verify(42));
+        let mut signers = vec![];
+
+        expr.apply_witness_with_signers(&Witness::Signature, &witnesses[4], &mut signers);
+        expr.apply_witness_with_signers(&Witness::Signature, &rando, &mut signers);
+        expr.apply_witness_with_signers(&Witness::Signature, &witnesses[4], &mut signers);
+        assert_eq!(signers, vec![witnesses[4]]);
+
+        expr.apply_witness_with_signers(&Witness::Signature, &witnesses[0], &mut signers);
+        assert_eq!(signers, vec![witnesses[4], witnesses[0]]);
+        assert_ne!(expr, BudgetExpr::new_payment(42, to));
+
+        expr.apply_witness_with_signers(&Witness::Signature, &witnesses[2], &mut signers);
+        assert_eq!(expr, BudgetExpr::new_payment(42, to));
+        assert!(signers.is_empty());
+    }
+
+    #[test]
+    fn test_threshold_signers_survive_reduction() {
+        let a = Keypair::new().pubkey();
+        let b = Keypair::new().pubkey();
+        let c = Keypair::new().pubkey();
+        let d = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let mut expr = BudgetExpr::And(
+            Condition::Threshold(2, vec![a, b]),
+            Condition::Threshold(2, vec![c, d]),
+            Box::new(BudgetExpr::new_payment(42, to)),
+        );
+
+        // c's signature is still counted after a and b satisfy the first threshold
+        let mut signers = vec![];
+        expr.apply_witness_with_signers(&Witness::Signature, &a, &mut signers);
+        expr.apply_witness_with_signers(&Witness::Signature, &c, &mut signers);
+        expr.apply_witness_with_signers(&Witness::Signature, &b, &mut signers);
+        assert_eq!(signers, vec![c]);
+        expr.apply_witness_with_signers(&Witness::Signature, &d, &mut signers);
+        assert_eq!(expr, BudgetExpr::new_payment(42, to));
+        assert!(signers.is_empty());
+    }
+
+    #[test]
+    fn test_unsatisfiable_threshold() {
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let expr = BudgetExpr::new_threshold_multisig_payment(2, vec![from], 42, to);
+        assert!(!expr.is_satisfiable());
+        let expr = BudgetExpr::new_threshold_multisig_payment(1, vec![from], 42, to);
+        assert!(expr.is_satisfiable());
+    }
+
+    #[test]
+    fn test_threshold_duplicate_pubkeys() {
+        let from = Keypair::new().pubkey();
+        let other = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+
+        // Listing one key twice doesn't let it meet a 2-of-3 threshold alone
+        let cond = Condition::Threshold(2, vec![from, from, other]);
+        assert!(!cond.is_satisfiable());
+        assert!(!cond.is_satisfied_with_signers(&Witness::Signature, &from, &[from]));
+        assert!(cond.is_satisfied_with_signers(&Witness::Signature, &other, &[from]));
+
+        let expr = BudgetExpr::new_threshold_multisig_payment(2, vec![from, from, other], 42, to);
+        assert!(!expr.is_satisfiable());
+    }
+
+    #[test]
+    fn test_vesting_payment() {
+        let start = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        let day = 24 * 60 * 60;
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let at = |days: i64| Witness::Timestamp(start + chrono::Duration::days(days));
+
+        // 10 tokens over 4 daily tranches, with a cliff on the third day
+        let cliff = start + chrono::Duration::days(2);
+        let mut expr =
+            BudgetExpr::new_vesting_payment(start, day, 4, Some(cliff), from, 10, to, None);
+        assert!(expr.verify(10));
+
+        expr.apply_witness(&at(-1), &from);
+        expr.apply_witness(&at(1), &from);
+        assert_eq!(expr.take_released_payment(), None);
+
+        expr.apply_witness(&at(2), &to); // <-- Attack! Only `from` can witness the time
+        assert_eq!(expr.take_released_payment(), None);
+
+        expr.apply_witness(&at(2), &from);
+        assert_eq!(
+            expr.take_released_payment(),
+            Some(Payment { tokens: 6, to })
+        );
+        assert_eq!(expr.take_released_payment(), None);
+        assert!(expr.verify(4));
+
+        expr.apply_witness(&at(2), &from);
+        assert_eq!(expr.take_released_payment(), None);
+
+        expr.apply_witness(&at(30), &from);
+        assert_eq!(expr, BudgetExpr::new_payment(4, to));
+    }
+
+    #[test]
+    fn test_vesting_payment_pays_remainder_last() {
+        let start = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let at = |secs: i64| Witness::Timestamp(start + chrono::Duration::seconds(secs));
+
+        // 10 tokens over 6 tranches vest as 1, 1, 1, 1, 1 and 5
+        let mut expr = BudgetExpr::new_vesting_payment(start, 1, 6, None, from, 10, to, None);
+        for secs in 0..5 {
+            expr.apply_witness(&at(secs), &from);
+            assert_eq!(
+                expr.take_released_payment(),
+                Some(Payment { tokens: 1, to })
+            );
+        }
+        assert!(expr.verify(5));
+        expr.apply_witness(&at(5), &from);
+        assert_eq!(expr, BudgetExpr::new_payment(5, to));
+    }
+
+    #[test]
+    fn test_cliff_vesting_payment() {
+        let cliff = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+
+        let mut expr = BudgetExpr::new_cliff_vesting_payment(cliff, from, 42, to, Some(from));
+        expr.apply_witness(
+            &Witness::Timestamp(cliff - chrono::Duration::seconds(1)),
+            &from,
+        );
+        assert_eq!(expr.take_released_payment(), None);
+        expr.apply_witness(&Witness::Timestamp(cliff), &from);
+        assert_eq!(expr, BudgetExpr::new_payment(42, to));
+    }
+
+    #[test]
+    fn test_recurring_payment() {
+        let start = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        let from = Keypair::new().pubkey();
+        let to = Keypair::new().pubkey();
+        let at = |secs: i64| Witness::Timestamp(start + chrono::Duration::seconds(secs));
+
+        let mut expr = BudgetExpr::new_recurring_payment(start, 10, 3, from, 10, to);
+        expr.apply_witness(&at(0), &from);
+        assert_eq!(
+            expr.take_released_payment(),
+            Some(Payment { tokens: 3, to })
+        );
+        expr.apply_witness(&at(15), &from);
+        assert_eq!(
+            expr.take_released_payment(),
+            Some(Payment { tokens: 3, to })
+        );
+
+        // The payer cancels and gets back the rest
+        let mut cancelled = expr.clone();
+        cancelled.apply_witness(&Witness::Signature, &to);
+        assert_eq!(cancelled.take_released_payment(), None);
+        cancelled.apply_witness(&Witness::Signature, &from);
+        assert_eq!(cancelled, BudgetExpr::new_payment(4, from));
+
+        // Otherwise the payments continue until the funds run out
+        expr.apply_witness(&at(20), &from);
+        assert_eq!(
+            expr.take_released_payment(),
+            Some(Payment { tokens: 3, to })
+        );
+        expr.apply_witness(&at(30), &from);
+        assert_eq!(expr, BudgetExpr::new_payment(1, to));
+    }
+
     #[test]
     fn test_multisig_after_ts() {
         let from0 = Keypair::new().pubkey();
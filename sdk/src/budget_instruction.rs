@@ -21,4 +21,7 @@ pub enum Instruction {
     /// Tell the budget that the `NewBudget` with `Signature` has been
     /// signed by the containing transaction's `Pubkey`.
     ApplySignature,
+
+    /// Reveal the preimage of a hash the budget is locked on.
+    ApplyPreimage(Vec<u8>),
 }
@@ -1,4 +1,7 @@
+use crate::budget_expr::BudgetExpr;
+use crate::native_program::CustomErrorCode;
 use crate::pubkey::Pubkey;
+use bincode::serialized_size;
 
 pub const BUDGET_PROGRAM_ID: [u8; 32] = [
     129, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -12,3 +15,74 @@ pub fn id() -> Pubkey {
 pub fn check_id(program_id: &Pubkey) -> bool {
     program_id.as_ref() == BUDGET_PROGRAM_ID
 }
+
+/// Return the number of bytes of userdata a contract account needs to hold `expr` and the
+/// signatures of up to `max_signers` of its witnesses. Mirrors the layout of the budget
+/// program's contract state: its length, whether it is initialized, the pending budget and,
+/// only if there are any, its signers
+pub fn get_state_size(expr: &BudgetExpr, max_signers: usize) -> u64 {
+    let mut size = 8 + serialized_size(&(true, Some(expr))).unwrap();
+    if max_signers > 0 {
+        size += serialized_size(&vec![Pubkey::default(); max_signers]).unwrap();
+    }
+    size
+}
+
+/// Reasons the budget program might reject an instruction, reported as
+/// `ProgramError::CustomError` codes
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BudgetError {
+    InsufficientFunds,
+    ContractAlreadyExists,
+    ContractNotPending,
+    SourceIsPendingContract,
+    UninitializedContract,
+    NegativeTokens,
+    DestinationMissing,
+    FailedWitness,
+    UserdataTooSmall,
+    UserdataDeserializeFailure,
+    UnsignedKey,
+    UnsatisfiableBudget,
+}
+
+impl CustomErrorCode for BudgetError {
+    fn code(&self) -> u32 {
+        *self as u32
+    }
+
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(BudgetError::InsufficientFunds),
+            1 => Some(BudgetError::ContractAlreadyExists),
+            2 => Some(BudgetError::ContractNotPending),
+            3 => Some(BudgetError::SourceIsPendingContract),
+            4 => Some(BudgetError::UninitializedContract),
+            5 => Some(BudgetError::NegativeTokens),
+            6 => Some(BudgetError::DestinationMissing),
+            7 => Some(BudgetError::FailedWitness),
+            8 => Some(BudgetError::UserdataTooSmall),
+            9 => Some(BudgetError::UserdataDeserializeFailure),
+            10 => Some(BudgetError::UnsignedKey),
+            11 => Some(BudgetError::UnsatisfiableBudget),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native_program::ProgramError;
+
+    #[test]
+    fn test_budget_error_codes() {
+        for code in 0..12 {
+            let err = BudgetError::from_code(code).unwrap();
+            assert_eq!(ProgramError::custom(&err), ProgramError::CustomError(code));
+            assert_eq!(ProgramError::CustomError(code).to_custom(), Some(err));
+        }
+        assert_eq!(BudgetError::from_code(12), None);
+        assert_eq!(ProgramError::GenericError.to_custom::<BudgetError>(), None);
+    }
+}
@@ -60,6 +60,63 @@ pub trait BudgetTransaction {
         last_id: Hash,
     ) -> Self;
 
+    fn budget_new_when_multisigned(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        threshold: u64,
+        witnesses: Vec<Pubkey>,
+        cancelable: Option<Pubkey>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self;
+
+    fn budget_new_preimage(
+        from_keypair: &Keypair,
+        contract: Pubkey,
+        to: Pubkey,
+        preimage: Vec<u8>,
+        last_id: Hash,
+    ) -> Self;
+
+    fn budget_new_when_hash_locked(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        hash: Hash,
+        refund: Option<(DateTime<Utc>, Pubkey, Pubkey)>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self;
+
+    fn budget_new_vesting(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        start: DateTime<Utc>,
+        period_secs: u64,
+        num_tranches: u64,
+        cliff: Option<DateTime<Utc>>,
+        dt_pubkey: Pubkey,
+        cancelable: Option<Pubkey>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self;
+
+    fn budget_new_recurring(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        start: DateTime<Utc>,
+        period_secs: u64,
+        tranche_tokens: u64,
+        payer: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self;
+
+    fn budget_new_cancel(from_keypair: &Keypair, contract: Pubkey, last_id: Hash) -> Self;
+
     fn instruction(&self, program_index: usize) -> Option<Instruction>;
     fn system_instruction(&self, program_index: usize) -> Option<SystemInstruction>;
 
@@ -221,6 +278,164 @@ impl BudgetTransaction for Transaction {
         )
     }
 
+    /// Create and sign a Transaction that pays out once any `threshold` of
+    /// `witnesses` have signed.
+    fn budget_new_when_multisigned(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        threshold: u64,
+        witnesses: Vec<Pubkey>,
+        cancelable: Option<Pubkey>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self {
+        let condition = Condition::Threshold(threshold, witnesses);
+        let expr = if let Some(from) = cancelable {
+            BudgetExpr::Or(
+                (condition, Box::new(BudgetExpr::new_payment(tokens, to))),
+                (
+                    Condition::Signature(from),
+                    Box::new(BudgetExpr::new_payment(tokens, from)),
+                ),
+            )
+        } else {
+            BudgetExpr::After(condition, Box::new(BudgetExpr::new_payment(tokens, to)))
+        };
+        let instruction = Instruction::NewBudget(expr);
+        Self::new(
+            from_keypair,
+            &[contract],
+            budget_program::id(),
+            &instruction,
+            last_id,
+            0,
+        )
+    }
+
+    /// Create and sign a new Witness Preimage. Used for unit-testing.
+    fn budget_new_preimage(
+        from_keypair: &Keypair,
+        contract: Pubkey,
+        to: Pubkey,
+        preimage: Vec<u8>,
+        last_id: Hash,
+    ) -> Self {
+        let instruction = Instruction::ApplyPreimage(preimage);
+        Self::new(
+            from_keypair,
+            &[contract, to],
+            budget_program::id(),
+            &instruction,
+            last_id,
+            0,
+        )
+    }
+
+    /// Create and sign a Transaction that pays out once the preimage of `hash`
+    /// is revealed. A `refund` of `(dt, timestamp_pubkey, from)` returns the tokens
+    /// to `from` once `timestamp_pubkey` witnesses a timestamp past `dt`.
+    fn budget_new_when_hash_locked(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        hash: Hash,
+        refund: Option<(DateTime<Utc>, Pubkey, Pubkey)>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self {
+        let expr = if let Some((dt, timestamp_pubkey, from)) = refund {
+            BudgetExpr::new_hash_time_locked_payment(hash, dt, timestamp_pubkey, from, tokens, to)
+        } else {
+            BudgetExpr::new_hash_locked_payment(hash, tokens, to)
+        };
+        let instruction = Instruction::NewBudget(expr);
+        Self::new(
+            from_keypair,
+            &[contract],
+            budget_program::id(),
+            &instruction,
+            last_id,
+            0,
+        )
+    }
+
+    /// Create and sign a Transaction that vests `tokens` to `to` in `num_tranches`
+    /// tranches, one every `period_secs` starting at `start`, as witnessed by
+    /// timestamps from `dt_pubkey`.
+    fn budget_new_vesting(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        start: DateTime<Utc>,
+        period_secs: u64,
+        num_tranches: u64,
+        cliff: Option<DateTime<Utc>>,
+        dt_pubkey: Pubkey,
+        cancelable: Option<Pubkey>,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self {
+        let expr = BudgetExpr::new_vesting_payment(
+            start,
+            period_secs,
+            num_tranches,
+            cliff,
+            dt_pubkey,
+            tokens,
+            to,
+            cancelable,
+        );
+        let instruction = Instruction::NewBudget(expr);
+        Self::new(
+            from_keypair,
+            &[contract],
+            budget_program::id(),
+            &instruction,
+            last_id,
+            0,
+        )
+    }
+
+    /// Create and sign a Transaction that pays `tranche_tokens` to `to` every
+    /// `period_secs` starting at `start`, until `tokens` run out or `payer`, who
+    /// witnesses the timestamps, cancels it.
+    fn budget_new_recurring(
+        from_keypair: &Keypair,
+        to: Pubkey,
+        contract: Pubkey,
+        start: DateTime<Utc>,
+        period_secs: u64,
+        tranche_tokens: u64,
+        payer: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+    ) -> Self {
+        let expr = BudgetExpr::new_recurring_payment(
+            start,
+            period_secs,
+            tranche_tokens,
+            payer,
+            tokens,
+            to,
+        );
+        let instruction = Instruction::NewBudget(expr);
+        Self::new(
+            from_keypair,
+            &[contract],
+            budget_program::id(),
+            &instruction,
+            last_id,
+            0,
+        )
+    }
+
+    /// Create and sign a Transaction that cancels a contract, returning the
+    /// tokens it hasn't paid out yet to the signer.
+    fn budget_new_cancel(from_keypair: &Keypair, contract: Pubkey, last_id: Hash) -> Self {
+        Self::budget_new_signature(from_keypair, contract, from_keypair.pubkey(), last_id)
+    }
+
     fn instruction(&self, instruction_index: usize) -> Option<Instruction> {
         deserialize(&self.userdata(instruction_index)).ok()
     }
@@ -14,12 +14,15 @@ pub mod payment_plan;
 pub mod pubkey;
 pub mod shortvec;
 pub mod signature;
+pub mod stake_program;
+pub mod stake_transaction;
 pub mod storage_program;
 pub mod system_instruction;
 pub mod system_program;
 pub mod system_transaction;
 pub mod timing;
 pub mod token_program;
+pub mod token_transaction;
 pub mod transaction;
 pub mod vote_program;
 pub mod vote_transaction;
@@ -1,3 +1,5 @@
+use crate::pubkey::Pubkey;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum LoaderInstruction {
     /// Write program data into an account
@@ -9,10 +11,37 @@ pub enum LoaderInstruction {
 
     /// Finalize an account loaded with program data for execution.
     /// The exact preparation steps is loader specific but on success the loader must set the executable
-    /// bit of the Account
+    /// bit of the Account. The BPF loader loads the account as an ELF shared object, applying its
+    /// relocations and checking that its calls resolve to registered helpers
     ///
     /// * key[0] - the account to prepare for execution
+    /// * key[1] - optional, an empty program-data account. If given, the BPF loader makes the
+    ///   program upgradeable, moving its code into the program-data account
+    /// * key[2] - the upgrade authority, required along with key[1]
     ///
-    /// The transaction must be signed by key[0]
+    /// The transaction must be signed by key[0], and by key[1] if given
     Finalize,
+
+    /// Replace the code of a program that has an upgrade authority with the program data
+    /// written into a buffer account. The new code goes into the program's program-data
+    /// account, and the buffer account is emptied, its tokens going to the upgrade authority.
+    /// Only the BPF loader supports upgrades; the Lua and native loaders reject them
+    ///
+    /// * key[0] - the buffer account
+    /// * key[1] - the program account
+    /// * key[2] - the program-data account
+    /// * key[3] - the upgrade authority
+    ///
+    /// The transaction must be signed by key[0] and key[3]
+    Upgrade,
+
+    /// Change the upgrade authority of a program. Setting no authority makes the program
+    /// immutable
+    ///
+    /// * key[0] - the current upgrade authority
+    /// * key[1] - the program account
+    /// * key[2] - the program-data account
+    ///
+    /// The transaction must be signed by key[0]
+    SetUpgradeAuthority(Option<Pubkey>),
 }
@@ -4,7 +4,7 @@ use crate::hash::Hash;
 use crate::loader_instruction::LoaderInstruction;
 use crate::pubkey::Pubkey;
 use crate::signature::Keypair;
-use crate::transaction::Transaction;
+use crate::transaction::{Instruction, Transaction};
 
 pub trait LoaderTransaction {
     fn loader_write(
@@ -17,6 +17,35 @@ pub trait LoaderTransaction {
     ) -> Self;
 
     fn loader_finalize(from_keypair: &Keypair, loader: Pubkey, last_id: Hash, fee: u64) -> Self;
+
+    fn loader_finalize_upgradeable(
+        program_keypair: &Keypair,
+        programdata_keypair: &Keypair,
+        upgrade_authority: Pubkey,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn loader_upgrade(
+        buffer_keypair: &Keypair,
+        authority_keypair: &Keypair,
+        program_id: Pubkey,
+        programdata: Pubkey,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn loader_set_upgrade_authority(
+        authority_keypair: &Keypair,
+        program_id: Pubkey,
+        programdata: Pubkey,
+        new_authority: Option<Pubkey>,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
 }
 
 impl LoaderTransaction for Transaction {
@@ -36,4 +65,64 @@ impl LoaderTransaction for Transaction {
         let instruction = LoaderInstruction::Finalize;
         Transaction::new(from_keypair, &[], loader, &instruction, last_id, fee)
     }
+
+    /// Finalize a program as upgradeable, its code moving into the empty program-data account
+    fn loader_finalize_upgradeable(
+        program_keypair: &Keypair,
+        programdata_keypair: &Keypair,
+        upgrade_authority: Pubkey,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = LoaderInstruction::Finalize;
+        Transaction::new_with_instructions(
+            &[program_keypair, programdata_keypair],
+            &[upgrade_authority],
+            last_id,
+            fee,
+            vec![loader],
+            vec![Instruction::new(0, &instruction, vec![0, 1, 2])],
+        )
+    }
+
+    fn loader_upgrade(
+        buffer_keypair: &Keypair,
+        authority_keypair: &Keypair,
+        program_id: Pubkey,
+        programdata: Pubkey,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = LoaderInstruction::Upgrade;
+        Transaction::new_with_instructions(
+            &[buffer_keypair, authority_keypair],
+            &[program_id, programdata],
+            last_id,
+            fee,
+            vec![loader],
+            vec![Instruction::new(0, &instruction, vec![0, 2, 3, 1])],
+        )
+    }
+
+    fn loader_set_upgrade_authority(
+        authority_keypair: &Keypair,
+        program_id: Pubkey,
+        programdata: Pubkey,
+        new_authority: Option<Pubkey>,
+        loader: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = LoaderInstruction::SetUpgradeAuthority(new_authority);
+        Transaction::new(
+            authority_keypair,
+            &[program_id, programdata],
+            loader,
+            &instruction,
+            last_id,
+            fee,
+        )
+    }
 }
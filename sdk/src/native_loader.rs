@@ -1,4 +1,7 @@
+use crate::hash::Hash;
+use crate::native_program::ProgramError;
 use crate::pubkey::Pubkey;
+use bincode::{deserialize, serialize};
 
 pub const NATIVE_LOADER_PROGRAM_ID: [u8; 32] = [
     1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -7,3 +10,55 @@ pub const NATIVE_LOADER_PROGRAM_ID: [u8; 32] = [
 pub fn id() -> Pubkey {
     Pubkey::new(&NATIVE_LOADER_PROGRAM_ID)
 }
+
+/// The userdata of a native program account, naming the shared library that implements the
+/// program
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct NativeProgram {
+    /// Name of the shared library, without the platform's prefix and extension
+    pub name: String,
+
+    /// Version of the shared library
+    pub version: String,
+
+    /// Hash of the shared library's contents. The loader refuses to run a library that
+    /// doesn't match it
+    pub code_hash: Option<Hash>,
+}
+
+impl NativeProgram {
+    pub fn new(name: &str, version: &str, code_hash: Option<Hash>) -> Self {
+        NativeProgram {
+            name: name.to_string(),
+            version: version.to_string(),
+            code_hash,
+        }
+    }
+
+    pub fn deserialize(input: &[u8]) -> Result<Self, ProgramError> {
+        deserialize(input).map_err(|_| ProgramError::InvalidUserdata)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        serialize(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash;
+
+    #[test]
+    fn test_native_program_serde() {
+        let program = NativeProgram::new("solana_noop", "0.12.0", Some(hash(b"noop")));
+        assert_eq!(
+            NativeProgram::deserialize(&program.serialize()),
+            Ok(program)
+        );
+        assert_eq!(
+            NativeProgram::deserialize(b"solana_noop"),
+            Err(ProgramError::InvalidUserdata)
+        );
+    }
+}
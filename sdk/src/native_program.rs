@@ -37,15 +37,59 @@ pub enum ProgramError {
     /// SystemInstruction::Spawn was attempted on an account that was not finalized by
     /// LoaderInstruction::Finalize
     AccountNotFinalized,
+
+    /// A program-specific error. Programs that define their own error enum return its code,
+    /// see `CustomErrorCode`
+    CustomError(u32),
+}
+
+impl ProgramError {
+    /// Wrap a program-specific error
+    pub fn custom<E: CustomErrorCode>(err: &E) -> Self {
+        ProgramError::CustomError(err.code())
+    }
+
+    /// The program-specific error this is, if it's a `CustomError` with a code `E` defines
+    pub fn to_custom<E: CustomErrorCode>(&self) -> Option<E> {
+        match self {
+            ProgramError::CustomError(code) => E::from_code(*code),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ProgramError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "error")
+        match self {
+            ProgramError::GenericError => write!(f, "program error"),
+            ProgramError::InvalidArgument => write!(f, "invalid instruction argument"),
+            ProgramError::ResultWithNegativeTokens => {
+                write!(f, "insufficient tokens for instruction")
+            }
+            ProgramError::UnbalancedInstruction => write!(f, "unbalanced instruction"),
+            ProgramError::ModifiedProgramId => write!(f, "modified an account's program id"),
+            ProgramError::ExternalAccountTokenSpend => {
+                write!(f, "spent the tokens of an account it doesn't own")
+            }
+            ProgramError::InvalidUserdata => write!(f, "invalid account userdata"),
+            ProgramError::UserdataTooSmall => write!(f, "account userdata too small"),
+            ProgramError::AssignOfUnownedAccount => write!(f, "assigned an unowned account"),
+            ProgramError::AccountNotFinalized => write!(f, "account not finalized"),
+            ProgramError::CustomError(code) => write!(f, "custom program error {}", code),
+        }
     }
 }
 impl std::error::Error for ProgramError {}
 
+/// A program's own error enum, reported to clients as `ProgramError::CustomError` codes
+pub trait CustomErrorCode: Sized {
+    /// The code that stands for this error
+    fn code(&self) -> u32;
+
+    /// The error `code` stands for, if any
+    fn from_code(code: u32) -> Option<Self>;
+}
+
 // All native programs export a symbol named process()
 pub const ENTRYPOINT: &str = "process";
 
@@ -14,6 +14,9 @@ pub enum Witness {
 
     /// A signature from Pubkey.
     Signature,
+
+    /// The preimage of a hash.
+    Preimage(Vec<u8>),
 }
 
 /// Some amount of tokens that should be sent to the `to` `Pubkey`.
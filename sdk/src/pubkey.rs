@@ -1,8 +1,26 @@
+use crate::hash::hashv;
 use bs58;
+use curve25519_dalek::edwards::CompressedEdwardsY;
 use generic_array::typenum::U32;
 use generic_array::GenericArray;
 use std::fmt;
 
+/// Maximum number of seeds a program address may be derived from
+pub const MAX_SEEDS: usize = 16;
+
+/// Maximum length in bytes of each program address seed
+pub const MAX_SEED_LEN: usize = 32;
+
+/// Reasons a program address couldn't be derived
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PubkeyError {
+    /// More than `MAX_SEEDS` seeds, or a seed longer than `MAX_SEED_LEN` bytes
+    MaxSeedLengthExceeded,
+
+    /// The seeds hash to a point on the ed25519 curve, which could have a private key
+    InvalidSeeds,
+}
+
 #[repr(C)]
 #[derive(Serialize, Deserialize, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Pubkey(GenericArray<u8, U32>);
@@ -11,6 +29,51 @@ impl Pubkey {
     pub fn new(pubkey_vec: &[u8]) -> Self {
         Pubkey(GenericArray::clone_from_slice(&pubkey_vec))
     }
+
+    /// Derive the address of an account controlled by `program_id` from `seeds`. The address
+    /// is never a valid ed25519 public key, so no one holds its private key and only
+    /// `program_id` can act for it
+    pub fn create_program_address(
+        seeds: &[&[u8]],
+        program_id: &Pubkey,
+    ) -> Result<Pubkey, PubkeyError> {
+        if seeds.len() > MAX_SEEDS || seeds.iter().any(|seed| seed.len() > MAX_SEED_LEN) {
+            Err(PubkeyError::MaxSeedLengthExceeded)?;
+        }
+
+        let mut vals = seeds.to_vec();
+        vals.push(program_id.as_ref());
+        vals.push(b"ProgramDerivedAddress");
+        let hash = hashv(&vals);
+
+        let address = Pubkey::new(hash.as_ref());
+        if address.is_on_curve() {
+            Err(PubkeyError::InvalidSeeds)?;
+        }
+        Ok(address)
+    }
+
+    /// Return true if the key is a point on the ed25519 curve. Keys off the curve, such as
+    /// program addresses, have no private key
+    pub fn is_on_curve(&self) -> bool {
+        CompressedEdwardsY::from_slice(self.as_ref())
+            .decompress()
+            .is_some()
+    }
+
+    /// Find a program address for `seeds`, appending the highest bump seed that moves the
+    /// address off the curve. Returns the address and its bump seed
+    pub fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+        for bump_seed in (0..=u8::max_value()).rev() {
+            let bump_seed = [bump_seed];
+            let mut seeds_with_bump = seeds.to_vec();
+            seeds_with_bump.push(&bump_seed);
+            if let Ok(address) = Pubkey::create_program_address(&seeds_with_bump, program_id) {
+                return (address, bump_seed[0]);
+            }
+        }
+        panic!("Unable to find a program address for the seeds");
+    }
 }
 
 impl AsRef<[u8]> for Pubkey {
@@ -30,3 +93,49 @@ impl fmt::Display for Pubkey {
         write!(f, "{}", bs58::encode(self.0).into_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{Keypair, KeypairUtil};
+
+    #[test]
+    fn test_create_program_address() {
+        let program_id = Keypair::new().pubkey();
+        let (address, bump_seed) = Pubkey::find_program_address(&[b"vault"], &program_id);
+        assert!(!address.is_on_curve());
+        assert_eq!(
+            Pubkey::create_program_address(&[b"vault", &[bump_seed]], &program_id),
+            Ok(address)
+        );
+
+        // The address depends on both the seeds and the program
+        let other_program_id = Keypair::new().pubkey();
+        assert_ne!(
+            Pubkey::create_program_address(&[b"vault", &[bump_seed]], &other_program_id),
+            Ok(address)
+        );
+        assert_ne!(
+            Pubkey::create_program_address(&[b"other", &[bump_seed]], &program_id),
+            Ok(address)
+        );
+
+        // Ordinary public keys are on the curve
+        assert!(program_id.is_on_curve());
+    }
+
+    #[test]
+    fn test_create_program_address_seed_limits() {
+        let program_id = Keypair::new().pubkey();
+        let long_seed = [0; MAX_SEED_LEN + 1];
+        assert_eq!(
+            Pubkey::create_program_address(&[&long_seed], &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+        let seeds = vec![&b"seed"[..]; MAX_SEEDS + 1];
+        assert_eq!(
+            Pubkey::create_program_address(&seeds, &program_id),
+            Err(PubkeyError::MaxSeedLengthExceeded)
+        );
+    }
+}
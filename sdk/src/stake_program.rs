@@ -0,0 +1,207 @@
+//! Stake program
+//! Delegates the tokens of stake accounts to the validators that vote with a "vote account"
+
+use crate::native_program::ProgramError;
+use crate::pubkey::Pubkey;
+use bincode::{deserialize, serialize_into, serialized_size, ErrorKind};
+
+pub const STAKE_PROGRAM_ID: [u8; 32] = [
+    134, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0,
+];
+
+pub fn check_id(program_id: &Pubkey) -> bool {
+    program_id.as_ref() == STAKE_PROGRAM_ID
+}
+
+pub fn id() -> Pubkey {
+    Pubkey::new(&STAKE_PROGRAM_ID)
+}
+
+// Number of ticks over which delegated stake linearly becomes active, one leader schedule
+// at the default seed rotation interval
+pub const STAKE_WARMUP_TICKS: u64 = 1024;
+
+// Number of ticks over which deactivated stake linearly becomes inactive
+pub const STAKE_COOLDOWN_TICKS: u64 = 1024;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum StakeInstruction {
+    /// Delegate the tokens of a stake account to the validator of a "vote account". A stake
+    /// account may be redelegated once its previous delegation has cooled down
+    /// * Transaction::keys[0] - the stake account, signed
+    /// * Transaction::keys[1] - the "vote account" to delegate to
+    DelegateStake,
+
+    /// Begin the cooldown of a stake account's delegation
+    /// * Transaction::keys[0] - the stake account, signed
+    Deactivate,
+
+    /// Move tokens that are not locked by a delegation out of a stake account
+    /// * Transaction::keys[0] - the stake account, signed
+    /// * Transaction::keys[1] - the account to receive the tokens
+    Withdraw(u64),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum StakeState {
+    Uninitialized,
+    Delegated {
+        /// The "vote account" the stake is delegated to
+        voter_id: Pubkey,
+
+        /// The number of tokens delegated
+        stake: u64,
+
+        /// The tick height at which the delegation began warming up
+        activation_height: u64,
+
+        /// The tick height at which the delegation began cooling down, if it has
+        deactivation_height: Option<u64>,
+    },
+}
+
+impl Default for StakeState {
+    fn default() -> Self {
+        StakeState::Uninitialized
+    }
+}
+
+pub fn get_max_size() -> usize {
+    serialized_size(&StakeState::Delegated {
+        voter_id: Pubkey::default(),
+        stake: 0,
+        activation_height: 0,
+        deactivation_height: Some(0),
+    })
+    .unwrap() as usize
+}
+
+// The portion of `stake` that has ramped up over `ticks` out of `total_ticks`
+fn ramp(stake: u64, ticks: u64, total_ticks: u64) -> u64 {
+    if ticks >= total_ticks {
+        stake
+    } else {
+        (u128::from(stake) * u128::from(ticks) / u128::from(total_ticks)) as u64
+    }
+}
+
+impl StakeState {
+    /// The number of delegated tokens that count towards the validator's stake at
+    /// `tick_height`
+    pub fn active_stake(&self, tick_height: u64) -> u64 {
+        match self {
+            StakeState::Uninitialized => 0,
+            StakeState::Delegated {
+                stake,
+                activation_height,
+                deactivation_height,
+                ..
+            } => {
+                // Warmup stops once cooldown begins
+                let warm_height = deactivation_height.map_or(tick_height, |deactivation_height| {
+                    tick_height.min(deactivation_height)
+                });
+                let warm_stake = ramp(
+                    *stake,
+                    warm_height.saturating_sub(*activation_height),
+                    STAKE_WARMUP_TICKS,
+                );
+                match deactivation_height {
+                    None => warm_stake,
+                    Some(deactivation_height) => {
+                        let cooled_stake = ramp(
+                            warm_stake,
+                            tick_height.saturating_sub(*deactivation_height),
+                            STAKE_COOLDOWN_TICKS,
+                        );
+                        warm_stake - cooled_stake
+                    }
+                }
+            }
+        }
+    }
+
+    /// The number of tokens that may not be withdrawn at `tick_height`. Delegated tokens
+    /// remain locked until their cooldown completes
+    pub fn locked_stake(&self, tick_height: u64) -> u64 {
+        match self {
+            StakeState::Uninitialized => 0,
+            StakeState::Delegated {
+                stake,
+                deactivation_height: None,
+                ..
+            } => *stake,
+            StakeState::Delegated { .. } => self.active_stake(tick_height),
+        }
+    }
+
+    pub fn deserialize(input: &[u8]) -> Result<StakeState, ProgramError> {
+        deserialize(input).map_err(|_| ProgramError::InvalidUserdata)
+    }
+
+    pub fn serialize(&self, output: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_into(output, self).map_err(|err| match *err {
+            ErrorKind::SizeLimit => ProgramError::UserdataTooSmall,
+            _ => ProgramError::GenericError,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delegated(stake: u64, deactivation_height: Option<u64>) -> StakeState {
+        StakeState::Delegated {
+            voter_id: Pubkey::new(&[1; 32]),
+            stake,
+            activation_height: 100,
+            deactivation_height,
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let mut buffer = vec![0; get_max_size()];
+        assert_eq!(StakeState::deserialize(&buffer), Ok(StakeState::default()));
+
+        let stake_state = delegated(42, Some(u64::max_value()));
+        stake_state.serialize(&mut buffer).unwrap();
+        assert_eq!(StakeState::deserialize(&buffer), Ok(stake_state));
+    }
+
+    #[test]
+    fn test_stake_warmup() {
+        let stake_state = delegated(1000, None);
+        assert_eq!(stake_state.active_stake(0), 0);
+        assert_eq!(stake_state.active_stake(100), 0);
+        assert_eq!(stake_state.active_stake(100 + STAKE_WARMUP_TICKS / 2), 500);
+        assert_eq!(stake_state.active_stake(100 + STAKE_WARMUP_TICKS), 1000);
+        assert_eq!(stake_state.active_stake(u64::max_value()), 1000);
+        assert_eq!(stake_state.locked_stake(0), 1000);
+    }
+
+    #[test]
+    fn test_stake_cooldown() {
+        let deactivation_height = 100 + STAKE_WARMUP_TICKS;
+        let stake_state = delegated(1000, Some(deactivation_height));
+        assert_eq!(stake_state.active_stake(deactivation_height), 1000);
+        assert_eq!(
+            stake_state.active_stake(deactivation_height + STAKE_COOLDOWN_TICKS / 4),
+            750
+        );
+        assert_eq!(
+            stake_state.locked_stake(deactivation_height + STAKE_COOLDOWN_TICKS / 4),
+            750
+        );
+        assert_eq!(
+            stake_state.active_stake(deactivation_height + STAKE_COOLDOWN_TICKS),
+            0
+        );
+
+        // Stake deactivated while warming up only cools down what had warmed up
+        let stake_state = delegated(1000, Some(100 + STAKE_WARMUP_TICKS / 2));
+        assert_eq!(stake_state.active_stake(100 + STAKE_WARMUP_TICKS), 250);
+    }
+}
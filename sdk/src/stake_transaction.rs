@@ -0,0 +1,130 @@
+//! The `stake_transaction` module provides functionality for creating stake transactions.
+
+use crate::hash::Hash;
+use crate::pubkey::Pubkey;
+use crate::signature::Keypair;
+use crate::stake_program::{self, StakeInstruction};
+use crate::system_instruction::SystemInstruction;
+use crate::system_program;
+use crate::transaction::{Instruction, Transaction};
+
+pub trait StakeTransaction {
+    fn stake_account_new(
+        from_keypair: &Keypair,
+        stake_account_id: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn stake_delegate(
+        stake_account: &Keypair,
+        vote_account_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn stake_deactivate(stake_account: &Keypair, last_id: Hash, fee: u64) -> Self;
+
+    fn stake_withdraw(
+        stake_account: &Keypair,
+        to: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+}
+
+impl StakeTransaction for Transaction {
+    /// Create a stake account holding `tokens`, ready to be delegated
+    fn stake_account_new(
+        from_keypair: &Keypair,
+        stake_account_id: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let create = SystemInstruction::CreateAccount {
+            tokens,
+            space: stake_program::get_max_size() as u64,
+            program_id: stake_program::id(),
+        };
+        Transaction::new_with_instructions(
+            &[from_keypair],
+            &[stake_account_id],
+            last_id,
+            fee,
+            vec![system_program::id()],
+            vec![Instruction::new(0, &create, vec![0, 1])],
+        )
+    }
+
+    /// Delegate all the tokens of `stake_account` to the validator voting with
+    /// `vote_account_id`
+    fn stake_delegate(
+        stake_account: &Keypair,
+        vote_account_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            stake_account,
+            &[vote_account_id],
+            stake_program::id(),
+            &StakeInstruction::DelegateStake,
+            last_id,
+            fee,
+        )
+    }
+
+    fn stake_deactivate(stake_account: &Keypair, last_id: Hash, fee: u64) -> Self {
+        Transaction::new(
+            stake_account,
+            &[],
+            stake_program::id(),
+            &StakeInstruction::Deactivate,
+            last_id,
+            fee,
+        )
+    }
+
+    fn stake_withdraw(
+        stake_account: &Keypair,
+        to: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            stake_account,
+            &[to],
+            stake_program::id(),
+            &StakeInstruction::Withdraw(tokens),
+            last_id,
+            fee,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::KeypairUtil;
+    use bincode::deserialize;
+
+    #[test]
+    fn test_stake_delegate_keys() {
+        let stake_account = Keypair::new();
+        let vote_account_id = Keypair::new().pubkey();
+        let tx = Transaction::stake_delegate(&stake_account, vote_account_id, Hash::default(), 0);
+        assert_eq!(
+            tx.account_keys,
+            vec![stake_account.pubkey(), vote_account_id]
+        );
+        assert_eq!(
+            deserialize::<StakeInstruction>(&tx.userdata(0)).unwrap(),
+            StakeInstruction::DelegateStake
+        );
+        assert!(tx.verify_signature());
+    }
+}
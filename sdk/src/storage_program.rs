@@ -1,15 +1,22 @@
 use crate::hash::Hash;
 use crate::pubkey::Pubkey;
 use crate::signature::{Keypair, Signature};
+use crate::system_transaction::SystemTransaction;
 use crate::transaction::Transaction;
 
 pub const ENTRIES_PER_SEGMENT: u64 = 16;
 
+/// Most proofs accepted per segment, so the state stays within its account
+pub const MAX_PROOFS_PER_SEGMENT: usize = 8;
+
+/// Most validations accepted per segment, so the state stays within its account
+pub const MAX_VALIDATIONS_PER_SEGMENT: usize = 8;
+
 pub fn get_segment_from_entry(entry_height: u64) -> usize {
     (entry_height / ENTRIES_PER_SEGMENT) as usize
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum ProofStatus {
     Valid,
     NotValid,
@@ -37,8 +44,16 @@ pub struct StorageProgramState {
     pub proofs: Vec<Vec<ProofInfo>>,
     pub previous_proofs: Vec<Vec<ProofInfo>>,
 
+    // The proofs that reward_validations voted on
+    pub reward_proofs: Vec<Vec<ProofInfo>>,
+
     pub lockout_validations: Vec<Vec<ValidationInfo>>,
     pub reward_validations: Vec<Vec<ValidationInfo>>,
+
+    // Who has already been paid a reward for each segment of reward_validations. Only
+    // keys with a nonzero reward are recorded, so there are at most as many as there
+    // are proofs and validations in the segment
+    pub reward_claims: Vec<Vec<Pubkey>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -52,12 +67,20 @@ pub enum StorageProgram {
         id: Hash,
         entry_height: u64,
     },
+    /// Pay the signer's validator and replicator rewards for a segment out of the
+    /// storage reward pool, the tokens held by the storage system account, which
+    /// anyone can fund with a system `Move`. Each key may claim once per segment. A
+    /// replicator with a proof found invalid gets no replicator reward for that segment,
+    /// and validators are only paid for verdicts that agree with the majority
     ClaimStorageReward {
         entry_height: u64,
     },
+    /// Record the signer's verdicts on the proofs of a segment, keyed by the signature
+    /// each proof was submitted with. The signer must be the node of the registered vote
+    /// account passed as the third account
     ProofValidation {
         entry_height: u64,
-        proof_mask: Vec<ProofStatus>,
+        proof_statuses: Vec<(Signature, ProofStatus)>,
     },
 }
 
@@ -101,12 +124,15 @@ pub trait StorageTransaction {
 
     fn storage_new_proof_validation(
         from_keypair: &Keypair,
+        vote_account: Pubkey,
         last_id: Hash,
         entry_height: u64,
-        proof_mask: Vec<ProofStatus>,
+        proof_statuses: Vec<(Signature, ProofStatus)>,
     ) -> Self;
 
     fn storage_new_reward_claim(from_keypair: &Keypair, last_id: Hash, entry_height: u64) -> Self;
+
+    fn storage_new_fund_reward_pool(from_keypair: &Keypair, tokens: u64, last_id: Hash) -> Self;
 }
 
 impl StorageTransaction for Transaction {
@@ -154,17 +180,18 @@ impl StorageTransaction for Transaction {
 
     fn storage_new_proof_validation(
         from_keypair: &Keypair,
+        vote_account: Pubkey,
         last_id: Hash,
         entry_height: u64,
-        proof_mask: Vec<ProofStatus>,
+        proof_statuses: Vec<(Signature, ProofStatus)>,
     ) -> Self {
         let program = StorageProgram::ProofValidation {
             entry_height,
-            proof_mask,
+            proof_statuses,
         };
         Transaction::new(
             from_keypair,
-            &[Pubkey::new(&STORAGE_SYSTEM_ACCOUNT_ID)],
+            &[Pubkey::new(&STORAGE_SYSTEM_ACCOUNT_ID), vote_account],
             id(),
             &program,
             last_id,
@@ -183,4 +210,9 @@ impl StorageTransaction for Transaction {
             0,
         )
     }
+
+    /// Move `tokens` into the storage reward pool
+    fn storage_new_fund_reward_pool(from_keypair: &Keypair, tokens: u64, last_id: Hash) -> Self {
+        Transaction::system_move(from_keypair, system_id(), tokens, last_id, 0)
+    }
 }
@@ -23,4 +23,42 @@ pub enum SystemInstruction {
 
     /// Spawn a new program from an account
     Spawn,
+
+    /// Initialize a nonce account, an account created with space for a `NonceState` and
+    /// assigned to the system program
+    /// * Transaction::keys[0] - the nonce account
+    InitializeNonce,
+
+    /// Replace the nonce stored in a nonce account. A transaction whose `last_id` is no
+    /// longer recent is still accepted if its `last_id` is the stored nonce and this is
+    /// its first instruction
+    /// * Transaction::keys[0] - the nonce account
+    AdvanceNonce,
+
+    /// Create a new account at the program address derived from `seeds` and `program_id`,
+    /// assigned to `program_id`. The derivation stands in for the new account's signature,
+    /// so that a program can keep tokens at an address no one holds the private key of. The
+    /// first seed must be the source's public key, so no one can claim an address derived for
+    /// someone else
+    /// * Transaction::keys[0] - source
+    /// * Transaction::keys[1] - the program address
+    /// * seeds - the seeds the address is derived from, see `Pubkey::create_program_address`
+    /// * tokens - number of tokens to transfer to the new account
+    /// * space - memory to allocate, at most `system_program::MAX_PROGRAM_ACCOUNT_SPACE`
+    /// * program_id - the program the address is derived from and assigned to
+    CreateProgramAccount {
+        seeds: Vec<Vec<u8>>,
+        tokens: u64,
+        space: u64,
+        program_id: Pubkey,
+    },
+
+    /// Provide the seeds of a program address, so that in this transaction's other
+    /// instructions the program that derives the address from `seeds` signs for it. Only that
+    /// program sees the signature, and since anyone can provide the seeds, it stands for the
+    /// program's own authority rather than any client's
+    /// * Transaction::keys[0] - source
+    /// * Transaction::keys[1] - the program address
+    /// * seeds - the seeds the address is derived from, see `Pubkey::create_program_address`
+    SignForProgramAddress { seeds: Vec<Vec<u8>> },
 }
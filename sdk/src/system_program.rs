@@ -1,7 +1,13 @@
+use crate::hash::Hash;
+use crate::native_program::ProgramError;
 use crate::pubkey::Pubkey;
+use bincode::{deserialize, serialize_into, serialized_size, ErrorKind};
 
 pub const SYSTEM_PROGRAM_ID: [u8; 32] = [0u8; 32];
 
+/// Maximum number of bytes of userdata `SystemInstruction::CreateProgramAccount` allocates
+pub const MAX_PROGRAM_ACCOUNT_SPACE: u64 = 10 * 1024 * 1024;
+
 pub fn id() -> Pubkey {
     Pubkey::new(&SYSTEM_PROGRAM_ID)
 }
@@ -9,3 +15,57 @@ pub fn id() -> Pubkey {
 pub fn check_id(program_id: &Pubkey) -> bool {
     program_id.as_ref() == SYSTEM_PROGRAM_ID
 }
+
+/// The userdata of a nonce account
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum NonceState {
+    Uninitialized,
+    Initialized {
+        /// Stands in for the `last_id` of the next transaction that advances it
+        nonce: Hash,
+    },
+}
+
+impl Default for NonceState {
+    fn default() -> Self {
+        NonceState::Uninitialized
+    }
+}
+
+impl NonceState {
+    pub fn get_max_size() -> usize {
+        serialized_size(&NonceState::Initialized {
+            nonce: Hash::default(),
+        })
+        .unwrap() as usize
+    }
+
+    pub fn deserialize(input: &[u8]) -> Result<NonceState, ProgramError> {
+        deserialize(input).map_err(|_| ProgramError::InvalidUserdata)
+    }
+
+    pub fn serialize(&self, output: &mut [u8]) -> Result<(), ProgramError> {
+        serialize_into(output, self).map_err(|err| match *err {
+            ErrorKind::SizeLimit => ProgramError::UserdataTooSmall,
+            _ => ProgramError::GenericError,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_state_serde() {
+        let mut buffer = vec![0; NonceState::get_max_size()];
+        assert_eq!(NonceState::deserialize(&buffer), Ok(NonceState::default()));
+
+        let nonce_state = NonceState::Initialized {
+            nonce: Hash::new(&[1; 32]),
+        };
+        assert!(nonce_state.serialize(&mut buffer[..4]).is_err());
+        nonce_state.serialize(&mut buffer).unwrap();
+        assert_eq!(NonceState::deserialize(&buffer), Ok(nonce_state));
+    }
+}
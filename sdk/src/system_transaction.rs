@@ -4,7 +4,7 @@ use crate::hash::Hash;
 use crate::pubkey::Pubkey;
 use crate::signature::Keypair;
 use crate::system_instruction::SystemInstruction;
-use crate::system_program;
+use crate::system_program::{self, NonceState};
 use crate::transaction::{Instruction, Transaction};
 
 pub trait SystemTransaction {
@@ -38,6 +38,33 @@ pub trait SystemTransaction {
     ) -> Self;
 
     fn system_spawn(from_keypair: &Keypair, last_id: Hash, fee: u64) -> Self;
+
+    fn system_create_nonce_account(
+        from_keypair: &Keypair,
+        nonce_keypair: &Keypair,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn system_move_with_nonce(
+        from_keypair: &Keypair,
+        nonce_keypair: &Keypair,
+        to: Pubkey,
+        tokens: u64,
+        nonce: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn system_create_program_account(
+        from_keypair: &Keypair,
+        seeds: &[&[u8]],
+        last_id: Hash,
+        tokens: u64,
+        space: u64,
+        program_id: Pubkey,
+        fee: u64,
+    ) -> Self;
 }
 
 impl SystemTransaction for Transaction {
@@ -132,6 +159,85 @@ impl SystemTransaction for Transaction {
             fee,
         )
     }
+    /// Create and sign a transaction that creates and initializes a nonce account
+    fn system_create_nonce_account(
+        from_keypair: &Keypair,
+        nonce_keypair: &Keypair,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let create = SystemInstruction::CreateAccount {
+            tokens,
+            space: NonceState::get_max_size() as u64,
+            program_id: system_program::id(),
+        };
+        let initialize = SystemInstruction::InitializeNonce;
+        Transaction::new_with_instructions(
+            &[from_keypair, nonce_keypair],
+            &[],
+            last_id,
+            fee,
+            vec![system_program::id()],
+            vec![
+                Instruction::new(0, &create, vec![0, 1]),
+                Instruction::new(0, &initialize, vec![1]),
+            ],
+        )
+    }
+    /// Create and sign a SystemInstruction::Move transaction that uses the `nonce` stored in
+    /// a nonce account in place of a recent `last_id`, advancing the nonce
+    fn system_move_with_nonce(
+        from_keypair: &Keypair,
+        nonce_keypair: &Keypair,
+        to: Pubkey,
+        tokens: u64,
+        nonce: Hash,
+        fee: u64,
+    ) -> Self {
+        let advance = SystemInstruction::AdvanceNonce;
+        let move_tokens = SystemInstruction::Move { tokens };
+        Transaction::new_with_instructions(
+            &[from_keypair, nonce_keypair],
+            &[to],
+            nonce,
+            fee,
+            vec![system_program::id()],
+            vec![
+                Instruction::new(0, &advance, vec![1]),
+                Instruction::new(0, &move_tokens, vec![0, 2]),
+            ],
+        )
+    }
+    /// Create and sign a SystemInstruction::CreateProgramAccount transaction creating the
+    /// account at the address `program_id` derives from `seeds`. The first seed must be
+    /// `from_keypair`'s public key
+    fn system_create_program_account(
+        from_keypair: &Keypair,
+        seeds: &[&[u8]],
+        last_id: Hash,
+        tokens: u64,
+        space: u64,
+        program_id: Pubkey,
+        fee: u64,
+    ) -> Self {
+        let to = Pubkey::create_program_address(seeds, &program_id)
+            .expect("seeds don't derive a program address");
+        let create = SystemInstruction::CreateProgramAccount {
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+            tokens,
+            space,
+            program_id,
+        };
+        Transaction::new(
+            from_keypair,
+            &[to],
+            system_program::id(),
+            &create,
+            last_id,
+            fee,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -154,4 +260,18 @@ mod tests {
         assert_eq!(tx.instructions[0].accounts, vec![0, 1]);
         assert_eq!(tx.instructions[1].accounts, vec![0, 2]);
     }
+
+    #[test]
+    fn test_move_with_nonce() {
+        let from = Keypair::new();
+        let nonce_keypair = Keypair::new();
+        let to = Keypair::new().pubkey();
+        let nonce = Hash::new(&[1; 32]);
+
+        let tx = Transaction::system_move_with_nonce(&from, &nonce_keypair, to, 1, nonce, 0);
+        assert_eq!(tx.last_id, nonce);
+        assert_eq!(tx.key(0, 0), Some(&nonce_keypair.pubkey()));
+        assert_eq!(tx.signer_key(1, 0), Some(&from.pubkey()));
+        assert_eq!(tx.key(1, 1), Some(&to));
+    }
 }
@@ -1,11 +1,356 @@
 //! An ERC20-like Token
+use crate::native_program::{CustomErrorCode, ProgramError};
 use crate::pubkey::Pubkey;
+use bincode::{deserialize, serialize_into, serialized_size};
+use log::*;
 
 const TOKEN_PROGRAM_ID: [u8; 32] = [
     131, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     0,
 ];
 
+pub fn check_id(program_id: &Pubkey) -> bool {
+    program_id.as_ref() == TOKEN_PROGRAM_ID
+}
+
 pub fn id() -> Pubkey {
     Pubkey::new(&TOKEN_PROGRAM_ID)
 }
+
+/// Reasons the token program might reject an instruction, reported as
+/// `ProgramError::CustomError` codes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenError {
+    InvalidArgument,
+    InsufficentFunds,
+    NotOwner,
+    AccountFrozen,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TokenError::InvalidArgument => write!(f, "invalid argument"),
+            TokenError::InsufficentFunds => write!(f, "insufficient funds"),
+            TokenError::NotOwner => write!(f, "not the owner"),
+            TokenError::AccountFrozen => write!(f, "account is frozen"),
+        }
+    }
+}
+impl std::error::Error for TokenError {}
+
+impl CustomErrorCode for TokenError {
+    fn code(&self) -> u32 {
+        *self as u32
+    }
+
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(TokenError::InvalidArgument),
+            1 => Some(TokenError::InsufficentFunds),
+            2 => Some(TokenError::NotOwner),
+            3 => Some(TokenError::AccountFrozen),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenInfo {
+    /**
+     * Total supply of tokens
+     */
+    pub supply: u64,
+
+    /**
+     * Number of base 10 digits to the right of the decimal place in the total supply
+     */
+    pub decimals: u8,
+
+    /**
+     * Descriptive name of this token
+     */
+    pub name: String,
+
+    /**
+     * Symbol for this token
+     */
+    pub symbol: String,
+
+    /**
+     * If `mint_authority` is Some(_), that key may mint additional supply
+     */
+    pub mint_authority: Option<Pubkey>,
+
+    /**
+     * If `freeze_authority` is Some(_), that key may freeze and thaw token accounts
+     */
+    pub freeze_authority: Option<Pubkey>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenAccountDelegateInfo {
+    /**
+     * The source account for the tokens
+     */
+    pub source: Pubkey,
+
+    /**
+     * The original amount that this delegate account was authorized to spend up to
+     */
+    pub original_amount: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenAccountInfo {
+    /**
+     * The kind of token this account holds
+     */
+    pub token: Pubkey,
+
+    /**
+     * Owner of this account
+     */
+    pub owner: Pubkey,
+
+    /**
+     * Amount of tokens this account holds
+     */
+    pub amount: u64,
+
+    /**
+     * If `delegate` None, `amount` belongs to this account.
+     * If `delegate` is Option<_>, `amount` represents the remaining allowance
+     * of tokens that may be transferred from the `source` account.
+     */
+    pub delegate: Option<TokenAccountDelegateInfo>,
+
+    /**
+     * A frozen account may not send, receive, burn or be closed until it is thawed
+     */
+    pub is_frozen: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub enum TokenInstruction {
+    /// Create a new token, crediting its entire supply to an existing token account
+    /// * Transaction::keys[0] - the new token, signed
+    /// * Transaction::keys[1] - the token account to receive the supply
+    NewToken(TokenInfo),
+
+    /// Create a new token account
+    /// * Transaction::keys[0] - the new token account, signed
+    /// * Transaction::keys[1] - the owner of the account
+    /// * Transaction::keys[2] - the token this account is associated with
+    /// * Transaction::keys[3] - the source account this account is a delegate for (optional)
+    NewTokenAccount,
+
+    /// Transfer tokens between token accounts
+    /// * Transaction::keys[0] - the owner of the source account, signed
+    /// * Transaction::keys[1] - the source account
+    /// * Transaction::keys[2] - the destination account
+    /// * Transaction::keys[3] - the account the source is a delegate for, if it is a delegate
+    Transfer(u64),
+
+    /// Authorize a delegate account to transfer up to the given amount
+    /// * Transaction::keys[0] - the owner of the source account, signed
+    /// * Transaction::keys[1] - the source account
+    /// * Transaction::keys[2] - the delegate account
+    Approve(u64),
+
+    /// Change the owner of a token account
+    /// * Transaction::keys[0] - the current owner, signed
+    /// * Transaction::keys[1] - the token account
+    /// * Transaction::keys[2] - the new owner
+    SetOwner,
+
+    /// Mint additional supply of a token
+    /// * Transaction::keys[0] - the mint authority of the token, signed
+    /// * Transaction::keys[1] - the token
+    /// * Transaction::keys[2] - the token account to receive the new supply
+    MintTo(u64),
+
+    /// Destroy tokens, reducing the supply
+    /// * Transaction::keys[0] - the owner of the source account, signed
+    /// * Transaction::keys[1] - the source account
+    /// * Transaction::keys[2] - the token
+    Burn(u64),
+
+    /// Freeze a token account
+    /// * Transaction::keys[0] - the freeze authority of the token, signed
+    /// * Transaction::keys[1] - the token account
+    /// * Transaction::keys[2] - the token
+    FreezeAccount,
+
+    /// Thaw a frozen token account
+    /// * Transaction::keys[0] - the freeze authority of the token, signed
+    /// * Transaction::keys[1] - the token account
+    /// * Transaction::keys[2] - the token
+    ThawAccount,
+
+    /// Close an empty token account, moving its tokens to another account
+    /// * Transaction::keys[0] - the owner of the account, signed
+    /// * Transaction::keys[1] - the token account
+    /// * Transaction::keys[2] - the account to receive its tokens
+    CloseAccount,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub enum TokenProgram {
+    Unallocated,
+    Token(TokenInfo),
+    Account(TokenAccountInfo),
+    Invalid,
+}
+impl Default for TokenProgram {
+    fn default() -> TokenProgram {
+        TokenProgram::Unallocated
+    }
+}
+
+/// Space required by the userdata of a token account
+pub fn get_account_size() -> usize {
+    let account_info = TokenAccountInfo {
+        delegate: Some(TokenAccountDelegateInfo::default()),
+        ..TokenAccountInfo::default()
+    };
+    1 + serialized_size(&account_info).unwrap() as usize
+}
+
+/// Space required by the userdata of a token described by `token_info`
+pub fn get_token_size(token_info: &TokenInfo) -> usize {
+    let token_info = TokenInfo {
+        mint_authority: Some(Pubkey::default()),
+        freeze_authority: Some(Pubkey::default()),
+        ..token_info.clone()
+    };
+    1 + serialized_size(&token_info).unwrap() as usize
+}
+
+impl TokenProgram {
+    pub fn deserialize(input: &[u8]) -> Result<TokenProgram, ProgramError> {
+        if input.is_empty() {
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        let map_to_invalid_userdata = |err: Box<bincode::ErrorKind>| {
+            warn!("invalid userdata: {:?}", err);
+            ProgramError::InvalidUserdata
+        };
+        match input[0] {
+            0 => Ok(TokenProgram::Unallocated),
+            1 => Ok(TokenProgram::Token(
+                deserialize(&input[1..]).map_err(map_to_invalid_userdata)?,
+            )),
+            2 => Ok(TokenProgram::Account(
+                deserialize(&input[1..]).map_err(map_to_invalid_userdata)?,
+            )),
+            _ => Err(ProgramError::InvalidUserdata),
+        }
+    }
+
+    pub fn serialize(self: &TokenProgram, output: &mut [u8]) -> Result<(), ProgramError> {
+        if output.is_empty() {
+            warn!("serialize fail: ouput.len is 0");
+            Err(ProgramError::UserdataTooSmall)?;
+        }
+        let map_to_too_small = |err: Box<bincode::ErrorKind>| {
+            warn!("serialize fail: {:?}", err);
+            ProgramError::UserdataTooSmall
+        };
+        match self {
+            TokenProgram::Unallocated | TokenProgram::Invalid => Err(ProgramError::InvalidArgument),
+            TokenProgram::Token(token_info) => {
+                output[0] = 1;
+                serialize_into(&mut output[1..], &token_info).map_err(map_to_too_small)
+            }
+            TokenProgram::Account(account_info) => {
+                output[0] = 2;
+                serialize_into(&mut output[1..], &account_info).map_err(map_to_too_small)
+            }
+        }
+    }
+
+    pub fn amount(&self) -> Result<u64, ProgramError> {
+        if let TokenProgram::Account(account_info) = self {
+            Ok(account_info.amount)
+        } else {
+            Err(ProgramError::InvalidArgument)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn serde() {
+        assert_eq!(TokenProgram::deserialize(&[0]), Ok(TokenProgram::default()));
+
+        let mut userdata = vec![0; 256];
+
+        let account = TokenProgram::Account(TokenAccountInfo {
+            token: Pubkey::new(&[1; 32]),
+            owner: Pubkey::new(&[2; 32]),
+            amount: 123,
+            delegate: None,
+            is_frozen: false,
+        });
+        account.serialize(&mut userdata).unwrap();
+        assert_eq!(TokenProgram::deserialize(&userdata), Ok(account));
+
+        let account = TokenProgram::Token(TokenInfo {
+            supply: 12345,
+            decimals: 2,
+            name: "A test token".to_string(),
+            symbol: "TEST".to_string(),
+            mint_authority: Some(Pubkey::new(&[3; 32])),
+            freeze_authority: None,
+        });
+        account.serialize(&mut userdata).unwrap();
+        assert_eq!(TokenProgram::deserialize(&userdata), Ok(account));
+    }
+
+    #[test]
+    pub fn serde_expect_fail() {
+        let mut userdata = vec![0; 256];
+
+        // Certain TokenProgram's may not be serialized
+        let account = TokenProgram::default();
+        assert_eq!(account, TokenProgram::Unallocated);
+        assert!(account.serialize(&mut userdata).is_err());
+        assert!(account.serialize(&mut userdata).is_err());
+        let account = TokenProgram::Invalid;
+        assert!(account.serialize(&mut userdata).is_err());
+
+        // Bad deserialize userdata
+        assert!(TokenProgram::deserialize(&[]).is_err());
+        assert!(TokenProgram::deserialize(&[1]).is_err());
+        assert!(TokenProgram::deserialize(&[1, 2]).is_err());
+        assert!(TokenProgram::deserialize(&[2, 2]).is_err());
+        assert!(TokenProgram::deserialize(&[3]).is_err());
+    }
+
+    #[test]
+    fn test_sizes() {
+        let mut userdata = vec![0; get_account_size()];
+        let account = TokenProgram::Account(TokenAccountInfo {
+            delegate: Some(TokenAccountDelegateInfo::default()),
+            ..TokenAccountInfo::default()
+        });
+        account.serialize(&mut userdata).unwrap();
+
+        let token_info = TokenInfo {
+            name: "A test token".to_string(),
+            symbol: "TEST".to_string(),
+            mint_authority: Some(Pubkey::new(&[3; 32])),
+            freeze_authority: Some(Pubkey::new(&[4; 32])),
+            ..TokenInfo::default()
+        };
+        let mut userdata = vec![0; get_token_size(&TokenInfo::default())];
+        let token = TokenProgram::Token(token_info.clone());
+        assert!(token.serialize(&mut userdata).is_err());
+        let mut userdata = vec![0; get_token_size(&token_info)];
+        token.serialize(&mut userdata).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! The `token_transaction` module provides functionality for creating token transactions.
+
+use crate::hash::Hash;
+use crate::pubkey::Pubkey;
+use crate::signature::Keypair;
+use crate::token_program::{self, TokenInfo, TokenInstruction};
+use crate::transaction::Transaction;
+
+pub trait TokenTransaction {
+    fn token_new(
+        token_keypair: &Keypair,
+        account: Pubkey,
+        token_info: TokenInfo,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_new_account(
+        account_keypair: &Keypair,
+        owner: Pubkey,
+        token: Pubkey,
+        delegate_source: Option<Pubkey>,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_transfer(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        dest: Pubkey,
+        delegate_source: Option<Pubkey>,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_approve(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        delegate: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_set_owner(
+        owner_keypair: &Keypair,
+        account: Pubkey,
+        new_owner: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_mint_to(
+        mint_authority_keypair: &Keypair,
+        token: Pubkey,
+        dest: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_burn(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        token: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_set_frozen(
+        freeze_authority_keypair: &Keypair,
+        account: Pubkey,
+        token: Pubkey,
+        is_frozen: bool,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn token_close_account(
+        owner_keypair: &Keypair,
+        account: Pubkey,
+        dest: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+}
+
+impl TokenTransaction for Transaction {
+    /// Create a token, crediting its supply to `account`, which must already be a
+    /// token account for the token
+    fn token_new(
+        token_keypair: &Keypair,
+        account: Pubkey,
+        token_info: TokenInfo,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = TokenInstruction::NewToken(token_info);
+        Transaction::new(
+            token_keypair,
+            &[account],
+            token_program::id(),
+            &instruction,
+            last_id,
+            fee,
+        )
+    }
+
+    /// Initialize a token account, or a delegate account for `delegate_source`
+    fn token_new_account(
+        account_keypair: &Keypair,
+        owner: Pubkey,
+        token: Pubkey,
+        delegate_source: Option<Pubkey>,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let mut keys = vec![owner, token];
+        keys.extend(delegate_source);
+        Transaction::new(
+            account_keypair,
+            &keys,
+            token_program::id(),
+            &TokenInstruction::NewTokenAccount,
+            last_id,
+            fee,
+        )
+    }
+
+    /// Transfer tokens from `source`. If `source` is a delegate account, the account
+    /// it is a delegate for must be given as `delegate_source`
+    fn token_transfer(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        dest: Pubkey,
+        delegate_source: Option<Pubkey>,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let mut keys = vec![source, dest];
+        keys.extend(delegate_source);
+        Transaction::new(
+            owner_keypair,
+            &keys,
+            token_program::id(),
+            &TokenInstruction::Transfer(amount),
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_approve(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        delegate: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            owner_keypair,
+            &[source, delegate],
+            token_program::id(),
+            &TokenInstruction::Approve(amount),
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_set_owner(
+        owner_keypair: &Keypair,
+        account: Pubkey,
+        new_owner: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            owner_keypair,
+            &[account, new_owner],
+            token_program::id(),
+            &TokenInstruction::SetOwner,
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_mint_to(
+        mint_authority_keypair: &Keypair,
+        token: Pubkey,
+        dest: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            mint_authority_keypair,
+            &[token, dest],
+            token_program::id(),
+            &TokenInstruction::MintTo(amount),
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_burn(
+        owner_keypair: &Keypair,
+        source: Pubkey,
+        token: Pubkey,
+        amount: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            owner_keypair,
+            &[source, token],
+            token_program::id(),
+            &TokenInstruction::Burn(amount),
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_set_frozen(
+        freeze_authority_keypair: &Keypair,
+        account: Pubkey,
+        token: Pubkey,
+        is_frozen: bool,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = if is_frozen {
+            TokenInstruction::FreezeAccount
+        } else {
+            TokenInstruction::ThawAccount
+        };
+        Transaction::new(
+            freeze_authority_keypair,
+            &[account, token],
+            token_program::id(),
+            &instruction,
+            last_id,
+            fee,
+        )
+    }
+
+    fn token_close_account(
+        owner_keypair: &Keypair,
+        account: Pubkey,
+        dest: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new(
+            owner_keypair,
+            &[account, dest],
+            token_program::id(),
+            &TokenInstruction::CloseAccount,
+            last_id,
+            fee,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::KeypairUtil;
+    use bincode::deserialize;
+
+    #[test]
+    fn test_token_transfer_keys() {
+        let owner = Keypair::new();
+        let source = Keypair::new().pubkey();
+        let dest = Keypair::new().pubkey();
+        let delegate_source = Keypair::new().pubkey();
+        let tx = Transaction::token_transfer(
+            &owner,
+            source,
+            dest,
+            Some(delegate_source),
+            42,
+            Hash::default(),
+            0,
+        );
+        assert_eq!(
+            tx.account_keys,
+            vec![owner.pubkey(), source, dest, delegate_source]
+        );
+        assert_eq!(tx.instructions[0].accounts, vec![0, 1, 2, 3]);
+        assert_eq!(
+            deserialize::<TokenInstruction>(&tx.userdata(0)).unwrap(),
+            TokenInstruction::Transfer(42)
+        );
+        assert!(tx.verify_signature());
+    }
+}
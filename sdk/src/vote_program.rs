@@ -1,9 +1,11 @@
 //! Vote program
 //! Receive and processes votes from validators
 
+use crate::hash::Hash;
 use crate::native_program::ProgramError;
 use crate::pubkey::Pubkey;
 use bincode::{deserialize, serialize_into, serialized_size, ErrorKind};
+use log::*;
 use std::collections::VecDeque;
 
 pub const VOTE_PROGRAM_ID: [u8; 32] = [
@@ -19,19 +21,56 @@ pub fn id() -> Pubkey {
     Pubkey::new(&VOTE_PROGRAM_ID)
 }
 
-// Maximum number of votes to keep around
-pub const MAX_VOTE_HISTORY: usize = 32;
+// Maximum number of votes to keep around; the oldest vote is dequeued into the root once
+// this many votes are stacked above it
+pub const MAX_LOCKOUT_HISTORY: usize = 32;
+
+// Lockout of a vote with a single confirmation, doubled by each later confirmation
+pub const INITIAL_LOCKOUT: usize = 2;
 
 #[derive(Serialize, Default, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct Vote {
-    // TODO: add signature of the state here as well
-    /// A vote for height tick_height
-    pub tick_height: u64,
+    /// The slot being voted on
+    pub slot: u64,
+
+    /// The bank hash at `slot`, identifying the fork being voted for. The vote program
+    /// doesn't check it against the bank yet
+    pub hash: Hash,
 }
 
 impl Vote {
-    pub fn new(tick_height: u64) -> Self {
-        Self { tick_height }
+    pub fn new(slot: u64, hash: Hash) -> Self {
+        Self { slot, hash }
+    }
+}
+
+#[derive(Serialize, Default, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Lockout {
+    pub slot: u64,
+    pub confirmation_count: u32,
+}
+
+impl Lockout {
+    pub fn new(vote: &Vote) -> Self {
+        Self {
+            slot: vote.slot,
+            confirmation_count: 1,
+        }
+    }
+
+    // The number of slots for which this vote is locked
+    pub fn lockout(&self) -> u64 {
+        (INITIAL_LOCKOUT as u64).pow(self.confirmation_count)
+    }
+
+    // The last slot at which this vote is still locked out. Votes for slots past this one
+    // may switch forks away from it
+    pub fn expiration_slot(&self) -> u64 {
+        self.slot + self.lockout()
+    }
+
+    pub fn is_expired(&self, slot: u64) -> bool {
+        self.expiration_slot() < slot
     }
 }
 
@@ -41,29 +80,121 @@ pub enum VoteInstruction {
     /// and initialize the VoteState for this "vote account"
     /// * Transaction::keys[0] - the validator id
     /// * Transaction::keys[1] - the new "vote account" to be associated with the validator
-    /// identified by keys[0] for voting
+    /// identified by keys[0] for voting. It signs its own votes until another voter is
+    /// authorized
     RegisterAccount,
+
+    /// Vote for a slot, which must be greater than the slot of every previous vote
+    /// * Transaction::keys[0] - the "vote account"
+    /// * Transaction::keys[1] - the authorized voter, signed, if it isn't the "vote account"
     NewVote(Vote),
+
+    /// Authorize a new key to sign the votes of a "vote account"
+    /// * Transaction::keys[0] - the "vote account"
+    /// * Transaction::keys[1] - the validator id the "vote account" is registered to, signed
+    AuthorizeVoter(Pubkey),
+
+    /// Move tokens out of a "vote account"
+    /// * Transaction::keys[0] - the "vote account"
+    /// * Transaction::keys[1] - the authorized withdrawer, signed
+    /// * Transaction::keys[2] - the account to receive the tokens
+    Withdraw(u64),
+
+    /// Hand the right to withdraw from a "vote account" to a new key, so that it need not be
+    /// the validator's own, online, identity
+    /// * Transaction::keys[0] - the "vote account"
+    /// * Transaction::keys[1] - the current authorized withdrawer, signed
+    AuthorizeWithdrawer(Pubkey),
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct VoteProgram {
-    pub votes: VecDeque<Vote>,
+    /// Stack of the votes still locked out, oldest first
+    pub votes: VecDeque<Lockout>,
     pub node_id: Pubkey,
+
+    /// The key that signs votes. Only `node_id` may change it
+    pub authorized_voter_id: Pubkey,
+
+    /// The key that may withdraw tokens. Starts out as `node_id`; only the current withdrawer may
+    /// change it
+    pub authorized_withdrawer_id: Pubkey,
+
+    /// The most recent vote to reach max lockout. Once rooted, a slot is never rolled back
+    pub root_slot: Option<u64>,
 }
 
 pub fn get_max_size() -> usize {
     // Upper limit on the size of the Vote State. Equal to
-    // sizeof(VoteProgram) when votes.len() is MAX_VOTE_HISTORY
+    // sizeof(VoteProgram) when votes.len() is MAX_LOCKOUT_HISTORY
     let mut vote_program = VoteProgram::default();
-    vote_program.votes = VecDeque::from(vec![Vote::default(); MAX_VOTE_HISTORY]);
+    vote_program.votes = VecDeque::from(vec![Lockout::default(); MAX_LOCKOUT_HISTORY]);
+    vote_program.root_slot = Some(0);
     serialized_size(&vote_program).unwrap() as usize
 }
 
 impl VoteProgram {
-    pub fn new(node_id: Pubkey) -> Self {
+    pub fn new(node_id: Pubkey, authorized_voter_id: Pubkey) -> Self {
         let votes = VecDeque::new();
-        Self { votes, node_id }
+        let root_slot = None;
+        Self {
+            votes,
+            node_id,
+            authorized_voter_id,
+            authorized_withdrawer_id: node_id,
+            root_slot,
+        }
+    }
+
+    /// Push `vote` onto the vote stack, following the lockout rules in
+    /// book/src/fork-selection.md: votes that expired by `vote.slot` are popped, the
+    /// lockouts of the remaining votes double, and the oldest vote becomes the root once
+    /// the stack is full
+    pub fn process_vote(&mut self, vote: &Vote) -> Result<(), ProgramError> {
+        let last_slot = self
+            .votes
+            .back()
+            .map(|lockout| lockout.slot)
+            .or(self.root_slot);
+        if last_slot.map_or(false, |last_slot| vote.slot <= last_slot) {
+            warn!(
+                "vote for slot {} is not newer than slot {:?}",
+                vote.slot, last_slot
+            );
+            Err(ProgramError::InvalidArgument)?;
+        }
+
+        self.pop_expired_votes(vote.slot);
+
+        // Once the stack is full, the oldest vote is at max lockout
+        if self.votes.len() == MAX_LOCKOUT_HISTORY {
+            let lockout = self.votes.pop_front().unwrap();
+            self.root_slot = Some(lockout.slot);
+        }
+
+        self.votes.push_back(Lockout::new(vote));
+        self.double_lockouts();
+        Ok(())
+    }
+
+    fn pop_expired_votes(&mut self, slot: u64) {
+        while let Some(lockout) = self.votes.back() {
+            if !lockout.is_expired(slot) {
+                break;
+            }
+            self.votes.pop_back();
+        }
+    }
+
+    fn double_lockouts(&mut self) {
+        let stack_depth = self.votes.len();
+        for (i, lockout) in self.votes.iter_mut().enumerate() {
+            // After a rollback, don't double a vote's lockout until the stack has grown back
+            // past the height at which it was last doubled
+            if stack_depth > i + lockout.confirmation_count as usize {
+                lockout.confirmation_count += 1;
+            }
+        }
     }
 
     pub fn deserialize(input: &[u8]) -> Result<VoteProgram, ProgramError> {
@@ -82,12 +213,93 @@ impl VoteProgram {
 mod tests {
     use super::*;
 
+    fn vote(slot: u64) -> Vote {
+        Vote::new(slot, Hash::default())
+    }
+
     #[test]
     fn test_serde() {
         let mut buffer: Vec<u8> = vec![0; get_max_size()];
         let mut vote_program = VoteProgram::default();
-        vote_program.votes = (0..MAX_VOTE_HISTORY).map(|_| Vote::default()).collect();
+        vote_program.votes = (0..MAX_LOCKOUT_HISTORY)
+            .map(|_| Lockout::default())
+            .collect();
+        vote_program.root_slot = Some(1);
         vote_program.serialize(&mut buffer).unwrap();
         assert_eq!(VoteProgram::deserialize(&buffer).unwrap(), vote_program);
     }
+
+    #[test]
+    fn test_vote_lockouts_double() {
+        let mut vote_program = VoteProgram::default();
+        for slot in 0..4 {
+            vote_program.process_vote(&vote(slot)).unwrap();
+        }
+        let lockouts: Vec<_> = vote_program.votes.iter().map(Lockout::lockout).collect();
+        assert_eq!(lockouts, vec![16, 8, 4, 2]);
+        assert_eq!(vote_program.root_slot, None);
+    }
+
+    #[test]
+    fn test_vote_not_newer() {
+        let mut vote_program = VoteProgram::default();
+        vote_program.process_vote(&vote(2)).unwrap();
+        assert_eq!(
+            vote_program.process_vote(&vote(2)),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(
+            vote_program.process_vote(&vote(1)),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(vote_program.votes.len(), 1);
+    }
+
+    #[test]
+    fn test_vote_pops_expired() {
+        let mut vote_program = VoteProgram::default();
+        for slot in 0..4 {
+            vote_program.process_vote(&vote(slot)).unwrap();
+        }
+
+        // Slot 3 is locked until slot 5 and slot 2 until slot 6, but slot 1 holds until 9
+        vote_program.process_vote(&vote(7)).unwrap();
+        let slots: Vec<_> = vote_program.votes.iter().map(|v| v.slot).collect();
+        assert_eq!(slots, vec![0, 1, 7]);
+
+        // The surviving votes don't double again until the stack grows back past them
+        let confirmations: Vec<_> = vote_program
+            .votes
+            .iter()
+            .map(|v| v.confirmation_count)
+            .collect();
+        assert_eq!(confirmations, vec![4, 3, 1]);
+    }
+
+    #[test]
+    fn test_vote_root() {
+        let mut vote_program = VoteProgram::default();
+        for slot in 0..MAX_LOCKOUT_HISTORY as u64 {
+            vote_program.process_vote(&vote(slot)).unwrap();
+        }
+        assert_eq!(vote_program.root_slot, None);
+        assert_eq!(
+            vote_program.votes[0].confirmation_count,
+            MAX_LOCKOUT_HISTORY as u32
+        );
+
+        vote_program
+            .process_vote(&vote(MAX_LOCKOUT_HISTORY as u64))
+            .unwrap();
+        assert_eq!(vote_program.root_slot, Some(0));
+        assert_eq!(vote_program.votes.len(), MAX_LOCKOUT_HISTORY);
+        assert_eq!(vote_program.votes[0].slot, 1);
+
+        // Votes can't go back past the root, even once the stack has expired
+        vote_program.votes.clear();
+        assert_eq!(
+            vote_program.process_vote(&vote(0)),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
 }
@@ -12,7 +12,16 @@ use bincode::deserialize;
 pub trait VoteTransaction {
     fn vote_new<T: KeypairUtil>(
         vote_account: &T,
-        tick_height: u64,
+        slot: u64,
+        hash: Hash,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+    fn vote_new_with_voter<T: KeypairUtil>(
+        authorized_voter: &T,
+        vote_account_id: Pubkey,
+        slot: u64,
+        hash: Hash,
         last_id: Hash,
         fee: u64,
     ) -> Self;
@@ -24,17 +33,44 @@ pub trait VoteTransaction {
         fee: u64,
     ) -> Self;
 
+    fn vote_authorize_voter(
+        validator_id: &Keypair,
+        vote_account_id: Pubkey,
+        authorized_voter_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn vote_authorize_withdrawer(
+        authorized_withdrawer: &Keypair,
+        vote_account_id: Pubkey,
+        authorized_withdrawer_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
+    fn vote_withdraw(
+        authorized_withdrawer: &Keypair,
+        vote_account_id: Pubkey,
+        to: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self;
+
     fn get_votes(&self) -> Vec<(Pubkey, Vote, Hash)>;
 }
 
 impl VoteTransaction for Transaction {
+    /// Vote for `slot` on the fork whose bank hash at that slot is `hash`
     fn vote_new<T: KeypairUtil>(
         vote_account: &T,
-        tick_height: u64,
+        slot: u64,
+        hash: Hash,
         last_id: Hash,
         fee: u64,
     ) -> Self {
-        let vote = Vote { tick_height };
+        let vote = Vote::new(slot, hash);
         let instruction = VoteInstruction::NewVote(vote);
         Transaction::new(
             vote_account,
@@ -46,6 +82,27 @@ impl VoteTransaction for Transaction {
         )
     }
 
+    /// Vote for `slot` with a key authorized to vote on behalf of `vote_account_id`
+    fn vote_new_with_voter<T: KeypairUtil>(
+        authorized_voter: &T,
+        vote_account_id: Pubkey,
+        slot: u64,
+        hash: Hash,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let vote = Vote::new(slot, hash);
+        let instruction = VoteInstruction::NewVote(vote);
+        Transaction::new_with_instructions(
+            &[authorized_voter],
+            &[vote_account_id],
+            last_id,
+            fee,
+            vec![vote_program::id()],
+            vec![Instruction::new(0, &instruction, vec![1, 0])],
+        )
+    }
+
     fn vote_account_new(
         validator_id: &Keypair,
         vote_account_id: Pubkey,
@@ -74,16 +131,109 @@ impl VoteTransaction for Transaction {
         )
     }
 
+    fn vote_authorize_voter(
+        validator_id: &Keypair,
+        vote_account_id: Pubkey,
+        authorized_voter_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = VoteInstruction::AuthorizeVoter(authorized_voter_id);
+        Transaction::new_with_instructions(
+            &[validator_id],
+            &[vote_account_id],
+            last_id,
+            fee,
+            vec![vote_program::id()],
+            vec![Instruction::new(0, &instruction, vec![1, 0])],
+        )
+    }
+
+    fn vote_authorize_withdrawer(
+        authorized_withdrawer: &Keypair,
+        vote_account_id: Pubkey,
+        authorized_withdrawer_id: Pubkey,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        let instruction = VoteInstruction::AuthorizeWithdrawer(authorized_withdrawer_id);
+        Transaction::new_with_instructions(
+            &[authorized_withdrawer],
+            &[vote_account_id],
+            last_id,
+            fee,
+            vec![vote_program::id()],
+            vec![Instruction::new(0, &instruction, vec![1, 0])],
+        )
+    }
+
+    fn vote_withdraw(
+        authorized_withdrawer: &Keypair,
+        vote_account_id: Pubkey,
+        to: Pubkey,
+        tokens: u64,
+        last_id: Hash,
+        fee: u64,
+    ) -> Self {
+        Transaction::new_with_instructions(
+            &[authorized_withdrawer],
+            &[vote_account_id, to],
+            last_id,
+            fee,
+            vec![vote_program::id()],
+            vec![Instruction::new(
+                0,
+                &VoteInstruction::Withdraw(tokens),
+                vec![1, 0, 2],
+            )],
+        )
+    }
+
+    /// Returns the "vote account" id, vote and last id of every vote in the transaction
     fn get_votes(&self) -> Vec<(Pubkey, Vote, Hash)> {
         let mut votes = vec![];
         for i in 0..self.instructions.len() {
             let tx_program_id = self.program_id(i);
             if vote_program::check_id(&tx_program_id) {
-                if let Ok(Some(VoteInstruction::NewVote(vote))) = deserialize(&self.userdata(i)) {
-                    votes.push((self.account_keys[0], vote, self.last_id))
+                if let Ok(VoteInstruction::NewVote(vote)) = deserialize(&self.userdata(i)) {
+                    if let Some(vote_account_id) = self.key(i, 0) {
+                        votes.push((*vote_account_id, vote, self.last_id))
+                    }
                 }
             }
         }
         votes
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_votes() {
+        let vote_account = Keypair::new();
+        let bank_hash = Hash::new(&[1; 32]);
+        let tx = Transaction::vote_new(&vote_account, 1, bank_hash, Hash::default(), 0);
+        let vote = Vote::new(1, bank_hash);
+        assert_eq!(
+            tx.get_votes(),
+            vec![(vote_account.pubkey(), vote, Hash::default())]
+        );
+
+        let authorized_voter = Keypair::new();
+        let tx = Transaction::vote_new_with_voter(
+            &authorized_voter,
+            vote_account.pubkey(),
+            2,
+            bank_hash,
+            Hash::default(),
+            0,
+        );
+        let vote = Vote::new(2, bank_hash);
+        assert_eq!(
+            tx.get_votes(),
+            vec![(vote_account.pubkey(), vote, Hash::default())]
+        );
+    }
+}
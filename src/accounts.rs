@@ -5,8 +5,10 @@ use bincode::serialize;
 use hashbrown::{HashMap, HashSet};
 use log::Level;
 use solana_sdk::account::Account;
+use solana_sdk::bpf_loader;
 use solana_sdk::hash::{hash, Hash};
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::stake_program;
 use solana_sdk::transaction::Transaction;
 use std::collections::BTreeMap;
 use std::ops::Deref;
@@ -34,23 +36,45 @@ pub struct AccountsDB {
     /// Mapping of known public keys/IDs to accounts
     pub accounts: HashMap<Pubkey, Account>,
 
+    /// Keys of the accounts owned by the stake program, so that stakes can be found
+    /// without scanning every account
+    stake_accounts: HashSet<Pubkey>,
+
     /// The number of transactions the bank has processed without error since the
     /// start of the ledger.
     transaction_count: u64,
 }
 
+/// The accounts of the transactions which are currently in the pipeline
+#[derive(Default)]
+struct AccountLocks {
+    /// accounts the transactions may modify, each locked by one transaction at a time
+    accounts: HashSet<Pubkey>,
+
+    /// programs the transactions invoke, with the number of transactions invoking each.
+    /// A program can't be modified, e.g. upgraded, while it's being invoked
+    programs: HashMap<Pubkey, usize>,
+}
+
+impl AccountLocks {
+    fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.programs.is_empty()
+    }
+}
+
 /// This structure handles synchronization for db
 pub struct Accounts {
     pub accounts_db: RwLock<AccountsDB>,
 
-    /// set of accounts which are currently in the pipeline
-    account_locks: Mutex<HashSet<Pubkey>>,
+    /// accounts which are currently in the pipeline
+    account_locks: Mutex<AccountLocks>,
 }
 
 impl Default for AccountsDB {
     fn default() -> Self {
         Self {
             accounts: HashMap::new(),
+            stake_accounts: HashSet::new(),
             transaction_count: 0,
         }
     }
@@ -59,7 +83,7 @@ impl Default for AccountsDB {
 impl Default for Accounts {
     fn default() -> Self {
         Self {
-            account_locks: Mutex::new(HashSet::new()),
+            account_locks: Mutex::new(AccountLocks::default()),
             accounts_db: RwLock::new(AccountsDB::default()),
         }
     }
@@ -103,6 +127,22 @@ impl AccountsDB {
         } else {
             self.accounts.insert(pubkey.clone(), account.clone());
         }
+        self.index_stake_account(pubkey, account);
+    }
+
+    fn index_stake_account(&mut self, pubkey: &Pubkey, account: &Account) {
+        if account.tokens > 0 && stake_program::check_id(&account.owner) {
+            self.stake_accounts.insert(*pubkey);
+        } else {
+            self.stake_accounts.remove(pubkey);
+        }
+    }
+
+    /// Return the accounts owned by the stake program
+    pub fn stake_accounts(&self) -> impl Iterator<Item = (&Pubkey, &Account)> {
+        self.stake_accounts
+            .iter()
+            .filter_map(move |pubkey| self.accounts.get(pubkey).map(|account| (pubkey, account)))
     }
 
     pub fn store_accounts(
@@ -164,6 +204,7 @@ impl AccountsDB {
         U: Deref<Target = Self>,
     {
         let mut accounts = Vec::new();
+        let mut programdata = None;
         let mut depth = 0;
         loop {
             if solana_native_loader::check_id(&program_id) {
@@ -189,11 +230,27 @@ impl AccountsDB {
                 return Err(BankError::AccountNotFound);
             }
 
+            // An upgradeable program's code is in its program-data account, which the loader
+            // is passed right after the program. Any transaction that modifies the program data
+            // also locks the program, so the code can't change while the program is invoked
+            if depth == 1 {
+                if let Some(programdata_address) = bpf_loader::get_programdata_address(&program) {
+                    match Self::load(checkpoints, &programdata_address) {
+                        Some(account) => programdata = Some((programdata_address, account)),
+                        None => {
+                            error_counters.account_not_found += 1;
+                            return Err(BankError::AccountNotFound);
+                        }
+                    }
+                }
+            }
+
             // add loader to chain
             accounts.insert(0, (program_id, program.clone()));
 
             program_id = program.loader;
         }
+        accounts.extend(programdata);
         Ok(accounts)
     }
 
@@ -253,6 +310,9 @@ impl AccountsDB {
     }
     fn merge(&mut self, other: Self) {
         self.transaction_count += other.transaction_count;
+        for (pubkey, account) in &other.accounts {
+            self.index_stake_account(pubkey, account);
+        }
         self.accounts.extend(other.accounts)
     }
 }
@@ -280,29 +340,46 @@ impl Accounts {
     }
 
     fn lock_account(
-        account_locks: &mut HashSet<Pubkey>,
-        keys: &[Pubkey],
+        account_locks: &mut AccountLocks,
+        tx: &Transaction,
         error_counters: &mut ErrorCounters,
     ) -> Result<()> {
         // Copy all the accounts
-        for k in keys {
-            if account_locks.contains(k) {
+        for k in &tx.account_keys {
+            if account_locks.accounts.contains(k) || account_locks.programs.contains_key(k) {
+                error_counters.account_in_use += 1;
+                return Err(BankError::AccountInUse);
+            }
+        }
+        // Any number of transactions may invoke a program, but not while another modifies it
+        for k in &tx.program_ids {
+            if account_locks.accounts.contains(k) {
                 error_counters.account_in_use += 1;
                 return Err(BankError::AccountInUse);
             }
         }
-        for k in keys {
-            account_locks.insert(*k);
+        for k in &tx.account_keys {
+            account_locks.accounts.insert(*k);
+        }
+        for k in &tx.program_ids {
+            *account_locks.programs.entry(*k).or_insert(0) += 1;
         }
         Ok(())
     }
 
-    fn unlock_account(tx: &Transaction, result: &Result<()>, account_locks: &mut HashSet<Pubkey>) {
+    fn unlock_account(tx: &Transaction, result: &Result<()>, account_locks: &mut AccountLocks) {
         match result {
             Err(BankError::AccountInUse) => (),
             _ => {
                 for k in &tx.account_keys {
-                    account_locks.remove(k);
+                    account_locks.accounts.remove(k);
+                }
+                for k in &tx.program_ids {
+                    let count = account_locks.programs.get_mut(k).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        account_locks.programs.remove(k);
+                    }
                 }
             }
         }
@@ -320,7 +397,7 @@ impl Accounts {
         let mut error_counters = ErrorCounters::default();
         let rv = txs
             .iter()
-            .map(|tx| Self::lock_account(&mut account_locks, &tx.account_keys, &mut error_counters))
+            .map(|tx| Self::lock_account(&mut account_locks, tx, &mut error_counters))
             .collect();
         if error_counters.account_in_use != 0 {
             inc_new_counter_info!(
@@ -396,10 +473,12 @@ impl Accounts {
 
         {
             let mut accounts_db = copy.accounts_db.write().unwrap();
-            for (key, val) in self.accounts_db.read().unwrap().accounts.iter() {
+            let self_db = self.accounts_db.read().unwrap();
+            for (key, val) in self_db.accounts.iter() {
                 accounts_db.accounts.insert(key.clone(), val.clone());
             }
-            accounts_db.transaction_count = self.transaction_count();
+            accounts_db.stake_accounts = self_db.stake_accounts.clone();
+            accounts_db.transaction_count = self_db.transaction_count();
         }
         copy
     }
@@ -417,6 +496,26 @@ mod tests {
     use solana_sdk::transaction::Instruction;
     use solana_sdk::transaction::Transaction;
 
+    #[test]
+    fn test_stake_accounts() {
+        let mut db = AccountsDB::default();
+        let stake_key = Keypair::new().pubkey();
+        let other_key = Keypair::new().pubkey();
+        let stake_account = Account::new(1, 0, stake_program::id());
+        db.store(true, &stake_key, &stake_account);
+        db.store(true, &other_key, &Account::new(1, 0, Pubkey::default()));
+        assert_eq!(
+            db.stake_accounts().collect::<Vec<_>>(),
+            vec![(&stake_key, &stake_account)]
+        );
+
+        // Accounts drop out of the index once they're emptied
+        let mut overlay = AccountsDB::default();
+        overlay.store(false, &stake_key, &Account::new(0, 0, stake_program::id()));
+        db.merge(overlay);
+        assert_eq!(db.stake_accounts().count(), 0);
+    }
+
     #[test]
     fn test_purge() {
         let mut db = AccountsDB::default();
@@ -794,4 +893,109 @@ mod tests {
             Err(e) => Err(e).unwrap(),
         }
     }
+
+    #[test]
+    fn test_load_accounts_upgradeable_program() {
+        let mut accounts: Vec<(Pubkey, Account)> = Vec::new();
+        let mut error_counters = ErrorCounters::default();
+
+        let keypair = Keypair::new();
+        let key0 = keypair.pubkey();
+        let program_id = Pubkey::new(&[5u8; 32]);
+        let programdata_id = Pubkey::new(&[6u8; 32]);
+
+        let account = Account::new(1, 1, Pubkey::default());
+        accounts.push((key0, account));
+
+        let mut account = Account::new(40, 1, Pubkey::default());
+        account.executable = true;
+        account.loader = solana_native_loader::id();
+        accounts.push((bpf_loader::id(), account));
+
+        let mut account = Account::new(41, 0, bpf_loader::id());
+        account.userdata = serialize(&bpf_loader::UpgradeableLoaderState::Program {
+            programdata_address: programdata_id,
+        })
+        .unwrap();
+        account.executable = true;
+        account.loader = bpf_loader::id();
+        accounts.push((program_id, account));
+
+        let tx = Transaction::new_with_instructions(
+            &[&keypair],
+            &[],
+            Hash::default(),
+            0,
+            vec![program_id],
+            vec![Instruction::new(0, &(), vec![0])],
+        );
+
+        // The program can't run without its code
+        let loaded_accounts = load_accounts(tx.clone(), &accounts, &mut error_counters);
+        assert_counters(&error_counters, [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(loaded_accounts.len(), 1);
+        assert_eq!(loaded_accounts[0], Err(BankError::AccountNotFound));
+
+        let account = Account::new(42, 1, bpf_loader::id());
+        accounts.push((programdata_id, account));
+
+        let mut error_counters = ErrorCounters::default();
+        let loaded_accounts = load_accounts(tx, &accounts, &mut error_counters);
+        assert_counters(&error_counters, [0, 0, 0, 0, 0, 0, 0, 0]);
+        match &loaded_accounts[0] {
+            Ok((_, l)) => {
+                assert_eq!(l.len(), 1);
+                let keys: Vec<_> = l[0].iter().map(|(key, _)| *key).collect();
+                assert_eq!(keys, vec![bpf_loader::id(), program_id, programdata_id]);
+            }
+            Err(e) => Err(e).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_lock_accounts_invoked_program() {
+        let accounts = Accounts::default();
+        let program_id = Keypair::new().pubkey();
+        let invoke = |keypair: &Keypair| {
+            Transaction::new_with_instructions(
+                &[keypair],
+                &[],
+                Hash::default(),
+                0,
+                vec![program_id],
+                vec![Instruction::new(0, &(), vec![0])],
+            )
+        };
+        let modify = Transaction::new_with_instructions(
+            &[&Keypair::new()],
+            &[program_id],
+            Hash::default(),
+            0,
+            vec![Pubkey::default()],
+            vec![Instruction::new(0, &(), vec![0, 1])],
+        );
+
+        // Any number of transactions may invoke the program, but none may modify it meanwhile
+        let txs = [invoke(&Keypair::new()), invoke(&Keypair::new())];
+        assert_eq!(accounts.lock_accounts(&txs), vec![Ok(()), Ok(())]);
+        assert_eq!(
+            accounts.lock_accounts(&[modify.clone()]),
+            vec![Err(BankError::AccountInUse)]
+        );
+        accounts.unlock_accounts(&txs[..1], &[Ok(())]);
+        assert_eq!(
+            accounts.lock_accounts(&[modify.clone()]),
+            vec![Err(BankError::AccountInUse)]
+        );
+        accounts.unlock_accounts(&txs[1..], &[Ok(())]);
+
+        // Nor may the program be invoked while it's modified
+        assert_eq!(accounts.lock_accounts(&[modify.clone()]), vec![Ok(())]);
+        assert_eq!(
+            accounts.lock_accounts(&[invoke(&Keypair::new())]),
+            vec![Err(BankError::AccountInUse)]
+        );
+        accounts.unlock_accounts(&[modify], &[Ok(())]);
+        assert!(accounts.account_locks.lock().unwrap().is_empty());
+    }
 }
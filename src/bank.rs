@@ -9,8 +9,10 @@ use crate::entry::Entry;
 use crate::entry::EntrySlice;
 use crate::genesis_block::GenesisBlock;
 use crate::leader_scheduler::LeaderScheduler;
+use crate::packet::to_packets;
 use crate::poh_recorder::PohRecorder;
 use crate::runtime::{self, RuntimeError};
+use crate::sigverify;
 use crate::status_deque::{Status, StatusDeque, StatusDequeError, MAX_ENTRY_IDS};
 use bincode::deserialize;
 use itertools::Itertools;
@@ -21,14 +23,17 @@ use solana_sdk::account::Account;
 use solana_sdk::bpf_loader;
 use solana_sdk::budget_program;
 use solana_sdk::hash::Hash;
+use solana_sdk::native_loader::NativeProgram;
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signature::Signature;
+use solana_sdk::stake_program;
 use solana_sdk::storage_program;
-use solana_sdk::system_program;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program::{self, NonceState};
 use solana_sdk::system_transaction::SystemTransaction;
-use solana_sdk::timing::duration_as_us;
+use solana_sdk::timing::{duration_as_ms, duration_as_us};
 use solana_sdk::token_program;
 use solana_sdk::transaction::Transaction;
 use solana_sdk::vote_program;
@@ -73,11 +78,21 @@ pub enum BankError {
 
     /// Transaction has a fee but has no signature present
     MissingSignatureForFee,
+
+    /// A transaction in the ledger failed signature verification
+    SignatureFailure,
+
+    /// A builtin program pinned by the genesis block doesn't match the installed library
+    BuiltinProgramMismatch,
 }
 
 pub type Result<T> = result::Result<T, BankError>;
 
-pub const VERIFY_BLOCK_SIZE: usize = 16;
+/// Number of entries `process_ledger` verifies ahead of the ones it is executing
+pub const VERIFY_BLOCK_SIZE: usize = 1024;
+
+/// How often `process_ledger` logs its progress
+const LEDGER_PROGRESS_INTERVAL_MS: u64 = 2000;
 
 pub trait BankSubscriptions {
     fn check_account(&self, pubkey: &Pubkey, account: &Account);
@@ -125,12 +140,24 @@ impl Default for Bank {
     }
 }
 
+/// The library names and ids of the builtin programs the native loader runs
+pub fn builtin_native_programs() -> Vec<(&'static str, Pubkey)> {
+    vec![
+        ("solana_vote_program", vote_program::id()),
+        ("solana_stake_program", stake_program::id()),
+        ("solana_storage_program", storage_program::id()),
+        ("solana_bpf_loader", bpf_loader::id()),
+        ("solana_budget_program", budget_program::id()),
+        ("solana_erc20", token_program::id()),
+    ]
+}
+
 impl Bank {
-    pub fn new(genesis_block: &GenesisBlock) -> Self {
+    pub fn new(genesis_block: &GenesisBlock) -> Result<Self> {
         let bank = Self::default();
         bank.process_genesis_block(genesis_block);
-        bank.add_builtin_programs();
-        bank
+        bank.add_builtin_programs(genesis_block)?;
+        Ok(bank)
     }
     pub fn set_subscriptions(&self, subscriptions: Box<Arc<BankSubscriptions + Send + Sync>>) {
         let mut sub = self.subscriptions.write().unwrap();
@@ -182,75 +209,50 @@ impl Bank {
             .store_slow(true, &system_program::id(), &system_program_account);
     }
 
-    fn add_builtin_programs(&self) {
+    /// Store the builtin programs. Fails if a shared library pinned by `genesis_block` doesn't
+    /// match its code hash, so that a validator built from different code refuses to start
+    fn add_builtin_programs(&self, genesis_block: &GenesisBlock) -> Result<()> {
         self.add_system_program();
 
-        // Vote program
-        let vote_program_account = Account {
-            tokens: 1,
-            owner: vote_program::id(),
-            userdata: b"solana_vote_program".to_vec(),
-            executable: true,
-            loader: solana_native_loader::id(),
-        };
-        self.accounts
-            .store_slow(true, &vote_program::id(), &vote_program_account);
-
-        // Storage program
-        let storage_program_account = Account {
-            tokens: 1,
-            owner: storage_program::id(),
-            userdata: b"solana_storage_program".to_vec(),
-            executable: true,
-            loader: solana_native_loader::id(),
-        };
-        self.accounts
-            .store_slow(true, &storage_program::id(), &storage_program_account);
+        for (name, program_id) in builtin_native_programs() {
+            let program = match genesis_block
+                .native_programs
+                .iter()
+                .find(|program| program.name == name)
+            {
+                Some(program) => {
+                    if solana_native_loader::check_program(program).is_err() {
+                        warn!(
+                            "{} {} doesn't match the code hash pinned by the genesis block",
+                            program.name, program.version
+                        );
+                        Err(BankError::BuiltinProgramMismatch)?;
+                    }
+                    program.clone()
+                }
+                None => NativeProgram::new(name, env!("CARGO_PKG_VERSION"), None),
+            };
+            let account = Account {
+                tokens: 1,
+                owner: program_id,
+                userdata: program.serialize(),
+                executable: true,
+                loader: solana_native_loader::id(),
+            };
+            self.accounts.store_slow(true, &program_id, &account);
+        }
 
+        // Holds the storage state, and its tokens are the pool storage rewards are paid from
         let storage_system_account = Account {
             tokens: 1,
-            owner: storage_program::system_id(),
+            owner: storage_program::id(),
             userdata: vec![0; 16 * 1024],
             executable: false,
             loader: Pubkey::default(),
         };
         self.accounts
             .store_slow(true, &storage_program::system_id(), &storage_system_account);
-
-        // Bpf Loader
-        let bpf_loader_account = Account {
-            tokens: 1,
-            owner: bpf_loader::id(),
-            userdata: b"solana_bpf_loader".to_vec(),
-            executable: true,
-            loader: solana_native_loader::id(),
-        };
-
-        self.accounts
-            .store_slow(true, &bpf_loader::id(), &bpf_loader_account);
-
-        // Budget program
-        let budget_program_account = Account {
-            tokens: 1,
-            owner: budget_program::id(),
-            userdata: b"solana_budget_program".to_vec(),
-            executable: true,
-            loader: solana_native_loader::id(),
-        };
-        self.accounts
-            .store_slow(true, &budget_program::id(), &budget_program_account);
-
-        // Erc20 token program
-        let erc20_account = Account {
-            tokens: 1,
-            owner: token_program::id(),
-            userdata: b"solana_erc20".to_vec(),
-            executable: true,
-            loader: solana_native_loader::id(),
-        };
-
-        self.accounts
-            .store_slow(true, &token_program::id(), &erc20_account);
+        Ok(())
     }
 
     /// Return the last entry ID registered.
@@ -294,10 +296,19 @@ impl Bank {
         self.last_ids.write().unwrap().clear_signatures();
     }
 
-    fn update_transaction_statuses(&self, txs: &[Transaction], res: &[Result<()>]) {
+    fn update_transaction_statuses(
+        &self,
+        txs: &[Transaction],
+        reserved_last_ids: &[Hash],
+        res: &[Result<()>],
+    ) {
         let mut last_ids = self.last_ids.write().unwrap();
         for (i, tx) in txs.iter().enumerate() {
-            last_ids.update_signature_status_with_last_id(&tx.signatures[0], &res[i], &tx.last_id);
+            last_ids.update_signature_status_with_last_id(
+                &tx.signatures[0],
+                &res[i],
+                &reserved_last_ids[i],
+            );
             self.subscriptions
                 .read()
                 .unwrap()
@@ -362,7 +373,7 @@ impl Bank {
         // the likelihood of any single thread getting starved and processing old ids.
         // TODO: Banking stage threads should be prioritized to complete faster then this queue
         // expires.
-        let (loaded_accounts, results) =
+        let (loaded_accounts, results, reserved_last_ids) =
             self.load_and_execute_transactions(txs, lock_results, MAX_ENTRY_IDS as usize / 2);
         let load_execute_time = now.elapsed();
 
@@ -374,7 +385,7 @@ impl Bank {
 
         let commit_time = {
             let now = Instant::now();
-            self.commit_transactions(txs, &loaded_accounts, &results);
+            self.commit_transactions(txs, &reserved_last_ids, &loaded_accounts, &results);
             now.elapsed()
         };
 
@@ -436,24 +447,48 @@ impl Bank {
     ) -> Vec<Result<(InstructionAccounts, InstructionLoaders)>> {
         Accounts::load_accounts(&[&self.accounts], txs, results, error_counters)
     }
+    /// A transaction whose `last_id` is too old is accepted in its place if its first
+    /// instruction advances a nonce account, signed for, storing that `last_id`
+    fn check_nonce(&self, tx: &Transaction) -> bool {
+        if tx.instructions.is_empty() || !system_program::check_id(tx.program_id(0)) {
+            return false;
+        }
+        match deserialize(tx.userdata(0)) {
+            Ok(SystemInstruction::AdvanceNonce) => (),
+            _ => return false,
+        }
+        tx.signer_key(0, 0)
+            .and_then(|nonce_id| self.get_account(nonce_id))
+            .filter(|account| system_program::check_id(&account.owner))
+            .and_then(|account| NonceState::deserialize(&account.userdata).ok())
+            == Some(NonceState::Initialized { nonce: tx.last_id })
+    }
+
+    /// Reserve each transaction's signature, returning the results along with the `last_id`
+    /// each signature was reserved under. That is the transaction's own `last_id` unless a
+    /// nonce stood in for it, in which case it's the latest `last_id`
     fn check_signatures(
         &self,
         txs: &[Transaction],
         lock_results: Vec<Result<()>>,
         max_age: usize,
         error_counters: &mut ErrorCounters,
-    ) -> Vec<Result<()>> {
+    ) -> (Vec<Result<()>>, Vec<Hash>) {
         let mut last_ids = self.last_ids.write().unwrap();
         txs.iter()
             .zip(lock_results.into_iter())
             .map(|(tx, lock_res)| {
+                let mut last_id = tx.last_id;
                 if lock_res.is_ok() {
-                    let r = if !last_ids.check_entry_id_age(tx.last_id, max_age) {
-                        Err(StatusDequeError::LastIdNotFound)
-                    } else {
+                    let r = if last_ids.check_entry_id_age(tx.last_id, max_age) {
                         last_ids.reserve_signature_with_last_id(&tx.last_id, &tx.signatures[0])
+                    } else if self.check_nonce(tx) {
+                        last_id = last_ids.last_id.unwrap();
+                        last_ids.reserve_signature_with_last_id(&last_id, &tx.signatures[0])
+                    } else {
+                        Err(StatusDequeError::LastIdNotFound)
                     };
-                    r.map_err(|err| match err {
+                    let r = r.map_err(|err| match err {
                         StatusDequeError::LastIdNotFound => {
                             error_counters.reserve_last_id += 1;
                             BankError::LastIdNotFound
@@ -462,12 +497,13 @@ impl Bank {
                             error_counters.duplicate_signature += 1;
                             BankError::DuplicateSignature
                         }
-                    })
+                    });
+                    (r, last_id)
                 } else {
-                    lock_res
+                    (lock_res, last_id)
                 }
             })
-            .collect()
+            .unzip()
     }
     #[allow(clippy::type_complexity)]
     fn load_and_execute_transactions(
@@ -478,11 +514,13 @@ impl Bank {
     ) -> (
         Vec<Result<(InstructionAccounts, InstructionLoaders)>>,
         Vec<Result<()>>,
+        Vec<Hash>,
     ) {
         debug!("processing transactions: {}", txs.len());
         let mut error_counters = ErrorCounters::default();
         let now = Instant::now();
-        let sig_results = self.check_signatures(txs, lock_results, max_age, &mut error_counters);
+        let (sig_results, reserved_last_ids) =
+            self.check_signatures(txs, lock_results, max_age, &mut error_counters);
         let mut loaded_accounts = self.load_accounts(txs, sig_results, &mut error_counters);
         let tick_height = self.tick_height();
 
@@ -491,14 +529,18 @@ impl Bank {
         let executed: Vec<Result<()>> = loaded_accounts
             .iter_mut()
             .zip(txs.iter())
-            .map(|(accs, tx)| match accs {
+            .zip(reserved_last_ids.iter())
+            .map(|((accs, tx), reserved_last_id)| match accs {
                 Err(e) => Err(e.clone()),
                 Ok((ref mut accounts, ref mut loaders)) => {
-                    runtime::execute_transaction(tx, loaders, accounts, tick_height).map_err(
-                        |RuntimeError::ProgramError(index, err)| {
-                            BankError::ProgramError(index, err)
-                        },
-                    )
+                    let result = if *reserved_last_id == tx.last_id {
+                        runtime::execute_transaction(tx, loaders, accounts, tick_height)
+                    } else {
+                        runtime::execute_nonce_transaction(tx, loaders, accounts, tick_height)
+                    };
+                    result.map_err(|RuntimeError::ProgramError(index, err)| {
+                        BankError::ProgramError(index, err)
+                    })
                 }
             })
             .collect();
@@ -559,18 +601,50 @@ impl Bank {
                 error_counters.insufficient_funds
             );
         }
-        (loaded_accounts, executed)
+        (loaded_accounts, executed, reserved_last_ids)
+    }
+
+    /// A transaction that used a nonce consumes it even if a later instruction fails,
+    /// otherwise it could be replayed once the `last_id` it was reserved under expires.
+    /// The runtime left the nonce account as the first instruction advanced it, so store
+    /// just that account
+    fn store_advanced_nonces(
+        &self,
+        txs: &[Transaction],
+        reserved_last_ids: &[Hash],
+        loaded_accounts: &[Result<(InstructionAccounts, InstructionLoaders)>],
+        executed: &[Result<()>],
+    ) {
+        for (i, tx) in txs.iter().enumerate() {
+            if reserved_last_ids[i] == tx.last_id {
+                continue;
+            }
+            if let (Err(BankError::ProgramError(index, _)), Ok((accounts, _))) =
+                (&executed[i], &loaded_accounts[i])
+            {
+                if *index > 0 {
+                    let nonce_index = tx.instructions[0].accounts[0] as usize;
+                    self.accounts.store_slow(
+                        true,
+                        &tx.account_keys[nonce_index],
+                        &accounts[nonce_index],
+                    );
+                }
+            }
+        }
     }
 
     fn commit_transactions(
         &self,
         txs: &[Transaction],
+        reserved_last_ids: &[Hash],
         loaded_accounts: &[Result<(InstructionAccounts, InstructionLoaders)>],
         executed: &[Result<()>],
     ) {
         let now = Instant::now();
         self.accounts
             .store_accounts(true, txs, executed, loaded_accounts);
+        self.store_advanced_nonces(txs, reserved_last_ids, loaded_accounts, executed);
 
         // Check account subscriptions and send notifications
         self.send_account_notifications(txs, executed, loaded_accounts);
@@ -582,7 +656,7 @@ impl Bank {
             duration_as_us(&write_elapsed),
             txs.len(),
         );
-        self.update_transaction_statuses(txs, &executed);
+        self.update_transaction_statuses(txs, reserved_last_ids, &executed);
     }
 
     /// Process a batch of transactions.
@@ -593,10 +667,10 @@ impl Bank {
         lock_results: Vec<Result<()>>,
         max_age: usize,
     ) -> Vec<Result<()>> {
-        let (loaded_accounts, executed) =
+        let (loaded_accounts, executed, reserved_last_ids) =
             self.load_and_execute_transactions(txs, lock_results, max_age);
 
-        self.commit_transactions(txs, &loaded_accounts, &executed);
+        self.commit_transactions(txs, &reserved_last_ids, &loaded_accounts, &executed);
         executed
     }
 
@@ -608,27 +682,6 @@ impl Bank {
         results
     }
 
-    pub fn process_entry(&self, entry: &Entry) -> Result<()> {
-        if !entry.is_tick() {
-            for result in self.process_transactions(&entry.transactions) {
-                match result {
-                    // Entries that result in a ProgramError are still valid and are written in the
-                    // ledger so map them to an ok return value
-                    Err(BankError::ProgramError(_, _)) => Ok(()),
-                    _ => result,
-                }?;
-            }
-        } else {
-            self.register_tick(&entry.id);
-            self.leader_scheduler
-                .write()
-                .unwrap()
-                .update_height(self.tick_height(), self);
-        }
-
-        Ok(())
-    }
-
     /// Process an ordered list of entries.
     pub fn process_entries(&self, entries: &[Entry]) -> Result<()> {
         self.par_process_entries(entries)
@@ -714,18 +767,54 @@ impl Bank {
         Ok(())
     }
 
-    /// Process an ordered list of entries, populating a circular buffer "tail"
-    /// as we go.
-    fn process_block(&self, entries: &[Entry]) -> Result<()> {
-        for entry in entries {
-            self.process_entry(entry)?;
+    /// Check that every transaction in `entries` is correctly signed.
+    fn verify_signatures(entries: &[Entry]) -> bool {
+        let transactions: Vec<_> = entries
+            .iter()
+            .flat_map(|entry| entry.transactions.iter())
+            .collect();
+        if transactions.is_empty() {
+            return true;
         }
+        sigverify::ed25519_verify(&to_packets(&transactions))
+            .iter()
+            .flatten()
+            .all(|verified| *verified == 1)
+    }
 
+    /// Verify the proof of history of a block of entries, and their transaction signatures
+    /// if `sigverify` is set.
+    fn verify_block(entries: &[Entry], last_id: &Hash, sigverify: bool) -> Result<()> {
+        let (poh_verified, signatures_verified) = rayon::join(
+            || entries.verify(last_id),
+            || !sigverify || Self::verify_signatures(entries),
+        );
+        if !poh_verified {
+            Err(BankError::LedgerVerificationFailed)?;
+        }
+        if !signatures_verified {
+            Err(BankError::SignatureFailure)?;
+        }
         Ok(())
     }
 
     /// Append entry blocks to the ledger, verifying them along the way.
     pub fn process_ledger<I>(&self, entries: I) -> Result<(u64, Hash)>
+    where
+        I: IntoIterator<Item = Entry>,
+    {
+        self.process_ledger_with_sigverify(entries, true)
+    }
+
+    /// Append entry blocks to the ledger, verifying their proof of history, and their
+    /// transaction signatures only if `sigverify` is set. A leader run without signature
+    /// verification may have recorded badly signed transactions, so its ledger only
+    /// replays with `sigverify` unset.
+    pub fn process_ledger_with_sigverify<I>(
+        &self,
+        entries: I,
+        sigverify: bool,
+    ) -> Result<(u64, Hash)>
     where
         I: IntoIterator<Item = Entry>,
     {
@@ -734,20 +823,59 @@ impl Bank {
         let mut entry_height = 0;
         let mut last_id = self.last_id();
 
+        sigverify::init();
+        let start = Instant::now();
+        let mut last_report = Instant::now();
+
         // Ledger verification needs to be parallelized, but we can't pull the whole
-        // thing into memory. We therefore chunk it.
-        for block in &entries.into_iter().chunks(VERIFY_BLOCK_SIZE) {
-            let block: Vec<_> = block.collect();
+        // thing into memory. We therefore chunk it, and verify each block while the
+        // one before it executes.
+        let chunks = entries.into_iter().chunks(VERIFY_BLOCK_SIZE);
+        let mut blocks = (&chunks).into_iter().map(|block| block.collect::<Vec<_>>());
+        let mut verified_block: Option<Vec<Entry>> = None;
+        loop {
+            let block = blocks.next();
+            let (verify_result, process_result) = rayon::join(
+                || match &block {
+                    Some(block) => Self::verify_block(block, &last_id, sigverify),
+                    None => Ok(()),
+                },
+                || match &verified_block {
+                    Some(verified_block) => self.par_process_entries(verified_block),
+                    None => Ok(()),
+                },
+            );
 
-            if !block.verify(&last_id) {
-                warn!("Ledger proof of history failed at entry: {}", entry_height);
-                return Err(BankError::LedgerVerificationFailed);
+            process_result?;
+            if let Some(verified_block) = verified_block.take() {
+                entry_height += verified_block.len() as u64;
+            }
+            if let Err(err) = verify_result {
+                warn!(
+                    "Ledger verification failed at entry {}: {:?}",
+                    entry_height, err
+                );
+                Err(err)?;
             }
 
-            self.process_block(&block)?;
+            if duration_as_ms(&last_report.elapsed()) >= LEDGER_PROGRESS_INTERVAL_MS {
+                let elapsed_ms = duration_as_ms(&start.elapsed());
+                info!(
+                    "processed {} ledger entries in {}ms ({} entries/s)",
+                    entry_height,
+                    elapsed_ms,
+                    entry_height * 1000 / elapsed_ms.max(1)
+                );
+                last_report = Instant::now();
+            }
 
-            last_id = block.last().unwrap().id;
-            entry_height += block.len() as u64;
+            match block {
+                Some(block) => {
+                    last_id = block.last().unwrap().id;
+                    verified_block = Some(block);
+                }
+                None => break,
+            }
         }
         Ok((entry_height, last_id))
     }
@@ -873,8 +1001,8 @@ mod tests {
     use solana_sdk::native_program::ProgramError;
     use solana_sdk::signature::Keypair;
     use solana_sdk::signature::KeypairUtil;
+    use solana_sdk::stake_program::StakeInstruction;
     use solana_sdk::storage_program::{StorageTransaction, ENTRIES_PER_SEGMENT};
-    use solana_sdk::system_instruction::SystemInstruction;
     use solana_sdk::system_transaction::SystemTransaction;
     use solana_sdk::transaction::Instruction;
     use std;
@@ -883,7 +1011,7 @@ mod tests {
     #[test]
     fn test_bank_new() {
         let (genesis_block, _) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         assert_eq!(bank.get_balance(&genesis_block.mint_id), 10_000);
     }
 
@@ -893,7 +1021,7 @@ mod tests {
         let dummy_leader_tokens = 1;
         let (genesis_block, _) =
             GenesisBlock::new_with_leader(10_000, dummy_leader_id, dummy_leader_tokens);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         assert_eq!(bank.get_balance(&genesis_block.mint_id), 9999);
         assert_eq!(bank.get_balance(&dummy_leader_id), 1);
     }
@@ -902,7 +1030,7 @@ mod tests {
     fn test_two_payments_to_one_party() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
         let pubkey = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         assert_eq!(bank.last_id(), genesis_block.last_id());
 
         bank.transfer(1_000, &mint_keypair, pubkey, genesis_block.last_id())
@@ -920,7 +1048,7 @@ mod tests {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1);
         let key1 = Keypair::new().pubkey();
         let key2 = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         assert_eq!(bank.last_id(), genesis_block.last_id());
 
         let t1 = Transaction::system_move(&mint_keypair, key1, 1, genesis_block.last_id(), 0);
@@ -948,7 +1076,7 @@ mod tests {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1);
         let key1 = Keypair::new().pubkey();
         let key2 = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let spend = SystemInstruction::Move { tokens: 1 };
         let instructions = vec![
             Instruction {
@@ -997,7 +1125,7 @@ mod tests {
         let (genesis_block, mint_keypair) = GenesisBlock::new(2);
         let key1 = Keypair::new().pubkey();
         let key2 = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let t1 = Transaction::system_move_many(
             &mint_keypair,
             &[(key1, 1), (key2, 1)],
@@ -1021,7 +1149,7 @@ mod tests {
     #[test]
     fn test_detect_failed_duplicate_transactions_issue_1157() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let dest = Keypair::new();
 
         // source with 0 program context
@@ -1059,7 +1187,7 @@ mod tests {
     #[test]
     fn test_account_not_found() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair = Keypair::new();
         assert_eq!(
             bank.transfer(1, &keypair, mint_keypair.pubkey(), genesis_block.last_id()),
@@ -1071,7 +1199,7 @@ mod tests {
     #[test]
     fn test_insufficient_funds() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(11_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let pubkey = Keypair::new().pubkey();
         bank.transfer(1_000, &mint_keypair, pubkey, genesis_block.last_id())
             .unwrap();
@@ -1094,7 +1222,7 @@ mod tests {
     #[test]
     fn test_transfer_to_newb() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let pubkey = Keypair::new().pubkey();
         bank.transfer(500, &mint_keypair, pubkey, genesis_block.last_id())
             .unwrap();
@@ -1104,7 +1232,7 @@ mod tests {
     #[test]
     fn test_debits_before_credits() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(2);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair = Keypair::new();
         let tx0 =
             Transaction::system_new(&mint_keypair, keypair.pubkey(), 2, genesis_block.last_id());
@@ -1121,7 +1249,7 @@ mod tests {
     #[test]
     fn test_process_empty_entry_is_registered() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair = Keypair::new();
         let entry = next_entry(&genesis_block.last_id(), 1, vec![]);
         let tx = Transaction::system_new(&mint_keypair, keypair.pubkey(), 1, entry.id);
@@ -1137,6 +1265,157 @@ mod tests {
         assert_eq!(bank.process_transaction(&tx), Ok(()));
     }
 
+    #[test]
+    fn test_process_transaction_with_nonce() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let nonce_keypair = Keypair::new();
+        let tx = Transaction::system_create_nonce_account(
+            &mint_keypair,
+            &nonce_keypair,
+            1,
+            genesis_block.last_id(),
+            0,
+        );
+        bank.process_transaction(&tx).unwrap();
+        let nonce_account = bank.get_account(&nonce_keypair.pubkey()).unwrap();
+        let nonce = match NonceState::deserialize(&nonce_account.userdata).unwrap() {
+            NonceState::Initialized { nonce } => nonce,
+            NonceState::Uninitialized => panic!("nonce account is not initialized"),
+        };
+
+        // Sign a payment with the nonce, then let every last_id expire
+        let pubkey = Keypair::new().pubkey();
+        let tx =
+            Transaction::system_move_with_nonce(&mint_keypair, &nonce_keypair, pubkey, 1, nonce, 0);
+        for i in 0..MAX_ENTRY_IDS {
+            bank.register_tick(&hash(&serialize(&i).unwrap()));
+        }
+        let old_tx = Transaction::system_new(&mint_keypair, pubkey, 1, genesis_block.last_id());
+        assert_eq!(
+            bank.process_transaction(&old_tx),
+            Err(BankError::LastIdNotFound)
+        );
+
+        bank.process_transaction(&tx).unwrap();
+        assert_eq!(bank.get_balance(&pubkey), 1);
+        assert_eq!(
+            bank.get_signature_status(&tx.signatures[0]),
+            Some(Status::Complete(Ok(())))
+        );
+
+        // The nonce has advanced, so the payment can't be replayed
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(BankError::LastIdNotFound)
+        );
+    }
+
+    #[test]
+    fn test_process_failed_transaction_with_nonce() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let nonce_keypair = Keypair::new();
+        let tx = Transaction::system_create_nonce_account(
+            &mint_keypair,
+            &nonce_keypair,
+            1,
+            genesis_block.last_id(),
+            0,
+        );
+        bank.process_transaction(&tx).unwrap();
+        let nonce_account = bank.get_account(&nonce_keypair.pubkey()).unwrap();
+        let nonce = match NonceState::deserialize(&nonce_account.userdata).unwrap() {
+            NonceState::Initialized { nonce } => nonce,
+            NonceState::Uninitialized => panic!("nonce account is not initialized"),
+        };
+
+        // The payment fails in its second instruction, after the nonce is advanced
+        let pubkey = Keypair::new().pubkey();
+        let tx = Transaction::system_move_with_nonce(
+            &mint_keypair,
+            &nonce_keypair,
+            pubkey,
+            20_000,
+            nonce,
+            0,
+        );
+        for i in 0..MAX_ENTRY_IDS {
+            bank.register_tick(&hash(&serialize(&i).unwrap()));
+        }
+        assert_matches!(
+            bank.process_transaction(&tx),
+            Err(BankError::ProgramError(1, _))
+        );
+        assert_eq!(bank.get_balance(&pubkey), 0);
+        assert_matches!(
+            bank.get_signature(&bank.last_id(), &tx.signatures[0]),
+            Some(Status::Complete(Err(BankError::ProgramError(1, _))))
+        );
+
+        // The nonce was consumed anyway, so the payment can't be replayed
+        let nonce_account = bank.get_account(&nonce_keypair.pubkey()).unwrap();
+        assert_ne!(
+            NonceState::deserialize(&nonce_account.userdata).unwrap(),
+            NonceState::Initialized { nonce }
+        );
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(BankError::LastIdNotFound)
+        );
+    }
+
+    #[test]
+    fn test_process_failed_transaction_moving_from_nonce() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let nonce_keypair = Keypair::new();
+        let tx = Transaction::system_create_nonce_account(
+            &mint_keypair,
+            &nonce_keypair,
+            10,
+            genesis_block.last_id(),
+            0,
+        );
+        bank.process_transaction(&tx).unwrap();
+        let nonce_account = bank.get_account(&nonce_keypair.pubkey()).unwrap();
+        let nonce = match NonceState::deserialize(&nonce_account.userdata).unwrap() {
+            NonceState::Initialized { nonce } => nonce,
+            NonceState::Uninitialized => panic!("nonce account is not initialized"),
+        };
+
+        // Move tokens out of the nonce account, then fail
+        let pubkey = Keypair::new().pubkey();
+        let tx = Transaction::new_with_instructions(
+            &[&mint_keypair, &nonce_keypair],
+            &[pubkey],
+            nonce,
+            0,
+            vec![system_program::id()],
+            vec![
+                Instruction::new(0, &SystemInstruction::AdvanceNonce, vec![1]),
+                Instruction::new(0, &SystemInstruction::Move { tokens: 5 }, vec![1, 2]),
+                Instruction::new(0, &SystemInstruction::Move { tokens: 20_000 }, vec![0, 2]),
+            ],
+        );
+        for i in 0..MAX_ENTRY_IDS {
+            bank.register_tick(&hash(&serialize(&i).unwrap()));
+        }
+        assert_matches!(
+            bank.process_transaction(&tx),
+            Err(BankError::ProgramError(2, _))
+        );
+
+        // The nonce advanced, but no tokens left the nonce account
+        let nonce_account = bank.get_account(&nonce_keypair.pubkey()).unwrap();
+        assert_ne!(
+            NonceState::deserialize(&nonce_account.userdata).unwrap(),
+            NonceState::Initialized { nonce }
+        );
+        assert_eq!(nonce_account.tokens, 10);
+        assert_eq!(bank.get_balance(&pubkey), 0);
+    }
+
     #[test]
     fn test_process_genesis() {
         let dummy_leader_id = Keypair::new().pubkey();
@@ -1227,6 +1506,7 @@ mod tests {
             bootstrap_leader_tokens: 1,
             mint_id: mint_keypair.pubkey(),
             tokens: length as u64 + 2,
+            native_programs: vec![],
         };
         let block = create_sample_block_with_ticks(&genesis_block, &mint_keypair, length, length);
         (genesis_block, mint_keypair, block)
@@ -1245,6 +1525,57 @@ mod tests {
         assert_eq!(bank.last_id(), last_id);
     }
 
+    #[test]
+    fn test_process_ledger_multiple_blocks() {
+        let length = VERIFY_BLOCK_SIZE;
+        let mint_keypair = Keypair::new();
+        let genesis_block = GenesisBlock {
+            bootstrap_leader_id: Keypair::new().pubkey(),
+            bootstrap_leader_tokens: 1,
+            mint_id: mint_keypair.pubkey(),
+            tokens: length as u64 + 2,
+            native_programs: vec![],
+        };
+        let ledger: Vec<_> =
+            create_sample_block_with_ticks(&genesis_block, &mint_keypair, length, 16).collect();
+        assert!(ledger.len() > 2 * VERIFY_BLOCK_SIZE);
+
+        let bank = Bank::default();
+        bank.process_genesis_block(&genesis_block);
+        bank.add_system_program();
+        let (ledger_height, last_id) = bank.process_ledger(ledger.clone()).unwrap();
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), 1);
+        assert_eq!(ledger_height, ledger.len() as u64);
+        assert_eq!(bank.tick_height(), (length / 16) as u64 + 1);
+        assert_eq!(bank.last_id(), last_id);
+    }
+
+    #[test]
+    fn test_process_ledger_bad_signature() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(2);
+        let mut tx = Transaction::system_new(
+            &mint_keypair,
+            Keypair::new().pubkey(),
+            1,
+            genesis_block.last_id(),
+        );
+        tx.signatures[0] = Signature::default();
+        let entry = Entry::new(&genesis_block.last_id(), 0, 1, vec![tx]);
+
+        let bank = Bank::new(&genesis_block).unwrap();
+        assert_eq!(
+            bank.process_ledger(vec![entry.clone()]),
+            Err(BankError::SignatureFailure)
+        );
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), 2);
+
+        // A ledger from a leader that didn't verify signatures replays without verifying them
+        let bank = Bank::new(&genesis_block).unwrap();
+        bank.process_ledger_with_sigverify(vec![entry], false)
+            .unwrap();
+        assert_eq!(bank.get_balance(&mint_keypair.pubkey()), 1);
+    }
+
     #[test]
     fn test_hash_internal_state() {
         let mint_keypair = Keypair::new();
@@ -1253,6 +1584,7 @@ mod tests {
             bootstrap_leader_tokens: 1,
             mint_id: mint_keypair.pubkey(),
             tokens: 2_000,
+            native_programs: vec![],
         };
         let seed = [0u8; 32];
         let mut rnd = GenKeys::new(seed);
@@ -1301,7 +1633,7 @@ mod tests {
     #[test]
     fn test_interleaving_locks() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(3);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let alice = Keypair::new();
         let bob = Keypair::new();
 
@@ -1369,7 +1701,7 @@ mod tests {
     #[test]
     fn test_par_process_entries_tick() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         // ensure bank can process a tick
         let tick = next_entry(&genesis_block.last_id(), 1, vec![]);
@@ -1379,7 +1711,7 @@ mod tests {
     #[test]
     fn test_par_process_entries_2_entries_collision() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair1 = Keypair::new();
         let keypair2 = Keypair::new();
 
@@ -1398,7 +1730,7 @@ mod tests {
     #[test]
     fn test_par_process_entries_2_txes_collision() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair1 = Keypair::new();
         let keypair2 = Keypair::new();
         let keypair3 = Keypair::new();
@@ -1446,7 +1778,7 @@ mod tests {
     #[test]
     fn test_par_process_entries_2_entries_par() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair1 = Keypair::new();
         let keypair2 = Keypair::new();
         let keypair3 = Keypair::new();
@@ -1472,7 +1804,7 @@ mod tests {
     #[test]
     fn test_par_process_entries_2_entries_tick() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let keypair1 = Keypair::new();
         let keypair2 = Keypair::new();
         let keypair3 = Keypair::new();
@@ -1542,6 +1874,10 @@ mod tests {
             133, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0,
         ]);
+        let stake = Pubkey::new(&[
+            134, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0,
+        ]);
 
         assert_eq!(system_program::id(), system);
         assert_eq!(solana_native_loader::id(), native);
@@ -1551,6 +1887,7 @@ mod tests {
         assert_eq!(token_program::id(), token);
         assert_eq!(vote_program::id(), vote);
         assert_eq!(storage_program::system_id(), storage_system);
+        assert_eq!(stake_program::id(), stake);
     }
 
     #[test]
@@ -1565,14 +1902,44 @@ mod tests {
             token_program::id(),
             vote_program::id(),
             storage_program::system_id(),
+            stake_program::id(),
         ];
         assert!(ids.into_iter().all(move |id| unique.insert(id)));
     }
 
+    #[test]
+    fn test_builtin_programs_unpinned() {
+        let (genesis_block, _) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let account = bank.get_account(&vote_program::id()).unwrap();
+        assert_eq!(
+            NativeProgram::deserialize(&account.userdata),
+            Ok(NativeProgram::new(
+                "solana_vote_program",
+                env!("CARGO_PKG_VERSION"),
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_builtin_program_hash_mismatch() {
+        let (mut genesis_block, _) = GenesisBlock::new(10_000);
+        genesis_block.native_programs = vec![NativeProgram::new(
+            "solana_vote_program",
+            env!("CARGO_PKG_VERSION"),
+            Some(Hash::default()),
+        )];
+        assert_eq!(
+            Bank::new(&genesis_block).err(),
+            Some(BankError::BuiltinProgramMismatch)
+        );
+    }
+
     #[test]
     fn test_bank_record_transactions() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let (entry_sender, entry_receiver) = channel();
         let poh_recorder = PohRecorder::new(bank.clone(), entry_sender, bank.last_id(), None);
         let pubkey = Keypair::new().pubkey();
@@ -1633,7 +2000,7 @@ mod tests {
     fn test_bank_storage() {
         solana_logger::setup();
         let (genesis_block, alice) = GenesisBlock::new(1000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         let bob = Keypair::new();
         let jack = Keypair::new();
@@ -1676,10 +2043,113 @@ mod tests {
         assert_eq!(bank.get_storage_last_id(), storage_last_id);
     }
 
+    #[test]
+    fn test_bank_fund_storage_reward_pool() {
+        let (genesis_block, alice) = GenesisBlock::new(1000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let last_id = genesis_block.last_id();
+        let pool_tokens = bank.get_balance(&storage_program::system_id());
+
+        let tx = Transaction::storage_new_fund_reward_pool(&alice, 100, last_id);
+        bank.process_transaction(&tx).unwrap();
+        assert_eq!(
+            bank.get_balance(&storage_program::system_id()),
+            pool_tokens + 100
+        );
+        assert_eq!(bank.get_storage_entry_height(), 0);
+    }
+
+    #[test]
+    fn test_bank_stake_program_address() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+        let last_id = genesis_block.last_id();
+        let owner = Keypair::new();
+        let owner_id = owner.pubkey();
+        bank.transfer(101, &mint_keypair, owner_id, last_id)
+            .unwrap();
+
+        // Keep tokens in a stake account at a program address derived for the owner, which
+        // only the owner can claim
+        let program_id = stake_program::id();
+        let (_, bump_seed) =
+            Pubkey::find_program_address(&[owner_id.as_ref(), b"stake"], &program_id);
+        let seeds: &[&[u8]] = &[owner_id.as_ref(), b"stake", &[bump_seed]];
+        let space = stake_program::get_max_size() as u64;
+        let tx = Transaction::system_create_program_account(
+            &mint_keypair,
+            seeds,
+            last_id,
+            100,
+            space,
+            program_id,
+            0,
+        );
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(BankError::ProgramError(0, ProgramError::InvalidArgument))
+        );
+        let tx = Transaction::system_create_program_account(
+            &owner, seeds, last_id, 100, space, program_id, 0,
+        );
+        bank.process_transaction(&tx).unwrap();
+        let address = tx.account_keys[1];
+
+        let sign_instruction = SystemInstruction::SignForProgramAddress {
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+        };
+        let withdraw = |sign: bool| {
+            let mut instructions = vec![Instruction::new(
+                1,
+                &StakeInstruction::Withdraw(100),
+                vec![1, 0],
+            )];
+            if sign {
+                instructions.insert(0, Instruction::new(0, &sign_instruction, vec![0, 1]));
+            }
+            Transaction::new_with_instructions(
+                &[&owner],
+                &[address],
+                last_id,
+                0,
+                vec![system_program::id(), program_id],
+                instructions,
+            )
+        };
+
+        // The stake program doesn't sign for the address without the seeds
+        assert_eq!(
+            bank.process_transaction(&withdraw(false)),
+            Err(BankError::ProgramError(0, ProgramError::InvalidArgument))
+        );
+
+        // Nor does any program other than the one the address is derived from
+        let tx = Transaction::new_with_instructions(
+            &[&owner],
+            &[address],
+            last_id,
+            0,
+            vec![system_program::id()],
+            vec![
+                Instruction::new(0, &sign_instruction, vec![0, 1]),
+                Instruction::new(0, &SystemInstruction::Move { tokens: 100 }, vec![1, 0]),
+            ],
+        );
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(BankError::ProgramError(1, ProgramError::InvalidArgument))
+        );
+        assert_eq!(bank.get_balance(&address), 100);
+
+        bank.process_transaction(&withdraw(true)).unwrap();
+        assert_eq!(bank.get_balance(&address), 0);
+        assert_eq!(bank.get_balance(&owner_id), 101);
+    }
+
     #[test]
     fn test_bank_process_and_record_transactions() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let pubkey = Keypair::new().pubkey();
 
         let transactions = vec![Transaction::system_move(
@@ -1731,5 +2201,4 @@ mod tests {
 
         assert_eq!(bank.get_balance(&pubkey), 1);
     }
-
 }
@@ -57,6 +57,7 @@ impl BankingStage {
         max_tick_height: Option<u64>,
         leader_id: Pubkey,
         to_validator_sender: &TpuRotationSender,
+        blob_compression: bool,
     ) -> (Self, Receiver<Vec<Entry>>) {
         let (entry_sender, entry_receiver) = channel();
         let shared_verified_receiver = Arc::new(Mutex::new(verified_receiver));
@@ -97,6 +98,7 @@ impl BankingStage {
                                 &thread_bank,
                                 &thread_verified_receiver,
                                 &thread_poh_recorder,
+                                blob_compression,
                             ) {
                                 debug!("got error {:?}", e);
                                 match e {
@@ -166,11 +168,18 @@ impl BankingStage {
         bank: &Arc<Bank>,
         transactions: &[Transaction],
         poh: &PohRecorder,
+        blob_compression: bool,
     ) -> Result<()> {
         debug!("transactions: {}", transactions.len());
         let mut chunk_start = 0;
         while chunk_start != transactions.len() {
-            let chunk_end = chunk_start + Entry::num_will_fit(&transactions[chunk_start..]);
+            let remaining = &transactions[chunk_start..];
+            let chunk_end = chunk_start
+                + if blob_compression {
+                    Entry::num_will_fit_compressed(remaining)
+                } else {
+                    Entry::num_will_fit(remaining)
+                };
 
             bank.process_and_record_transactions(&transactions[chunk_start..chunk_end], poh)?;
 
@@ -186,6 +195,7 @@ impl BankingStage {
         bank: &Arc<Bank>,
         verified_receiver: &Arc<Mutex<Receiver<VerifiedPackets>>>,
         poh: &PohRecorder,
+        blob_compression: bool,
     ) -> Result<()> {
         let recv_start = Instant::now();
         let mms = verified_receiver
@@ -225,7 +235,7 @@ impl BankingStage {
                 })
                 .collect();
             debug!("verified transactions {}", transactions.len());
-            Self::process_transactions(bank, &transactions, poh)?;
+            Self::process_transactions(bank, &transactions, poh, blob_compression)?;
             new_tx_count += transactions.len();
         }
 
@@ -296,7 +306,7 @@ mod tests {
     #[test]
     fn test_banking_stage_shutdown1() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let (verified_sender, verified_receiver) = channel();
         let (to_validator_sender, _) = channel();
@@ -308,6 +318,7 @@ mod tests {
             None,
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
         drop(verified_sender);
         assert_eq!(
@@ -319,7 +330,7 @@ mod tests {
     #[test]
     fn test_banking_stage_shutdown2() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let (_verified_sender, verified_receiver) = channel();
         let (to_validator_sender, _) = channel();
@@ -331,6 +342,7 @@ mod tests {
             None,
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
         drop(entry_receiver);
         assert_eq!(
@@ -342,7 +354,7 @@ mod tests {
     #[test]
     fn test_banking_stage_tick() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let start_hash = bank.last_id();
         let (verified_sender, verified_receiver) = channel();
@@ -355,6 +367,7 @@ mod tests {
             None,
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
         sleep(Duration::from_millis(500));
         drop(verified_sender);
@@ -372,7 +385,7 @@ mod tests {
     #[test]
     fn test_banking_stage_entries_only() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let start_hash = bank.last_id();
         let (verified_sender, verified_receiver) = channel();
@@ -385,6 +398,7 @@ mod tests {
             None,
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
 
         // good tx
@@ -431,7 +445,7 @@ mod tests {
         // differently if either the server doesn't signal the ledger to add an
         // Entry OR if the verifier tries to parallelize across multiple Entries.
         let (genesis_block, mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let (verified_sender, verified_receiver) = channel();
         let (to_validator_sender, _) = channel();
@@ -443,6 +457,7 @@ mod tests {
             None,
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
 
         // Process a batch that includes a transaction that receives two tokens.
@@ -474,7 +489,7 @@ mod tests {
         // Assert the user holds one token, not two. If the stage only outputs one
         // entry, then the second transaction will be rejected, because it drives
         // the account balance below zero before the credit is added.
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         for entry in entries {
             bank.process_transactions(&entry.transactions)
                 .iter()
@@ -488,7 +503,7 @@ mod tests {
     #[test]
     fn test_max_tick_height_shutdown() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(2);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let dummy_leader_id = Keypair::new().pubkey();
         let (_verified_sender_, verified_receiver) = channel();
         let (to_validator_sender, _to_validator_receiver) = channel();
@@ -501,6 +516,7 @@ mod tests {
             Some(max_tick_height),
             dummy_leader_id,
             &to_validator_sender,
+            false,
         );
         assert_eq!(
             banking_stage.join().unwrap(),
@@ -34,6 +34,7 @@ struct Broadcast {
     id: Pubkey,
     max_tick_height: Option<u64>,
     blob_index: u64,
+    blob_compression: bool,
 
     #[cfg(feature = "erasure")]
     coding_generator: CodingGenerator,
@@ -77,9 +78,22 @@ impl Broadcast {
         //  this may span slots if this leader broadcasts for consecutive slots...
         let slots = generate_slots(&ventries, leader_scheduler);
 
+        let blob_compression = self.blob_compression;
         let blobs: Vec<_> = ventries
             .into_par_iter()
-            .flat_map(|p| p.to_shared_blobs())
+            .map(|p| {
+                if blob_compression {
+                    p.to_compressed_shared_blobs()
+                } else {
+                    p.to_shared_blobs()
+                }
+            })
+            .collect();
+        let blobs: Vec<_> = blobs
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
             .collect();
 
         // TODO: blob_index should be slot-relative...
@@ -190,6 +204,7 @@ impl BroadcastService {
         leader_scheduler: &Arc<RwLock<LeaderScheduler>>,
         receiver: &Receiver<Vec<Entry>>,
         max_tick_height: Option<u64>,
+        blob_compression: bool,
         exit_signal: &Arc<AtomicBool>,
     ) -> BroadcastServiceReturnType {
         let me = cluster_info.read().unwrap().my_data().clone();
@@ -198,6 +213,7 @@ impl BroadcastService {
             id: me.id,
             max_tick_height,
             blob_index: entry_height,
+            blob_compression,
             #[cfg(feature = "erasure")]
             coding_generator: CodingGenerator::new(),
         };
@@ -234,6 +250,7 @@ impl BroadcastService {
     /// * `cluster_info` - ClusterInfo structure
     /// * `window` - Cache of blobs that we have broadcast
     /// * `receiver` - Receive channel for blobs to be retransmitted to all the layer 1 nodes.
+    /// * `blob_compression` - Compress entries into blobs whenever that makes them smaller.
     /// * `exit_sender` - Set to true when this service exits, allows rest of Tpu to exit cleanly.
     /// Otherwise, when a Tpu closes, it only closes the stages that come after it. The stages
     /// that come before could be blocked on a receive, and never notice that they need to
@@ -249,6 +266,7 @@ impl BroadcastService {
         leader_scheduler: Arc<RwLock<LeaderScheduler>>,
         receiver: Receiver<Vec<Entry>>,
         max_tick_height: Option<u64>,
+        blob_compression: bool,
         exit_sender: Arc<AtomicBool>,
     ) -> Self {
         let exit_signal = Arc::new(AtomicBool::new(false));
@@ -264,6 +282,7 @@ impl BroadcastService {
                     &leader_scheduler,
                     &receiver,
                     max_tick_height,
+                    blob_compression,
                     &exit_signal,
                 )
             })
@@ -337,6 +356,7 @@ mod test {
             leader_scheduler,
             entry_receiver,
             Some(max_tick_height),
+            false,
             exit_sender,
         );
 
@@ -41,6 +41,17 @@ pub fn chacha_cbc_encrypt_ledger(
 ) -> io::Result<usize> {
     let mut out_file =
         BufWriter::new(File::create(out_path).expect("Can't open ledger encrypted data file"));
+    chacha_cbc_encrypt_ledger_to(db_ledger, slice, &mut out_file, ivec)
+}
+
+/// Encrypts the segment starting at entry `slice` into `out_file`, returning the number of bytes
+/// written
+pub fn chacha_cbc_encrypt_ledger_to<W: Write>(
+    db_ledger: &Arc<DbLedger>,
+    slice: u64,
+    out_file: &mut W,
+    ivec: &mut [u8; CHACHA_BLOCK_SIZE],
+) -> io::Result<usize> {
     const BUFFER_SIZE: usize = 8 * 1024;
     let mut buffer = [0; BUFFER_SIZE];
     let mut encrypted_buffer = [0; BUFFER_SIZE];
@@ -57,19 +57,22 @@ impl ComputeLeaderConfirmationService {
             })
             .collect();
 
+        let leader_scheduler = bank.leader_scheduler.read().unwrap();
         let mut ticks_and_stakes: Vec<(u64, u64)> = vote_states
             .iter()
             .filter_map(|vote_state| {
                 let validator_stake = bank.get_balance(&vote_state.node_id);
                 total_stake += validator_stake;
                 // Filter out any validators that don't have at least one vote
-                // by returning None
-                vote_state
-                    .votes
-                    .back()
-                    .map(|vote| (vote.tick_height, validator_stake))
+                // by returning None. A vote for a slot confirms at least the
+                // first tick of that slot
+                vote_state.votes.back().map(|vote| {
+                    let tick_height = leader_scheduler.slot_height_to_first_tick_height(vote.slot);
+                    (tick_height, validator_stake)
+                })
             })
             .collect();
+        drop(leader_scheduler);
 
         let super_majority_stake = (2 * total_stake) / 3;
 
@@ -163,12 +166,13 @@ pub mod tests {
 
     use crate::genesis_block::GenesisBlock;
     use crate::leader_scheduler::tests::new_vote_account;
+    use crate::leader_scheduler::{LeaderScheduler, LeaderSchedulerConfig};
     use bincode::serialize;
     use solana_sdk::hash::hash;
     use solana_sdk::signature::{Keypair, KeypairUtil};
     use solana_sdk::transaction::Transaction;
     use solana_sdk::vote_transaction::VoteTransaction;
-    use std::sync::Arc;
+    use std::sync::{Arc, RwLock};
     use std::thread::sleep;
     use std::time::Duration;
 
@@ -178,7 +182,13 @@ pub mod tests {
 
         let (genesis_block, mint_keypair) = GenesisBlock::new(1234);
         let dummy_leader_id = Keypair::new().pubkey();
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let mut bank = Bank::new(&genesis_block).unwrap();
+        // One tick per slot after the genesis tick, so a vote for slot i + 1 confirms the
+        // tick that registered ids[i]
+        let leader_scheduler_config = LeaderSchedulerConfig::new(1, 1, 1, 1);
+        bank.leader_scheduler =
+            Arc::new(RwLock::new(LeaderScheduler::new(&leader_scheduler_config)));
+        let bank = Arc::new(bank);
         // generate 10 validators, but only vote for the first 6 validators
         let ids: Vec<_> = (0..10)
             .map(|i| {
@@ -205,7 +215,8 @@ pub mod tests {
                 new_vote_account(&validator_keypair, &vote_signer, &bank, 1, last_id);
 
                 if i < 6 {
-                    let vote_tx = Transaction::vote_new(&vote_signer, (i + 1) as u64, last_id, 0);
+                    let vote_tx =
+                        Transaction::vote_new(&vote_signer, (i + 1) as u64, last_id, last_id, 0);
                     bank.process_transaction(&vote_tx).unwrap();
                 }
                 (vote_signer, validator_keypair)
@@ -223,7 +234,7 @@ pub mod tests {
 
         // Get another validator to vote, so we now have 2/3 consensus
         let vote_signer = &vote_accounts[7].0;
-        let vote_tx = Transaction::vote_new(vote_signer, 7, ids[6], 0);
+        let vote_tx = Transaction::vote_new(vote_signer, 7, ids[6], ids[6], 0);
         bank.process_transaction(&vote_tx).unwrap();
 
         ComputeLeaderConfirmationService::compute_confirmation(
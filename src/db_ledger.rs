@@ -382,16 +382,22 @@ impl DbLedger {
             .into_iter()
             .enumerate()
             .map(|(idx, entry)| {
-                let mut b = entry.borrow().to_blob();
-                b.set_index(idx as u64 + index).unwrap();
-                b.set_slot(slot).unwrap();
-                b
+                let mut b = Self::entry_to_blob(entry.borrow())?;
+                b.set_index(idx as u64 + index)?;
+                b.set_slot(slot)?;
+                Ok(b)
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         self.write_blobs(&blobs)
     }
 
+    /// Serialize `entry` into a blob to store. An entry from a leader that compresses its
+    /// blobs may only fit compressed, and the ledger holds those as they were broadcast
+    fn entry_to_blob(entry: &Entry) -> Result<Blob> {
+        entry.to_blob().or_else(|_| entry.to_compressed_blob())
+    }
+
     pub fn insert_data_blobs<I>(&self, new_blobs: I) -> Result<Vec<Entry>>
     where
         I: IntoIterator,
@@ -478,10 +484,8 @@ impl DbLedger {
                     // look for the blob in the database.
                     if let Some(next_blob) = found_blob {
                         current_slot = next_blob.slot()?;
-                        let serialized_entry_data = &next_blob.data
-                            [BLOB_HEADER_SIZE..BLOB_HEADER_SIZE + next_blob.size()?];
                         // Verify entries can actually be reconstructed
-                        deserialize(serialized_entry_data).expect(
+                        Entry::from_blob(next_blob).expect(
                             "Blob made it past validation, so must be deserializable at this point",
                         )
                     } else {
@@ -501,7 +505,7 @@ impl DbLedger {
                                 break 'outer;
                             }
                         };
-                        deserialize(&blob_data[BLOB_HEADER_SIZE..])
+                        Entry::from_blob_bytes(&blob_data)
                             .expect("Blobs in database must be deserializable")
                     }
                 };
@@ -631,6 +635,11 @@ impl DbLedger {
 
     /// Return an iterator for all the entries in the given file.
     pub fn read_ledger(&self) -> Result<impl Iterator<Item = Entry>> {
+        Ok(self.read_ledger_with_slots()?.map(|(_, entry)| entry))
+    }
+
+    /// Return an iterator for all the entries in the given file as (slot, entry) tuples.
+    pub fn read_ledger_with_slots(&self) -> Result<impl Iterator<Item = (u64, Entry)>> {
         let mut db_iterator = self.db.raw_iterator_cf(self.data_cf.handle())?;
 
         db_iterator.seek_to_first();
@@ -640,6 +649,15 @@ impl DbLedger {
         })
     }
 
+    /// Return an iterator over all the coding blobs in the ledger as
+    /// (slot, index, blob bytes) tuples, in key order.
+    pub fn read_coding_blobs(&self) -> Result<impl Iterator<Item = (u64, u64, Vec<u8>)>> {
+        let mut db_iterator = self.erasure_cf.raw_iterator();
+
+        db_iterator.seek_to_first();
+        Ok(CodingBlobIterator { db_iterator })
+    }
+
     pub fn get_coding_blob_bytes(&self, slot: u64, index: u64) -> Result<Option<Vec<u8>>> {
         self.erasure_cf.get_by_slot_index(slot, index)
     }
@@ -809,12 +827,15 @@ struct EntryIterator {
 }
 
 impl Iterator for EntryIterator {
-    type Item = Entry;
+    type Item = (u64, Entry);
 
-    fn next(&mut self) -> Option<Entry> {
+    fn next(&mut self) -> Option<(u64, Entry)> {
         if self.db_iterator.valid() {
-            if let Some(value) = self.db_iterator.value() {
-                if let Ok(entry) = deserialize::<Entry>(&value[BLOB_HEADER_SIZE..]) {
+            if let (Some(key), Some(value)) = (self.db_iterator.key(), self.db_iterator.value()) {
+                if let (Ok(slot), Ok(entry)) = (
+                    DataCf::slot_height_from_key(&key),
+                    Entry::from_blob_bytes(&value),
+                ) {
                     if let Some(last_id) = self.last_id {
                         if !entry.verify(&last_id) {
                             return None;
@@ -822,7 +843,7 @@ impl Iterator for EntryIterator {
                     }
                     self.db_iterator.next();
                     self.last_id = Some(entry.id);
-                    return Some(entry);
+                    return Some((slot, entry));
                 }
             }
         }
@@ -830,6 +851,26 @@ impl Iterator for EntryIterator {
     }
 }
 
+struct CodingBlobIterator {
+    db_iterator: DBRawIterator,
+}
+
+impl Iterator for CodingBlobIterator {
+    type Item = (u64, u64, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.db_iterator.valid() {
+            return None;
+        }
+        let key = self.db_iterator.key()?;
+        let value = self.db_iterator.value()?;
+        let slot = ErasureCf::slot_height_from_key(&key).ok()?;
+        let index = ErasureCf::index_from_key(&key).ok()?;
+        self.db_iterator.next();
+        Some((slot, index, value))
+    }
+}
+
 pub fn create_empty_ledger(ledger_path: &str, genesis_block: &GenesisBlock) -> Result<()> {
     DbLedger::destroy(ledger_path)?;
     DbLedger::open(ledger_path)?;
@@ -848,13 +889,13 @@ where
         .into_iter()
         .enumerate()
         .map(|(idx, entry)| {
-            let mut b = entry.borrow().to_blob();
-            b.set_index(idx as u64).unwrap();
-            b.set_id(&keypair.pubkey()).unwrap();
-            b.set_slot(DEFAULT_SLOT_HEIGHT).unwrap();
-            b
+            let mut b = DbLedger::entry_to_blob(entry.borrow())?;
+            b.set_index(idx as u64)?;
+            b.set_id(&keypair.pubkey())?;
+            b.set_slot(DEFAULT_SLOT_HEIGHT)?;
+            Ok(b)
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     db_ledger.write_blobs(&blobs[..])?;
     Ok(())
@@ -987,7 +1028,7 @@ mod tests {
 
     #[test]
     fn test_read_blobs_bytes() {
-        let shared_blobs = make_tiny_test_entries(10).to_shared_blobs();
+        let shared_blobs = make_tiny_test_entries(10).to_shared_blobs().unwrap();
         let slot = DEFAULT_SLOT_HEIGHT;
         index_blobs(&shared_blobs, &Keypair::new().pubkey(), 0, &[slot; 10]);
 
@@ -1053,7 +1094,7 @@ mod tests {
     #[test]
     fn test_insert_data_blobs_basic() {
         let entries = make_tiny_test_entries(2);
-        let shared_blobs = entries.to_shared_blobs();
+        let shared_blobs = entries.to_shared_blobs().unwrap();
 
         for (i, b) in shared_blobs.iter().enumerate() {
             b.write().unwrap().set_index(i as u64).unwrap();
@@ -1097,7 +1138,7 @@ mod tests {
     fn test_insert_data_blobs_multiple() {
         let num_blobs = 10;
         let entries = make_tiny_test_entries(num_blobs);
-        let shared_blobs = entries.to_shared_blobs();
+        let shared_blobs = entries.to_shared_blobs().unwrap();
         for (i, b) in shared_blobs.iter().enumerate() {
             b.write().unwrap().set_index(i as u64).unwrap();
         }
@@ -1134,7 +1175,7 @@ mod tests {
     fn test_insert_data_blobs_slots() {
         let num_blobs = 10;
         let entries = make_tiny_test_entries(num_blobs);
-        let shared_blobs = entries.to_shared_blobs();
+        let shared_blobs = entries.to_shared_blobs().unwrap();
         for (i, b) in shared_blobs.iter().enumerate() {
             b.write().unwrap().set_index(i as u64).unwrap();
         }
@@ -1182,7 +1223,7 @@ mod tests {
             // Write entries
             let num_entries = 8;
             let entries = make_tiny_test_entries(num_entries);
-            let shared_blobs = entries.to_shared_blobs();
+            let shared_blobs = entries.to_shared_blobs().unwrap();
 
             for (i, b) in shared_blobs.iter().enumerate() {
                 let mut w_b = b.write().unwrap();
@@ -1225,7 +1266,7 @@ mod tests {
             // Write entries
             let num_entries = 20 as u64;
             let original_entries = make_tiny_test_entries(num_entries as usize);
-            let shared_blobs = original_entries.clone().to_shared_blobs();
+            let shared_blobs = original_entries.clone().to_shared_blobs().unwrap();
             for (i, b) in shared_blobs.iter().enumerate() {
                 let mut w_b = b.write().unwrap();
                 w_b.set_index(i as u64).unwrap();
@@ -1271,7 +1312,7 @@ mod tests {
                 .flat_map(|e| vec![e; num_duplicates])
                 .collect();
 
-            let shared_blobs = original_entries.clone().to_shared_blobs();
+            let shared_blobs = original_entries.clone().to_shared_blobs().unwrap();
             for (i, b) in shared_blobs.iter().enumerate() {
                 let index = (i / 2) as u64;
                 let mut w_b = b.write().unwrap();
@@ -1322,7 +1363,7 @@ mod tests {
             // Write entries
             let num_entries = 20 as u64;
             let original_entries = make_tiny_test_entries(num_entries as usize);
-            let shared_blobs = original_entries.to_shared_blobs();
+            let shared_blobs = original_entries.to_shared_blobs().unwrap();
             for (i, b) in shared_blobs.iter().enumerate() {
                 let mut w_b = b.write().unwrap();
                 w_b.set_index(i as u64).unwrap();
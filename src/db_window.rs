@@ -436,7 +436,7 @@ mod test {
         assert_eq!(db_ledger.find_missing_data_indexes(slot, 4, 3, 1), empty);
         assert_eq!(db_ledger.find_missing_data_indexes(slot, 1, 2, 0), empty);
 
-        let mut blobs = make_tiny_test_entries(2).to_blobs();
+        let mut blobs = make_tiny_test_entries(2).to_blobs().unwrap();
 
         const ONE: u64 = 1;
         const OTHER: u64 = 4;
@@ -477,7 +477,7 @@ mod test {
         let gap = 10;
         assert!(gap > 3);
         let num_entries = 10;
-        let mut blobs = make_tiny_test_entries(num_entries).to_blobs();
+        let mut blobs = make_tiny_test_entries(num_entries).to_blobs().unwrap();
         for (i, b) in blobs.iter_mut().enumerate() {
             b.set_index(i as u64 * gap).unwrap();
             b.set_slot(slot).unwrap();
@@ -559,7 +559,9 @@ mod test {
 
         // Write entries
         let num_entries = 10;
-        let shared_blobs = make_tiny_test_entries(num_entries).to_shared_blobs();
+        let shared_blobs = make_tiny_test_entries(num_entries)
+            .to_shared_blobs()
+            .unwrap();
 
         index_blobs(
             &shared_blobs,
@@ -655,7 +657,7 @@ mod test {
         let leader_scheduler = Arc::new(RwLock::new(leader_scheduler));
         let num_entries = 10;
         let original_entries = make_tiny_test_entries(num_entries);
-        let shared_blobs = original_entries.clone().to_shared_blobs();
+        let shared_blobs = original_entries.clone().to_shared_blobs().unwrap();
 
         index_blobs(
             &shared_blobs,
@@ -2,11 +2,14 @@
 //! unique ID that is the hash of the Entry before it, plus the hash of the
 //! transactions within it. Entries cannot be reordered, and its field `num_hashes`
 //! represents an approximate amount of time since the last Entry was created.
-use crate::packet::{Blob, SharedBlob, BLOB_DATA_SIZE};
+use crate::packet::{
+    Blob, BlobError, SharedBlob, BLOB_DATA_SIZE, BLOB_FLAG_IS_COMPRESSED, BLOB_HEADER_SIZE,
+};
 use crate::poh::Poh;
-use crate::result::Result;
-use bincode::{deserialize, serialize_into, serialized_size};
+use crate::result::{Error, Result};
+use bincode::{deserialize, serialize};
 use chrono::prelude::Utc;
+use libflate::deflate::{Decoder, Encoder};
 use rayon::prelude::*;
 use solana_sdk::budget_transaction::BudgetTransaction;
 use solana_sdk::hash::{hash, Hash};
@@ -16,7 +19,7 @@ use solana_sdk::transaction::Transaction;
 use solana_sdk::vote_program::Vote;
 use solana_sdk::vote_transaction::VoteTransaction;
 use std::borrow::Borrow;
-use std::io::Cursor;
+use std::io::{self, Read, Write};
 use std::mem::size_of;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, RwLock};
@@ -24,6 +27,10 @@ use std::sync::{Arc, RwLock};
 pub type EntrySender = Sender<Vec<Entry>>;
 pub type EntryReceiver = Receiver<Vec<Entry>>;
 
+/// Upper bound on the inflated size of a compressed entry, so a malicious blob
+/// can't make a node inflate it into an arbitrary amount of memory
+pub const MAX_INFLATED_ENTRY_SIZE: usize = 16 * BLOB_DATA_SIZE;
+
 /// Each Entry contains three pieces of data. The `num_hashes` field is the number
 /// of hashes performed since the previous entry.  The `id` field is the result
 /// of hashing `id` from the previous entry `num_hashes` times.  The `transactions`
@@ -96,31 +103,75 @@ impl Entry {
             }
         };
 
-        let size = Entry::serialized_size(&entry.transactions[..]);
-        if size > BLOB_DATA_SIZE as u64 {
+        if !Entry::fits_in_blob(&entry.transactions[..]) {
             panic!(
                 "Serialized entry size too large: {} ({} transactions):",
-                size,
+                Entry::serialized_size(&entry.transactions[..]),
                 entry.transactions.len()
             );
         }
         entry
     }
 
-    pub fn to_shared_blob(&self) -> SharedBlob {
-        let blob = self.to_blob();
-        Arc::new(RwLock::new(blob))
+    pub fn to_shared_blob(&self) -> Result<SharedBlob> {
+        let blob = self.to_blob()?;
+        Ok(Arc::new(RwLock::new(blob)))
+    }
+
+    /// Serialize the entry into an uncompressed blob, failing if it doesn't fit
+    pub fn to_blob(&self) -> Result<Blob> {
+        self.to_blob_with_compression(false)
+    }
+
+    /// Serialize the entry into a blob, compressing it whenever that makes it smaller,
+    /// failing if it doesn't fit even then
+    pub fn to_compressed_blob(&self) -> Result<Blob> {
+        self.to_blob_with_compression(true)
     }
 
-    pub fn to_blob(&self) -> Blob {
+    fn to_blob_with_compression(&self, compress: bool) -> Result<Blob> {
+        let mut data = serialize(&self)?;
+        let mut compressed = false;
+        if compress {
+            let deflated = deflate(&data);
+            if deflated.len() < data.len() {
+                data = deflated;
+                compressed = true;
+            }
+        }
+        if data.len() > BLOB_DATA_SIZE {
+            Err(Error::BlobError(BlobError::EntryTooLarge))?;
+        }
+
         let mut blob = Blob::default();
-        let pos = {
-            let mut out = Cursor::new(blob.data_mut());
-            serialize_into(&mut out, &self).expect("failed to serialize output");
-            out.position() as usize
-        };
-        blob.set_size(pos);
-        blob
+        blob.data_mut()[..data.len()].copy_from_slice(&data);
+        blob.set_size(data.len());
+        if compressed {
+            blob.set_compressed()?;
+        }
+        Ok(blob)
+    }
+
+    /// Deserialize the entry carried by `blob`
+    pub fn from_blob(blob: &Blob) -> Result<Entry> {
+        let size = blob.size()?;
+        Self::from_blob_data(blob.flags()?, &blob.data()[..size])
+    }
+
+    /// Deserialize the entry carried by a serialized blob, such as one stored in
+    /// the ledger
+    pub fn from_blob_bytes(bytes: &[u8]) -> Result<Entry> {
+        let flags = Blob::flags_from_bytes(bytes)?;
+        Self::from_blob_data(flags, &bytes[BLOB_HEADER_SIZE..])
+    }
+
+    fn from_blob_data(flags: u32, data: &[u8]) -> Result<Entry> {
+        if flags & BLOB_FLAG_IS_COMPRESSED != 0 {
+            let data = inflate(data)?;
+            Ok(deserialize(&data)?)
+        } else {
+            Ok(deserialize(data)?)
+        }
     }
 
     /// Estimate serialized_size of Entry without creating an Entry.
@@ -134,7 +185,38 @@ impl Entry {
         (3 * size_of::<u64>() + size_of::<Hash>()) as u64 + txs_size
     }
 
+    /// Size of an Entry holding `transactions` once serialized and compressed.
+    pub fn compressed_size(transactions: &[Transaction]) -> u64 {
+        // Same layout as a serialized Entry, without needing to own the transactions
+        let data = serialize(&(0u64, 0u64, Hash::default(), transactions))
+            .expect("failed to serialize transactions");
+        deflate(&data).len() as u64
+    }
+
+    /// Whether an Entry holding `transactions` fits in a single blob, compressed or not
+    pub fn fits_in_blob(transactions: &[Transaction]) -> bool {
+        let size = Self::serialized_size(transactions);
+        size <= BLOB_DATA_SIZE as u64
+            || (size <= MAX_INFLATED_ENTRY_SIZE as u64
+                && Self::compressed_size(transactions) <= BLOB_DATA_SIZE as u64)
+    }
+
+    /// Number of leading `transactions` that fit in one uncompressed blob
     pub fn num_will_fit(transactions: &[Transaction]) -> usize {
+        Self::num_fit(transactions, |transactions| {
+            Self::serialized_size(transactions) <= BLOB_DATA_SIZE as u64
+        })
+    }
+
+    /// Number of leading `transactions` that fit in one blob once compressed
+    pub fn num_will_fit_compressed(transactions: &[Transaction]) -> usize {
+        Self::num_fit(transactions, Self::fits_in_blob)
+    }
+
+    fn num_fit<F>(transactions: &[Transaction], fits: F) -> usize
+    where
+        F: Fn(&[Transaction]) -> bool,
+    {
         if transactions.is_empty() {
             return 0;
         }
@@ -151,7 +233,7 @@ impl Entry {
                 next,
                 transactions.len()
             );
-            if Self::serialized_size(&transactions[..num]) <= BLOB_DATA_SIZE as u64 {
+            if fits(&transactions[..num]) {
                 next = (upper + num) / 2;
                 lower = num;
                 debug!("num {} fits, maybe too well? trying {}", num, next);
@@ -179,7 +261,7 @@ impl Entry {
         let entry = Self::new(start_hash, 0, *num_hashes, transactions);
         *start_hash = entry.id;
         *num_hashes = 0;
-        assert!(serialized_size(&entry).unwrap() <= BLOB_DATA_SIZE as u64);
+        assert!(Self::fits_in_blob(&entry.transactions));
         entry
     }
 
@@ -246,10 +328,7 @@ where
     let mut num_ticks = 0;
 
     for blob in blobs.into_iter() {
-        let entry: Entry = {
-            let msg_size = blob.borrow().size()?;
-            deserialize(&blob.borrow().data()[..msg_size])?
-        };
+        let entry = Entry::from_blob(blob.borrow())?;
 
         if entry.is_tick() {
             num_ticks += 1
@@ -259,12 +338,33 @@ where
     Ok((entries, num_ticks))
 }
 
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = Encoder::new(Vec::new());
+    encoder.write_all(data).expect("deflate into memory");
+    encoder.finish().into_result().expect("deflate into memory")
+}
+
+fn inflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut inflated = vec![];
+    Decoder::new(data)
+        .take(MAX_INFLATED_ENTRY_SIZE as u64 + 1)
+        .read_to_end(&mut inflated)?;
+    if inflated.len() > MAX_INFLATED_ENTRY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "compressed entry inflates past MAX_INFLATED_ENTRY_SIZE",
+        ));
+    }
+    Ok(inflated)
+}
+
 // an EntrySlice is a slice of Entries
 pub trait EntrySlice {
     /// Verifies the hashes and counts of a slice of transactions are all consistent.
     fn verify(&self, start_hash: &Hash) -> bool;
-    fn to_shared_blobs(&self) -> Vec<SharedBlob>;
-    fn to_blobs(&self) -> Vec<Blob>;
+    fn to_shared_blobs(&self) -> Result<Vec<SharedBlob>>;
+    fn to_compressed_shared_blobs(&self) -> Result<Vec<SharedBlob>>;
+    fn to_blobs(&self) -> Result<Vec<Blob>>;
     fn votes(&self) -> Vec<(Pubkey, Vote, Hash)>;
 }
 
@@ -291,14 +391,20 @@ impl EntrySlice for [Entry] {
         })
     }
 
-    fn to_blobs(&self) -> Vec<Blob> {
+    fn to_blobs(&self) -> Result<Vec<Blob>> {
         self.iter().map(|entry| entry.to_blob()).collect()
     }
 
-    fn to_shared_blobs(&self) -> Vec<SharedBlob> {
+    fn to_shared_blobs(&self) -> Result<Vec<SharedBlob>> {
         self.iter().map(|entry| entry.to_shared_blob()).collect()
     }
 
+    fn to_compressed_shared_blobs(&self) -> Result<Vec<SharedBlob>> {
+        self.iter()
+            .map(|entry| Ok(Arc::new(RwLock::new(entry.to_compressed_blob()?))))
+            .collect()
+    }
+
     fn votes(&self) -> Vec<(Pubkey, Vote, Hash)> {
         self.iter()
             .flat_map(|entry| {
@@ -455,7 +561,7 @@ pub fn make_consecutive_blobs(
 ) -> Vec<SharedBlob> {
     let entries = create_ticks(num_blobs_to_make, start_hash);
 
-    let blobs = entries.to_shared_blobs();
+    let blobs = entries.to_shared_blobs().unwrap();
     let mut index = start_height;
     for blob in &blobs {
         let mut blob = blob.write().unwrap();
@@ -484,6 +590,7 @@ mod tests {
     use super::*;
     use crate::entry::Entry;
     use crate::packet::{to_blobs, BLOB_DATA_SIZE, PACKET_DATA_SIZE};
+    use bincode::serialized_size;
     use solana_sdk::budget_transaction::BudgetTransaction;
     use solana_sdk::hash::hash;
     use solana_sdk::signature::{Keypair, KeypairUtil};
@@ -607,7 +714,7 @@ mod tests {
         let one = hash(&zero.as_ref());
         let keypair = Keypair::new();
         let vote_account = Keypair::new();
-        let tx0 = Transaction::vote_new(&vote_account, 1, one, 1);
+        let tx0 = Transaction::vote_new(&vote_account, 1, Hash::default(), one, 1);
         let tx1 = Transaction::budget_new_timestamp(
             &keypair,
             keypair.pubkey(),
@@ -635,7 +742,7 @@ mod tests {
         solana_logger::setup();
         let entries = make_test_entries();
 
-        let blob_q = entries.to_blobs();
+        let blob_q = entries.to_blobs().unwrap();
 
         assert_eq!(reconstruct_entries_from_blobs(blob_q).unwrap().0, entries);
     }
@@ -655,7 +762,7 @@ mod tests {
         let next_id = hash(&id.as_ref());
         let keypair = Keypair::new();
         let vote_account = Keypair::new();
-        let tx_small = Transaction::vote_new(&vote_account, 1, next_id, 2);
+        let tx_small = Transaction::vote_new(&vote_account, 1, Hash::default(), next_id, 2);
         let tx_large = Transaction::budget_new(&keypair, keypair.pubkey(), 1, next_id);
 
         let tx_small_size = tx_small.serialized_size().unwrap() as usize;
@@ -696,4 +803,76 @@ mod tests {
         assert!(entries0.verify(&id));
     }
 
+    #[test]
+    fn test_compressed_blob_roundtrip() {
+        let entries = make_test_entries();
+
+        let blobs: Vec<_> = entries
+            .to_compressed_shared_blobs()
+            .unwrap()
+            .into_iter()
+            .map(|blob| blob.read().unwrap().clone())
+            .collect();
+        assert!(blobs.iter().all(|blob| blob.is_compressed()));
+        assert!(blobs.iter().zip(&entries).all(|(blob, entry)| {
+            (blob.size().unwrap() as u64) < serialized_size(entry).unwrap()
+        }));
+        assert_eq!(
+            reconstruct_entries_from_blobs(blobs.clone()).unwrap().0,
+            entries
+        );
+
+        let blob = &blobs[0];
+        let bytes = &blob.data[..BLOB_HEADER_SIZE + blob.size().unwrap()];
+        assert_eq!(Entry::from_blob_bytes(bytes).unwrap(), entries[0]);
+    }
+
+    #[test]
+    fn test_num_will_fit_compressed() {
+        let zero = Hash::default();
+        let keypair = Keypair::new();
+        let tx = Transaction::system_new(&keypair, keypair.pubkey(), 0, zero);
+        let transactions = vec![tx; 1000];
+
+        let num_uncompressed = Entry::num_will_fit(&transactions);
+        let num_compressed = Entry::num_will_fit_compressed(&transactions);
+        assert!(num_compressed > num_uncompressed);
+
+        // An entry too big for an uncompressed blob still goes out in a single compressed
+        // blob, but isn't compressed unless asked to be
+        let entry = next_entry(&zero, 1, transactions[..num_compressed].to_vec());
+        match entry.to_blob() {
+            Err(Error::BlobError(BlobError::EntryTooLarge)) => (),
+            result => panic!("oversized entry made a blob: {:?}", result.map(|_| ())),
+        }
+        let blob = entry.to_compressed_blob().unwrap();
+        assert!(blob.is_compressed());
+        assert_eq!(Entry::from_blob(&blob).unwrap(), entry);
+
+        // Nor does compressing make an entry fit that can't, such as one full of distinct
+        // signatures
+        let tx_size = serialized_size(&transactions[0]).unwrap() as usize;
+        let transactions = (0..4 * BLOB_DATA_SIZE / tx_size)
+            .map(|_| {
+                let keypair = Keypair::new();
+                Transaction::system_new(&keypair, keypair.pubkey(), 0, zero)
+            })
+            .collect();
+        let entry = Entry {
+            tick_height: 0,
+            num_hashes: 0,
+            id: zero,
+            transactions,
+        };
+        assert!(entry.to_compressed_blob().is_err());
+    }
+
+    #[test]
+    fn test_inflate_bounded() {
+        let data = vec![0u8; MAX_INFLATED_ENTRY_SIZE + 1];
+        let deflated = deflate(&data);
+        assert!(deflated.len() < BLOB_DATA_SIZE);
+        assert!(inflate(&deflated).is_err());
+        assert_eq!(inflate(&deflate(&data[1..])).unwrap(), &data[1..]);
+    }
 }
@@ -1,40 +1,515 @@
 //! The `entry_stream` module provides a method for streaming entries out via a
 //! local unix socket, to provide client services such as a block explorer with
 //! real-time access to entries.
+//!
+//! Events are written as newline-delimited JSON. A background thread owns the
+//! connection and reconnects whenever the consumer goes away, while the
+//! `ReplayStage` only ever pushes into a bounded backlog. If the consumer falls
+//! too far behind, new events are dropped rather than stalling replay.
+//!
+//! The sink is chosen by the prefix of the stream location:
+//!
+//! * `unix:<path>` or a bare path: a unix domain socket
+//! * `file:<path>`: a local file, rotated once it grows past `MAX_FILE_BYTES`
+//! * `tcp:<host:port>`: a TCP connection
+//! * `kafka:<host:port>/<topic>`: a Kafka broker, one message per event
 
+use crate::bank::{self, Bank, BankError};
+use crate::counter::Counter;
 use crate::entry::Entry;
-use crate::result::Result;
+use crate::result::{Error, Result};
+use crate::status_deque::Status;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use log::Level;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::fs::{self, File, OpenOptions};
+use std::io;
 use std::io::prelude::*;
-use std::net::Shutdown;
+use std::net::{TcpStream, ToSocketAddrs};
 use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, sleep, Builder, JoinHandle};
+use std::time::Duration;
+
+/// Maximum number of events held for a slow or absent consumer
+pub const ENTRY_STREAM_BACKLOG: usize = 16 * 1024;
+
+/// How long to wait between attempts to reach the consumer
+const RECONNECT_DELAY_MS: u64 = 1000;
+
+/// How often the writer thread checks for shutdown while idle
+const RECV_TIMEOUT_MS: u64 = 100;
+
+/// Size at which a `file:` stream is rotated
+pub const MAX_FILE_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Number of rotated files kept next to the live `file:` stream
+pub const MAX_ROTATED_FILES: usize = 4;
+
+/// How long the Kafka broker has to acknowledge a produce request
+const KAFKA_ACK_TIMEOUT_MS: i32 = 5000;
+
+/// How long a socket consumer has to accept a connection or an event before
+/// the writer gives up on it and reconnects. Without it a stalled consumer
+/// would block the writer thread, and with it the node's shutdown, forever
+const SOCKET_TIMEOUT_MS: u64 = 5000;
+
+/// Largest produce response accepted from the Kafka broker. A response for a
+/// single partition is a few dozen bytes, so anything near this is garbage
+const MAX_KAFKA_RESPONSE_BYTES: i32 = 64 * 1024;
+
+/// Execution status of a streamed transaction
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum TransactionStatus {
+    Confirmed,
+    ProgramError { instruction: u8, error: String },
+    Failed(String),
+}
+
+impl TransactionStatus {
+    pub fn new(result: &bank::Result<()>) -> Self {
+        match result {
+            Ok(()) => TransactionStatus::Confirmed,
+            Err(BankError::ProgramError(instruction, err)) => TransactionStatus::ProgramError {
+                instruction: *instruction,
+                error: format!("{:?}", err),
+            },
+            Err(err) => TransactionStatus::Failed(format!("{:?}", err)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryStreamEvent {
+    /// A new slot has begun at `tick_height` with `leader_id` as its leader
+    Slot {
+        slot: u64,
+        tick_height: u64,
+        leader_id: Pubkey,
+    },
+    /// An entry that was replayed in `slot`
+    Entry { slot: u64, entry: Entry },
+    /// A transaction from the preceding entry and the result of executing it
+    Transaction {
+        slot: u64,
+        signature: Signature,
+        status: TransactionStatus,
+    },
+}
 
 pub trait EntryStreamHandler {
-    fn stream_entries(&mut self, entries: &[Entry]) -> Result<()>;
+    fn emit_event(&mut self, event: EntryStreamEvent) -> Result<()>;
+
+    fn emit_slot_event(&mut self, slot: u64, tick_height: u64, leader_id: Pubkey) -> Result<()> {
+        self.emit_event(EntryStreamEvent::Slot {
+            slot,
+            tick_height,
+            leader_id,
+        })
+    }
+
+    /// Stream `entries` that have already been processed by `bank`, each followed by
+    /// the status of its transactions
+    fn stream_entries(&mut self, slot: u64, entries: &[Entry], bank: &Bank) -> Result<()> {
+        for entry in entries {
+            self.emit_event(EntryStreamEvent::Entry {
+                slot,
+                entry: entry.clone(),
+            })?;
+            for tx in &entry.transactions {
+                let signature = match tx.signatures.first() {
+                    Some(signature) => *signature,
+                    None => continue,
+                };
+                if let Some(Status::Complete(result)) = bank.get_signature(&tx.last_id, &signature)
+                {
+                    self.emit_event(EntryStreamEvent::Transaction {
+                        slot,
+                        signature,
+                        status: TransactionStatus::new(&result),
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 pub struct EntryStream {
     pub socket: String,
+    sender: SyncSender<String>,
+    exit: Arc<AtomicBool>,
+    t_writer: Option<JoinHandle<()>>,
 }
 
 impl EntryStream {
+    /// Open a stream at `socket`, using the sink selected by its prefix
     pub fn new(socket: String) -> Self {
-        EntryStream { socket }
+        if let Some(path) = strip_prefix(&socket, "file:") {
+            let path = PathBuf::from(path);
+            Self::spawn(socket, move || RotatingFile::open(&path, MAX_FILE_BYTES))
+        } else if let Some(addr) = strip_prefix(&socket, "tcp:") {
+            let addr = addr.to_string();
+            Self::spawn(socket, move || connect_tcp(&addr))
+        } else if let Some(location) = strip_prefix(&socket, "kafka:") {
+            let (addr, topic) = match location.find('/') {
+                Some(i) => (location[..i].to_string(), location[i + 1..].to_string()),
+                None => (location.to_string(), "solana-entries".to_string()),
+            };
+            Self::spawn(socket, move || KafkaProducer::connect(&addr, &topic))
+        } else {
+            let path = strip_prefix(&socket, "unix:")
+                .unwrap_or(&socket)
+                .to_string();
+            Self::spawn(socket, move || {
+                let stream = UnixStream::connect(Path::new(&path))?;
+                stream.set_write_timeout(Some(Duration::from_millis(SOCKET_TIMEOUT_MS)))?;
+                Ok(stream)
+            })
+        }
+    }
+
+    fn spawn<W, F>(socket: String, connect: F) -> Self
+    where
+        W: Write,
+        F: FnMut() -> io::Result<W> + Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(ENTRY_STREAM_BACKLOG);
+        let exit = Arc::new(AtomicBool::new(false));
+        let exit_ = exit.clone();
+        let t_writer = Builder::new()
+            .name("solana-entry-stream".to_string())
+            .spawn(move || Self::write_events(connect, &receiver, &exit_))
+            .unwrap();
+        EntryStream {
+            socket,
+            sender,
+            exit,
+            t_writer: Some(t_writer),
+        }
+    }
+
+    fn write_events<W, F>(mut connect: F, receiver: &Receiver<String>, exit: &AtomicBool)
+    where
+        W: Write,
+        F: FnMut() -> io::Result<W>,
+    {
+        let mut stream = None;
+        loop {
+            let event = match receiver.recv_timeout(Duration::from_millis(RECV_TIMEOUT_MS)) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => {
+                    if exit.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+
+            // Hold on to the event until it is delivered, so a restarting consumer only
+            // misses what overflowed the backlog in the meantime
+            loop {
+                if exit.load(Ordering::Relaxed) {
+                    return;
+                }
+                if stream.is_none() {
+                    match connect() {
+                        Ok(s) => stream = Some(s),
+                        Err(e) => {
+                            debug!("entry stream connect failed: {:?}", e);
+                            sleep(Duration::from_millis(RECONNECT_DELAY_MS));
+                            continue;
+                        }
+                    }
+                }
+                let s = stream.as_mut().unwrap();
+                match s.write_all(event.as_bytes()).and_then(|_| s.flush()) {
+                    Ok(()) => break,
+                    Err(e) => {
+                        warn!("entry stream write failed, reconnecting: {:?}", e);
+                        stream = None;
+                    }
+                }
+            }
+        }
     }
 }
 
 impl EntryStreamHandler for EntryStream {
-    fn stream_entries(&mut self, entries: &[Entry]) -> Result<()> {
-        let mut socket = UnixStream::connect(Path::new(&self.socket))?;
-        for entry in entries {
-            let result = serde_json::to_string(&entry)?;
-            socket.write_all(result.as_bytes())?;
+    fn emit_event(&mut self, event: EntryStreamEvent) -> Result<()> {
+        let mut line = serde_json::to_string(&event)?;
+        line.push('\n');
+        match self.sender.try_send(line) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                inc_new_counter_info!("entry_stream-dropped_events", 1);
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(Error::SendError),
+        }
+    }
+}
+
+impl Drop for EntryStream {
+    fn drop(&mut self) {
+        self.exit.store(true, Ordering::Relaxed);
+        if let Some(t_writer) = self.t_writer.take() {
+            if thread::panicking() {
+                return;
+            }
+            t_writer.join().unwrap();
+        }
+    }
+}
+
+/// Connect to `addr`, bounding both the connect and every later write by
+/// `SOCKET_TIMEOUT_MS`
+fn connect_tcp(addr: &str) -> io::Result<TcpStream> {
+    let timeout = Duration::from_millis(SOCKET_TIMEOUT_MS);
+    let mut last_err = io::Error::new(io::ErrorKind::InvalidInput, "no addresses to connect to");
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(timeout))?;
+                return Ok(stream);
+            }
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    if s.starts_with(prefix) {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// A local file that is moved aside once it reaches `max_bytes`, keeping up to
+/// `MAX_ROTATED_FILES` older files as `<path>.1`, `<path>.2`, ...
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            file,
+            len,
+            max_bytes,
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for n in (1..MAX_ROTATED_FILES).rev() {
+            let from = self.rotated_path(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        *self = Self::open(&self.path, self.max_bytes)?;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        // Events are written whole, so rotating here never splits one across
+        // two files
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.len += buf.len() as u64;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Minimal Kafka producer speaking version 0 of the Produce API. Every event
+/// written between flushes becomes one message on partition 0 of `topic`, and
+/// a flush waits for the leader to acknowledge it.
+struct KafkaProducer {
+    stream: TcpStream,
+    topic: String,
+    correlation_id: i32,
+    pending: Vec<u8>,
+}
+
+const KAFKA_PRODUCE_API_KEY: i16 = 0;
+const KAFKA_CLIENT_ID: &str = "solana-entry-stream";
+
+impl KafkaProducer {
+    fn connect(addr: &str, topic: &str) -> io::Result<Self> {
+        let stream = connect_tcp(addr)?;
+        stream.set_read_timeout(Some(Duration::from_millis(2 * KAFKA_ACK_TIMEOUT_MS as u64)))?;
+        Ok(KafkaProducer {
+            stream,
+            topic: topic.to_string(),
+            correlation_id: 0,
+            pending: vec![],
+        })
+    }
+
+    fn write_string(buf: &mut Vec<u8>, s: &str) -> io::Result<()> {
+        buf.write_i16::<BigEndian>(s.len() as i16)?;
+        buf.write_all(s.as_bytes())
+    }
+
+    fn read_string(buf: &mut &[u8]) -> io::Result<String> {
+        let len = buf.read_i16::<BigEndian>()?;
+        if len < 0 {
+            return Ok(String::new());
+        }
+        let mut s = vec![0; len as usize];
+        buf.read_exact(&mut s)?;
+        String::from_utf8(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Encode `values` as a v0 MessageSet
+    fn message_set(values: &[&[u8]]) -> io::Result<Vec<u8>> {
+        let mut set = vec![];
+        for value in values {
+            let mut message = vec![];
+            message.write_i8(0)?; // magic
+            message.write_i8(0)?; // attributes, no compression
+            message.write_i32::<BigEndian>(-1)?; // null key
+            message.write_i32::<BigEndian>(value.len() as i32)?;
+            message.write_all(value)?;
+
+            set.write_i64::<BigEndian>(0)?; // offset, assigned by the broker
+            set.write_i32::<BigEndian>(message.len() as i32 + 4)?;
+            set.write_u32::<BigEndian>(crc32(&message))?;
+            set.write_all(&message)?;
+        }
+        Ok(set)
+    }
+
+    fn produce_request(&self, values: &[&[u8]]) -> io::Result<Vec<u8>> {
+        let message_set = Self::message_set(values)?;
+        let mut body = vec![];
+        body.write_i16::<BigEndian>(KAFKA_PRODUCE_API_KEY)?;
+        body.write_i16::<BigEndian>(0)?; // api version
+        body.write_i32::<BigEndian>(self.correlation_id)?;
+        Self::write_string(&mut body, KAFKA_CLIENT_ID)?;
+        body.write_i16::<BigEndian>(1)?; // acks from the partition leader
+        body.write_i32::<BigEndian>(KAFKA_ACK_TIMEOUT_MS)?;
+        body.write_i32::<BigEndian>(1)?; // one topic
+        Self::write_string(&mut body, &self.topic)?;
+        body.write_i32::<BigEndian>(1)?; // one partition
+        body.write_i32::<BigEndian>(0)?; // partition 0
+        body.write_i32::<BigEndian>(message_set.len() as i32)?;
+        body.write_all(&message_set)?;
+
+        let mut request = vec![];
+        request.write_i32::<BigEndian>(body.len() as i32)?;
+        request.write_all(&body)?;
+        Ok(request)
+    }
+
+    fn check_produce_response(&mut self) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let len = self.stream.read_i32::<BigEndian>()?;
+        if len < 0 || len > MAX_KAFKA_RESPONSE_BYTES {
+            return Err(invalid(format!("invalid kafka response length {}", len)));
+        }
+        let mut response = vec![0; len as usize];
+        self.stream.read_exact(&mut response)?;
+        let mut buf = &response[..];
+
+        let correlation_id = buf.read_i32::<BigEndian>()?;
+        if correlation_id != self.correlation_id {
+            return Err(invalid(format!(
+                "unexpected kafka correlation id {}",
+                correlation_id
+            )));
+        }
+        for _ in 0..buf.read_i32::<BigEndian>()? {
+            let topic = Self::read_string(&mut buf)?;
+            for _ in 0..buf.read_i32::<BigEndian>()? {
+                let partition = buf.read_i32::<BigEndian>()?;
+                let error_code = buf.read_i16::<BigEndian>()?;
+                let _offset = buf.read_i64::<BigEndian>()?;
+                if error_code != 0 {
+                    return Err(invalid(format!(
+                        "kafka error {} producing to {}/{}",
+                        error_code, topic, partition
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for KafkaProducer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
         }
-        socket.shutdown(Shutdown::Write)?;
+        let values: Vec<_> = self
+            .pending
+            .split(|b| *b == b'\n')
+            .filter(|value| !value.is_empty())
+            .collect();
+        let request = self.produce_request(&values)?;
+        self.stream.write_all(&request)?;
+        self.check_produce_response()?;
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        self.pending.clear();
         Ok(())
     }
 }
 
+/// CRC-32 (IEEE) as used by Kafka message checksums
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
 pub struct MockEntryStream {
     pub socket: Vec<String>,
 }
@@ -47,11 +522,262 @@ impl MockEntryStream {
 }
 
 impl EntryStreamHandler for MockEntryStream {
-    fn stream_entries(&mut self, entries: &[Entry]) -> Result<()> {
-        for entry in entries {
-            let result = serde_json::to_string(&entry)?;
-            self.socket.push(result);
-        }
+    fn emit_event(&mut self, event: EntryStreamEvent) -> Result<()> {
+        let result = serde_json::to_string(&event)?;
+        self.socket.push(result);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db_ledger::get_tmp_ledger_path;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
+    use solana_sdk::system_transaction::SystemTransaction;
+    use solana_sdk::transaction::Transaction;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::os::unix::net::UnixListener;
+
+    fn test_socket_path(name: &str) -> String {
+        // Unix socket paths are length limited, so stay out of OUT_DIR
+        let tmp_dir = std::env::temp_dir();
+        format!("{}/{}-{}.sock", tmp_dir.display(), name, std::process::id())
+    }
+
+    fn slot_event(slot: u64) -> EntryStreamEvent {
+        EntryStreamEvent::Slot {
+            slot,
+            tick_height: slot * 8,
+            leader_id: Pubkey::default(),
+        }
+    }
+
+    fn read_event(reader: &mut BufReader<UnixStream>) -> EntryStreamEvent {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.ends_with('\n'));
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn test_entry_stream_reconnects() {
+        let path = test_socket_path("test_entry_stream_reconnects");
+        let _ignored = std::fs::remove_file(&path);
+
+        // Events emitted before the consumer is listening are held in the backlog
+        let mut entry_stream = EntryStream::new(path.clone());
+        entry_stream.emit_event(slot_event(1)).unwrap();
+        entry_stream.emit_event(slot_event(2)).unwrap();
+
+        let listener = UnixListener::bind(&path).unwrap();
+        {
+            let (socket, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(socket);
+            assert_eq!(read_event(&mut reader), slot_event(1));
+            assert_eq!(read_event(&mut reader), slot_event(2));
+        }
+
+        // The consumer went away, the stream picks up once it is back
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+        entry_stream.emit_event(slot_event(3)).unwrap();
+        let listener = UnixListener::bind(&path).unwrap();
+        entry_stream.emit_event(slot_event(4)).unwrap();
+        let (socket, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(socket);
+        let mut event = read_event(&mut reader);
+        if event == slot_event(3) {
+            event = read_event(&mut reader);
+        }
+        assert_eq!(event, slot_event(4));
+
+        drop(entry_stream);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_entry_stream_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut entry_stream = EntryStream::new(format!("tcp:{}", addr));
+        entry_stream.emit_event(slot_event(1)).unwrap();
+
+        let (socket, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(socket).read_line(&mut line).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EntryStreamEvent>(&line).unwrap(),
+            slot_event(1)
+        );
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let path = PathBuf::from(get_tmp_ledger_path("test_rotating_file"));
+        fs::create_dir_all(&path).unwrap();
+        let path = path.join("entries");
+        let line = b"0123456789\n";
+
+        let mut file = RotatingFile::open(&path, 2 * line.len() as u64).unwrap();
+        for _ in 0..(2 * MAX_ROTATED_FILES + 1) {
+            file.write_all(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().len(), line.len() as u64);
+        for n in 1..=MAX_ROTATED_FILES {
+            let rotated = file.rotated_path(n);
+            assert_eq!(fs::metadata(&rotated).unwrap().len(), 2 * line.len() as u64);
+        }
+        assert!(!file.rotated_path(MAX_ROTATED_FILES + 1).exists());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    /// Accept one produce request like a Kafka broker would and acknowledge it,
+    /// returning the topic and message values it carried
+    fn stand_in_broker_produce(socket: &mut TcpStream) -> (String, Vec<Vec<u8>>) {
+        let len = socket.read_i32::<BigEndian>().unwrap();
+        let mut request = vec![0; len as usize];
+        socket.read_exact(&mut request).unwrap();
+        let mut buf = &request[..];
+
+        assert_eq!(buf.read_i16::<BigEndian>().unwrap(), KAFKA_PRODUCE_API_KEY);
+        assert_eq!(buf.read_i16::<BigEndian>().unwrap(), 0);
+        let correlation_id = buf.read_i32::<BigEndian>().unwrap();
+        assert_eq!(
+            KafkaProducer::read_string(&mut buf).unwrap(),
+            KAFKA_CLIENT_ID
+        );
+        let _acks = buf.read_i16::<BigEndian>().unwrap();
+        let _timeout = buf.read_i32::<BigEndian>().unwrap();
+        assert_eq!(buf.read_i32::<BigEndian>().unwrap(), 1);
+        let topic = KafkaProducer::read_string(&mut buf).unwrap();
+        assert_eq!(buf.read_i32::<BigEndian>().unwrap(), 1);
+        assert_eq!(buf.read_i32::<BigEndian>().unwrap(), 0);
+        let set_len = buf.read_i32::<BigEndian>().unwrap() as usize;
+        assert_eq!(set_len, buf.len());
+
+        let mut values = vec![];
+        while !buf.is_empty() {
+            let _offset = buf.read_i64::<BigEndian>().unwrap();
+            let message_len = buf.read_i32::<BigEndian>().unwrap() as usize;
+            let crc = buf.read_u32::<BigEndian>().unwrap();
+            let (message, rest) = buf.split_at(message_len - 4);
+            assert_eq!(crc, crc32(message));
+            buf = rest;
+
+            let mut message = message;
+            assert_eq!(message.read_i8().unwrap(), 0);
+            assert_eq!(message.read_i8().unwrap(), 0);
+            assert_eq!(message.read_i32::<BigEndian>().unwrap(), -1);
+            let value_len = message.read_i32::<BigEndian>().unwrap() as usize;
+            assert_eq!(value_len, message.len());
+            values.push(message.to_vec());
+        }
+
+        let mut body = vec![];
+        body.write_i32::<BigEndian>(correlation_id).unwrap();
+        body.write_i32::<BigEndian>(1).unwrap();
+        KafkaProducer::write_string(&mut body, &topic).unwrap();
+        body.write_i32::<BigEndian>(1).unwrap();
+        body.write_i32::<BigEndian>(0).unwrap();
+        body.write_i16::<BigEndian>(0).unwrap();
+        body.write_i64::<BigEndian>(0).unwrap();
+        socket.write_i32::<BigEndian>(body.len() as i32).unwrap();
+        socket.write_all(&body).unwrap();
+
+        (topic, values)
+    }
+
+    #[test]
+    fn test_entry_stream_kafka() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut entry_stream = EntryStream::new(format!("kafka:{}/entries", addr));
+        entry_stream.emit_event(slot_event(1)).unwrap();
+        entry_stream.emit_event(slot_event(2)).unwrap();
+
+        let (mut socket, _) = listener.accept().unwrap();
+        for slot in 1..=2 {
+            let (topic, values) = stand_in_broker_produce(&mut socket);
+            assert_eq!(topic, "entries");
+            assert_eq!(values.len(), 1);
+            assert_eq!(
+                serde_json::from_slice::<EntryStreamEvent>(&values[0]).unwrap(),
+                slot_event(slot)
+            );
+        }
+    }
+
+    #[test]
+    fn test_kafka_oversized_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut producer = KafkaProducer::connect(&addr.to_string(), "entries").unwrap();
+        let (mut socket, _) = listener.accept().unwrap();
+        socket.write_i32::<BigEndian>(i32::max_value()).unwrap();
+
+        producer.write_all(b"event\n").unwrap();
+        let err = producer.flush().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_mock_entry_stream_events() {
+        let bank = Bank::default();
+        let mut entry_stream = MockEntryStream::new("test_stream".to_string());
+        let entry = Entry::new(&Hash::default(), 0, 0, vec![]);
+        let entries = vec![entry.clone(), entry.clone()];
+        entry_stream
+            .emit_slot_event(1, 8, Pubkey::default())
+            .unwrap();
+        entry_stream.stream_entries(1, &entries, &bank).unwrap();
+
+        let events: Vec<EntryStreamEvent> = entry_stream
+            .socket
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events[0], slot_event(1));
+        assert_eq!(
+            events[1..].to_vec(),
+            vec![
+                EntryStreamEvent::Entry {
+                    slot: 1,
+                    entry: entry.clone()
+                };
+                2
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_entries_unsigned_transaction() {
+        let bank = Bank::default();
+        let mut entry_stream = MockEntryStream::new("test_stream".to_string());
+        let keypair = Keypair::new();
+        let mut tx = Transaction::system_new(&keypair, keypair.pubkey(), 1, Hash::default());
+        tx.signatures.clear();
+        let entry = Entry::new(&Hash::default(), 0, 0, vec![tx]);
+        entry_stream
+            .stream_entries(1, &[entry.clone()], &bank)
+            .unwrap();
+
+        let events: Vec<EntryStreamEvent> = entry_stream
+            .socket
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events, vec![EntryStreamEvent::Entry { slot: 1, entry }]);
+    }
+}
@@ -912,7 +912,7 @@ pub mod test {
     }
 
     fn generate_test_blobs(offset: usize, num_blobs: usize) -> Vec<SharedBlob> {
-        let blobs = make_tiny_test_entries(num_blobs).to_shared_blobs();
+        let blobs = make_tiny_test_entries(num_blobs).to_shared_blobs().unwrap();
 
         index_blobs(
             &blobs,
@@ -69,6 +69,7 @@ pub struct FullnodeConfig {
     pub sigverify_disabled: bool,
     pub entry_stream: Option<String>,
     pub storage_rotate_count: u64,
+    pub blob_compression: bool,
 }
 impl Default for FullnodeConfig {
     fn default() -> Self {
@@ -80,6 +81,7 @@ impl Default for FullnodeConfig {
             sigverify_disabled: false,
             entry_stream: None,
             storage_rotate_count: NUM_HASHES_FOR_STORAGE_ROTATE,
+            blob_compression: false,
         }
     }
 }
@@ -93,6 +95,7 @@ pub struct Fullnode {
     bank: Arc<Bank>,
     cluster_info: Arc<RwLock<ClusterInfo>>,
     sigverify_disabled: bool,
+    blob_compression: bool,
     tpu_sockets: Vec<UdpSocket>,
     broadcast_socket: UdpSocket,
     pub node_services: NodeServices,
@@ -110,8 +113,12 @@ impl Fullnode {
         config: FullnodeConfig,
     ) -> Self {
         let (genesis_block, db_ledger) = Self::make_db_ledger(ledger_path);
-        let (bank, entry_height, last_entry_id) =
-            Self::new_bank_from_db_ledger(&genesis_block, &db_ledger, leader_scheduler);
+        let (bank, entry_height, last_entry_id) = Self::new_bank_from_db_ledger(
+            &genesis_block,
+            &db_ledger,
+            leader_scheduler,
+            config.sigverify_disabled,
+        );
 
         info!("node info: {:?}", node.info);
         info!("node entrypoint_info: {:?}", entrypoint_info_option);
@@ -247,6 +254,7 @@ impl Fullnode {
             keypair.pubkey(),
             scheduled_leader == keypair.pubkey(),
             &to_validator_sender,
+            config.blob_compression,
         );
 
         inc_new_counter_info!("fullnode-new", 1);
@@ -256,6 +264,7 @@ impl Fullnode {
             cluster_info,
             bank,
             sigverify_disabled: config.sigverify_disabled,
+            blob_compression: config.blob_compression,
             gossip_service,
             rpc_service: Some(rpc_service),
             rpc_pubsub_service: Some(rpc_pubsub_service),
@@ -326,6 +335,7 @@ impl Fullnode {
             &last_id,
             self.keypair.pubkey(),
             &to_validator_sender,
+            self.blob_compression,
         )
     }
 
@@ -375,8 +385,9 @@ impl Fullnode {
         genesis_block: &GenesisBlock,
         db_ledger: &DbLedger,
         leader_scheduler: Arc<RwLock<LeaderScheduler>>,
+        sigverify_disabled: bool,
     ) -> (Bank, u64, Hash) {
-        let mut bank = Bank::new(genesis_block);
+        let mut bank = Bank::new(genesis_block).expect("creating bank from the genesis block");
         leader_scheduler.write().unwrap().bootstrap_leader = genesis_block.bootstrap_leader_id;
         bank.leader_scheduler = leader_scheduler;
 
@@ -384,7 +395,9 @@ impl Fullnode {
         let entries = db_ledger.read_ledger().expect("opening ledger");
         info!("processing ledger...");
 
-        let (entry_height, last_entry_id) = bank.process_ledger(entries).expect("process_ledger");
+        let (entry_height, last_entry_id) = bank
+            .process_ledger_with_sigverify(entries, !sigverify_disabled)
+            .expect("process_ledger");
         // entry_height is the network-wide agreed height of the ledger.
         //  initialize it from the input ledger
         info!(
@@ -400,7 +413,7 @@ impl Fullnode {
         leader_scheduler: Arc<RwLock<LeaderScheduler>>,
     ) -> (Bank, u64, Hash) {
         let (genesis_block, db_ledger) = Self::make_db_ledger(ledger_path);
-        Self::new_bank_from_db_ledger(&genesis_block, &db_ledger, leader_scheduler)
+        Self::new_bank_from_db_ledger(&genesis_block, &db_ledger, leader_scheduler, false)
     }
 
     pub fn get_leader_scheduler(&self) -> &Arc<RwLock<LeaderScheduler>> {
@@ -1,6 +1,9 @@
 //! The `genesis_block` module is a library for generating the chain's genesis block.
 
+use crate::bank::builtin_native_programs;
+use solana_native_loader;
 use solana_sdk::hash::{hash, Hash};
+use solana_sdk::native_loader::NativeProgram;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, KeypairUtil};
 use std::fs::File;
@@ -13,6 +16,11 @@ pub struct GenesisBlock {
     pub bootstrap_leader_tokens: u64,
     pub mint_id: Pubkey,
     pub tokens: u64,
+    /// The builtin native programs whose shared libraries are pinned to a code hash. A validator
+    /// refuses to start if its libraries don't match. Left out of the JSON when empty, so that
+    /// the `last_id` of a genesis block written before programs were pinned doesn't change
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub native_programs: Vec<NativeProgram>,
 }
 
 impl GenesisBlock {
@@ -25,6 +33,7 @@ impl GenesisBlock {
                 bootstrap_leader_tokens: 0,
                 mint_id: mint_keypair.pubkey(),
                 tokens,
+                native_programs: vec![],
             },
             mint_keypair,
         )
@@ -42,11 +51,28 @@ impl GenesisBlock {
                 bootstrap_leader_tokens,
                 mint_id: mint_keypair.pubkey(),
                 tokens,
+                native_programs: vec![],
             },
             mint_keypair,
         )
     }
 
+    /// Pin the builtin native programs to the shared libraries next to the current executable
+    pub fn pin_native_programs(&mut self) -> Result<(), std::io::Error> {
+        self.native_programs = builtin_native_programs()
+            .into_iter()
+            .map(|(name, _)| {
+                let code_hash = solana_native_loader::hash_library(name)?;
+                Ok(NativeProgram::new(
+                    name,
+                    env!("CARGO_PKG_VERSION"),
+                    Some(code_hash),
+                ))
+            })
+            .collect::<Result<_, std::io::Error>>()?;
+        Ok(())
+    }
+
     pub fn last_id(&self) -> Hash {
         let serialized = serde_json::to_string(self).unwrap();
         hash(&serialized.into_bytes())
@@ -89,4 +115,18 @@ mod tests {
         assert_eq!(genesis_block.bootstrap_leader_id, leader_keypair.pubkey());
         assert_eq!(genesis_block.bootstrap_leader_tokens, 123);
     }
+
+    #[test]
+    fn test_genesis_block_last_id_without_native_programs() {
+        let leader_id = Keypair::new().pubkey();
+        let mint_id = Keypair::new().pubkey();
+        let json = format!(
+            r#"{{"bootstrap_leader_id":{},"bootstrap_leader_tokens":1,"mint_id":{},"tokens":2}}"#,
+            serde_json::to_string(&leader_id).unwrap(),
+            serde_json::to_string(&mint_id).unwrap(),
+        );
+        let genesis_block: GenesisBlock = serde_json::from_str(&json).unwrap();
+        assert!(genesis_block.native_programs.is_empty());
+        assert_eq!(genesis_block.last_id(), hash(json.as_bytes()));
+    }
 }
@@ -7,10 +7,11 @@ use crate::entry::{create_ticks, Entry};
 use crate::vote_signer_proxy::VoteSignerProxy;
 use bincode::serialize;
 use byteorder::{LittleEndian, ReadBytesExt};
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use solana_sdk::hash::{hash, Hash};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, KeypairUtil};
+use solana_sdk::stake_program::{self, StakeState};
 use solana_sdk::system_transaction::SystemTransaction;
 use solana_sdk::transaction::Transaction;
 use solana_sdk::vote_program::{self, VoteProgram};
@@ -300,7 +301,7 @@ impl LeaderScheduler {
 
     // Maps the nth slot (where n == slot_height) to the tick height of
     // the first tick for that slot
-    fn slot_height_to_first_tick_height(&self, slot_height: u64) -> u64 {
+    pub fn slot_height_to_first_tick_height(&self, slot_height: u64) -> u64 {
         if slot_height == 0 {
             0
         } else {
@@ -308,6 +309,22 @@ impl LeaderScheduler {
         }
     }
 
+    // Maps the nth slot (where n == slot_height) to the tick height of
+    // the last tick for that slot
+    pub fn slot_height_to_last_tick_height(&self, slot_height: u64) -> u64 {
+        slot_height * self.leader_rotation_interval + self.bootstrap_height
+    }
+
+    // Maps a tick height to the slot that tick belongs to. The bootstrapping
+    // period, up to and including bootstrap_height, is slot 0
+    pub fn tick_height_to_slot(&self, height: u64) -> u64 {
+        if height <= self.bootstrap_height {
+            0
+        } else {
+            (height - self.bootstrap_height - 1) / self.leader_rotation_interval + 1
+        }
+    }
+
     // TODO: We use a HashSet for now because a single validator could potentially register
     // multiple vote account. Once that is no longer possible (see the TODO in vote_program.rs,
     // process_transaction(), case VoteInstruction::RegisterAccount), we can use a vector.
@@ -329,8 +346,11 @@ impl LeaderScheduler {
                                 .votes
                                 .back()
                                 .filter(|vote| {
-                                    vote.tick_height > lower_bound
-                                        && vote.tick_height <= upper_bound
+                                    // A vote keeps the validator active through the end of
+                                    // the slot it was cast for
+                                    let vote_height =
+                                        self.slot_height_to_last_tick_height(vote.slot);
+                                    vote_height > lower_bound && vote_height <= upper_bound
                                 })
                                 .map(|_| vote_state.node_id);
                         }
@@ -350,7 +370,7 @@ impl LeaderScheduler {
         let seed = Self::calculate_seed(height);
         self.seed = seed;
         let active_set = self.get_active_set(height, &bank);
-        let ranked_active_set = Self::rank_active_set(bank, active_set.iter());
+        let ranked_active_set = Self::rank_active_set(bank, height, active_set.iter());
 
         // Handle case where there are no active validators with
         // non-zero stake. In this case, use the bootstrap leader for
@@ -408,13 +428,42 @@ impl LeaderScheduler {
         self.last_seed_height = Some(height);
     }
 
-    fn rank_active_set<'a, I>(bank: &Bank, active: I) -> Vec<(&'a Pubkey, u64)>
+    // Returns the stake delegated to each validator that is active at `height`, keyed by the
+    // node id of the "vote account" the stake is delegated to
+    fn get_delegated_stakes(bank: &Bank, height: u64) -> HashMap<Pubkey, u64> {
+        let accounts = bank.accounts.accounts_db.read().unwrap();
+
+        let mut delegated_stakes = HashMap::new();
+        for (_, account) in accounts.stake_accounts() {
+            if let Ok(stake_state) = StakeState::deserialize(&account.userdata) {
+                if let StakeState::Delegated { voter_id, .. } = &stake_state {
+                    let node_id = accounts
+                        .accounts
+                        .get(voter_id)
+                        .filter(|vote_account| vote_program::check_id(&vote_account.owner))
+                        .and_then(|vote_account| {
+                            VoteProgram::deserialize(&vote_account.userdata).ok()
+                        })
+                        .map(|vote_state| vote_state.node_id);
+                    if let Some(node_id) = node_id {
+                        *delegated_stakes.entry(node_id).or_insert(0) +=
+                            stake_state.active_stake(height);
+                    }
+                }
+            }
+        }
+        delegated_stakes
+    }
+
+    fn rank_active_set<'a, I>(bank: &Bank, height: u64, active: I) -> Vec<(&'a Pubkey, u64)>
     where
         I: Iterator<Item = &'a Pubkey>,
     {
+        let delegated_stakes = Self::get_delegated_stakes(bank, height);
         let mut active_accounts: Vec<(&'a Pubkey, u64)> = active
             .filter_map(|pk| {
-                let stake = bank.get_balance(pk);
+                // A validator's own balance counts as stake delegated to itself
+                let stake = bank.get_balance(pk) + delegated_stakes.get(pk).cloned().unwrap_or(0);
                 if stake > 0 {
                     Some((pk, stake as u64))
                 } else {
@@ -498,8 +547,8 @@ pub fn make_active_set_entries(
     let new_vote_account_entry = Entry::new(&last_entry_id, 0, 1, vec![new_vote_account_tx]);
     last_entry_id = new_vote_account_entry.id;
 
-    // 3) Create vote entry
-    let vote_tx = Transaction::vote_new(&vote_signer, 1, *last_tick_id, 0);
+    // 3) Create vote entry for the bootstrap slot
+    let vote_tx = Transaction::vote_new(&vote_signer, 0, *last_tick_id, *last_tick_id, 0);
     let vote_entry = Entry::new(&last_entry_id, 0, 1, vec![vote_tx]);
     last_entry_id = vote_entry.id;
 
@@ -521,9 +570,11 @@ pub mod tests {
     };
     use crate::vote_signer_proxy::VoteSignerProxy;
     use hashbrown::HashSet;
+    use solana_sdk::account::Account;
     use solana_sdk::hash::Hash;
     use solana_sdk::pubkey::Pubkey;
     use solana_sdk::signature::{Keypair, KeypairUtil};
+    use solana_sdk::stake_program::STAKE_WARMUP_TICKS;
     use std::hash::Hash as StdHash;
     use std::iter::FromIterator;
     use std::sync::Arc;
@@ -552,8 +603,8 @@ pub mod tests {
         bank.process_transaction(&tx).unwrap();
     }
 
-    fn push_vote(vote_signer: &VoteSignerProxy, bank: &Bank, height: u64, last_id: Hash) {
-        let new_vote_tx = Transaction::vote_new(vote_signer, height, last_id, 0);
+    fn push_vote(vote_signer: &VoteSignerProxy, bank: &Bank, slot: u64, last_id: Hash) {
+        let new_vote_tx = Transaction::vote_new(vote_signer, slot, last_id, last_id, 0);
         bank.process_transaction(&new_vote_tx).unwrap();
     }
 
@@ -584,7 +635,7 @@ pub mod tests {
             (((num_validators + 1) / 2) * (num_validators + 1)
                 + num_vote_account_tokens * num_validators) as u64,
         );
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let mut validators = vec![];
         let last_id = genesis_block.last_id();
         for i in 0..num_validators {
@@ -612,7 +663,7 @@ pub mod tests {
 
             // Vote to make the validator part of the active set for the entire test
             // (we made the active_window_length large enough at the beginning of the test)
-            push_vote(&vote_signer, &bank, 1, genesis_block.last_id());
+            push_vote(&vote_signer, &bank, 0, genesis_block.last_id());
         }
 
         // The scheduled leader during the bootstrapping period (assuming a seed + schedule
@@ -696,7 +747,7 @@ pub mod tests {
         let leader_id = Keypair::new().pubkey();
         let active_window_length = 1000;
         let (genesis_block, mint_keypair) = GenesisBlock::new_with_leader(10000, leader_id, 500);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         let leader_scheduler_config =
             LeaderSchedulerConfig::new(100, 100, 100, active_window_length);
@@ -704,8 +755,9 @@ pub mod tests {
         let mut leader_scheduler = LeaderScheduler::new(&leader_scheduler_config);
         leader_scheduler.bootstrap_leader = leader_id;
 
-        // Insert a bunch of votes at height "start_height"
-        let start_height = 3;
+        // Insert a bunch of votes for slot "start_slot", which ends at height "start_height"
+        let start_slot = 1;
+        let start_height = leader_scheduler.slot_height_to_last_tick_height(start_slot);
         let num_old_ids = 20;
         let mut old_ids = HashSet::new();
         for _ in 0..num_old_ids {
@@ -728,10 +780,13 @@ pub mod tests {
             );
 
             // Push a vote for the account
-            push_vote(&vote_signer, &bank, start_height, genesis_block.last_id());
+            push_vote(&vote_signer, &bank, start_slot, genesis_block.last_id());
         }
 
-        // Insert a bunch of votes at height "start_height + active_window_length"
+        // Insert a bunch of votes for the slot ending at height
+        // "start_height + active_window_length"
+        let new_slot =
+            start_slot + active_window_length / leader_scheduler.leader_rotation_interval;
         let num_new_ids = 10;
         let mut new_ids = HashSet::new();
         for _ in 0..num_new_ids {
@@ -752,12 +807,7 @@ pub mod tests {
                 genesis_block.last_id(),
             );
 
-            push_vote(
-                &vote_signer,
-                &bank,
-                start_height + active_window_length,
-                genesis_block.last_id(),
-            );
+            push_vote(&vote_signer, &bank, new_slot, genesis_block.last_id());
         }
 
         // Queries for the active set
@@ -795,7 +845,7 @@ pub mod tests {
         // Give genesis_block sum(1..num_validators) tokens
         let (genesis_block, mint_keypair) =
             GenesisBlock::new((((num_validators + 1) / 2) * (num_validators + 1)) as u64);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let mut validators = vec![];
         let last_id = genesis_block.last_id();
         for i in 0..num_validators {
@@ -812,7 +862,7 @@ pub mod tests {
         }
 
         let validators_pk: Vec<Pubkey> = validators.iter().map(Keypair::pubkey).collect();
-        let result = LeaderScheduler::rank_active_set(&bank, validators_pk.iter());
+        let result = LeaderScheduler::rank_active_set(&bank, 0, validators_pk.iter());
 
         assert_eq!(result.len(), validators.len());
 
@@ -843,7 +893,7 @@ pub mod tests {
             .chain(new_validators.iter())
             .map(Keypair::pubkey)
             .collect();
-        let result = LeaderScheduler::rank_active_set(&bank, all_validators.iter());
+        let result = LeaderScheduler::rank_active_set(&bank, 0, all_validators.iter());
         assert_eq!(result.len(), new_validators.len());
 
         for (i, (pk, balance)) in result.into_iter().enumerate() {
@@ -853,7 +903,7 @@ pub mod tests {
 
         // Break ties between validators with the same balances using public key
         let (genesis_block, mint_keypair) = GenesisBlock::new(num_validators as u64);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let mut tied_validators_pk = vec![];
         let last_id = genesis_block.last_id();
 
@@ -865,7 +915,7 @@ pub mod tests {
                 .unwrap();
         }
 
-        let result = LeaderScheduler::rank_active_set(&bank, tied_validators_pk.iter());
+        let result = LeaderScheduler::rank_active_set(&bank, 0, tied_validators_pk.iter());
         let mut sorted: Vec<&Pubkey> = tied_validators_pk.iter().map(|x| x).collect();
         sorted.sort_by(|pk1, pk2| pk1.cmp(pk2));
         assert_eq!(result.len(), tied_validators_pk.len());
@@ -875,6 +925,47 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_rank_active_set_with_delegated_stake() {
+        let (genesis_block, mint_keypair) = GenesisBlock::new(10_000);
+        let bank = Bank::new(&genesis_block).unwrap();
+
+        let validators: Vec<_> = (0..2).map(|_| Keypair::new().pubkey()).collect();
+        for validator in &validators {
+            bank.transfer(1, &mint_keypair, *validator, genesis_block.last_id())
+                .unwrap();
+        }
+
+        // Delegate stake to the "vote account" of the first validator
+        let vote_account_id = Keypair::new().pubkey();
+        let mut vote_account = Account::new(1, vote_program::get_max_size(), vote_program::id());
+        VoteProgram::new(validators[0], vote_account_id)
+            .serialize(&mut vote_account.userdata)
+            .unwrap();
+        bank.accounts
+            .store_slow(true, &vote_account_id, &vote_account);
+
+        let stake_account_id = Keypair::new().pubkey();
+        let mut stake_account =
+            Account::new(10, stake_program::get_max_size(), stake_program::id());
+        let stake_state = StakeState::Delegated {
+            voter_id: vote_account_id,
+            stake: 10,
+            activation_height: 0,
+            deactivation_height: None,
+        };
+        stake_state.serialize(&mut stake_account.userdata).unwrap();
+        bank.accounts
+            .store_slow(true, &stake_account_id, &stake_account);
+
+        // The delegated stake counts once it has warmed up
+        let result = LeaderScheduler::rank_active_set(&bank, 0, validators.iter());
+        assert!(result.iter().all(|(_, stake)| *stake == 1));
+
+        let result = LeaderScheduler::rank_active_set(&bank, STAKE_WARMUP_TICKS, validators.iter());
+        assert_eq!(result, vec![(&validators[1], 1), (&validators[0], 11)]);
+    }
+
     #[test]
     fn test_choose_account() {
         let tokens = vec![10, 30, 50, 5, 1];
@@ -1003,7 +1094,7 @@ pub mod tests {
             ((((num_validators + 1) / 2) * (num_validators + 1))
                 + (num_vote_account_tokens * num_validators)) as u64,
         );
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let mut validators = vec![];
         let last_id = genesis_block.last_id();
         for i in 0..num_validators {
@@ -1029,11 +1120,11 @@ pub mod tests {
                 genesis_block.last_id(),
             );
 
-            // Vote at height i * active_window_length for validator i
+            // Vote in the slot ending at height i * active_window_length for validator i
             push_vote(
                 &vote_signer,
                 &bank,
-                i * active_window_length + bootstrap_height,
+                leader_scheduler.tick_height_to_slot(i * active_window_length + bootstrap_height),
                 genesis_block.last_id(),
             );
         }
@@ -1060,7 +1151,7 @@ pub mod tests {
         let leader_id = leader_keypair.pubkey();
         let active_window_length = 1000;
         let (genesis_block, _mint_keypair) = GenesisBlock::new_with_leader(10000, leader_id, 500);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         let leader_scheduler_config =
             LeaderSchedulerConfig::new(100, 100, 100, active_window_length);
@@ -1070,7 +1161,7 @@ pub mod tests {
 
         // Check that a node that votes twice in a row will get included in the active
         // window
-        let initial_vote_height = 1;
+        let initial_vote_slot = 1;
 
         let vote_signer = VoteSignerProxy::new_local(&leader_keypair);
         // Create a vote account
@@ -1086,21 +1177,24 @@ pub mod tests {
         push_vote(
             &vote_signer,
             &bank,
-            initial_vote_height,
+            initial_vote_slot,
             genesis_block.last_id(),
         );
         push_vote(
             &vote_signer,
             &bank,
-            initial_vote_height + 1,
+            initial_vote_slot + 1,
             genesis_block.last_id(),
         );
 
+        // The second vote keeps the node active through the end of its slot
+        let last_vote_height =
+            leader_scheduler.slot_height_to_last_tick_height(initial_vote_slot + 1);
         let result =
-            leader_scheduler.get_active_set(initial_vote_height + active_window_length, &bank);
+            leader_scheduler.get_active_set(last_vote_height + active_window_length - 1, &bank);
         assert_eq!(result, to_hashset_owned(&vec![leader_id]));
         let result =
-            leader_scheduler.get_active_set(initial_vote_height + active_window_length + 1, &bank);
+            leader_scheduler.get_active_set(last_vote_height + active_window_length, &bank);
         assert!(result.is_empty());
     }
 
@@ -1212,9 +1306,9 @@ pub mod tests {
         // Create mint and bank
         let (genesis_block, mint_keypair) =
             GenesisBlock::new_with_leader(10000, bootstrap_leader_id, 0);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let last_id = genesis_block.last_id();
-        let initial_vote_height = 1;
+        let initial_vote_slot = 0;
 
         // Create and add validator to the active set
         let validator_keypair = Arc::new(Keypair::new());
@@ -1235,7 +1329,7 @@ pub mod tests {
             push_vote(
                 &vote_signer,
                 &bank,
-                initial_vote_height,
+                initial_vote_slot,
                 genesis_block.last_id(),
             );
         }
@@ -1271,7 +1365,7 @@ pub mod tests {
         push_vote(
             &vote_signer,
             &bank,
-            initial_vote_height,
+            initial_vote_slot,
             genesis_block.last_id(),
         );
 
@@ -1321,9 +1415,9 @@ pub mod tests {
         // Create mint and bank
         let (genesis_block, mint_keypair) =
             GenesisBlock::new_with_leader(10000, bootstrap_leader_id, 0);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let last_id = genesis_block.last_id();
-        let initial_vote_height = 1;
+        let initial_vote_slot = 0;
 
         // No schedule generated yet, so for all heights <= bootstrap height, the
         // max height will be bootstrap height
@@ -1397,7 +1491,7 @@ pub mod tests {
         push_vote(
             &vote_signer,
             &bank,
-            initial_vote_height,
+            initial_vote_slot,
             genesis_block.last_id(),
         );
 
@@ -1417,7 +1511,7 @@ pub mod tests {
         push_vote(
             &vote_signer,
             &bank,
-            initial_vote_height,
+            initial_vote_slot,
             genesis_block.last_id(),
         );
 
@@ -0,0 +1,550 @@
+//! The `ledger_archive` module exports a ledger to, and rebuilds a `DbLedger` from, a
+//! portable archive that does not depend on the RocksDB on-disk format.
+//!
+//! An archive is a magic string and a format version followed by a stream of records:
+//!
+//! ```text
+//! header: "SOLLEDGR" | version: u16
+//! record: kind: u8 | length: u32 | payload: [u8; length] | checksum: [u8; 32]
+//! ```
+//!
+//! Integers are little-endian. The checksum of a record is the SHA-256 of the previous
+//! record's checksum (zeros for the first record), the kind, the length and the payload,
+//! so reordered, altered and dropped records are all detected. The first record is the
+//! genesis block as JSON, followed by entries, optional coding blobs, and a final record
+//! holding the number of entries and coding blobs in the archive. An entry record is the
+//! entry's slot as a u64 followed by the entry as bincode. Version 1 archives carried no
+//! slot, so their entries are imported at `DEFAULT_SLOT_HEIGHT`.
+
+use crate::db_ledger::{create_empty_ledger, DbLedger, DEFAULT_SLOT_HEIGHT};
+use crate::entry::Entry;
+use crate::genesis_block::GenesisBlock;
+use crate::result::{Error, Result};
+use bincode::{deserialize, serialize};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use solana_sdk::hash::{hashv, Hash};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const ARCHIVE_MAGIC: &[u8; 8] = b"SOLLEDGR";
+pub const ARCHIVE_VERSION: u16 = 2;
+
+/// Number of entries written to the `DbLedger` at a time during import
+const IMPORT_BATCH_SIZE: usize = 1024;
+
+/// Largest record payload accepted on import; comfortably larger than any blob
+const MAX_RECORD_SIZE: u32 = 64 * 1024 * 1024;
+
+const GENESIS_RECORD: u8 = 1;
+const ENTRY_RECORD: u8 = 2;
+const CODING_RECORD: u8 = 3;
+const END_RECORD: u8 = 0xff;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LedgerArchiveError {
+    /// The stream does not start with `ARCHIVE_MAGIC`
+    NotAnArchive,
+    /// The archive was written by a newer, incompatible version
+    UnsupportedVersion(u16),
+    /// The record at this position failed checksum verification
+    ChecksumMismatch(u64),
+    /// The record at this position is of an unknown kind or out of place
+    UnexpectedRecord(u64),
+    /// The archive ended without its final record, or its counts don't match
+    Truncated,
+    /// The ledger path to import to already holds files
+    LedgerExists,
+}
+
+/// A record following the genesis block in an archive
+#[derive(Debug, PartialEq)]
+pub enum ArchiveRecord {
+    Entry {
+        slot: u64,
+        entry: Entry,
+    },
+    Coding {
+        slot: u64,
+        index: u64,
+        blob: Vec<u8>,
+    },
+}
+
+/// Writes an archive record by record to any `Write`
+pub struct ArchiveWriter<W: Write> {
+    writer: W,
+    checksum: Hash,
+    num_entries: u64,
+    num_coding: u64,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    pub fn new(mut writer: W, genesis_block: &GenesisBlock) -> Result<Self> {
+        writer.write_all(ARCHIVE_MAGIC)?;
+        writer.write_u16::<LittleEndian>(ARCHIVE_VERSION)?;
+        let mut archive_writer = ArchiveWriter {
+            writer,
+            checksum: Hash::default(),
+            num_entries: 0,
+            num_coding: 0,
+        };
+        archive_writer.write_record(GENESIS_RECORD, &serde_json::to_vec(genesis_block)?)?;
+        Ok(archive_writer)
+    }
+
+    fn write_record(&mut self, kind: u8, payload: &[u8]) -> Result<()> {
+        let mut len = [0u8; 4];
+        (&mut len[..]).write_u32::<LittleEndian>(payload.len() as u32)?;
+        self.checksum = hashv(&[self.checksum.as_ref(), &[kind], &len, payload]);
+
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&len)?;
+        self.writer.write_all(payload)?;
+        self.writer.write_all(self.checksum.as_ref())?;
+        Ok(())
+    }
+
+    pub fn write_entry(&mut self, slot: u64, entry: &Entry) -> Result<()> {
+        let mut payload = vec![];
+        payload.write_u64::<LittleEndian>(slot)?;
+        payload.extend_from_slice(&serialize(entry)?);
+        self.write_record(ENTRY_RECORD, &payload)?;
+        self.num_entries += 1;
+        Ok(())
+    }
+
+    pub fn write_coding_blob(&mut self, slot: u64, index: u64, blob: &[u8]) -> Result<()> {
+        let mut payload = Vec::with_capacity(16 + blob.len());
+        payload.write_u64::<LittleEndian>(slot)?;
+        payload.write_u64::<LittleEndian>(index)?;
+        payload.extend_from_slice(blob);
+        self.write_record(CODING_RECORD, &payload)?;
+        self.num_coding += 1;
+        Ok(())
+    }
+
+    /// Write the final record and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let mut payload = vec![];
+        payload.write_u64::<LittleEndian>(self.num_entries)?;
+        payload.write_u64::<LittleEndian>(self.num_coding)?;
+        self.write_record(END_RECORD, &payload)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads and verifies an archive record by record from any `Read`
+pub struct ArchiveReader<R: Read> {
+    reader: R,
+    version: u16,
+    checksum: Hash,
+    position: u64,
+    num_entries: u64,
+    num_coding: u64,
+    finished: bool,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// Check the archive header and read its genesis block
+    pub fn new(mut reader: R) -> Result<(Self, GenesisBlock)> {
+        let mut magic = [0u8; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| LedgerArchiveError::NotAnArchive)?;
+        if &magic != ARCHIVE_MAGIC {
+            Err(LedgerArchiveError::NotAnArchive)?;
+        }
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version > ARCHIVE_VERSION {
+            Err(LedgerArchiveError::UnsupportedVersion(version))?;
+        }
+
+        let mut archive_reader = ArchiveReader {
+            reader,
+            version,
+            checksum: Hash::default(),
+            position: 0,
+            num_entries: 0,
+            num_coding: 0,
+            finished: false,
+        };
+        match archive_reader.read_record()? {
+            (GENESIS_RECORD, payload) => {
+                let genesis_block = serde_json::from_slice(&payload)?;
+                Ok((archive_reader, genesis_block))
+            }
+            _ => Err(LedgerArchiveError::UnexpectedRecord(0))?,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<(u8, Vec<u8>)> {
+        let kind = self
+            .reader
+            .read_u8()
+            .map_err(|_| LedgerArchiveError::Truncated)?;
+        let mut len = [0u8; 4];
+        self.reader
+            .read_exact(&mut len)
+            .map_err(|_| LedgerArchiveError::Truncated)?;
+        let payload_len = (&len[..]).read_u32::<LittleEndian>()?;
+        if payload_len > MAX_RECORD_SIZE {
+            Err(LedgerArchiveError::UnexpectedRecord(self.position))?;
+        }
+        let mut payload = vec![0u8; payload_len as usize];
+        let mut checksum = [0u8; 32];
+        self.reader
+            .read_exact(&mut payload)
+            .and_then(|_| self.reader.read_exact(&mut checksum))
+            .map_err(|_| LedgerArchiveError::Truncated)?;
+
+        let expected = hashv(&[self.checksum.as_ref(), &[kind], &len, &payload]);
+        if expected.as_ref() != &checksum[..] {
+            Err(LedgerArchiveError::ChecksumMismatch(self.position))?;
+        }
+        self.checksum = expected;
+        self.position += 1;
+        Ok((kind, payload))
+    }
+
+    fn next_record(&mut self) -> Result<Option<ArchiveRecord>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let position = self.position;
+        let (kind, mut payload) = self.read_record()?;
+        match kind {
+            ENTRY_RECORD => {
+                let (slot, entry) = if self.version == 1 {
+                    (DEFAULT_SLOT_HEIGHT, &payload[..])
+                } else {
+                    if payload.len() < 8 {
+                        Err(LedgerArchiveError::UnexpectedRecord(position))?;
+                    }
+                    let (mut slot, entry) = payload.split_at(8);
+                    (slot.read_u64::<LittleEndian>()?, entry)
+                };
+                self.num_entries += 1;
+                Ok(Some(ArchiveRecord::Entry {
+                    slot,
+                    entry: deserialize(entry)?,
+                }))
+            }
+            CODING_RECORD => {
+                if payload.len() < 16 {
+                    Err(LedgerArchiveError::UnexpectedRecord(position))?;
+                }
+                let blob = payload.split_off(16);
+                let mut header = &payload[..];
+                let slot = header.read_u64::<LittleEndian>()?;
+                let index = header.read_u64::<LittleEndian>()?;
+                self.num_coding += 1;
+                Ok(Some(ArchiveRecord::Coding { slot, index, blob }))
+            }
+            END_RECORD => {
+                let mut counts = &payload[..];
+                let num_entries = counts.read_u64::<LittleEndian>()?;
+                let num_coding = counts.read_u64::<LittleEndian>()?;
+                if num_entries != self.num_entries || num_coding != self.num_coding {
+                    Err(LedgerArchiveError::Truncated)?;
+                }
+                self.finished = true;
+                Ok(None)
+            }
+            _ => Err(LedgerArchiveError::UnexpectedRecord(position))?,
+        }
+    }
+}
+
+impl<R: Read> Iterator for ArchiveReader<R> {
+    type Item = Result<ArchiveRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => {
+                // Stop at the first bad record, nothing after it can be trusted
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Write the genesis block and all entries of the ledger at `ledger_path`, and its
+/// coding blobs if `include_coding` is set, as an archive to `writer`.
+/// Returns the number of entries exported.
+pub fn export_ledger<W: Write>(ledger_path: &str, writer: W, include_coding: bool) -> Result<u64> {
+    let genesis_block = GenesisBlock::load(ledger_path)?;
+    let db_ledger = DbLedger::open(ledger_path)?;
+
+    let mut archive_writer = ArchiveWriter::new(writer, &genesis_block)?;
+    for (slot, entry) in db_ledger.read_ledger_with_slots()? {
+        archive_writer.write_entry(slot, &entry)?;
+    }
+    if include_coding {
+        for (slot, index, blob) in db_ledger.read_coding_blobs()? {
+            archive_writer.write_coding_blob(slot, index, &blob)?;
+        }
+    }
+    let num_entries = archive_writer.num_entries;
+    archive_writer.finish()?;
+    Ok(num_entries)
+}
+
+/// Create a new ledger at `ledger_path` from the archive read from `reader`. `ledger_path`
+/// must not exist or be an empty directory. The ledger is built in a temporary directory
+/// next to it, which only replaces `ledger_path` once the whole archive has verified.
+/// Returns the archive's genesis block and the number of entries imported.
+pub fn import_ledger<R: Read>(reader: R, ledger_path: &str) -> Result<(GenesisBlock, u64)> {
+    let target = Path::new(ledger_path);
+    if target.exists() && (!target.is_dir() || fs::read_dir(target)?.next().is_some()) {
+        Err(LedgerArchiveError::LedgerExists)?;
+    }
+
+    let (archive_reader, genesis_block) = ArchiveReader::new(reader)?;
+    let import_path = format!("{}.import", ledger_path.trim_end_matches('/'));
+    let _ignored = fs::remove_dir_all(&import_path);
+
+    let result = write_archive_records(archive_reader, &genesis_block, &import_path);
+    let result = result.and_then(|num_entries| {
+        if target.exists() {
+            fs::remove_dir(target)?;
+        }
+        fs::rename(&import_path, target)?;
+        Ok(num_entries)
+    });
+    match result {
+        Ok(num_entries) => Ok((genesis_block, num_entries)),
+        Err(err) => {
+            let _ignored = fs::remove_dir_all(&import_path);
+            Err(err)
+        }
+    }
+}
+
+/// Write the records of `archive_reader` to a new ledger at `ledger_path`, failing if the
+/// archive doesn't verify through its final record
+fn write_archive_records<R: Read>(
+    archive_reader: ArchiveReader<R>,
+    genesis_block: &GenesisBlock,
+    ledger_path: &str,
+) -> Result<u64> {
+    create_empty_ledger(ledger_path, genesis_block)?;
+    let db_ledger = DbLedger::open(ledger_path)?;
+
+    let mut num_entries = 0;
+    let mut entries = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut entries_slot = DEFAULT_SLOT_HEIGHT;
+    for record in archive_reader {
+        match record? {
+            ArchiveRecord::Entry { slot, entry } => {
+                // A batch is written at a single slot, so a new slot starts a new batch
+                if entries.len() == IMPORT_BATCH_SIZE
+                    || (!entries.is_empty() && slot != entries_slot)
+                {
+                    db_ledger.write_entries(entries_slot, num_entries, &entries)?;
+                    num_entries += entries.len() as u64;
+                    entries.clear();
+                }
+                entries_slot = slot;
+                entries.push(entry);
+            }
+            ArchiveRecord::Coding { slot, index, blob } => {
+                db_ledger.put_coding_blob_bytes(slot, index, &blob)?;
+            }
+        }
+    }
+    if !entries.is_empty() {
+        db_ledger.write_entries(entries_slot, num_entries, &entries)?;
+        num_entries += entries.len() as u64;
+    }
+
+    Ok(num_entries)
+}
+
+impl std::convert::From<LedgerArchiveError> for Error {
+    fn from(e: LedgerArchiveError) -> Error {
+        Error::LedgerArchiveError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_ledger::{create_tmp_sample_ledger, get_tmp_ledger_path};
+    use crate::entry::make_tiny_test_entries_from_id;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
+    use std::fs::remove_dir_all;
+    use std::path::Path;
+
+    fn sample_archive(include_coding: bool) -> (String, Vec<(u64, Entry)>, Vec<u8>) {
+        let (_genesis_block, _mint, ledger_path, entries) =
+            create_tmp_sample_ledger("test_ledger_archive", 10_000, 3, Keypair::new().pubkey(), 1);
+        let more_entries = make_tiny_test_entries_from_id(&entries.last().unwrap().id, 5);
+        let db_ledger = DbLedger::open(&ledger_path).unwrap();
+        db_ledger
+            .write_entries(1, entries.len() as u64, &more_entries)
+            .unwrap();
+        db_ledger.put_coding_blob_bytes(0, 1, &[1, 2, 3]).unwrap();
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| (DEFAULT_SLOT_HEIGHT, entry))
+            .collect();
+        entries.extend(more_entries.into_iter().map(|entry| (1, entry)));
+
+        let mut archive = vec![];
+        let num_entries = export_ledger(&ledger_path, &mut archive, include_coding).unwrap();
+        assert_eq!(num_entries, entries.len() as u64);
+        (ledger_path, entries, archive)
+    }
+
+    #[test]
+    fn test_ledger_archive_round_trip() {
+        let (ledger_path, entries, archive) = sample_archive(true);
+        let imported_path = get_tmp_ledger_path("test_ledger_archive_round_trip");
+
+        let (genesis_block, num_entries) = import_ledger(&archive[..], &imported_path).unwrap();
+        assert_eq!(num_entries, entries.len() as u64);
+        assert_eq!(
+            genesis_block.last_id(),
+            GenesisBlock::load(&ledger_path).unwrap().last_id()
+        );
+
+        let db_ledger = DbLedger::open(&imported_path).unwrap();
+        let imported_entries: Vec<_> = db_ledger.read_ledger_with_slots().unwrap().collect();
+        assert_eq!(imported_entries, entries);
+        assert_eq!(
+            db_ledger.get_coding_blob_bytes(0, 1).unwrap(),
+            Some(vec![1, 2, 3])
+        );
+
+        // Exporting the imported ledger reproduces the archive byte for byte
+        let mut archive_again = vec![];
+        export_ledger(&imported_path, &mut archive_again, true).unwrap();
+        assert_eq!(archive_again, archive);
+
+        DbLedger::destroy(&ledger_path).unwrap();
+        DbLedger::destroy(&imported_path).unwrap();
+        let _ignored = remove_dir_all(&ledger_path);
+        let _ignored = remove_dir_all(&imported_path);
+    }
+
+    #[test]
+    fn test_ledger_archive_without_coding() {
+        let (ledger_path, entries, archive) = sample_archive(false);
+        let (archive_reader, _genesis_block) = ArchiveReader::new(&archive[..]).unwrap();
+        let records: Vec<_> = archive_reader.map(|record| record.unwrap()).collect();
+        assert_eq!(
+            records,
+            entries
+                .into_iter()
+                .map(|(slot, entry)| ArchiveRecord::Entry { slot, entry })
+                .collect::<Vec<_>>()
+        );
+
+        DbLedger::destroy(&ledger_path).unwrap();
+        let _ignored = remove_dir_all(&ledger_path);
+    }
+
+    #[test]
+    fn test_ledger_archive_detects_corruption() {
+        let (ledger_path, _entries, archive) = sample_archive(false);
+
+        // Flip a byte in the middle of the archive
+        let mut corrupted = archive.clone();
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0xff;
+        let (archive_reader, _genesis_block) = ArchiveReader::new(&corrupted[..]).unwrap();
+        let last = archive_reader.last().unwrap();
+        match last {
+            Err(Error::LedgerArchiveError(LedgerArchiveError::ChecksumMismatch(_)))
+            | Err(Error::LedgerArchiveError(LedgerArchiveError::UnexpectedRecord(_)))
+            | Err(Error::LedgerArchiveError(LedgerArchiveError::Truncated)) => (),
+            _ => panic!("corruption not detected: {:?}", last),
+        }
+
+        // Drop the final record
+        let truncated = &archive[..archive.len() - 1];
+        let (archive_reader, _genesis_block) = ArchiveReader::new(truncated).unwrap();
+        match archive_reader.last().unwrap() {
+            Err(Error::LedgerArchiveError(LedgerArchiveError::Truncated)) => (),
+            last => panic!("truncation not detected: {:?}", last),
+        }
+
+        // Bump the version past what this build understands
+        let mut newer = archive.clone();
+        newer[ARCHIVE_MAGIC.len()] = 0xff;
+        match ArchiveReader::new(&newer[..]) {
+            Err(Error::LedgerArchiveError(LedgerArchiveError::UnsupportedVersion(_))) => (),
+            _ => panic!("newer version accepted"),
+        }
+
+        assert!(ArchiveReader::new(&b"not an archive"[..]).is_err());
+
+        DbLedger::destroy(&ledger_path).unwrap();
+        let _ignored = remove_dir_all(&ledger_path);
+    }
+
+    #[test]
+    fn test_ledger_archive_version_1() {
+        let (ledger_path, entries, _archive) = sample_archive(false);
+        let genesis_block = GenesisBlock::load(&ledger_path).unwrap();
+
+        // Version 1 entry records are the bare entry, without a slot
+        let mut archive_writer = ArchiveWriter::new(vec![], &genesis_block).unwrap();
+        archive_writer.writer[ARCHIVE_MAGIC.len()] = 1;
+        for (_slot, entry) in &entries {
+            archive_writer
+                .write_record(ENTRY_RECORD, &serialize(entry).unwrap())
+                .unwrap();
+            archive_writer.num_entries += 1;
+        }
+        let archive = archive_writer.finish().unwrap();
+
+        let (archive_reader, _genesis_block) = ArchiveReader::new(&archive[..]).unwrap();
+        let records: Vec<_> = archive_reader.map(|record| record.unwrap()).collect();
+        assert_eq!(
+            records,
+            entries
+                .into_iter()
+                .map(|(_slot, entry)| ArchiveRecord::Entry {
+                    slot: DEFAULT_SLOT_HEIGHT,
+                    entry
+                })
+                .collect::<Vec<_>>()
+        );
+
+        DbLedger::destroy(&ledger_path).unwrap();
+        let _ignored = remove_dir_all(&ledger_path);
+    }
+
+    #[test]
+    fn test_ledger_archive_import_is_atomic() {
+        let (ledger_path, entries, archive) = sample_archive(false);
+
+        // An existing ledger is never overwritten
+        match import_ledger(&archive[..], &ledger_path) {
+            Err(Error::LedgerArchiveError(LedgerArchiveError::LedgerExists)) => (),
+            result => panic!("existing ledger overwritten: {:?}", result),
+        }
+        let db_ledger = DbLedger::open(&ledger_path).unwrap();
+        assert_eq!(db_ledger.read_ledger().unwrap().count(), entries.len());
+        drop(db_ledger);
+
+        // A truncated archive leaves nothing behind
+        let imported_path = get_tmp_ledger_path("test_ledger_archive_import_is_atomic");
+        let truncated = &archive[..archive.len() - 1];
+        match import_ledger(truncated, &imported_path) {
+            Err(Error::LedgerArchiveError(LedgerArchiveError::Truncated)) => (),
+            result => panic!("truncation not detected: {:?}", result),
+        }
+        assert!(!Path::new(&imported_path).exists());
+        assert!(!Path::new(&format!("{}.import", imported_path)).exists());
+
+        DbLedger::destroy(&ledger_path).unwrap();
+        let _ignored = remove_dir_all(&ledger_path);
+    }
+}
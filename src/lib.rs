@@ -42,6 +42,7 @@ pub mod gen_keys;
 pub mod genesis_block;
 pub mod gossip_service;
 pub mod leader_scheduler;
+pub mod ledger_archive;
 pub mod local_vote_signer_service;
 pub mod packet;
 pub mod poh;
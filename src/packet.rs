@@ -163,6 +163,8 @@ pub enum BlobError {
     BadState,
     /// Blob verification failed
     VerificationFailed,
+    /// the serialized Entry doesn't fit in a Blob's data
+    EntryTooLarge,
 }
 
 impl Packets {
@@ -285,6 +287,7 @@ macro_rules! align {
 }
 
 pub const BLOB_FLAG_IS_CODING: u32 = 0x1;
+pub const BLOB_FLAG_IS_COMPRESSED: u32 = 0x2;
 pub const BLOB_HEADER_SIZE: usize = align!(BLOB_SIZE_END, 64);
 
 impl Blob {
@@ -333,7 +336,15 @@ impl Blob {
     }
 
     pub fn flags(&self) -> Result<u32> {
-        let mut rdr = io::Cursor::new(&self.data[BLOB_ID_END..BLOB_FLAGS_END]);
+        Self::flags_from_bytes(&self.data)
+    }
+
+    /// Read the flags out of a serialized blob, such as one stored in the ledger
+    pub fn flags_from_bytes(data: &[u8]) -> Result<u32> {
+        if data.len() < BLOB_FLAGS_END {
+            return Err(Error::BlobError(BlobError::BadState));
+        }
+        let mut rdr = io::Cursor::new(&data[BLOB_ID_END..BLOB_FLAGS_END]);
         let r = rdr.read_u32::<LittleEndian>()?;
         Ok(r)
     }
@@ -354,6 +365,15 @@ impl Blob {
         self.set_flags(flags | BLOB_FLAG_IS_CODING)
     }
 
+    pub fn is_compressed(&self) -> bool {
+        (self.flags().unwrap() & BLOB_FLAG_IS_COMPRESSED) != 0
+    }
+
+    pub fn set_compressed(&mut self) -> Result<()> {
+        let flags = self.flags().unwrap();
+        self.set_flags(flags | BLOB_FLAG_IS_COMPRESSED)
+    }
+
     pub fn data_size(&self) -> Result<u64> {
         let mut rdr = io::Cursor::new(&self.data[BLOB_FLAGS_END..BLOB_SIZE_END]);
         let r = rdr.read_u64::<LittleEndian>()?;
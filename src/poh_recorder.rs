@@ -124,7 +124,7 @@ mod tests {
     #[test]
     fn test_poh() {
         let (genesis_block, _mint_keypair) = GenesisBlock::new(1);
-        let bank = Arc::new(Bank::new(&genesis_block));
+        let bank = Arc::new(Bank::new(&genesis_block).unwrap());
         let prev_id = bank.last_id();
         let (entry_sender, entry_receiver) = channel();
         let mut poh_recorder = PohRecorder::new(bank, entry_sender, prev_id, Some(3));
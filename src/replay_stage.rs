@@ -69,7 +69,7 @@ impl ReplayStage {
         ledger_entry_sender: &EntrySender,
         entry_height: &Arc<RwLock<u64>>,
         last_entry_id: &Arc<RwLock<Hash>>,
-        entry_stream: Option<&mut EntryStream>,
+        mut entry_stream: Option<&mut EntryStream>,
     ) -> Result<()> {
         let timer = Duration::new(1, 0);
         //coalesce all the available entries into a single vote
@@ -81,12 +81,6 @@ impl ReplayStage {
             }
         }
 
-        if let Some(stream) = entry_stream {
-            stream.stream_entries(&entries).unwrap_or_else(|e| {
-                error!("Entry Stream error: {:?}, {:?}", e, stream.socket);
-            });
-        }
-
         submit(
             influxdb::Point::new("replicate-stage")
                 .add_field("count", influxdb::Value::Integer(entries.len() as i64))
@@ -105,7 +99,7 @@ impl ReplayStage {
             duration_as_ms(&now.elapsed()) as usize
         );
 
-        let (current_leader, _) = bank
+        let (current_leader, mut current_slot) = bank
             .get_current_leader()
             .expect("Scheduled leader should be calculated by this point");
         let my_id = keypair.pubkey();
@@ -130,6 +124,14 @@ impl ReplayStage {
             if 0 == num_ticks_to_next_vote || (i + 1) == entries.len() {
                 res = bank.process_entries(&entries[start_entry_index..=i]);
 
+                if let Some(stream) = entry_stream.as_mut() {
+                    stream
+                        .stream_entries(current_slot, &entries[start_entry_index..=i], bank)
+                        .unwrap_or_else(|e| {
+                            error!("Entry Stream error: {:?}, {:?}", e, stream.socket);
+                        });
+                }
+
                 if res.is_err() {
                     // TODO: This will return early from the first entry that has an erroneous
                     // transaction, instead of processing the rest of the entries in the vector
@@ -154,10 +156,21 @@ impl ReplayStage {
                         }
                     }
                 }
-                let (scheduled_leader, _) = bank
+                let (scheduled_leader, scheduled_slot) = bank
                     .get_current_leader()
                     .expect("Scheduled leader should be calculated by this point");
 
+                if scheduled_slot != current_slot {
+                    current_slot = scheduled_slot;
+                    if let Some(stream) = entry_stream.as_mut() {
+                        stream
+                            .emit_slot_event(scheduled_slot, bank.tick_height(), scheduled_leader)
+                            .unwrap_or_else(|e| {
+                                error!("Entry Stream error: {:?}, {:?}", e, stream.socket);
+                            });
+                    }
+                }
+
                 // TODO: Remove this soon once we boot the leader from ClusterInfo
                 if scheduled_leader != current_leader {
                     did_rotate = true;
@@ -228,10 +241,19 @@ impl ReplayStage {
                 let _exit = Finalizer::new(exit);
                 let entry_height_ = entry_height;
                 let last_entry_id = last_entry_id;
-                let (mut last_leader_id, _) = bank
+                let (mut last_leader_id, slot) = bank
                     .get_current_leader()
                     .expect("Scheduled leader should be calculated by this point");
                 let mut entry_stream = entry_stream.map(EntryStream::new);
+                // Later slot events are only sent when the slot changes, so announce the
+                // slot replay starts in
+                if let Some(stream) = entry_stream.as_mut() {
+                    stream
+                        .emit_slot_event(slot, bank.tick_height(), last_leader_id)
+                        .unwrap_or_else(|e| {
+                            error!("Entry Stream error: {:?}, {:?}", e, stream.socket);
+                        });
+                }
                 loop {
                     let (leader_id, _) = bank
                         .get_current_leader()
@@ -296,6 +318,7 @@ mod test {
     use crate::db_ledger::{DbLedger, DEFAULT_SLOT_HEIGHT};
     use crate::entry::create_ticks;
     use crate::entry::Entry;
+    use crate::entry_stream::EntryStreamEvent;
     use crate::fullnode::Fullnode;
     use crate::leader_scheduler::{
         make_active_set_entries, LeaderScheduler, LeaderSchedulerConfig,
@@ -779,7 +802,10 @@ mod test {
         for _ in 0..5 {
             let entry = Entry::new(&mut last_id, 0, 1, vec![]); //just ticks
             last_id = entry.id;
-            expected_entries.push(serde_json::to_string(&entry).unwrap());
+            expected_entries.push(EntryStreamEvent::Entry {
+                slot: 0,
+                entry: entry.clone(),
+            });
             entries.push(entry);
         }
         entry_sender
@@ -802,7 +828,12 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(entry_stream.socket.len(), 5);
-        assert_eq!(entry_stream.socket, expected_entries);
+        let events: Vec<EntryStreamEvent> = entry_stream
+            .socket
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events, expected_entries);
     }
 }
@@ -1,6 +1,6 @@
 use crate::blob_fetch_stage::BlobFetchStage;
 #[cfg(feature = "chacha")]
-use crate::chacha::{chacha_cbc_encrypt_ledger, CHACHA_BLOCK_SIZE};
+use crate::chacha::{chacha_cbc_encrypt_ledger_to, CHACHA_BLOCK_SIZE};
 use crate::client::mk_client;
 use crate::cluster_info::{ClusterInfo, Node, NodeInfo};
 use crate::db_ledger::DbLedger;
@@ -21,11 +21,15 @@ use solana_sdk::signature::{Keypair, KeypairUtil, Signature};
 use solana_sdk::storage_program::StorageTransaction;
 use solana_sdk::transaction::Transaction;
 use std::fs::File;
+#[cfg(feature = "chacha")]
+use std::fs::OpenOptions;
 use std::io;
 use std::io::BufReader;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
+#[cfg(feature = "chacha")]
+use std::io::Write;
 use std::io::{Error, ErrorKind};
 use std::mem::size_of;
 use std::net::UdpSocket;
@@ -48,15 +52,22 @@ pub struct Replicator {
 
 pub fn sample_file(in_path: &Path, sample_offsets: &[u64]) -> io::Result<Hash> {
     let in_file = File::open(in_path)?;
-    let metadata = in_file.metadata()?;
-    let mut buffer_file = BufReader::new(in_file);
+    let file_len = in_file.metadata()?.len();
+    sample_reader(&mut BufReader::new(in_file), file_len, sample_offsets)
+}
 
+/// Hashes the `size_of::<Hash>()` byte samples at `sample_offsets` of the first `file_len`
+/// bytes of `buffer_file`
+pub fn sample_reader<R: Read + Seek>(
+    buffer_file: &mut R,
+    file_len: u64,
+    sample_offsets: &[u64],
+) -> io::Result<Hash> {
     let mut hasher = Hasher::default();
     let sample_size = size_of::<Hash>();
     let sample_size64 = sample_size as u64;
     let mut buf = vec![0; sample_size];
 
-    let file_len = metadata.len();
     if file_len < sample_size64 {
         return Err(Error::new(ErrorKind::Other, "file too short!"));
     }
@@ -81,6 +92,42 @@ pub fn sample_file(in_path: &Path, sample_offsets: &[u64]) -> io::Result<Hash> {
     Ok(hasher.result())
 }
 
+/// Computes the storage mining proof for the segment starting at `entry_height`: the segment
+/// is encrypted into `encrypted` with `signature` as the IV, and then sampled at offsets seeded
+/// by that same signature. Replicators submit this hash, and validators recompute it from their
+/// own copy of the ledger to check it
+#[cfg(feature = "chacha")]
+pub fn get_mining_proof<F: Read + Write + Seek>(
+    db_ledger: &Arc<DbLedger>,
+    entry_height: u64,
+    signature: &[u8],
+    encrypted: &mut F,
+) -> io::Result<Hash> {
+    use crate::storage_stage::NUM_STORAGE_SAMPLES;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    let mut ivec = [0u8; CHACHA_BLOCK_SIZE];
+    ivec.copy_from_slice(signature);
+
+    let num_encrypted_bytes =
+        chacha_cbc_encrypt_ledger_to(db_ledger, entry_height, encrypted, &mut ivec)?;
+    encrypted.flush()?;
+
+    let num_chacha_blocks = num_encrypted_bytes / CHACHA_BLOCK_SIZE;
+    if num_chacha_blocks == 0 {
+        return Err(Error::new(ErrorKind::Other, "no entries to sample"));
+    }
+    let mut rng_seed = [0u8; 32];
+    rng_seed.copy_from_slice(&signature[0..32]);
+    let mut rng = ChaChaRng::from_seed(rng_seed);
+    let sampling_offsets: Vec<_> = (0..NUM_STORAGE_SAMPLES)
+        .map(|_| rng.gen_range(0, num_chacha_blocks) as u64)
+        .collect();
+
+    sample_reader(encrypted, num_encrypted_bytes as u64, &sampling_offsets)
+}
+
 fn get_entry_heights_from_last_id(
     signature: &ring::signature::Signature,
     storage_entry_height: u64,
@@ -225,39 +272,29 @@ impl Replicator {
 
         let ledger_path = Path::new(ledger_path.unwrap());
         let ledger_data_file_encrypted = ledger_path.join("ledger.enc");
-        let mut sampling_offsets = Vec::new();
 
         #[cfg(not(feature = "chacha"))]
-        sampling_offsets.push(0);
+        let mining_proof = sample_file(&ledger_data_file_encrypted, &[0]);
 
         #[cfg(feature = "chacha")]
-        {
-            use crate::storage_stage::NUM_STORAGE_SAMPLES;
-            use rand::{Rng, SeedableRng};
-            use rand_chacha::ChaChaRng;
-
-            let mut ivec = [0u8; 64];
-            ivec.copy_from_slice(signature.as_ref());
-
-            let num_encrypted_bytes = chacha_cbc_encrypt_ledger(
+        let mining_proof = {
+            let mut encrypted_file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&ledger_data_file_encrypted)?;
+            get_mining_proof(
                 &db_ledger,
                 entry_height,
-                &ledger_data_file_encrypted,
-                &mut ivec,
-            )?;
-
-            let num_chacha_blocks = num_encrypted_bytes / CHACHA_BLOCK_SIZE;
-            let mut rng_seed = [0u8; 32];
-            rng_seed.copy_from_slice(&signature.as_ref()[0..32]);
-            let mut rng = ChaChaRng::from_seed(rng_seed);
-            for _ in 0..NUM_STORAGE_SAMPLES {
-                sampling_offsets.push(rng.gen_range(0, num_chacha_blocks) as u64);
-            }
-        }
+                signature.as_ref(),
+                &mut encrypted_file,
+            )
+        };
 
         info!("Done encrypting the ledger");
 
-        match sample_file(&ledger_data_file_encrypted, &sampling_offsets) {
+        match mining_proof {
             Ok(hash) => {
                 let last_id = client.get_last_id();
                 info!("sampled hash: {}", hash);
@@ -5,6 +5,7 @@ use crate::cluster_info;
 use crate::db_ledger;
 #[cfg(feature = "erasure")]
 use crate::erasure;
+use crate::ledger_archive;
 use crate::packet;
 use crate::poh_recorder;
 use crate::vote_signer_proxy;
@@ -31,6 +32,7 @@ pub enum Error {
     PohRecorderError(poh_recorder::PohRecorderError),
     VoteError(vote_signer_proxy::VoteError),
     DbLedgerError(db_ledger::DbLedgerError),
+    LedgerArchiveError(ledger_archive::LedgerArchiveError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
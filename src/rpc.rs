@@ -12,6 +12,7 @@ use bincode::{deserialize, serialize};
 use bs58;
 use solana_drone::drone::request_airdrop_transaction;
 use solana_sdk::account::Account;
+use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use solana_sdk::transaction::Transaction;
@@ -117,14 +118,35 @@ pub struct Meta {
 }
 impl Metadata for Meta {}
 
-#[derive(Copy, Clone, PartialEq, Serialize, Debug)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub enum RpcSignatureStatus {
     AccountInUse,
     Confirmed,
+    /// The instruction at the given index failed with a program-specific error code
+    CustomProgramError(u8, u32),
     GenericFailure,
     ProgramRuntimeError,
     SignatureNotFound,
 }
+
+impl RpcSignatureStatus {
+    pub fn from_bank_result(res: &bank::Result<()>) -> Self {
+        match res {
+            Ok(_) => RpcSignatureStatus::Confirmed,
+            Err(BankError::AccountInUse) => RpcSignatureStatus::AccountInUse,
+            Err(BankError::ProgramError(index, ProgramError::CustomError(code))) => {
+                RpcSignatureStatus::CustomProgramError(*index, *code)
+            }
+            Err(BankError::ProgramError(_, _)) => RpcSignatureStatus::ProgramRuntimeError,
+            Err(err) => {
+                trace!("mapping {:?} to GenericFailure", err);
+                RpcSignatureStatus::GenericFailure
+            }
+        }
+    }
+}
+/// Parses a status by name, or as the JSON it is serialized to, as in
+/// `{"CustomProgramError":[0,2]}`
 impl FromStr for RpcSignatureStatus {
     type Err = Error;
 
@@ -135,7 +157,7 @@ impl FromStr for RpcSignatureStatus {
             "GenericFailure" => Ok(RpcSignatureStatus::GenericFailure),
             "ProgramRuntimeError" => Ok(RpcSignatureStatus::ProgramRuntimeError),
             "SignatureNotFound" => Ok(RpcSignatureStatus::SignatureNotFound),
-            _ => Err(Error::parse_error()),
+            _ => serde_json::from_str(s).map_err(|_| Error::parse_error()),
         }
     }
 }
@@ -235,17 +257,7 @@ impl RpcSol for RpcSolImpl {
                         // transitory while the bank processes the associated transaction.
                         RpcSignatureStatus::SignatureNotFound
                     }
-                    Status::Complete(res) => match res {
-                        Ok(_) => RpcSignatureStatus::Confirmed,
-                        Err(BankError::AccountInUse) => RpcSignatureStatus::AccountInUse,
-                        Err(BankError::ProgramError(_, _)) => {
-                            RpcSignatureStatus::ProgramRuntimeError
-                        }
-                        Err(err) => {
-                            trace!("mapping {:?} to GenericFailure", err);
-                            RpcSignatureStatus::GenericFailure
-                        }
-                    },
+                    Status::Complete(res) => RpcSignatureStatus::from_bank_result(&res),
                 }
             }
         };
@@ -468,7 +480,7 @@ mod tests {
 
     fn start_rpc_handler_with_tx(pubkey: Pubkey) -> (MetaIoHandler<Meta>, Meta, Hash, Keypair) {
         let (genesis_block, alice) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         let last_id = bank.last_id();
         let tx = Transaction::system_move(&alice, pubkey, 20, last_id, 0);
@@ -501,7 +513,7 @@ mod tests {
     #[test]
     fn test_rpc_new() {
         let (genesis_block, alice) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let cluster_info = Arc::new(RwLock::new(ClusterInfo::new(NodeInfo::default())));
         let rpc_addr = SocketAddr::new(
             IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
@@ -538,7 +550,7 @@ mod tests {
     fn test_rpc_request_processor_new() {
         let (genesis_block, alice) = GenesisBlock::new(10_000);
         let bob_pubkey = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let arc_bank = Arc::new(bank);
         let request_processor =
             JsonRpcRequestProcessor::new(arc_bank.clone(), StorageState::default());
@@ -667,6 +679,54 @@ mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_signature_status_from_bank_result() {
+        assert_eq!(
+            RpcSignatureStatus::from_bank_result(&Err(BankError::ProgramError(
+                1,
+                ProgramError::CustomError(42)
+            ))),
+            RpcSignatureStatus::CustomProgramError(1, 42)
+        );
+        assert_eq!(
+            RpcSignatureStatus::from_bank_result(&Err(BankError::ProgramError(
+                1,
+                ProgramError::InvalidArgument
+            ))),
+            RpcSignatureStatus::ProgramRuntimeError
+        );
+        assert_eq!(
+            serde_json::to_string(&RpcSignatureStatus::CustomProgramError(1, 42)).unwrap(),
+            r#"{"CustomProgramError":[1,42]}"#
+        );
+    }
+
+    #[test]
+    fn test_rpc_signature_status_from_str() {
+        let statuses = [
+            RpcSignatureStatus::AccountInUse,
+            RpcSignatureStatus::Confirmed,
+            RpcSignatureStatus::CustomProgramError(1, 42),
+            RpcSignatureStatus::GenericFailure,
+            RpcSignatureStatus::ProgramRuntimeError,
+            RpcSignatureStatus::SignatureNotFound,
+        ];
+        for status in statuses.iter() {
+            let json = serde_json::to_string(status).unwrap();
+            assert_eq!(RpcSignatureStatus::from_str(&json).unwrap(), *status);
+        }
+        assert_eq!(
+            RpcSignatureStatus::from_str("Confirmed").unwrap(),
+            RpcSignatureStatus::Confirmed
+        );
+        assert_eq!(
+            RpcSignatureStatus::from_str(r#"{"CustomProgramError":[0,2]}"#).unwrap(),
+            RpcSignatureStatus::CustomProgramError(0, 2)
+        );
+        assert!(RpcSignatureStatus::from_str("CustomProgramError").is_err());
+        assert!(RpcSignatureStatus::from_str(r#"{"CustomProgramError":[256,2]}"#).is_err());
+    }
+
     #[test]
     fn test_rpc_get_confirmation() {
         let bob_pubkey = Keypair::new().pubkey();
@@ -720,7 +780,7 @@ mod tests {
     #[test]
     fn test_rpc_send_bad_tx() {
         let (genesis_block, _) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
 
         let mut io = MetaIoHandler::default();
         let rpc = RpcSolImpl;
@@ -1,7 +1,11 @@
 // Implementation of RpcRequestHandler trait for testing Rpc requests without i/o
 
+use crate::rpc::RpcSignatureStatus;
 use crate::rpc_request::{RpcRequest, RpcRequestHandler};
+use bincode::serialize;
 use serde_json::{self, Number, Value};
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader::{self, UpgradeableLoaderState};
 use solana_sdk::hash::Hash;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, KeypairUtil};
@@ -47,12 +51,27 @@ impl MockRpcClient {
                     Value::Null
                 }
             }
+            RpcRequest::GetAccountInfo => {
+                // An upgradeable BPF program
+                let mut account = Account::new(1, 0, bpf_loader::id());
+                account.userdata = serialize(&UpgradeableLoaderState::Program {
+                    programdata_address: Pubkey::new(&[1; 32]),
+                })?;
+                account.executable = true;
+                account.loader = bpf_loader::id();
+                serde_json::to_value(account)?
+            }
             RpcRequest::GetBalance => {
                 let n = if self.addr == "airdrop" { 0 } else { 50 };
                 Value::Number(Number::from(n))
             }
             RpcRequest::GetLastId => Value::String(PUBKEY.to_string()),
             RpcRequest::GetSignatureStatus => {
+                if self.addr == "custom_program_error" {
+                    return Ok(serde_json::to_value(
+                        RpcSignatureStatus::CustomProgramError(0, 2),
+                    )?);
+                }
                 let str = if self.addr == "account_in_use" {
                     "AccountInUse"
                 } else if self.addr == "bad_sig_status" {
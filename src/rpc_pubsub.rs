@@ -1,7 +1,7 @@
 //! The `pubsub` module implements a threaded subscription service on client RPC request
 
 use crate::bank;
-use crate::bank::{Bank, BankError, BankSubscriptions};
+use crate::bank::{Bank, BankSubscriptions};
 use crate::jsonrpc_core::futures::Future;
 use crate::jsonrpc_core::*;
 use crate::jsonrpc_macros::pubsub;
@@ -164,12 +164,7 @@ impl BankSubscriptions for RpcSubscriptions {
     }
 
     fn check_signature(&self, signature: &Signature, bank_error: &bank::Result<()>) {
-        let status = match bank_error {
-            Ok(_) => RpcSignatureStatus::Confirmed,
-            Err(BankError::AccountInUse) => RpcSignatureStatus::AccountInUse,
-            Err(BankError::ProgramError(_, _)) => RpcSignatureStatus::ProgramRuntimeError,
-            Err(_) => RpcSignatureStatus::GenericFailure,
-        };
+        let status = RpcSignatureStatus::from_bank_result(bank_error);
 
         let mut subscriptions = self.signature_subscriptions.write().unwrap();
         if let Some(hashmap) = subscriptions.get(signature) {
@@ -399,7 +394,7 @@ mod tests {
     #[test]
     fn test_pubsub_new() {
         let (genesis_block, _) = GenesisBlock::new(10_000);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let pubsub_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
         let pubsub_service = PubSubService::new(&Arc::new(bank), pubsub_addr);
         let thread = pubsub_service.thread_hdl.thread();
@@ -411,7 +406,7 @@ mod tests {
         let (genesis_block, alice) = GenesisBlock::new(10_000);
         let bob = Keypair::new();
         let bob_pubkey = bob.pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let arc_bank = Arc::new(bank);
         let last_id = arc_bank.last_id();
 
@@ -444,7 +439,7 @@ mod tests {
     fn test_signature_unsubscribe() {
         let (genesis_block, alice) = GenesisBlock::new(10_000);
         let bob_pubkey = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let arc_bank = Arc::new(bank);
         let last_id = arc_bank.last_id();
 
@@ -498,7 +493,7 @@ mod tests {
         let budget_program_id = budget_program::id();
         let loader = Pubkey::default(); // TODO
         let executable = false; // TODO
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let arc_bank = Arc::new(bank);
         let last_id = arc_bank.last_id();
 
@@ -648,7 +643,7 @@ mod tests {
     fn test_account_unsubscribe() {
         let (genesis_block, _) = GenesisBlock::new(10_000);
         let bob_pubkey = Keypair::new().pubkey();
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let arc_bank = Arc::new(bank);
 
         let (sender, _receiver) = mpsc::channel(1);
@@ -694,7 +689,7 @@ mod tests {
     #[test]
     fn test_check_account_subscribe() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(100);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let alice = Keypair::new();
         let last_id = bank.last_id();
         let tx = Transaction::system_create(
@@ -739,7 +734,7 @@ mod tests {
     #[test]
     fn test_check_signature_subscribe() {
         let (genesis_block, mint_keypair) = GenesisBlock::new(100);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let alice = Keypair::new();
         let last_id = bank.last_id();
         let tx = Transaction::system_move(&mint_keypair, alice.pubkey(), 20, last_id, 0);
@@ -1,10 +1,13 @@
+use bincode::deserialize;
 use solana_native_loader;
 use solana_sdk::account::{create_keyed_accounts, Account, KeyedAccount};
 use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
 use solana_sdk::system_program;
 use solana_sdk::transaction::Transaction;
 use solana_system_program;
+use std::ops::Range;
 
 /// Reasons the runtime might have rejected a transaction.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -13,6 +16,36 @@ pub enum RuntimeError {
     ProgramError(u8, ProgramError),
 }
 
+/// Return true if `program_id`, the program an instruction invokes, acts as the signer for
+/// the account at `key`. An account at a program address has no private key to sign with, so
+/// the program it is derived from signs for it, when the transaction carries a
+/// `SystemInstruction::SignForProgramAddress` whose seeds derive `key` from `program_id`.
+/// The signature is the invoking program's alone: no other program's instructions see it,
+/// whoever provided the seeds. Accounts owned by the system program belong to no program, so
+/// it never signs for them
+fn is_program_signer(
+    tx: &Transaction,
+    program_id: &Pubkey,
+    key: &Pubkey,
+    account: &Account,
+) -> bool {
+    if system_program::check_id(program_id) || account.owner != *program_id {
+        return false;
+    }
+    (0..tx.instructions.len()).any(|index| {
+        if !system_program::check_id(tx.program_id(index)) {
+            return false;
+        }
+        match deserialize(tx.userdata(index)) {
+            Ok(SystemInstruction::SignForProgramAddress { seeds }) => {
+                let seeds: Vec<_> = seeds.iter().map(Vec::as_slice).collect();
+                Pubkey::create_program_address(&seeds, program_id) == Ok(*key)
+            }
+            _ => false,
+        }
+    })
+}
+
 /// Process an instruction
 /// This method calls the instruction's program entrypoint method
 fn process_instruction(
@@ -28,13 +61,14 @@ fn process_instruction(
     let mut keyed_accounts2: Vec<_> = tx.instructions[instruction_index]
         .accounts
         .iter()
-        .map(|&index| {
+        .zip(program_accounts.iter_mut())
+        .map(|(&index, account)| {
             let index = index as usize;
             let key = &tx.account_keys[index];
-            (key, index < tx.signatures.len())
+            let is_signer =
+                index < tx.signatures.len() || is_program_signer(tx, &program_id, key, account);
+            KeyedAccount::new(key, is_signer, account)
         })
-        .zip(program_accounts.iter_mut())
-        .map(|((key, is_signer), account)| KeyedAccount::new(key, is_signer, account))
         .collect();
     keyed_accounts.append(&mut keyed_accounts2);
 
@@ -159,7 +193,50 @@ pub fn execute_transaction(
     tx_accounts: &mut [Account],
     tick_height: u64,
 ) -> Result<(), RuntimeError> {
-    for (instruction_index, instruction) in tx.instructions.iter().enumerate() {
+    execute_instructions(
+        tx,
+        0..tx.instructions.len(),
+        loaders,
+        tx_accounts,
+        tick_height,
+    )
+}
+
+/// Execute a transaction that used a nonce in place of its `last_id`. If an instruction
+/// after the first fails, the nonce account is put back the way the first instruction
+/// left it, so that the advanced nonce can be stored without any later changes to the
+/// account
+pub fn execute_nonce_transaction(
+    tx: &Transaction,
+    loaders: &mut [Vec<(Pubkey, Account)>],
+    tx_accounts: &mut [Account],
+    tick_height: u64,
+) -> Result<(), RuntimeError> {
+    let nonce_index = tx.instructions[0].accounts[0] as usize;
+    execute_instructions(tx, 0..1, loaders, tx_accounts, tick_height)?;
+    let nonce_account = tx_accounts[nonce_index].clone();
+    execute_instructions(
+        tx,
+        1..tx.instructions.len(),
+        loaders,
+        tx_accounts,
+        tick_height,
+    )
+    .map_err(|err| {
+        tx_accounts[nonce_index] = nonce_account;
+        err
+    })
+}
+
+fn execute_instructions(
+    tx: &Transaction,
+    instruction_indexes: Range<usize>,
+    loaders: &mut [Vec<(Pubkey, Account)>],
+    tx_accounts: &mut [Account],
+    tick_height: u64,
+) -> Result<(), RuntimeError> {
+    for instruction_index in instruction_indexes {
+        let instruction = &tx.instructions[instruction_index];
         let executable_accounts = &mut (&mut loaders[instruction.program_ids_index as usize]);
         let mut program_accounts = get_subset_unchecked_mut(tx_accounts, &instruction.accounts);
         execute_instruction(
@@ -177,6 +254,9 @@ pub fn execute_transaction(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::{Keypair, KeypairUtil};
+    use solana_sdk::transaction::Instruction;
 
     #[test]
     fn test_has_duplicates() {
@@ -193,6 +273,67 @@ mod tests {
         );
     }
 
+    fn sign_for_program_address_tx(
+        signer: &Keypair,
+        seeds: &[&[u8]],
+        address: Pubkey,
+        program_id: Pubkey,
+    ) -> Transaction {
+        let sign = SystemInstruction::SignForProgramAddress {
+            seeds: seeds.iter().map(|seed| seed.to_vec()).collect(),
+        };
+        Transaction::new_with_instructions(
+            &[signer],
+            &[address],
+            Hash::default(),
+            0,
+            vec![system_program::id(), program_id],
+            vec![
+                Instruction::new(0, &sign, vec![0, 1]),
+                Instruction::new(1, &(), vec![1]),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_is_program_signer() {
+        let owner = Keypair::new();
+        let owner_id = owner.pubkey();
+        let program_id = Keypair::new().pubkey();
+        let (address, bump_seed) =
+            Pubkey::find_program_address(&[owner_id.as_ref(), b"vault"], &program_id);
+        let seeds: &[&[u8]] = &[owner_id.as_ref(), b"vault", &[bump_seed]];
+        let account = Account::new(1, 0, program_id);
+        let tx = sign_for_program_address_tx(&owner, seeds, address, program_id);
+        assert!(is_program_signer(&tx, &program_id, &address, &account));
+
+        // Only the program the seeds derive the address from signs, and only for that address
+        let other_id = Keypair::new().pubkey();
+        let other_account = Account::new(1, 0, other_id);
+        assert!(!is_program_signer(&tx, &other_id, &address, &other_account));
+        assert!(!is_program_signer(&tx, &program_id, &other_id, &account));
+        assert!(!is_program_signer(
+            &tx,
+            &program_id,
+            &address,
+            &other_account
+        ));
+
+        let system_id = system_program::id();
+        let account = Account::new(1, 0, system_id);
+        assert!(!is_program_signer(&tx, &system_id, &address, &account));
+
+        // Without the seeds, the program doesn't sign
+        let account = Account::new(1, 0, program_id);
+        let tx = Transaction::new(&owner, &[address], program_id, &(), Hash::default(), 0);
+        assert!(!is_program_signer(&tx, &program_id, &address, &account));
+        let (_, other_bump_seed) =
+            Pubkey::find_program_address(&[owner_id.as_ref(), b"other"], &program_id);
+        let other_seeds: &[&[u8]] = &[owner_id.as_ref(), b"other", &[other_bump_seed]];
+        let tx = sign_for_program_address_tx(&owner, other_seeds, address, program_id);
+        assert!(!is_program_signer(&tx, &program_id, &address, &account));
+    }
+
     #[test]
     #[should_panic]
     fn test_get_subset_unchecked_mut_duplicate_index() {
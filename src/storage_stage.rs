@@ -8,6 +8,8 @@ use crate::client::mk_client;
 use crate::cluster_info::ClusterInfo;
 use crate::db_ledger::DbLedger;
 use crate::entry::EntryReceiver;
+#[cfg(feature = "chacha")]
+use crate::replicator::get_mining_proof;
 use crate::result::{Error, Result};
 use crate::service::Service;
 use bincode::deserialize;
@@ -18,12 +20,15 @@ use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signature::Signature;
 use solana_sdk::storage_program;
+use solana_sdk::storage_program::ProofStatus;
 use solana_sdk::storage_program::StorageProgram;
 use solana_sdk::storage_program::StorageTransaction;
 use solana_sdk::transaction::Transaction;
 use solana_sdk::vote_program;
 use std::collections::HashSet;
 use std::io;
+#[cfg(feature = "chacha")]
+use std::io::Cursor;
 use std::mem::size_of;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::RecvTimeoutError;
@@ -46,6 +51,8 @@ pub struct StorageStateInner {
     replicator_map: ReplicatorMap,
     storage_last_id: Hash,
     entry_height: u64,
+    // Verdicts on the proofs submitted since the last crossing, by segment and proof signature
+    proof_statuses: Vec<Vec<(Signature, ProofStatus)>>,
 }
 
 #[derive(Clone, Default)]
@@ -87,6 +94,45 @@ fn get_identity_index_from_signature(key: &Signature) -> usize {
     res
 }
 
+// Check a replicator's proof by recomputing it from this validator's copy of the segment,
+// encrypted and sampled with the replicator's own signature. Proofs for segments this
+// validator can't read can't be checked
+#[cfg(feature = "chacha")]
+fn verify_proof(
+    db_ledger: &Arc<DbLedger>,
+    entry_height: u64,
+    signature: &Signature,
+    sha_state: &Hash,
+) -> ProofStatus {
+    match get_mining_proof(
+        db_ledger,
+        entry_height,
+        signature.as_ref(),
+        &mut Cursor::new(vec![]),
+    ) {
+        Ok(mining_proof) if mining_proof == *sha_state => ProofStatus::Valid,
+        Ok(_) => ProofStatus::NotValid,
+        Err(e) => {
+            info!(
+                "can't verify proof at entry_height {}: {:?}",
+                entry_height, e
+            );
+            ProofStatus::Skipped
+        }
+    }
+}
+
+// Without chacha the proofs can't be recomputed
+#[cfg(not(feature = "chacha"))]
+fn verify_proof(
+    _db_ledger: &Arc<DbLedger>,
+    _entry_height: u64,
+    _signature: &Signature,
+    _sha_state: &Hash,
+) -> ProofStatus {
+    ProofStatus::Skipped
+}
+
 impl StorageState {
     pub fn new() -> Self {
         let storage_keys = vec![0u8; KEY_SIZE * NUM_IDENTITIES];
@@ -99,6 +145,7 @@ impl StorageState {
             replicator_map,
             entry_height: 0,
             storage_last_id: Hash::default(),
+            proof_statuses: vec![],
         };
 
         StorageState {
@@ -147,6 +194,7 @@ impl StorageStage {
         storage_entry_receiver: EntryReceiver,
         db_ledger: Option<Arc<DbLedger>>,
         keypair: &Arc<Keypair>,
+        vote_account: Option<Pubkey>,
         exit: &Arc<AtomicBool>,
         entry_height: u64,
         storage_rotate_count: u64,
@@ -170,6 +218,7 @@ impl StorageStage {
                     if let Some(ref some_db_ledger) = db_ledger {
                         if let Err(e) = Self::process_entries(
                             &keypair0,
+                            vote_account,
                             &storage_state_inner,
                             &storage_entry_receiver,
                             &some_db_ledger,
@@ -269,6 +318,7 @@ impl StorageStage {
     pub fn process_entry_crossing(
         state: &Arc<RwLock<StorageStateInner>>,
         keypair: &Arc<Keypair>,
+        vote_account: Option<Pubkey>,
         _db_ledger: &Arc<DbLedger>,
         entry_id: Hash,
         entry_height: u64,
@@ -285,6 +335,28 @@ impl StorageStage {
         );
         tx_sender.send(tx)?;
 
+        // Now that the proofs submitted since the last crossing are the previous proofs,
+        // send our verdicts on them. Only validators with a registered vote account may.
+        let proof_statuses: Vec<_> = state.write().unwrap().proof_statuses.drain(..).collect();
+        if let Some(vote_account) = vote_account {
+            for (segment, statuses) in proof_statuses.into_iter().enumerate() {
+                if statuses
+                    .iter()
+                    .all(|(_, status)| *status == ProofStatus::Skipped)
+                {
+                    continue;
+                }
+                let tx = Transaction::storage_new_proof_validation(
+                    keypair,
+                    vote_account,
+                    Hash::default(),
+                    segment as u64 * ENTRIES_PER_SEGMENT,
+                    statuses,
+                );
+                tx_sender.send(tx)?;
+            }
+        }
+
         seed.copy_from_slice(&signature.as_ref()[..32]);
 
         let mut rng = ChaChaRng::from_seed(seed);
@@ -338,13 +410,12 @@ impl StorageStage {
                 }
             }
         }
-        // TODO: bundle up mining submissions from replicators
-        // and submit them in a tx to the leader to get reward.
         Ok(())
     }
 
     pub fn process_entries(
         keypair: &Arc<Keypair>,
+        vote_account: Option<Pubkey>,
         storage_state: &Arc<RwLock<StorageStateInner>>,
         entry_receiver: &EntryReceiver,
         db_ledger: &Arc<DbLedger>,
@@ -374,10 +445,17 @@ impl StorageStage {
                     } else if storage_program::check_id(&program_id) {
                         match deserialize(&tx.instructions[i].userdata) {
                             Ok(StorageProgram::SubmitMiningProof {
+                                sha_state,
                                 entry_height: proof_entry_height,
-                                ..
+                                signature,
                             }) => {
                                 if proof_entry_height < *entry_height {
+                                    let status = verify_proof(
+                                        db_ledger,
+                                        proof_entry_height,
+                                        &signature,
+                                        &sha_state,
+                                    );
                                     let mut statew = storage_state.write().unwrap();
                                     let max_segment_index =
                                         (*entry_height / ENTRIES_PER_SEGMENT) as usize;
@@ -392,6 +470,14 @@ impl StorageStage {
                                         statew.replicator_map[proof_segment_index]
                                             .insert(tx.account_keys[0]);
                                     }
+
+                                    if statew.proof_statuses.len() <= proof_segment_index {
+                                        statew
+                                            .proof_statuses
+                                            .resize(proof_segment_index + 1, Vec::new());
+                                    }
+                                    statew.proof_statuses[proof_segment_index]
+                                        .push((signature, status));
                                 }
                                 debug!("storage proof: entry_height: {}", entry_height);
                             }
@@ -413,6 +499,7 @@ impl StorageStage {
                 Self::process_entry_crossing(
                     &storage_state,
                     &keypair,
+                    vote_account,
                     &db_ledger,
                     entry.id,
                     *entry_height,
@@ -446,13 +533,14 @@ mod tests {
     use crate::storage_stage::StorageState;
     use crate::storage_stage::NUM_IDENTITIES;
     use crate::storage_stage::{
-        get_identity_index_from_signature, StorageStage, STORAGE_ROTATE_TEST_COUNT,
+        get_identity_index_from_signature, verify_proof, StorageStage, STORAGE_ROTATE_TEST_COUNT,
     };
     use rayon::prelude::*;
     use solana_sdk::hash::Hash;
     use solana_sdk::hash::Hasher;
     use solana_sdk::pubkey::Pubkey;
     use solana_sdk::signature::{Keypair, KeypairUtil};
+    use solana_sdk::storage_program::ProofStatus;
     use solana_sdk::transaction::Transaction;
     use solana_sdk::vote_transaction::VoteTransaction;
     use std::cmp::{max, min};
@@ -477,6 +565,7 @@ mod tests {
             storage_entry_receiver,
             None,
             &keypair,
+            None,
             &exit.clone(),
             0,
             STORAGE_ROTATE_TEST_COUNT,
@@ -521,6 +610,7 @@ mod tests {
             storage_entry_receiver,
             Some(Arc::new(db_ledger)),
             &keypair,
+            None,
             &exit.clone(),
             0,
             STORAGE_ROTATE_TEST_COUNT,
@@ -589,6 +679,7 @@ mod tests {
             storage_entry_receiver,
             Some(Arc::new(db_ledger)),
             &keypair,
+            None,
             &exit.clone(),
             0,
             STORAGE_ROTATE_TEST_COUNT,
@@ -604,7 +695,7 @@ mod tests {
         }
         let mut vote_txs: Vec<Transaction> = Vec::new();
         let keypair = Keypair::new();
-        let vote_tx = Transaction::vote_new(&keypair, 123456, Hash::default(), 1);
+        let vote_tx = Transaction::vote_new(&keypair, 123456, Hash::default(), Hash::default(), 1);
         vote_txs.push(vote_tx);
         let vote_entries = vec![Entry::new(&Hash::default(), 0, 1, vote_txs)];
         storage_entry_sender.send(vote_entries).unwrap();
@@ -632,6 +723,68 @@ mod tests {
         remove_dir_all(ledger_path).unwrap();
     }
 
+    #[test]
+    fn test_verify_proof() {
+        let (_genesis_block, _mint, ledger_path, genesis_entries) = create_tmp_sample_ledger(
+            "storage_stage_verify_proof",
+            1000,
+            1,
+            Keypair::new().pubkey(),
+            1,
+        );
+
+        let entries = make_tiny_test_entries(64);
+        let db_ledger = Arc::new(DbLedger::open(&ledger_path).unwrap());
+        db_ledger
+            .write_entries(DEFAULT_SLOT_HEIGHT, genesis_entries.len() as u64, &entries)
+            .unwrap();
+
+        let keypair = Keypair::new();
+        let signature = keypair.sign_message(Hash::default().as_ref());
+
+        #[cfg(feature = "chacha")]
+        {
+            use crate::replicator::get_mining_proof;
+            use crate::storage_stage::ENTRIES_PER_SEGMENT;
+            use std::io::Cursor;
+
+            // The proof an honest replicator submits for the first segment
+            let answer =
+                get_mining_proof(&db_ledger, 0, signature.as_ref(), &mut Cursor::new(vec![]))
+                    .unwrap();
+            assert_eq!(
+                verify_proof(&db_ledger, 0, &signature, &answer),
+                ProofStatus::Valid
+            );
+            assert_eq!(
+                verify_proof(&db_ledger, 0, &signature, &Hash::default()),
+                ProofStatus::NotValid
+            );
+
+            // The proof is bound to the replicator's signature
+            let other_signature = Keypair::new().sign_message(Hash::default().as_ref());
+            assert_eq!(
+                verify_proof(&db_ledger, 0, &other_signature, &answer),
+                ProofStatus::NotValid
+            );
+
+            // Segments this validator doesn't have can't be checked
+            assert_eq!(
+                verify_proof(&db_ledger, 1000 * ENTRIES_PER_SEGMENT, &signature, &answer),
+                ProofStatus::Skipped
+            );
+        }
+
+        #[cfg(not(feature = "chacha"))]
+        assert_eq!(
+            verify_proof(&db_ledger, 0, &signature, &Hash::default()),
+            ProofStatus::Skipped
+        );
+
+        drop(db_ledger);
+        remove_dir_all(ledger_path).unwrap();
+    }
+
     #[test]
     fn test_signature_distribution() {
         // See that signatures have an even-ish distribution..
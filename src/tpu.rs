@@ -81,6 +81,7 @@ impl Tpu {
         leader_id: Pubkey,
         is_leader: bool,
         to_validator_sender: &TpuRotationSender,
+        blob_compression: bool,
     ) -> Self {
         let exit = Arc::new(AtomicBool::new(false));
 
@@ -99,6 +100,7 @@ impl Tpu {
                 max_tick_height,
                 leader_id,
                 &to_validator_sender,
+                blob_compression,
             );
 
             let broadcast_service = BroadcastService::new(
@@ -109,6 +111,7 @@ impl Tpu {
                 bank.leader_scheduler.clone(),
                 entry_receiver,
                 max_tick_height,
+                blob_compression,
                 exit.clone(),
             );
 
@@ -162,6 +165,7 @@ impl Tpu {
         last_entry_id: &Hash,
         leader_id: Pubkey,
         to_validator_sender: &TpuRotationSender,
+        blob_compression: bool,
     ) {
         match &self.tpu_mode {
             TpuMode::Leader(svcs) => {
@@ -186,6 +190,7 @@ impl Tpu {
             max_tick_height,
             leader_id,
             &to_validator_sender,
+            blob_compression,
         );
 
         let broadcast_service = BroadcastService::new(
@@ -196,6 +201,7 @@ impl Tpu {
             bank.leader_scheduler.clone(),
             entry_receiver,
             max_tick_height,
+            blob_compression,
             self.exit.clone(),
         );
 
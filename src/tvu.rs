@@ -23,7 +23,7 @@ use crate::service::Service;
 use crate::storage_stage::{StorageStage, StorageState};
 use crate::vote_signer_proxy::VoteSignerProxy;
 use solana_sdk::hash::Hash;
-use solana_sdk::signature::Keypair;
+use solana_sdk::signature::{Keypair, KeypairUtil};
 use std::net::UdpSocket;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
@@ -112,6 +112,7 @@ impl Tvu {
         let l_entry_height = Arc::new(RwLock::new(entry_height));
         let l_last_entry_id = Arc::new(RwLock::new(last_entry_id));
 
+        let vote_account = vote_signer.as_ref().map(|signer| signer.pubkey());
         let (replay_stage, ledger_entry_receiver) = ReplayStage::new(
             keypair.clone(),
             vote_signer,
@@ -130,6 +131,7 @@ impl Tvu {
             ledger_entry_receiver,
             Some(db_ledger),
             &keypair,
+            vote_account,
             &exit.clone(),
             entry_height,
             storage_rotate_count,
@@ -267,7 +269,7 @@ pub mod tests {
         let leader_scheduler = Arc::new(RwLock::new(LeaderScheduler::from_bootstrap_leader(
             leader_id,
         )));
-        let mut bank = Bank::new(&genesis_block);
+        let mut bank = Bank::new(&genesis_block).unwrap();
         bank.leader_scheduler = leader_scheduler;
         let bank = Arc::new(bank);
 
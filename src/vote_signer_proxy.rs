@@ -1,4 +1,4 @@
-//! The `vote_signer_proxy` votes on the `last_id` of the bank at a regular cadence
+//! The `vote_signer_proxy` votes on the bank's current slot at a regular cadence
 
 use crate::bank::Bank;
 use crate::cluster_info::ClusterInfo;
@@ -95,6 +95,7 @@ pub struct VoteSignerProxy {
     signer: Box<VoteSigner + Send + Sync>,
     vote_account: Pubkey,
     last_leader: RwLock<Pubkey>,
+    last_vote_slot: RwLock<Option<u64>>,
     unsent_votes: RwLock<Vec<Transaction>>,
 }
 
@@ -110,6 +111,7 @@ impl VoteSignerProxy {
             signer,
             vote_account,
             last_leader: RwLock::new(vote_account),
+            last_vote_slot: RwLock::new(None),
             unsent_votes: RwLock::new(vec![]),
         }
     }
@@ -139,7 +141,28 @@ impl VoteSignerProxy {
             );
         }
 
-        let tx = Transaction::vote_new(self, bank.tick_height(), bank.last_id(), 0);
+        // The vote program only accepts one vote per slot, so only vote again once the bank
+        // has moved on to a new slot
+        let slot = bank
+            .leader_scheduler
+            .read()
+            .unwrap()
+            .tick_height_to_slot(bank.tick_height());
+        let tx = {
+            let mut last_vote_slot = self.last_vote_slot.write().unwrap();
+            if last_vote_slot.map_or(false, |last_vote_slot| slot <= last_vote_slot) {
+                None
+            } else {
+                *last_vote_slot = Some(slot);
+                Some(Transaction::vote_new(
+                    self,
+                    slot,
+                    bank.hash_internal_state(),
+                    bank.last_id(),
+                    0,
+                ))
+            }
+        };
 
         match VoteSignerProxy::get_leader_tpu(&bank, cluster_info) {
             Ok(tpu) => {
@@ -151,14 +174,18 @@ impl VoteSignerProxy {
                     }
                     false
                 });
-                if let Ok(shared_blob) = self.new_signed_vote_blob(&tx, tpu) {
-                    inc_new_counter_info!("validator-vote_sent", 1);
-                    vote_blob_sender.send(vec![shared_blob])?;
+                if let Some(tx) = tx {
+                    if let Ok(shared_blob) = self.new_signed_vote_blob(&tx, tpu) {
+                        inc_new_counter_info!("validator-vote_sent", 1);
+                        vote_blob_sender.send(vec![shared_blob])?;
+                    }
                 }
             }
             Err(_) => {
-                self.unsent_votes.write().unwrap().push(tx);
-                inc_new_counter_info!("validator-new_pending_vote", 1);
+                if let Some(tx) = tx {
+                    self.unsent_votes.write().unwrap().push(tx);
+                    inc_new_counter_info!("validator-new_pending_vote", 1);
+                }
             }
         };
 
@@ -200,7 +227,9 @@ mod test {
     use crate::bank::Bank;
     use crate::cluster_info::{ClusterInfo, Node};
     use crate::genesis_block::GenesisBlock;
+    use crate::leader_scheduler::{LeaderScheduler, LeaderSchedulerConfig};
     use crate::vote_signer_proxy::VoteSignerProxy;
+    use solana_sdk::hash::hash;
     use solana_sdk::signature::{Keypair, KeypairUtil};
     use std::sync::mpsc::channel;
     use std::sync::{Arc, RwLock};
@@ -219,7 +248,11 @@ mod test {
         let cluster_info = Arc::new(RwLock::new(ClusterInfo::new(my_node.info.clone())));
 
         let (genesis_block, _) = GenesisBlock::new_with_leader(10000, my_id, 500);
-        let bank = Bank::new(&genesis_block);
+        let mut bank = Bank::new(&genesis_block).unwrap();
+        // One tick per slot, so every registered tick starts a new slot to vote on
+        let leader_scheduler_config = LeaderSchedulerConfig::new(1, 1, 1, 1);
+        bank.leader_scheduler =
+            Arc::new(RwLock::new(LeaderScheduler::new(&leader_scheduler_config)));
         let (sender, receiver) = channel();
 
         assert_eq!(signer.unsent_votes.read().unwrap().len(), 0);
@@ -229,6 +262,13 @@ mod test {
         assert_eq!(signer.unsent_votes.read().unwrap().len(), 1);
         assert!(receiver.recv_timeout(Duration::from_millis(400)).is_err());
 
+        // A second vote in the same slot is not queued
+        signer
+            .send_validator_vote(&bank, &cluster_info, &sender)
+            .unwrap();
+        assert_eq!(signer.unsent_votes.read().unwrap().len(), 1);
+
+        bank.register_tick(&hash(b"next slot"));
         signer
             .send_validator_vote(&bank, &cluster_info, &sender)
             .unwrap();
@@ -7,6 +7,7 @@ use solana::status_deque::Status;
 #[cfg(feature = "bpf_c")]
 use solana_sdk::bpf_loader;
 use solana_sdk::loader_transaction::LoaderTransaction;
+use solana_sdk::native_loader::NativeProgram;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, KeypairUtil};
 use solana_sdk::system_transaction::SystemTransaction;
@@ -45,6 +46,12 @@ fn check_tx_results(bank: &Bank, tx: &Transaction, result: Vec<solana::bank::Res
     );
 }
 
+/// Userdata of a native program pinned to the library `name`
+fn native_program(name: &str) -> Vec<u8> {
+    let code_hash = solana_native_loader::hash_library(name).unwrap();
+    NativeProgram::new(name, "0.12.0", Some(code_hash)).serialize()
+}
+
 struct Loader {
     genesis_block: GenesisBlock,
     mint_keypair: Keypair,
@@ -55,8 +62,9 @@ struct Loader {
 impl Loader {
     pub fn new_dynamic(loader_name: &str) -> Self {
         let (genesis_block, mint_keypair) = GenesisBlock::new(50);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let loader = Keypair::new();
+        let userdata = native_program(loader_name);
 
         // allocate, populate, finalize, and spawn loader
 
@@ -65,18 +73,17 @@ impl Loader {
             loader.pubkey(),
             genesis_block.last_id(),
             1,
-            56, // TODO
+            userdata.len() as u64,
             solana_native_loader::id(),
             0,
         );
         check_tx_results(&bank, &tx, bank.process_transactions(&vec![tx.clone()]));
 
-        let name = String::from(loader_name);
         let tx = Transaction::loader_write(
             &loader,
             solana_native_loader::id(),
             0,
-            name.as_bytes().to_vec(),
+            userdata,
             genesis_block.last_id(),
             0,
         );
@@ -103,7 +110,7 @@ impl Loader {
 
     pub fn new_native() -> Self {
         let (genesis_block, mint_keypair) = GenesisBlock::new(50);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let loader = solana_native_loader::id();
 
         Loader {
@@ -117,7 +124,7 @@ impl Loader {
     #[cfg(feature = "bpf_c")]
     pub fn new_bpf() -> Self {
         let (genesis_block, mint_keypair) = GenesisBlock::new(50);
-        let bank = Bank::new(&genesis_block);
+        let bank = Bank::new(&genesis_block).unwrap();
         let loader = bpf_loader::id();
 
         Loader {
@@ -201,8 +208,7 @@ fn test_program_native_noop() {
     solana_logger::setup();
 
     let loader = Loader::new_native();
-    let name = String::from("noop");
-    let userdata = name.as_bytes().to_vec();
+    let userdata = native_program("noop");
     let program = Program::new(&loader, &userdata);
 
     // Call user program
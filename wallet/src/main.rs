@@ -190,11 +190,61 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                         .takes_value(true)
                         .required(true)
                         .help("/path/to/program.o"),
+                )
+                .arg(
+                    Arg::with_name("upgradeable")
+                        .long("upgradeable")
+                        .takes_value(false)
+                        .help("Allow your key to upgrade the program"),
                 ), // TODO: Add "loader" argument; current default is bpf_loader
         )
         .subcommand(
             SubCommand::with_name("get-transaction-count").about("Get current transaction count"),
         )
+        .subcommand(
+            SubCommand::with_name("hash-lock")
+                .about("Send a payment that unlocks when the preimage of a hash is revealed")
+                .arg(
+                    Arg::with_name("to")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The pubkey of recipient"),
+                )
+                .arg(
+                    Arg::with_name("tokens")
+                        .index(2)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tokens to send"),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .index(3)
+                        .value_name("HASH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The hash of the secret that unlocks the tokens"),
+                )
+                .arg(
+                    Arg::with_name("refund-after")
+                        .long("refund-after")
+                        .value_name("DATETIME")
+                        .takes_value(true)
+                        .requires("timestamp-pubkey")
+                        .help("A timestamp after which you may reclaim the tokens if the secret is still unrevealed"),
+                )
+                .arg(
+                    Arg::with_name("timestamp-pubkey")
+                        .long("require-timestamp-from")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .requires("refund-after")
+                        .help("Require the refund timestamp from this third party"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("pay")
                 .about("Send a payment")
@@ -238,12 +288,91 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                         .use_delimiter(true)
                         .help("Any third party signatures required to unlock the tokens"),
                 )
+                .arg(
+                    Arg::with_name("required-signatures")
+                        .long("required-signatures")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .requires("witness")
+                        .help("How many of the signatures are required to unlock the tokens, defaults to all"),
+                )
                 .arg(
                     Arg::with_name("cancelable")
                         .long("cancelable")
                         .takes_value(false),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("pay-recurring")
+                .about("Pay a fixed amount every period until the tokens run out")
+                .arg(
+                    Arg::with_name("to")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The pubkey of recipient"),
+                )
+                .arg(
+                    Arg::with_name("tokens")
+                        .index(2)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The total number of tokens to pay"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .long("amount")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tokens to pay each period"),
+                )
+                .arg(
+                    Arg::with_name("period")
+                        .long("period")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of seconds between payments"),
+                )
+                .arg(
+                    Arg::with_name("start")
+                        .long("start")
+                        .value_name("DATETIME")
+                        .takes_value(true)
+                        .help("When the first payment comes due, defaults to now"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("send-preimage")
+                .about("Reveal a secret to unlock a hash-locked transfer")
+                .arg(
+                    Arg::with_name("to")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The pubkey of recipient"),
+                )
+                .arg(
+                    Arg::with_name("process-id")
+                        .index(2)
+                        .value_name("PROCESS_ID")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The process id of the transfer to unlock"),
+                )
+                .arg(
+                    Arg::with_name("preimage")
+                        .index(3)
+                        .value_name("SECRET")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The secret whose hash locks the transfer"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("send-signature")
                 .about("Send a signature to authorize a transfer")
@@ -291,6 +420,257 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                         .help("Optional arbitrary timestamp to apply"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("token-approve")
+                .about("Authorize a delegate account to transfer tokens from a token account")
+                .arg(
+                    Arg::with_name("source")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The token account to transfer from"),
+                )
+                .arg(
+                    Arg::with_name("delegate")
+                        .index(2)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The delegate account"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .index(3)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tokens the delegate may transfer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("token-balance")
+                .about("Get the balance of a token account, or the supply of a token")
+                .arg(
+                    Arg::with_name("account")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The token account or token"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("token-create")
+                .about("Create a new token, and a token account holding its supply")
+                .arg(
+                    Arg::with_name("supply")
+                        .index(1)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The initial supply of the token"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .index(2)
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Descriptive name of the token"),
+                )
+                .arg(
+                    Arg::with_name("symbol")
+                        .index(3)
+                        .value_name("SYMBOL")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Symbol of the token"),
+                )
+                .arg(
+                    Arg::with_name("decimals")
+                        .long("decimals")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Number of base 10 digits to the right of the decimal place"),
+                )
+                .arg(
+                    Arg::with_name("mintable")
+                        .long("mintable")
+                        .takes_value(false)
+                        .help("Allow your key to mint additional supply"),
+                )
+                .arg(
+                    Arg::with_name("freezable")
+                        .long("freezable")
+                        .takes_value(false)
+                        .help("Allow your key to freeze and thaw token accounts"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("token-create-account")
+                .about("Create a token account owned by your key")
+                .arg(
+                    Arg::with_name("token")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The token the account will hold"),
+                )
+                .arg(
+                    Arg::with_name("delegate-for")
+                        .long("delegate-for")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Make the account a delegate for this token account"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("token-transfer")
+                .about("Transfer tokens between token accounts")
+                .arg(
+                    Arg::with_name("source")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The token account to transfer from"),
+                )
+                .arg(
+                    Arg::with_name("dest")
+                        .index(2)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The token account to transfer to"),
+                )
+                .arg(
+                    Arg::with_name("amount")
+                        .index(3)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tokens to transfer"),
+                )
+                .arg(
+                    Arg::with_name("delegate-for")
+                        .long("delegate-for")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("If the source is a delegate account, the token account it is a delegate for"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("upgrade")
+                .about("Replace the code of a program you deployed with --upgradeable")
+                .arg(
+                    Arg::with_name("program-id")
+                        .index(1)
+                        .value_name("PROGRAM_ID")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The program to upgrade"),
+                )
+                .arg(
+                    Arg::with_name("program-location")
+                        .index(2)
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("/path/to/program.o"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-upgrade-authority")
+                .about("Hand the upgrade authority of a program to another key, or give it up")
+                .arg(
+                    Arg::with_name("program-id")
+                        .index(1)
+                        .value_name("PROGRAM_ID")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The program to change the upgrade authority of"),
+                )
+                .arg(
+                    Arg::with_name("new-authority")
+                        .index(2)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required_unless("final")
+                        .help("The new upgrade authority"),
+                )
+                .arg(
+                    Arg::with_name("final")
+                        .long("final")
+                        .takes_value(false)
+                        .conflicts_with("new-authority")
+                        .help("Make the program immutable; it can never be upgraded again"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vest")
+                .about("Vest tokens to a recipient in equal tranches")
+                .arg(
+                    Arg::with_name("to")
+                        .index(1)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The pubkey of recipient"),
+                )
+                .arg(
+                    Arg::with_name("tokens")
+                        .index(2)
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tokens to vest"),
+                )
+                .arg(
+                    Arg::with_name("tranches")
+                        .long("tranches")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of tranches to vest the tokens in"),
+                )
+                .arg(
+                    Arg::with_name("period")
+                        .long("period")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The number of seconds between tranches"),
+                )
+                .arg(
+                    Arg::with_name("start")
+                        .long("start")
+                        .value_name("DATETIME")
+                        .takes_value(true)
+                        .help("When the first tranche comes due, defaults to now"),
+                )
+                .arg(
+                    Arg::with_name("cliff")
+                        .long("cliff")
+                        .value_name("DATETIME")
+                        .takes_value(true)
+                        .help("Hold the tranches that come due before this timestamp until it passes"),
+                )
+                .arg(
+                    Arg::with_name("timestamp-pubkey")
+                        .long("require-timestamp-from")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Require timestamps from this third party"),
+                )
+                .arg(
+                    Arg::with_name("cancelable")
+                        .long("cancelable")
+                        .takes_value(false),
+                ),
+        )
         .get_matches();
 
     let config = parse_args(&matches)?;
@@ -13,19 +13,23 @@ use solana::rpc_request::{get_rpc_request_str, RpcRequest};
 #[cfg(not(test))]
 use solana_drone::drone::request_airdrop_transaction;
 use solana_drone::drone::DRONE_PORT;
+use solana_sdk::account::Account;
 use solana_sdk::bpf_loader;
-use solana_sdk::budget_program;
+use solana_sdk::budget_instruction::Instruction;
+use solana_sdk::budget_program::{self, BudgetError};
 use solana_sdk::budget_transaction::BudgetTransaction;
 use solana_sdk::hash::Hash;
 use solana_sdk::loader_transaction::LoaderTransaction;
+use solana_sdk::native_program::ProgramError;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, KeypairUtil, Signature};
 use solana_sdk::system_transaction::SystemTransaction;
+use solana_sdk::token_program::{self, TokenError, TokenInfo, TokenProgram};
+use solana_sdk::token_transaction::TokenTransaction;
 use solana_sdk::transaction::Transaction;
 use std::fs::File;
 use std::io::Read;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 use std::{error, fmt, mem};
@@ -39,19 +43,52 @@ pub enum WalletCommand {
     Balance,
     Cancel(Pubkey),
     Confirm(Signature),
-    Deploy(String),
+    // Deploy(program_location, upgradeable)
+    Deploy(String, bool),
     GetTransactionCount,
-    // Pay(tokens, to, timestamp, timestamp_pubkey, witness(es), cancelable)
+    // HashLock(tokens, to, hash, (refund_after, timestamp_pubkey))
+    HashLock(u64, Pubkey, Hash, Option<(DateTime<Utc>, Pubkey)>),
+    // Pay(tokens, to, timestamp, timestamp_pubkey, witness(es), required_signatures, cancelable)
     Pay(
         u64,
         Pubkey,
         Option<DateTime<Utc>>,
         Option<Pubkey>,
         Option<Vec<Pubkey>>,
+        Option<u64>,
         Option<Pubkey>,
     ),
+    // PayRecurring(tokens, to, start, period_secs, tranche_tokens)
+    PayRecurring(u64, Pubkey, DateTime<Utc>, u64, u64),
+    // Preimage(to, process_id, preimage)
+    Preimage(Pubkey, Pubkey, Vec<u8>),
+    // SetUpgradeAuthority(program_id, new_authority)
+    SetUpgradeAuthority(Pubkey, Option<Pubkey>),
     // TimeElapsed(to, process_id, timestamp)
     TimeElapsed(Pubkey, Pubkey, DateTime<Utc>),
+    // TokenApprove(source, delegate, amount)
+    TokenApprove(Pubkey, Pubkey, u64),
+    // TokenBalance(account)
+    TokenBalance(Pubkey),
+    // TokenCreate(token_info)
+    TokenCreate(TokenInfo),
+    // TokenCreateAccount(token, delegate_source)
+    TokenCreateAccount(Pubkey, Option<Pubkey>),
+    // TokenTransfer(source, dest, amount, delegate_source)
+    TokenTransfer(Pubkey, Pubkey, u64, Option<Pubkey>),
+    // Upgrade(program_id, program_location)
+    Upgrade(Pubkey, String),
+    // Vest(tokens, to, start, period_secs, num_tranches, cliff, timestamp_pubkey, cancelable)
+    Vest(
+        u64,
+        Pubkey,
+        DateTime<Utc>,
+        u64,
+        u64,
+        Option<DateTime<Utc>>,
+        Option<Pubkey>,
+        Option<Pubkey>,
+    ),
     // Witness(to, process_id)
     Witness(Pubkey, Pubkey),
 }
@@ -122,6 +159,33 @@ impl WalletConfig {
     }
 }
 
+fn parse_pubkey(matches: &ArgMatches<'_>, name: &str) -> Result<Pubkey, WalletError> {
+    let pubkey_vec = bs58::decode(matches.value_of(name).unwrap())
+        .into_vec()
+        .map_err(|_| WalletError::BadParameter(format!("Invalid {} public key", name)))?;
+
+    if pubkey_vec.len() != mem::size_of::<Pubkey>() {
+        eprintln!("{}", matches.usage());
+        Err(WalletError::BadParameter(format!(
+            "Invalid {} public key",
+            name
+        )))?;
+    }
+    Ok(Pubkey::new(&pubkey_vec))
+}
+
+/// Parse the DateTime argument `name`, or return `Utc::now()` if it wasn't given
+fn parse_datetime_or_now(
+    matches: &ArgMatches<'_>,
+    name: &str,
+) -> Result<DateTime<Utc>, Box<dyn error::Error>> {
+    match matches.value_of(name) {
+        Some(date) if date.contains('Z') => Ok(serde_json::from_str(&format!("\"{}\"", date))?),
+        Some(date) => Ok(serde_json::from_str(&format!("\"{}Z\"", date))?),
+        None => Ok(Utc::now()),
+    }
+}
+
 pub fn parse_command(
     pubkey: Pubkey,
     matches: &ArgMatches<'_>,
@@ -163,8 +227,75 @@ pub fn parse_command(
                 .value_of("program-location")
                 .unwrap()
                 .to_string(),
+            deploy_matches.is_present("upgradeable"),
         )),
         ("get-transaction-count", Some(_matches)) => Ok(WalletCommand::GetTransactionCount),
+        ("hash-lock", Some(hash_lock_matches)) => {
+            let pubkey_vec = bs58::decode(hash_lock_matches.value_of("to").unwrap())
+                .into_vec()
+                .expect("base58-encoded public key");
+
+            if pubkey_vec.len() != mem::size_of::<Pubkey>() {
+                eprintln!("{}", hash_lock_matches.usage());
+                Err(WalletError::BadParameter(
+                    "Invalid to public key".to_string(),
+                ))?;
+            }
+            let to = Pubkey::new(&pubkey_vec);
+            let tokens = hash_lock_matches.value_of("tokens").unwrap().parse()?;
+
+            let hash_vec = bs58::decode(hash_lock_matches.value_of("hash").unwrap())
+                .into_vec()
+                .map_err(|_| WalletError::BadParameter("Invalid hash".to_string()))?;
+            if hash_vec.len() != mem::size_of::<Hash>() {
+                eprintln!("{}", hash_lock_matches.usage());
+                Err(WalletError::BadParameter("Invalid hash".to_string()))?;
+            }
+            let hash = Hash::new(&hash_vec);
+
+            let refund = if hash_lock_matches.is_present("refund-after") {
+                // Parse input for serde_json
+                let date_string = if !hash_lock_matches
+                    .value_of("refund-after")
+                    .unwrap()
+                    .contains('Z')
+                {
+                    format!(
+                        "\"{}Z\"",
+                        hash_lock_matches.value_of("refund-after").unwrap()
+                    )
+                } else {
+                    format!(
+                        "\"{}\"",
+                        hash_lock_matches.value_of("refund-after").unwrap()
+                    )
+                };
+                let pubkey_vec =
+                    bs58::decode(hash_lock_matches.value_of("timestamp-pubkey").unwrap())
+                        .into_vec()
+                        .map_err(|_| {
+                            WalletError::BadParameter("Invalid timestamp public key".to_string())
+                        })?;
+                if pubkey_vec.len() != mem::size_of::<Pubkey>() {
+                    eprintln!("{}", hash_lock_matches.usage());
+                    Err(WalletError::BadParameter(
+                        "Invalid timestamp public key".to_string(),
+                    ))?;
+                }
+                // The refund is only time-locked if someone other than the payer
+                // witnesses the time
+                let timestamp_pubkey = Pubkey::new(&pubkey_vec);
+                if timestamp_pubkey == pubkey {
+                    Err(WalletError::BadParameter(
+                        "The timestamp must come from a third party".to_string(),
+                    ))?;
+                }
+                Some((serde_json::from_str(&date_string)?, timestamp_pubkey))
+            } else {
+                None
+            };
+            Ok(WalletCommand::HashLock(tokens, to, hash, refund))
+        }
         ("pay", Some(pay_matches)) => {
             let tokens = pay_matches.value_of("tokens").unwrap().parse()?;
             let to = if pay_matches.is_present("to") {
@@ -222,12 +353,36 @@ pub fn parse_command(
                             "Invalid witness public key".to_string(),
                         ))?;
                     }
-                    collection.push(Pubkey::new(&pubkey_vec));
+                    let pubkey = Pubkey::new(&pubkey_vec);
+                    if collection.contains(&pubkey) {
+                        eprintln!("{}", pay_matches.usage());
+                        Err(WalletError::BadParameter(
+                            "Duplicate witness public key".to_string(),
+                        ))?;
+                    }
+                    collection.push(pubkey);
                 }
                 Some(collection)
             } else {
                 None
             };
+            let required_signatures = if pay_matches.is_present("required-signatures") {
+                let required_signatures: u64 = pay_matches
+                    .value_of("required-signatures")
+                    .unwrap()
+                    .parse()?;
+                let num_witnesses = witness_vec.as_ref().map_or(0, |witnesses| witnesses.len());
+                if required_signatures == 0 || required_signatures > num_witnesses as u64 {
+                    eprintln!("{}", pay_matches.usage());
+                    Err(WalletError::BadParameter(format!(
+                        "Required signatures must be between 1 and {}",
+                        num_witnesses
+                    )))?;
+                }
+                Some(required_signatures)
+            } else {
+                None
+            };
             let cancelable = if pay_matches.is_present("cancelable") {
                 Some(pubkey)
             } else {
@@ -240,9 +395,57 @@ pub fn parse_command(
                 timestamp,
                 timestamp_pubkey,
                 witness_vec,
+                required_signatures,
                 cancelable,
             ))
         }
+        ("pay-recurring", Some(recurring_matches)) => {
+            let to = parse_pubkey(recurring_matches, "to")?;
+            let tokens = recurring_matches.value_of("tokens").unwrap().parse()?;
+            let start = parse_datetime_or_now(recurring_matches, "start")?;
+            let period_secs = recurring_matches.value_of("period").unwrap().parse()?;
+            let tranche_tokens = recurring_matches.value_of("amount").unwrap().parse()?;
+            if tranche_tokens == 0 {
+                eprintln!("{}", recurring_matches.usage());
+                Err(WalletError::BadParameter(
+                    "The amount per period must be positive".to_string(),
+                ))?;
+            }
+            Ok(WalletCommand::PayRecurring(
+                tokens,
+                to,
+                start,
+                period_secs,
+                tranche_tokens,
+            ))
+        }
+        ("send-preimage", Some(preimage_matches)) => {
+            let pubkey_vec = bs58::decode(preimage_matches.value_of("to").unwrap())
+                .into_vec()
+                .expect("base58-encoded public key");
+
+            if pubkey_vec.len() != mem::size_of::<Pubkey>() {
+                eprintln!("{}", preimage_matches.usage());
+                Err(WalletError::BadParameter("Invalid public key".to_string()))?;
+            }
+            let to = Pubkey::new(&pubkey_vec);
+
+            let pubkey_vec = bs58::decode(preimage_matches.value_of("process-id").unwrap())
+                .into_vec()
+                .expect("base58-encoded public key");
+
+            if pubkey_vec.len() != mem::size_of::<Pubkey>() {
+                eprintln!("{}", preimage_matches.usage());
+                Err(WalletError::BadParameter("Invalid public key".to_string()))?;
+            }
+            let process_id = Pubkey::new(&pubkey_vec);
+            let preimage = preimage_matches
+                .value_of("preimage")
+                .unwrap()
+                .as_bytes()
+                .to_vec();
+            Ok(WalletCommand::Preimage(to, process_id, preimage))
+        }
         ("send-signature", Some(sig_matches)) => {
             let pubkey_vec = bs58::decode(sig_matches.value_of("to").unwrap())
                 .into_vec()
@@ -302,6 +505,116 @@ pub fn parse_command(
             };
             Ok(WalletCommand::TimeElapsed(to, process_id, dt))
         }
+        ("token-approve", Some(approve_matches)) => {
+            let source = parse_pubkey(approve_matches, "source")?;
+            let delegate = parse_pubkey(approve_matches, "delegate")?;
+            let amount = approve_matches.value_of("amount").unwrap().parse()?;
+            Ok(WalletCommand::TokenApprove(source, delegate, amount))
+        }
+        ("token-balance", Some(balance_matches)) => Ok(WalletCommand::TokenBalance(parse_pubkey(
+            balance_matches,
+            "account",
+        )?)),
+        ("token-create", Some(create_matches)) => {
+            let authority = |flag| {
+                if create_matches.is_present(flag) {
+                    Some(pubkey)
+                } else {
+                    None
+                }
+            };
+            Ok(WalletCommand::TokenCreate(TokenInfo {
+                supply: create_matches.value_of("supply").unwrap().parse()?,
+                decimals: create_matches.value_of("decimals").unwrap().parse()?,
+                name: create_matches.value_of("name").unwrap().to_string(),
+                symbol: create_matches.value_of("symbol").unwrap().to_string(),
+                mint_authority: authority("mintable"),
+                freeze_authority: authority("freezable"),
+            }))
+        }
+        ("token-create-account", Some(create_matches)) => {
+            let token = parse_pubkey(create_matches, "token")?;
+            let delegate_source = if create_matches.is_present("delegate-for") {
+                Some(parse_pubkey(create_matches, "delegate-for")?)
+            } else {
+                None
+            };
+            Ok(WalletCommand::TokenCreateAccount(token, delegate_source))
+        }
+        ("token-transfer", Some(transfer_matches)) => {
+            let source = parse_pubkey(transfer_matches, "source")?;
+            let dest = parse_pubkey(transfer_matches, "dest")?;
+            let amount = transfer_matches.value_of("amount").unwrap().parse()?;
+            let delegate_source = if transfer_matches.is_present("delegate-for") {
+                Some(parse_pubkey(transfer_matches, "delegate-for")?)
+            } else {
+                None
+            };
+            Ok(WalletCommand::TokenTransfer(
+                source,
+                dest,
+                amount,
+                delegate_source,
+            ))
+        }
+        ("upgrade", Some(upgrade_matches)) => {
+            let program_id = parse_pubkey(upgrade_matches, "program-id")?;
+            let program_location = upgrade_matches
+                .value_of("program-location")
+                .unwrap()
+                .to_string();
+            Ok(WalletCommand::Upgrade(program_id, program_location))
+        }
+        ("set-upgrade-authority", Some(authority_matches)) => {
+            let program_id = parse_pubkey(authority_matches, "program-id")?;
+            let new_authority = if authority_matches.is_present("final") {
+                None
+            } else {
+                Some(parse_pubkey(authority_matches, "new-authority")?)
+            };
+            Ok(WalletCommand::SetUpgradeAuthority(
+                program_id,
+                new_authority,
+            ))
+        }
+        ("vest", Some(vest_matches)) => {
+            let to = parse_pubkey(vest_matches, "to")?;
+            let tokens = vest_matches.value_of("tokens").unwrap().parse()?;
+            let start = parse_datetime_or_now(vest_matches, "start")?;
+            let period_secs = vest_matches.value_of("period").unwrap().parse()?;
+            let num_tranches = vest_matches.value_of("tranches").unwrap().parse()?;
+            if num_tranches == 0 {
+                eprintln!("{}", vest_matches.usage());
+                Err(WalletError::BadParameter(
+                    "The number of tranches must be positive".to_string(),
+                ))?;
+            }
+            let cliff = if vest_matches.is_present("cliff") {
+                Some(parse_datetime_or_now(vest_matches, "cliff")?)
+            } else {
+                None
+            };
+            let timestamp_pubkey = if vest_matches.is_present("timestamp-pubkey") {
+                Some(parse_pubkey(vest_matches, "timestamp-pubkey")?)
+            } else {
+                None
+            };
+            let cancelable = if vest_matches.is_present("cancelable") {
+                Some(pubkey)
+            } else {
+                None
+            };
+            Ok(WalletCommand::Vest(
+                tokens,
+                to,
+                start,
+                period_secs,
+                num_tranches,
+                cliff,
+                timestamp_pubkey,
+                cancelable,
+            ))
+        }
         ("", None) => {
             eprintln!("{}", matches.usage());
             Err(WalletError::CommandNotRecognized(
@@ -388,9 +701,8 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
         // Cancel a contract by contract Pubkey
         WalletCommand::Cancel(pubkey) => {
             let last_id = get_last_id(&rpc_client)?;
-            let mut tx =
-                Transaction::budget_new_signature(&config.id, pubkey, config.id.pubkey(), last_id);
-            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
+            let mut tx = Transaction::budget_new_cancel(&config.id, pubkey, last_id);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
             Ok(signature_str.to_string())
         }
         // Confirm the last client transaction by signature
@@ -413,7 +725,7 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
             }
         }
         // Deploy a custom program to the chain
-        WalletCommand::Deploy(ref program_location) => {
+        WalletCommand::Deploy(ref program_location, upgradeable) => {
             let params = json!([format!("{}", config.id.pubkey())]);
             let balance = rpc_client
                 .retry_make_rpc_request(1, &RpcRequest::GetBalance, Some(params), 5)?
@@ -426,60 +738,36 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
                 }
             }
 
-            let last_id = get_last_id(&rpc_client)?;
             let program_id = Keypair::new();
-            let mut file = File::open(program_location).map_err(|err| {
-                WalletError::DynamicProgramError(
-                    format!("Unable to open program file: {}", err).to_string(),
-                )
-            })?;
-            let mut program_userdata = Vec::new();
-            file.read_to_end(&mut program_userdata).map_err(|err| {
-                WalletError::DynamicProgramError(
-                    format!("Unable to read program file: {}", err).to_string(),
-                )
-            })?;
-
-            let mut tx = Transaction::system_create(
-                &config.id,
-                program_id.pubkey(),
-                last_id,
-                1,
-                program_userdata.len() as u64,
-                bpf_loader::id(),
-                0,
-            );
-            send_and_confirm_tx(&rpc_client, &mut tx, &config.id).map_err(|_| {
-                WalletError::DynamicProgramError("Program allocate space failed".to_string())
-            })?;
+            let program_elf = read_program(program_location)?;
+            write_program(&rpc_client, &config.id, &program_id, &program_elf)?;
 
-            let mut offset = 0;
-            for chunk in program_userdata.chunks(USERDATA_CHUNK_SIZE) {
-                let mut tx = Transaction::loader_write(
+            let last_id = get_last_id(&rpc_client)?;
+            let finalized = if upgradeable {
+                // The code moves into a program-data account, with the wallet as its upgrade
+                // authority
+                let programdata = Keypair::new();
+                create_program_account(&rpc_client, &config.id, &programdata, 0)?;
+                let mut tx = Transaction::loader_finalize_upgradeable(
                     &program_id,
+                    &programdata,
+                    config.id.pubkey(),
                     bpf_loader::id(),
-                    offset,
-                    chunk.to_vec(),
                     last_id,
                     0,
                 );
-                send_and_confirm_tx(&rpc_client, &mut tx, &program_id).map_err(|_| {
-                    WalletError::DynamicProgramError(format!(
-                        "Program write failed at offset {:?}",
-                        offset
-                    ))
-                })?;
-                offset += USERDATA_CHUNK_SIZE as u32;
-            }
-
-            let last_id = get_last_id(&rpc_client)?;
-            let mut tx = Transaction::loader_finalize(&program_id, bpf_loader::id(), last_id, 0);
-            send_and_confirm_tx(&rpc_client, &mut tx, &program_id).map_err(|_| {
+                send_and_confirm_tx(&rpc_client, &mut tx, &[&program_id, &programdata])
+            } else {
+                let mut tx =
+                    Transaction::loader_finalize(&program_id, bpf_loader::id(), last_id, 0);
+                send_and_confirm_tx(&rpc_client, &mut tx, &[&program_id])
+            };
+            finalized.map_err(|_| {
                 WalletError::DynamicProgramError("Program finalize transaction failed".to_string())
             })?;
 
             let mut tx = Transaction::system_spawn(&program_id, last_id, 0);
-            send_and_confirm_tx(&rpc_client, &mut tx, &program_id).map_err(|_| {
+            send_and_confirm_tx(&rpc_client, &mut tx, &[&program_id]).map_err(|_| {
                 WalletError::DynamicProgramError("Program spawn failed".to_string())
             })?;
 
@@ -488,6 +776,50 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
             })
             .to_string())
         }
+        // Replace the code of an upgradeable program, staging the new code in a buffer account
+        WalletCommand::Upgrade(program_id, ref program_location) => {
+            let buffer = Keypair::new();
+            let program_elf = read_program(program_location)?;
+            write_program(&rpc_client, &config.id, &buffer, &program_elf)?;
+
+            let programdata = get_programdata_address(&rpc_client, &program_id)?;
+            let last_id = get_last_id(&rpc_client)?;
+            let mut tx = Transaction::loader_upgrade(
+                &buffer,
+                &config.id,
+                program_id,
+                programdata,
+                bpf_loader::id(),
+                last_id,
+                0,
+            );
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&buffer, &config.id])
+                .map_err(|_| {
+                WalletError::DynamicProgramError("Program upgrade failed".to_string())
+            })?;
+
+            Ok(signature_str.to_string())
+        }
+        // Hand the upgrade authority of a program to another key, or make the program immutable
+        WalletCommand::SetUpgradeAuthority(program_id, new_authority) => {
+            let programdata = get_programdata_address(&rpc_client, &program_id)?;
+            let last_id = get_last_id(&rpc_client)?;
+            let mut tx = Transaction::loader_set_upgrade_authority(
+                &config.id,
+                program_id,
+                programdata,
+                new_authority,
+                bpf_loader::id(),
+                last_id,
+                0,
+            );
+            let signature_str =
+                send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id]).map_err(|_| {
+                    WalletError::DynamicProgramError("Set upgrade authority failed".to_string())
+                })?;
+
+            Ok(signature_str.to_string())
+        }
         WalletCommand::GetTransactionCount => {
             let transaction_count = rpc_client
                 .retry_make_rpc_request(1, &RpcRequest::GetTransactionCount, None, 5)?
@@ -499,13 +831,46 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
                 ))?,
             }
         }
+        // Lock tokens behind the hash of a secret, optionally refundable after a timestamp
+        WalletCommand::HashLock(tokens, to, hash, refund) => {
+            let last_id = get_last_id(&rpc_client)?;
+            let contract_funds = Keypair::new();
+            let contract_state = Keypair::new();
+            let contract_tx = Transaction::budget_new_when_hash_locked(
+                &contract_funds,
+                to,
+                contract_state.pubkey(),
+                hash,
+                refund.map(|(dt, timestamp_pubkey)| (dt, timestamp_pubkey, config.id.pubkey())),
+                tokens,
+                last_id,
+            );
+            create_contract(
+                &rpc_client,
+                config,
+                tokens,
+                &contract_funds,
+                &contract_state,
+                contract_tx,
+                0,
+                last_id,
+            )
+        }
         // If client has positive balance, pay tokens to another address
-        WalletCommand::Pay(tokens, to, timestamp, timestamp_pubkey, ref witnesses, cancelable) => {
+        WalletCommand::Pay(
+            tokens,
+            to,
+            timestamp,
+            timestamp_pubkey,
+            ref witnesses,
+            required_signatures,
+            cancelable,
+        ) => {
             let last_id = get_last_id(&rpc_client)?;
 
             if timestamp == None && *witnesses == None {
                 let mut tx = Transaction::system_new(&config.id, to, tokens, last_id);
-                let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
+                let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
                 Ok(signature_str.to_string())
             } else if *witnesses == None {
                 let dt = timestamp.unwrap();
@@ -516,34 +881,7 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
 
                 let contract_funds = Keypair::new();
                 let contract_state = Keypair::new();
-                let budget_program_id = budget_program::id();
-
-                // Create account for contract funds
-                let mut tx = Transaction::system_create(
-                    &config.id,
-                    contract_funds.pubkey(),
-                    last_id,
-                    tokens,
-                    0,
-                    budget_program_id,
-                    0,
-                );
-                send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                // Create account for contract state
-                let mut tx = Transaction::system_create(
-                    &config.id,
-                    contract_state.pubkey(),
-                    last_id,
-                    1,
-                    196,
-                    budget_program_id,
-                    0,
-                );
-                send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                // Initializing contract
-                let mut tx = Transaction::budget_new_on_date(
+                let contract_tx = Transaction::budget_new_on_date(
                     &contract_funds,
                     to,
                     contract_state.pubkey(),
@@ -553,75 +891,100 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
                     tokens,
                     last_id,
                 );
-                let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                Ok(json!({
-                    "signature": signature_str,
-                    "processId": format!("{}", contract_state.pubkey()),
-                })
-                .to_string())
+                create_contract(
+                    &rpc_client,
+                    config,
+                    tokens,
+                    &contract_funds,
+                    &contract_state,
+                    contract_tx,
+                    0,
+                    last_id,
+                )
             } else if timestamp == None {
                 let last_id = get_last_id(&rpc_client)?;
 
-                let witness = if let Some(ref witness_vec) = *witnesses {
-                    witness_vec[0]
+                let witness_vec = if let Some(ref witness_vec) = *witnesses {
+                    witness_vec.clone()
                 } else {
                     Err(WalletError::BadParameter(
                         "Could not parse required signature pubkey(s)".to_string(),
                     ))?
                 };
+                let is_multisig = witness_vec.len() > 1 || required_signatures.is_some();
 
                 let contract_funds = Keypair::new();
                 let contract_state = Keypair::new();
-                let budget_program_id = budget_program::id();
+                let contract_tx = if is_multisig {
+                    let threshold = required_signatures.unwrap_or(witness_vec.len() as u64);
+                    Transaction::budget_new_when_multisigned(
+                        &contract_funds,
+                        to,
+                        contract_state.pubkey(),
+                        threshold,
+                        witness_vec.clone(),
+                        cancelable,
+                        tokens,
+                        last_id,
+                    )
+                } else {
+                    Transaction::budget_new_when_signed(
+                        &contract_funds,
+                        to,
+                        contract_state.pubkey(),
+                        witness_vec[0],
+                        cancelable,
+                        tokens,
+                        last_id,
+                    )
+                };
 
-                // Create account for contract funds
-                let mut tx = Transaction::system_create(
-                    &config.id,
-                    contract_funds.pubkey(),
-                    last_id,
+                // Leave room in the contract state for the signatures collected from
+                // the witnesses if this is a multisig contract
+                let max_signers = if is_multisig { witness_vec.len() } else { 0 };
+                create_contract(
+                    &rpc_client,
+                    config,
                     tokens,
-                    0,
-                    budget_program_id,
-                    0,
-                );
-                send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                // Create account for contract state
-                let mut tx = Transaction::system_create(
-                    &config.id,
-                    contract_state.pubkey(),
-                    last_id,
-                    1,
-                    196,
-                    budget_program_id,
-                    0,
-                );
-                send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                // Initializing contract
-                let mut tx = Transaction::budget_new_when_signed(
                     &contract_funds,
-                    to,
-                    contract_state.pubkey(),
-                    witness,
-                    cancelable,
-                    tokens,
+                    &contract_state,
+                    contract_tx,
+                    max_signers,
                     last_id,
-                );
-                let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
-
-                Ok(json!({
-                    "signature": signature_str,
-                    "processId": format!("{}", contract_state.pubkey()),
-                })
-                .to_string())
+                )
             } else {
                 Ok("Combo transactions not yet handled".to_string())
             }
         }
-        // Apply time elapsed to contract
-        WalletCommand::TimeElapsed(to, pubkey, dt) => {
+        // Pay tokens to another address every period until they run out
+        WalletCommand::PayRecurring(tokens, to, start, period_secs, tranche_tokens) => {
+            let last_id = get_last_id(&rpc_client)?;
+            let contract_funds = Keypair::new();
+            let contract_state = Keypair::new();
+            let contract_tx = Transaction::budget_new_recurring(
+                &contract_funds,
+                to,
+                contract_state.pubkey(),
+                start,
+                period_secs,
+                tranche_tokens,
+                config.id.pubkey(),
+                tokens,
+                last_id,
+            );
+            create_contract(
+                &rpc_client,
+                config,
+                tokens,
+                &contract_funds,
+                &contract_state,
+                contract_tx,
+                0,
+                last_id,
+            )
+        }
+        // Reveal a preimage to contract
+        WalletCommand::Preimage(to, pubkey, ref preimage) => {
             let params = json!([format!("{}", config.id.pubkey())]);
             let balance = rpc_client
                 .retry_make_rpc_request(1, &RpcRequest::GetBalance, Some(params), 5)?
@@ -632,14 +995,14 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
             }
 
             let last_id = get_last_id(&rpc_client)?;
-
-            let mut tx = Transaction::budget_new_timestamp(&config.id, pubkey, to, dt, last_id);
-            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
+            let mut tx =
+                Transaction::budget_new_preimage(&config.id, pubkey, to, preimage.clone(), last_id);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
 
             Ok(signature_str.to_string())
         }
-        // Apply witness signature to contract
-        WalletCommand::Witness(to, pubkey) => {
+        // Apply time elapsed to contract
+        WalletCommand::TimeElapsed(to, pubkey, dt) => {
             let params = json!([format!("{}", config.id.pubkey())]);
             let balance = rpc_client
                 .retry_make_rpc_request(1, &RpcRequest::GetBalance, Some(params), 5)?
@@ -650,18 +1013,350 @@ pub fn process_command(config: &WalletConfig) -> Result<String, Box<dyn error::E
             }
 
             let last_id = get_last_id(&rpc_client)?;
-            let mut tx = Transaction::budget_new_signature(&config.id, pubkey, to, last_id);
-            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &config.id)?;
+
+            let mut tx = Transaction::budget_new_timestamp(&config.id, pubkey, to, dt, last_id);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
 
             Ok(signature_str.to_string())
         }
-    }
-}
+        // Authorize a delegate to transfer tokens from a token account
+        WalletCommand::TokenApprove(source, delegate, amount) => {
+            let last_id = get_last_id(&rpc_client)?;
+            let mut tx =
+                Transaction::token_approve(&config.id, source, delegate, amount, last_id, 0);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+            Ok(signature_str.to_string())
+        }
+        // Decode the token program state of an account
+        WalletCommand::TokenBalance(pubkey) => {
+            let params = json!([format!("{}", pubkey)]);
+            let account_json = rpc_client.retry_make_rpc_request(
+                1,
+                &RpcRequest::GetAccountInfo,
+                Some(params),
+                5,
+            )?;
+            let account: Account = serde_json::from_value(account_json).map_err(|_| {
+                WalletError::RpcRequestError("Received bad account info".to_string())
+            })?;
+            if !token_program::check_id(&account.owner) {
+                Err(WalletError::BadParameter(
+                    "Account is not owned by the token program".to_string(),
+                ))?;
+            }
+            match TokenProgram::deserialize(&account.userdata) {
+                Ok(TokenProgram::Token(token_info)) => Ok(format!(
+                    "Token supply is: {} {}",
+                    token_info.supply, token_info.symbol
+                )),
+                Ok(TokenProgram::Account(account_info)) => {
+                    if account_info.delegate.is_some() {
+                        Ok(format!("Delegate allowance is: {}", account_info.amount))
+                    } else {
+                        Ok(format!("Token balance is: {}", account_info.amount))
+                    }
+                }
+                _ => Err(WalletError::BadParameter(
+                    "Account is not a token or token account".to_string(),
+                ))?,
+            }
+        }
+        // Create a token, and a token account owned by this client holding its supply
+        WalletCommand::TokenCreate(ref token_info) => {
+            let last_id = get_last_id(&rpc_client)?;
 
-fn get_last_id(rpc_client: &RpcClient) -> Result<Hash, Box<dyn error::Error>> {
-    let result = rpc_client.retry_make_rpc_request(1, &RpcRequest::GetLastId, None, 5)?;
-    if result.as_str().is_none() {
-        Err(WalletError::RpcRequestError(
+            let token = Keypair::new();
+            let account = Keypair::new();
+            let token_program_id = token_program::id();
+
+            // Create the token account for the supply
+            let mut tx = Transaction::system_create(
+                &config.id,
+                account.pubkey(),
+                last_id,
+                1,
+                token_program::get_account_size() as u64,
+                token_program_id,
+                0,
+            );
+            send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+            let mut tx = Transaction::token_new_account(
+                &account,
+                config.id.pubkey(),
+                token.pubkey(),
+                None,
+                last_id,
+                0,
+            );
+            send_and_confirm_tx(&rpc_client, &mut tx, &[&account])?;
+
+            // Create the token
+            let mut tx = Transaction::system_create(
+                &config.id,
+                token.pubkey(),
+                last_id,
+                1,
+                token_program::get_token_size(token_info) as u64,
+                token_program_id,
+                0,
+            );
+            send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+            let mut tx =
+                Transaction::token_new(&token, account.pubkey(), token_info.clone(), last_id, 0);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&token])?;
+
+            Ok(json!({
+                "signature": signature_str,
+                "token": format!("{}", token.pubkey()),
+                "account": format!("{}", account.pubkey()),
+            })
+            .to_string())
+        }
+        // Create a token account owned by this client
+        WalletCommand::TokenCreateAccount(token, delegate_source) => {
+            let last_id = get_last_id(&rpc_client)?;
+
+            let account = Keypair::new();
+            let mut tx = Transaction::system_create(
+                &config.id,
+                account.pubkey(),
+                last_id,
+                1,
+                token_program::get_account_size() as u64,
+                token_program::id(),
+                0,
+            );
+            send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+            let mut tx = Transaction::token_new_account(
+                &account,
+                config.id.pubkey(),
+                token,
+                delegate_source,
+                last_id,
+                0,
+            );
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&account])?;
+
+            Ok(json!({
+                "signature": signature_str,
+                "account": format!("{}", account.pubkey()),
+            })
+            .to_string())
+        }
+        // Transfer tokens between token accounts
+        WalletCommand::TokenTransfer(source, dest, amount, delegate_source) => {
+            let last_id = get_last_id(&rpc_client)?;
+            let mut tx = Transaction::token_transfer(
+                &config.id,
+                source,
+                dest,
+                delegate_source,
+                amount,
+                last_id,
+                0,
+            );
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+            Ok(signature_str.to_string())
+        }
+        // Apply witness signature to contract
+        // Vest tokens to another address in tranches
+        WalletCommand::Vest(
+            tokens,
+            to,
+            start,
+            period_secs,
+            num_tranches,
+            cliff,
+            timestamp_pubkey,
+            cancelable,
+        ) => {
+            let last_id = get_last_id(&rpc_client)?;
+            let contract_funds = Keypair::new();
+            let contract_state = Keypair::new();
+            let contract_tx = Transaction::budget_new_vesting(
+                &contract_funds,
+                to,
+                contract_state.pubkey(),
+                start,
+                period_secs,
+                num_tranches,
+                cliff,
+                timestamp_pubkey.unwrap_or_else(|| config.id.pubkey()),
+                cancelable,
+                tokens,
+                last_id,
+            );
+            create_contract(
+                &rpc_client,
+                config,
+                tokens,
+                &contract_funds,
+                &contract_state,
+                contract_tx,
+                0,
+                last_id,
+            )
+        }
+        WalletCommand::Witness(to, pubkey) => {
+            let params = json!([format!("{}", config.id.pubkey())]);
+            let balance = rpc_client
+                .retry_make_rpc_request(1, &RpcRequest::GetBalance, Some(params), 5)?
+                .as_u64();
+
+            if let Some(0) = balance {
+                request_and_confirm_airdrop(&rpc_client, &drone_addr, &config.id, 1)?;
+            }
+
+            let last_id = get_last_id(&rpc_client)?;
+            let mut tx = Transaction::budget_new_signature(&config.id, pubkey, to, last_id);
+            let signature_str = send_and_confirm_tx(&rpc_client, &mut tx, &[&config.id])?;
+
+            Ok(signature_str.to_string())
+        }
+    }
+}
+
+/// Fund `contract_funds` with `tokens` and create `contract_state` with room for the
+/// budget `contract_tx` initializes it with and the signatures of up to `max_signers`
+/// of its witnesses, then send `contract_tx`
+fn create_contract(
+    rpc_client: &RpcClient,
+    config: &WalletConfig,
+    tokens: u64,
+    contract_funds: &Keypair,
+    contract_state: &Keypair,
+    mut contract_tx: Transaction,
+    max_signers: usize,
+    last_id: Hash,
+) -> Result<String, Box<dyn error::Error>> {
+    let budget_program_id = budget_program::id();
+
+    // Create account for contract funds
+    let mut tx = Transaction::system_create(
+        &config.id,
+        contract_funds.pubkey(),
+        last_id,
+        tokens,
+        0,
+        budget_program_id,
+        0,
+    );
+    send_and_confirm_tx(rpc_client, &mut tx, &[&config.id])?;
+
+    // Create account for contract state
+    let state_size = match contract_tx.instruction(0) {
+        Some(Instruction::NewBudget(ref expr)) => budget_program::get_state_size(expr, max_signers),
+        _ => Err(WalletError::BadParameter(
+            "Not a budget contract".to_string(),
+        ))?,
+    };
+    let mut tx = Transaction::system_create(
+        &config.id,
+        contract_state.pubkey(),
+        last_id,
+        1,
+        state_size,
+        budget_program_id,
+        0,
+    );
+    send_and_confirm_tx(rpc_client, &mut tx, &[&config.id])?;
+
+    // Initializing contract
+    let signature_str = send_and_confirm_tx(rpc_client, &mut contract_tx, &[&config.id])?;
+
+    Ok(json!({
+        "signature": signature_str,
+        "processId": format!("{}", contract_state.pubkey()),
+    })
+    .to_string())
+}
+
+fn read_program(program_location: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let mut file = File::open(program_location).map_err(|err| {
+        WalletError::DynamicProgramError(
+            format!("Unable to open program file: {}", err).to_string(),
+        )
+    })?;
+    let mut program_userdata = Vec::new();
+    file.read_to_end(&mut program_userdata).map_err(|err| {
+        WalletError::DynamicProgramError(
+            format!("Unable to read program file: {}", err).to_string(),
+        )
+    })?;
+    Ok(program_userdata)
+}
+
+/// Create a BPF loader account for `program_id` and write `program_userdata` into it
+/// Create an account of `space` bytes owned by the BPF loader
+fn create_program_account(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    account: &Keypair,
+    space: u64,
+) -> Result<(), Box<dyn error::Error>> {
+    let last_id = get_last_id(rpc_client)?;
+    let mut tx = Transaction::system_create(
+        payer,
+        account.pubkey(),
+        last_id,
+        1,
+        space,
+        bpf_loader::id(),
+        0,
+    );
+    send_and_confirm_tx(rpc_client, &mut tx, &[payer]).map_err(|_| {
+        WalletError::DynamicProgramError("Program allocate space failed".to_string())
+    })?;
+    Ok(())
+}
+
+fn write_program(
+    rpc_client: &RpcClient,
+    payer: &Keypair,
+    program_id: &Keypair,
+    program_userdata: &[u8],
+) -> Result<(), Box<dyn error::Error>> {
+    create_program_account(rpc_client, payer, program_id, program_userdata.len() as u64)?;
+
+    let last_id = get_last_id(rpc_client)?;
+    let mut offset = 0;
+    for chunk in program_userdata.chunks(USERDATA_CHUNK_SIZE) {
+        let mut tx = Transaction::loader_write(
+            program_id,
+            bpf_loader::id(),
+            offset,
+            chunk.to_vec(),
+            last_id,
+            0,
+        );
+        send_and_confirm_tx(rpc_client, &mut tx, &[program_id]).map_err(|_| {
+            WalletError::DynamicProgramError(format!("Program write failed at offset {:?}", offset))
+        })?;
+        offset += USERDATA_CHUNK_SIZE as u32;
+    }
+    Ok(())
+}
+
+/// Look up the program-data account that holds the code of the upgradeable program `program_id`
+fn get_programdata_address(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<Pubkey, Box<dyn error::Error>> {
+    let params = json!([format!("{}", program_id)]);
+    let account_json =
+        rpc_client.retry_make_rpc_request(1, &RpcRequest::GetAccountInfo, Some(params), 5)?;
+    let account: Account = serde_json::from_value(account_json)
+        .map_err(|_| WalletError::RpcRequestError("Received bad account info".to_string()))?;
+    let programdata = bpf_loader::get_programdata_address(&account).ok_or_else(|| {
+        WalletError::BadParameter(format!("{} is not an upgradeable program", program_id))
+    })?;
+    Ok(programdata)
+}
+
+fn get_last_id(rpc_client: &RpcClient) -> Result<Hash, Box<dyn error::Error>> {
+    let result = rpc_client.retry_make_rpc_request(1, &RpcRequest::GetLastId, None, 5)?;
+    if result.as_str().is_none() {
+        Err(WalletError::RpcRequestError(
             "Received bad last_id".to_string(),
         ))?
     }
@@ -692,22 +1387,40 @@ fn confirm_tx(
     let params = json!([signature.to_string()]);
     let signature_status =
         rpc_client.retry_make_rpc_request(1, &RpcRequest::GetSignatureStatus, Some(params), 5)?;
-    if let Some(status) = signature_status.as_str() {
-        let rpc_status = RpcSignatureStatus::from_str(status).map_err(|_| {
-            WalletError::RpcRequestError("Unable to parse signature status".to_string())
-        })?;
-        Ok(rpc_status)
-    } else {
-        Err(WalletError::RpcRequestError(
-            "Received result of an unexpected type".to_string(),
-        ))?
-    }
+    let rpc_status = serde_json::from_value(signature_status).map_err(|_| {
+        WalletError::RpcRequestError("Unable to parse signature status".to_string())
+    })?;
+    Ok(rpc_status)
+}
+
+/// Describe the status of a transaction, naming the error of a failed instruction if it was
+/// processed by a program whose errors the SDK defines
+fn describe_status(tx: &Transaction, status: RpcSignatureStatus) -> String {
+    let program_error = match status {
+        RpcSignatureStatus::CustomProgramError(index, code)
+            if (index as usize) < tx.instructions.len() =>
+        {
+            let program_id = tx.program_id(index as usize);
+            let err = ProgramError::CustomError(code);
+            if budget_program::check_id(program_id) {
+                err.to_custom::<BudgetError>()
+                    .map(|err| format!("{:?}", err))
+            } else if token_program::check_id(program_id) {
+                err.to_custom::<TokenError>()
+                    .map(|err| format!("{:?}", err))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    program_error.unwrap_or_else(|| format!("{:?}", status))
 }
 
 fn send_and_confirm_tx(
     rpc_client: &RpcClient,
     tx: &mut Transaction,
-    signer: &Keypair,
+    signers: &[&Keypair],
 ) -> Result<String, Box<dyn error::Error>> {
     let mut send_retries = 5;
     loop {
@@ -730,7 +1443,7 @@ fn send_and_confirm_tx(
         match status {
             RpcSignatureStatus::AccountInUse | RpcSignatureStatus::SignatureNotFound => {
                 // Fetch a new last_id and re-sign the transaction before sending it again
-                resign_tx(rpc_client, tx, signer)?;
+                resign_tx(rpc_client, tx, signers)?;
                 send_retries -= 1;
             }
             RpcSignatureStatus::Confirmed => {
@@ -742,8 +1455,9 @@ fn send_and_confirm_tx(
         }
         if send_retries == 0 {
             Err(WalletError::RpcRequestError(format!(
-                "Transaction {:?} failed: {:?}",
-                signature_str, status
+                "Transaction {:?} failed: {}",
+                signature_str,
+                describe_status(tx, status)
             )))?;
         }
     }
@@ -752,7 +1466,7 @@ fn send_and_confirm_tx(
 fn resign_tx(
     rpc_client: &RpcClient,
     tx: &mut Transaction,
-    signer_key: &Keypair,
+    signers: &[&Keypair],
 ) -> Result<(), Box<dyn error::Error>> {
     // Fetch a new last_id to prevent the retry from getting rejected as a
     // DuplicateSignature
@@ -775,7 +1489,7 @@ fn resign_tx(
         sleep(Duration::from_secs(1));
     };
 
-    tx.sign(&[signer_key], last_id);
+    tx.sign(signers, last_id);
     Ok(())
 }
 
@@ -787,7 +1501,7 @@ pub fn request_and_confirm_airdrop(
 ) -> Result<(), Box<dyn error::Error>> {
     let last_id = get_last_id(rpc_client)?;
     let mut tx = request_airdrop_transaction(drone_addr, &signer.pubkey(), tokens, last_id)?;
-    send_and_confirm_tx(rpc_client, &mut tx, signer)?;
+    send_and_confirm_tx(rpc_client, &mut tx, &[signer])?;
     Ok(())
 }
 
@@ -882,12 +1596,62 @@ mod tests {
                             .takes_value(true)
                             .required(true)
                             .help("/path/to/program.o"),
+                    )
+                    .arg(
+                        Arg::with_name("upgradeable")
+                            .long("upgradeable")
+                            .takes_value(false)
+                            .help("Allow your key to upgrade the program"),
                     ), // TODO: Add "loader" argument; current default is bpf_loader
             )
             .subcommand(
                 SubCommand::with_name("get-transaction-count")
                     .about("Get current transaction count"),
             )
+            .subcommand(
+                SubCommand::with_name("hash-lock")
+                    .about("Send a payment that unlocks when the preimage of a hash is revealed")
+                    .arg(
+                        Arg::with_name("to")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The pubkey of recipient"),
+                    )
+                    .arg(
+                        Arg::with_name("tokens")
+                            .index(2)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tokens to send"),
+                    )
+                    .arg(
+                        Arg::with_name("hash")
+                            .index(3)
+                            .value_name("HASH")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The hash of the secret that unlocks the tokens"),
+                    )
+                    .arg(
+                        Arg::with_name("refund-after")
+                            .long("refund-after")
+                            .value_name("DATETIME")
+                            .takes_value(true)
+                            .requires("timestamp-pubkey")
+                            .help("A timestamp after which you may reclaim the tokens if the secret is still unrevealed"),
+                    )
+                    .arg(
+                        Arg::with_name("timestamp-pubkey")
+                            .long("require-timestamp-from")
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .requires("refund-after")
+                            .help("Require the refund timestamp from this third party"),
+                    ),
+            )
             .subcommand(
                 SubCommand::with_name("pay")
                     .about("Send a payment")
@@ -931,12 +1695,91 @@ mod tests {
                             .use_delimiter(true)
                             .help("Any third party signatures required to unlock the tokens"),
                     )
+                    .arg(
+                        Arg::with_name("required-signatures")
+                            .long("required-signatures")
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .requires("witness")
+                            .help("How many of the signatures are required to unlock the tokens, defaults to all"),
+                    )
                     .arg(
                         Arg::with_name("cancelable")
                             .long("cancelable")
                             .takes_value(false),
                     ),
             )
+            .subcommand(
+                SubCommand::with_name("pay-recurring")
+                    .about("Pay a fixed amount every period until the tokens run out")
+                    .arg(
+                        Arg::with_name("to")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The pubkey of recipient"),
+                    )
+                    .arg(
+                        Arg::with_name("tokens")
+                            .index(2)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The total number of tokens to pay"),
+                    )
+                    .arg(
+                        Arg::with_name("amount")
+                            .long("amount")
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tokens to pay each period"),
+                    )
+                    .arg(
+                        Arg::with_name("period")
+                            .long("period")
+                            .value_name("SECONDS")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of seconds between payments"),
+                    )
+                    .arg(
+                        Arg::with_name("start")
+                            .long("start")
+                            .value_name("DATETIME")
+                            .takes_value(true)
+                            .help("When the first payment comes due, defaults to now"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("send-preimage")
+                    .about("Reveal a secret to unlock a hash-locked transfer")
+                    .arg(
+                        Arg::with_name("to")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The pubkey of recipient"),
+                    )
+                    .arg(
+                        Arg::with_name("process-id")
+                            .index(2)
+                            .value_name("PROCESS_ID")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The process id of the transfer to unlock"),
+                    )
+                    .arg(
+                        Arg::with_name("preimage")
+                            .index(3)
+                            .value_name("SECRET")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The secret whose hash locks the transfer"),
+                    ),
+            )
             .subcommand(
                 SubCommand::with_name("send-signature")
                     .about("Send a signature to authorize a transfer")
@@ -983,6 +1826,195 @@ mod tests {
                             .takes_value(true)
                             .help("Optional arbitrary timestamp to apply"),
                     ),
+            )
+            .subcommand(
+                SubCommand::with_name("token-approve")
+                    .about("Authorize a delegate account to transfer tokens from a token account")
+                    .arg(
+                        Arg::with_name("source")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The token account to transfer from"),
+                    )
+                    .arg(
+                        Arg::with_name("delegate")
+                            .index(2)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The delegate account"),
+                    )
+                    .arg(
+                        Arg::with_name("amount")
+                            .index(3)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tokens the delegate may transfer"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("token-balance")
+                    .about("Get the balance of a token account, or the supply of a token")
+                    .arg(
+                        Arg::with_name("account")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The token account or token"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("token-create")
+                    .about("Create a new token, and a token account holding its supply")
+                    .arg(
+                        Arg::with_name("supply")
+                            .index(1)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The initial supply of the token"),
+                    )
+                    .arg(
+                        Arg::with_name("name")
+                            .index(2)
+                            .value_name("NAME")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Descriptive name of the token"),
+                    )
+                    .arg(
+                        Arg::with_name("symbol")
+                            .index(3)
+                            .value_name("SYMBOL")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Symbol of the token"),
+                    )
+                    .arg(
+                        Arg::with_name("decimals")
+                            .long("decimals")
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .default_value("0")
+                            .help("Number of base 10 digits to the right of the decimal place"),
+                    )
+                    .arg(
+                        Arg::with_name("mintable")
+                            .long("mintable")
+                            .takes_value(false)
+                            .help("Allow your key to mint additional supply"),
+                    )
+                    .arg(
+                        Arg::with_name("freezable")
+                            .long("freezable")
+                            .takes_value(false)
+                            .help("Allow your key to freeze and thaw token accounts"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("token-create-account")
+                    .about("Create a token account owned by your key")
+                    .arg(
+                        Arg::with_name("token")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The token the account will hold"),
+                    )
+                    .arg(
+                        Arg::with_name("delegate-for")
+                            .long("delegate-for")
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .help("Make the account a delegate for this token account"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("token-transfer")
+                    .about("Transfer tokens between token accounts")
+                    .arg(
+                        Arg::with_name("source")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The token account to transfer from"),
+                    )
+                    .arg(
+                        Arg::with_name("dest")
+                            .index(2)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The token account to transfer to"),
+                    )
+                    .arg(
+                        Arg::with_name("amount")
+                            .index(3)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tokens to transfer"),
+                    )
+                    .arg(
+                        Arg::with_name("delegate-for")
+                            .long("delegate-for")
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .help("If the source is a delegate account, the token account it is a delegate for"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("upgrade")
+                    .about("Replace the code of a program you deployed with --upgradeable")
+                    .arg(
+                        Arg::with_name("program-id")
+                            .index(1)
+                            .value_name("PROGRAM_ID")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The program to upgrade"),
+                    )
+                    .arg(
+                        Arg::with_name("program-location")
+                            .index(2)
+                            .value_name("PATH")
+                            .takes_value(true)
+                            .required(true)
+                            .help("/path/to/program.o"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("set-upgrade-authority")
+                    .about("Hand the upgrade authority of a program to another key, or give it up")
+                    .arg(
+                        Arg::with_name("program-id")
+                            .index(1)
+                            .value_name("PROGRAM_ID")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The program to change the upgrade authority of"),
+                    )
+                    .arg(
+                        Arg::with_name("new-authority")
+                            .index(2)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required_unless("final")
+                            .help("The new upgrade authority"),
+                    )
+                    .arg(
+                        Arg::with_name("final")
+                            .long("final")
+                            .takes_value(false)
+                            .conflicts_with("new-authority")
+                            .help("Make the program immutable; it can never be upgraded again"),
+                    ),
             );
         let pubkey = Keypair::new().pubkey();
         let pubkey_string = format!("{}", pubkey);
@@ -1038,25 +2070,179 @@ mod tests {
                 .get_matches_from(vec!["test", "deploy", "/Users/test/program.o"]);
         assert_eq!(
             parse_command(pubkey, &test_deploy).unwrap(),
-            WalletCommand::Deploy("/Users/test/program.o".to_string())
+            WalletCommand::Deploy("/Users/test/program.o".to_string(), false)
+        );
+        let test_deploy_upgradeable = test_commands.clone().get_matches_from(vec![
+            "test",
+            "deploy",
+            "/Users/test/program.o",
+            "--upgradeable",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_deploy_upgradeable).unwrap(),
+            WalletCommand::Deploy("/Users/test/program.o".to_string(), true)
+        );
+
+        // Test Upgrade Subcommand
+        let test_upgrade = test_commands.clone().get_matches_from(vec![
+            "test",
+            "upgrade",
+            &pubkey_string,
+            "/Users/test/program.o",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_upgrade).unwrap(),
+            WalletCommand::Upgrade(pubkey, "/Users/test/program.o".to_string())
+        );
+        let test_bad_upgrade = test_commands.clone().get_matches_from(vec![
+            "test",
+            "upgrade",
+            "deadbeef",
+            "/Users/test/program.o",
+        ]);
+        assert!(parse_command(pubkey, &test_bad_upgrade).is_err());
+
+        // Test SetUpgradeAuthority Subcommand
+        let test_set_upgrade_authority = test_commands.clone().get_matches_from(vec![
+            "test",
+            "set-upgrade-authority",
+            &pubkey_string,
+            &witness0_string,
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_set_upgrade_authority).unwrap(),
+            WalletCommand::SetUpgradeAuthority(pubkey, Some(witness0))
+        );
+        let test_set_upgrade_authority_final = test_commands.clone().get_matches_from(vec![
+            "test",
+            "set-upgrade-authority",
+            &pubkey_string,
+            "--final",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_set_upgrade_authority_final).unwrap(),
+            WalletCommand::SetUpgradeAuthority(pubkey, None)
+        );
+        let test_bad_set_upgrade_authority = test_commands.clone().get_matches_from(vec![
+            "test",
+            "set-upgrade-authority",
+            &pubkey_string,
+            "deadbeef",
+        ]);
+        assert!(parse_command(pubkey, &test_bad_set_upgrade_authority).is_err());
+
+        // Test Simple Pay Subcommand
+        let test_pay =
+            test_commands
+                .clone()
+                .get_matches_from(vec!["test", "pay", &pubkey_string, "50"]);
+        assert_eq!(
+            parse_command(pubkey, &test_pay).unwrap(),
+            WalletCommand::Pay(50, pubkey, None, None, None, None, None)
+        );
+        let test_bad_pubkey = test_commands
+            .clone()
+            .get_matches_from(vec!["test", "pay", "deadbeef", "50"]);
+        assert!(parse_command(pubkey, &test_bad_pubkey).is_err());
+
+        // Test Pay Subcommand w/ Witness
+        let test_pay_multiple_witnesses = test_commands.clone().get_matches_from(vec![
+            "test",
+            "pay",
+            &pubkey_string,
+            "50",
+            "--require-signature-from",
+            &witness0_string,
+            "--require-signature-from",
+            &witness1_string,
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_pay_multiple_witnesses).unwrap(),
+            WalletCommand::Pay(
+                50,
+                pubkey,
+                None,
+                None,
+                Some(vec![witness0, witness1]),
+                None,
+                None
+            )
+            .subcommand(
+                SubCommand::with_name("vest")
+                    .about("Vest tokens to a recipient in equal tranches")
+                    .arg(
+                        Arg::with_name("to")
+                            .index(1)
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The pubkey of recipient"),
+                    )
+                    .arg(
+                        Arg::with_name("tokens")
+                            .index(2)
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tokens to vest"),
+                    )
+                    .arg(
+                        Arg::with_name("tranches")
+                            .long("tranches")
+                            .value_name("NUM")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of tranches to vest the tokens in"),
+                    )
+                    .arg(
+                        Arg::with_name("period")
+                            .long("period")
+                            .value_name("SECONDS")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The number of seconds between tranches"),
+                    )
+                    .arg(
+                        Arg::with_name("start")
+                            .long("start")
+                            .value_name("DATETIME")
+                            .takes_value(true)
+                            .help("When the first tranche comes due, defaults to now"),
+                    )
+                    .arg(
+                        Arg::with_name("cliff")
+                            .long("cliff")
+                            .value_name("DATETIME")
+                            .takes_value(true)
+                            .help("Hold the tranches that come due before this timestamp until it passes"),
+                    )
+                    .arg(
+                        Arg::with_name("timestamp-pubkey")
+                            .long("require-timestamp-from")
+                            .value_name("PUBKEY")
+                            .takes_value(true)
+                            .help("Require timestamps from this third party"),
+                    )
+                    .arg(
+                        Arg::with_name("cancelable")
+                            .long("cancelable")
+                            .takes_value(false),
+                    ),
+            )
         );
-
-        // Test Simple Pay Subcommand
-        let test_pay =
-            test_commands
-                .clone()
-                .get_matches_from(vec!["test", "pay", &pubkey_string, "50"]);
+        let test_pay_single_witness = test_commands.clone().get_matches_from(vec![
+            "test",
+            "pay",
+            &pubkey_string,
+            "50",
+            "--require-signature-from",
+            &witness0_string,
+        ]);
         assert_eq!(
-            parse_command(pubkey, &test_pay).unwrap(),
-            WalletCommand::Pay(50, pubkey, None, None, None, None)
+            parse_command(pubkey, &test_pay_single_witness).unwrap(),
+            WalletCommand::Pay(50, pubkey, None, None, Some(vec![witness0]), None, None)
         );
-        let test_bad_pubkey = test_commands
-            .clone()
-            .get_matches_from(vec!["test", "pay", "deadbeef", "50"]);
-        assert!(parse_command(pubkey, &test_bad_pubkey).is_err());
-
-        // Test Pay Subcommand w/ Witness
-        let test_pay_multiple_witnesses = test_commands.clone().get_matches_from(vec![
+        let test_pay_required_signatures = test_commands.clone().get_matches_from(vec![
             "test",
             "pay",
             &pubkey_string,
@@ -1065,23 +2251,32 @@ mod tests {
             &witness0_string,
             "--require-signature-from",
             &witness1_string,
+            "--required-signatures",
+            "1",
         ]);
         assert_eq!(
-            parse_command(pubkey, &test_pay_multiple_witnesses).unwrap(),
-            WalletCommand::Pay(50, pubkey, None, None, Some(vec![witness0, witness1]), None)
+            parse_command(pubkey, &test_pay_required_signatures).unwrap(),
+            WalletCommand::Pay(
+                50,
+                pubkey,
+                None,
+                None,
+                Some(vec![witness0, witness1]),
+                Some(1),
+                None
+            )
         );
-        let test_pay_single_witness = test_commands.clone().get_matches_from(vec![
+        let test_pay_too_many_required_signatures = test_commands.clone().get_matches_from(vec![
             "test",
             "pay",
             &pubkey_string,
             "50",
             "--require-signature-from",
             &witness0_string,
+            "--required-signatures",
+            "2",
         ]);
-        assert_eq!(
-            parse_command(pubkey, &test_pay_single_witness).unwrap(),
-            WalletCommand::Pay(50, pubkey, None, None, Some(vec![witness0]), None)
-        );
+        assert!(parse_command(pubkey, &test_pay_too_many_required_signatures).is_err());
 
         // Test Pay Subcommand w/ Timestamp
         let test_pay_timestamp = test_commands.clone().get_matches_from(vec![
@@ -1096,8 +2291,69 @@ mod tests {
         ]);
         assert_eq!(
             parse_command(pubkey, &test_pay_timestamp).unwrap(),
-            WalletCommand::Pay(50, pubkey, Some(dt), Some(witness0), None, None)
+            WalletCommand::Pay(50, pubkey, Some(dt), Some(witness0), None, None, None)
+        );
+
+        // Test Pay-Recurring Subcommand
+        let test_pay_recurring = test_commands.clone().get_matches_from(vec![
+            "test",
+            "pay-recurring",
+            &pubkey_string,
+            "50",
+            "--amount",
+            "5",
+            "--period",
+            "60",
+            "--start",
+            "2018-09-19T17:30:59",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_pay_recurring).unwrap(),
+            WalletCommand::PayRecurring(50, pubkey, dt, 60, 5)
+        );
+
+        // Test Vest Subcommand
+        let test_vest = test_commands.clone().get_matches_from(vec![
+            "test",
+            "vest",
+            &pubkey_string,
+            "50",
+            "--tranches",
+            "4",
+            "--period",
+            "60",
+            "--start",
+            "2018-09-19T17:30:59",
+            "--cliff",
+            "2018-09-19T17:30:59",
+            "--require-timestamp-from",
+            &witness0_string,
+            "--cancelable",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_vest).unwrap(),
+            WalletCommand::Vest(
+                50,
+                pubkey,
+                dt,
+                60,
+                4,
+                Some(dt),
+                Some(witness0),
+                Some(pubkey)
+            )
         );
+        let test_vest_no_tranches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "vest",
+            &pubkey_string,
+            "50",
+            "--tranches",
+            "0",
+            "--period",
+            "60",
+        ]);
+        assert!(parse_command(pubkey, &test_vest_no_tranches).is_err());
 
         // Test Send-Signature Subcommand
         let test_send_signature = test_commands.clone().get_matches_from(vec![
@@ -1132,10 +2388,90 @@ mod tests {
                 Some(dt),
                 Some(witness0),
                 Some(vec![witness0, witness1]),
+                None,
                 None
             )
         );
 
+        // Test Hash-Lock Subcommand
+        let hash = Hash::new(&[7; 32]);
+        let hash_string = format!("{}", hash);
+        let test_hash_lock = test_commands.clone().get_matches_from(vec![
+            "test",
+            "hash-lock",
+            &pubkey_string,
+            "50",
+            &hash_string,
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_hash_lock).unwrap(),
+            WalletCommand::HashLock(50, pubkey, hash, None)
+        );
+        let oracle = Keypair::new().pubkey();
+        let oracle_string = format!("{}", oracle);
+        let test_hash_lock_refund = test_commands.clone().get_matches_from(vec![
+            "test",
+            "hash-lock",
+            &pubkey_string,
+            "50",
+            &hash_string,
+            "--refund-after",
+            "2018-09-19T17:30:59",
+            "--require-timestamp-from",
+            &oracle_string,
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_hash_lock_refund).unwrap(),
+            WalletCommand::HashLock(50, pubkey, hash, Some((dt, oracle)))
+        );
+
+        // The payer can't witness its own refund time
+        let test_hash_lock_self_refund = test_commands.clone().get_matches_from(vec![
+            "test",
+            "hash-lock",
+            &pubkey_string,
+            "50",
+            &hash_string,
+            "--refund-after",
+            "2018-09-19T17:30:59",
+            "--require-timestamp-from",
+            &pubkey_string,
+        ]);
+        assert!(parse_command(pubkey, &test_hash_lock_self_refund).is_err());
+        assert!(test_commands
+            .clone()
+            .get_matches_from_safe(vec![
+                "test",
+                "hash-lock",
+                &pubkey_string,
+                "50",
+                &hash_string,
+                "--refund-after",
+                "2018-09-19T17:30:59",
+            ])
+            .is_err());
+        let test_bad_hash = test_commands.clone().get_matches_from(vec![
+            "test",
+            "hash-lock",
+            &pubkey_string,
+            "50",
+            "deadbeef",
+        ]);
+        assert!(parse_command(pubkey, &test_bad_hash).is_err());
+
+        // Test Send-Preimage Subcommand
+        let test_send_preimage = test_commands.clone().get_matches_from(vec![
+            "test",
+            "send-preimage",
+            &pubkey_string,
+            &pubkey_string,
+            "secret",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_send_preimage).unwrap(),
+            WalletCommand::Preimage(pubkey, pubkey, b"secret".to_vec())
+        );
+
         // Test Send-Timestamp Subcommand
         let test_send_timestamp = test_commands.clone().get_matches_from(vec![
             "test",
@@ -1158,6 +2494,75 @@ mod tests {
             "20180919T17:30:59",
         ]);
         assert!(parse_command(pubkey, &test_bad_timestamp).is_err());
+
+        // Test Token Subcommands
+        let test_token_create = test_commands.clone().get_matches_from(vec![
+            "test",
+            "token-create",
+            "1000",
+            "A test token",
+            "TEST",
+            "--decimals",
+            "2",
+            "--mintable",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_token_create).unwrap(),
+            WalletCommand::TokenCreate(TokenInfo {
+                supply: 1000,
+                decimals: 2,
+                name: "A test token".to_string(),
+                symbol: "TEST".to_string(),
+                mint_authority: Some(pubkey),
+                freeze_authority: None,
+            })
+        );
+        let test_token_create_account = test_commands.clone().get_matches_from(vec![
+            "test",
+            "token-create-account",
+            &witness0_string,
+            "--delegate-for",
+            &witness1_string,
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_token_create_account).unwrap(),
+            WalletCommand::TokenCreateAccount(witness0, Some(witness1))
+        );
+        let test_token_transfer = test_commands.clone().get_matches_from(vec![
+            "test",
+            "token-transfer",
+            &witness0_string,
+            &witness1_string,
+            "50",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_token_transfer).unwrap(),
+            WalletCommand::TokenTransfer(witness0, witness1, 50, None)
+        );
+        let test_token_approve = test_commands.clone().get_matches_from(vec![
+            "test",
+            "token-approve",
+            &witness0_string,
+            &witness1_string,
+            "50",
+        ]);
+        assert_eq!(
+            parse_command(pubkey, &test_token_approve).unwrap(),
+            WalletCommand::TokenApprove(witness0, witness1, 50)
+        );
+        let test_token_balance =
+            test_commands
+                .clone()
+                .get_matches_from(vec!["test", "token-balance", &pubkey_string]);
+        assert_eq!(
+            parse_command(pubkey, &test_token_balance).unwrap(),
+            WalletCommand::TokenBalance(pubkey)
+        );
+        let test_bad_token_balance =
+            test_commands
+                .clone()
+                .get_matches_from(vec!["test", "token-balance", "deadbeef"]);
+        assert!(parse_command(pubkey, &test_bad_token_balance).is_err());
     }
 
     #[test]
@@ -1190,7 +2595,7 @@ mod tests {
         assert_eq!(process_command(&config).unwrap(), "1234");
 
         let bob_pubkey = Keypair::new().pubkey();
-        config.command = WalletCommand::Pay(10, bob_pubkey, None, None, None, None);
+        config.command = WalletCommand::Pay(10, bob_pubkey, None, None, None, None, None);
         let signature = process_command(&config);
         assert_eq!(signature.unwrap(), SIGNATURE.to_string());
 
@@ -1203,6 +2608,7 @@ mod tests {
             Some(config.id.pubkey()),
             None,
             None,
+            None,
         );
         let result = process_command(&config);
         let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
@@ -1223,6 +2629,42 @@ mod tests {
             None,
             None,
             Some(vec![witness]),
+            None,
+            Some(config.id.pubkey()),
+        );
+        let result = process_command(&config);
+        let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(
+            json.as_object()
+                .unwrap()
+                .get("signature")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            SIGNATURE.to_string()
+        );
+
+        config.command = WalletCommand::PayRecurring(10, bob_pubkey, dt, 60, 3);
+        let result = process_command(&config);
+        let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(
+            json.as_object()
+                .unwrap()
+                .get("signature")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            SIGNATURE.to_string()
+        );
+
+        config.command = WalletCommand::Vest(
+            10,
+            bob_pubkey,
+            dt,
+            60,
+            4,
+            None,
+            None,
             Some(config.id.pubkey()),
         );
         let result = process_command(&config);
@@ -1247,6 +2689,45 @@ mod tests {
         let signature = process_command(&config);
         assert_eq!(signature.unwrap(), SIGNATURE.to_string());
 
+        config.command = WalletCommand::Preimage(bob_pubkey, process_id, b"secret".to_vec());
+        let signature = process_command(&config);
+        assert_eq!(signature.unwrap(), SIGNATURE.to_string());
+
+        config.command = WalletCommand::TokenCreate(TokenInfo::default());
+        let result = process_command(&config);
+        let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(
+            json.as_object()
+                .unwrap()
+                .get("signature")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            SIGNATURE
+        );
+
+        let token = Keypair::new().pubkey();
+        config.command = WalletCommand::TokenCreateAccount(token, None);
+        let result = process_command(&config);
+        let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
+        assert_eq!(
+            json.as_object()
+                .unwrap()
+                .get("signature")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            SIGNATURE
+        );
+
+        config.command = WalletCommand::TokenTransfer(process_id, bob_pubkey, 10, None);
+        let signature = process_command(&config);
+        assert_eq!(signature.unwrap(), SIGNATURE.to_string());
+
+        config.command = WalletCommand::TokenApprove(process_id, bob_pubkey, 10);
+        let signature = process_command(&config);
+        assert_eq!(signature.unwrap(), SIGNATURE.to_string());
+
         // Need airdrop cases
         config.command = WalletCommand::Airdrop(50);
         assert!(process_command(&config).is_err());
@@ -1277,7 +2758,7 @@ mod tests {
         config.command = WalletCommand::GetTransactionCount;
         assert!(process_command(&config).is_err());
 
-        config.command = WalletCommand::Pay(10, bob_pubkey, None, None, None, None);
+        config.command = WalletCommand::Pay(10, bob_pubkey, None, None, None, None, None);
         assert!(process_command(&config).is_err());
 
         config.command = WalletCommand::Pay(
@@ -1287,6 +2768,7 @@ mod tests {
             Some(config.id.pubkey()),
             None,
             None,
+            None,
         );
         assert!(process_command(&config).is_err());
 
@@ -1296,12 +2778,16 @@ mod tests {
             None,
             None,
             Some(vec![witness]),
+            None,
             Some(config.id.pubkey()),
         );
         assert!(process_command(&config).is_err());
 
         config.command = WalletCommand::TimeElapsed(bob_pubkey, process_id, dt);
         assert!(process_command(&config).is_err());
+
+        config.command = WalletCommand::TokenBalance(bob_pubkey);
+        assert!(process_command(&config).is_err());
     }
 
     #[test]
@@ -1316,7 +2802,7 @@ mod tests {
         let mut config = WalletConfig::default();
         config.rpc_client = Some(RpcClient::new("succeeds".to_string()));
 
-        config.command = WalletCommand::Deploy(pathbuf.to_str().unwrap().to_string());
+        config.command = WalletCommand::Deploy(pathbuf.to_str().unwrap().to_string(), false);
         let result = process_command(&config);
         let json: Value = serde_json::from_str(&result.unwrap()).unwrap();
         let program_id = json
@@ -1333,8 +2819,32 @@ mod tests {
         config.rpc_client = Some(RpcClient::new("airdrop".to_string()));
         assert!(process_command(&config).is_err());
 
-        config.command = WalletCommand::Deploy("bad/file/location.so".to_string());
+        config.command = WalletCommand::Deploy("bad/file/location.so".to_string(), false);
+        assert!(process_command(&config).is_err());
+    }
+
+    #[test]
+    fn test_wallet_upgrade() {
+        let mut pathbuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        pathbuf.push("tests");
+        pathbuf.push("fixtures");
+        pathbuf.push("noop");
+        pathbuf.set_extension("so");
+        let program_id = Keypair::new().pubkey();
+
+        let mut config = WalletConfig::default();
+        config.rpc_client = Some(RpcClient::new("succeeds".to_string()));
+        config.command = WalletCommand::Deploy(pathbuf.to_str().unwrap().to_string(), true);
+        assert!(process_command(&config).is_ok());
+
+        config.command = WalletCommand::Upgrade(program_id, pathbuf.to_str().unwrap().to_string());
+        assert!(process_command(&config).is_ok());
+
+        config.command = WalletCommand::Upgrade(program_id, "bad/file/location.so".to_string());
         assert!(process_command(&config).is_err());
+
+        config.command = WalletCommand::SetUpgradeAuthority(program_id, None);
+        assert!(process_command(&config).is_ok());
     }
 
     fn tmp_file_path(name: &str) -> String {
@@ -1413,6 +2923,24 @@ mod tests {
         assert!(status.is_err());
     }
 
+    #[test]
+    fn test_wallet_custom_program_error() {
+        let rpc_client = RpcClient::new("custom_program_error".to_string());
+        let status = confirm_tx(&rpc_client, "good_signature").unwrap();
+        assert_eq!(status, RpcSignatureStatus::CustomProgramError(0, 2));
+
+        let key = Keypair::new();
+        let tx = Transaction::new(&key, &[], budget_program::id(), &(), Hash::default(), 0);
+        assert_eq!(describe_status(&tx, status), "ContractNotPending");
+        let tx = Transaction::system_new(&key, Keypair::new().pubkey(), 50, Hash::default());
+        assert_eq!(describe_status(&tx, status), "CustomProgramError(0, 2)");
+
+        let mut tx = Transaction::system_new(&key, Keypair::new().pubkey(), 50, Hash::default());
+        let result = send_and_confirm_tx(&rpc_client, &mut tx, &[&key]);
+        let err = format!("{:?}", result.unwrap_err());
+        assert!(err.contains("failed: CustomProgramError(0, 2)"));
+    }
+
     #[test]
     fn test_wallet_send_and_confirm_tx() {
         let rpc_client = RpcClient::new("succeeds".to_string());
@@ -1424,15 +2952,15 @@ mod tests {
 
         let signer = Keypair::new();
 
-        let result = send_and_confirm_tx(&rpc_client, &mut tx, &signer);
+        let result = send_and_confirm_tx(&rpc_client, &mut tx, &[&signer]);
         result.unwrap();
 
         let rpc_client = RpcClient::new("account_in_use".to_string());
-        let result = send_and_confirm_tx(&rpc_client, &mut tx, &signer);
+        let result = send_and_confirm_tx(&rpc_client, &mut tx, &[&signer]);
         assert!(result.is_err());
 
         let rpc_client = RpcClient::new("fails".to_string());
-        let result = send_and_confirm_tx(&rpc_client, &mut tx, &signer);
+        let result = send_and_confirm_tx(&rpc_client, &mut tx, &[&signer]);
         assert!(result.is_err());
     }
 
@@ -1449,7 +2977,7 @@ mod tests {
         let prev_tx = Transaction::system_new(&key, to, 50, last_id);
         let mut tx = Transaction::system_new(&key, to, 50, last_id);
 
-        resign_tx(&rpc_client, &mut tx, &key).unwrap();
+        resign_tx(&rpc_client, &mut tx, &[&key]).unwrap();
 
         assert_ne!(prev_tx, tx);
         assert_ne!(prev_tx.signatures, tx.signatures);
@@ -31,7 +31,7 @@ fn test_wallet_deploy_program() {
     config.command = WalletCommand::Airdrop(50);
     process_command(&config).unwrap();
 
-    config.command = WalletCommand::Deploy(pathbuf.to_str().unwrap().to_string());
+    config.command = WalletCommand::Deploy(pathbuf.to_str().unwrap().to_string(), false);
 
     let response = process_command(&config);
     let json: Value = serde_json::from_str(&response.unwrap()).unwrap();
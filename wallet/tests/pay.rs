@@ -57,6 +57,7 @@ fn test_wallet_timestamp_tx() {
         Some(config_witness.id.pubkey()),
         None,
         None,
+        None,
     );
     let sig_response = process_command(&config_payer);
 
@@ -120,6 +121,7 @@ fn test_wallet_witness_tx() {
         None,
         Some(vec![config_witness.id.pubkey()]),
         None,
+        None,
     );
     let sig_response = process_command(&config_payer);
 
@@ -182,6 +184,7 @@ fn test_wallet_cancel_tx() {
         None,
         None,
         Some(vec![config_witness.id.pubkey()]),
+        None,
         Some(config_payer.id.pubkey()),
     );
     let sig_response = process_command(&config_payer);